@@ -0,0 +1,128 @@
+// benches/pipeline.rs
+//! Timings of the encode/decode pipeline and of its two expensive steps.
+//!
+//! `serialize` and `deserialize` run the whole pipeline with the app's
+//! defaults. `key_derivation` and `compression` time Argon2 and zstd on their
+//! own with other limits and levels; compare them with the pipeline numbers to
+//! see which step dominates at which input size. Run with `cargo bench`, or
+//! e.g. `cargo bench -- key_derivation` for one group.
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use qr_data_exchange::qr::processor::QrDataProcessor;
+use sodiumoxide::crypto::{pwhash::argon2i13, secretbox};
+use std::hint::black_box;
+use std::time::Duration;
+
+const PASSWORD: &str = "correct horse battery staple";
+const INPUT_SIZES: [usize; 4] = [64, 1024, 64 * 1024, 1024 * 1024];
+const COMPRESSION_LEVELS: [i32; 4] = [3, 9, 16, 19];
+
+/// Text-like input that compresses about as well as a config file: words
+/// from a small vocabulary picked by a fixed xorshift sequence.
+fn sample_input(len: usize) -> Vec<u8> {
+    const WORDS: [&str; 12] = [
+        "user", "password", "host", "port", "=", "\n", "true", "false", "8080", "admin", "token",
+        "#",
+    ];
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    let mut input = Vec::with_capacity(len + 16);
+    while input.len() < len {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        input.extend_from_slice(WORDS[(state % WORDS.len() as u64) as usize].as_bytes());
+        input.push(b' ');
+    }
+    input.truncate(len);
+    input
+}
+
+fn serialize(c: &mut Criterion) {
+    let mut group = c.benchmark_group("serialize");
+    // Every iteration runs Argon2, so keep the sample count low.
+    group
+        .sample_size(10)
+        .measurement_time(Duration::from_secs(10));
+    for len in INPUT_SIZES {
+        let input = sample_input(len);
+        group.throughput(Throughput::Bytes(len as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(len), &input, |b, input| {
+            b.iter(|| QrDataProcessor::serialize(black_box(input), PASSWORD).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn deserialize(c: &mut Criterion) {
+    let mut group = c.benchmark_group("deserialize");
+    group
+        .sample_size(10)
+        .measurement_time(Duration::from_secs(10));
+    for len in INPUT_SIZES {
+        let payload = QrDataProcessor::serialize(&sample_input(len), PASSWORD).unwrap();
+        group.throughput(Throughput::Bytes(len as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(len), &payload, |b, payload| {
+            b.iter(|| QrDataProcessor::deserialize(black_box(payload), PASSWORD).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn key_derivation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("key_derivation");
+    group
+        .sample_size(10)
+        .measurement_time(Duration::from_secs(20));
+    let salt = argon2i13::Salt([7; argon2i13::SALTBYTES]);
+    let limits = [
+        (
+            "interactive",
+            argon2i13::OPSLIMIT_INTERACTIVE,
+            argon2i13::MEMLIMIT_INTERACTIVE,
+        ),
+        (
+            "moderate",
+            argon2i13::OPSLIMIT_MODERATE,
+            argon2i13::MEMLIMIT_MODERATE,
+        ),
+        (
+            "moderate_ops_interactive_mem",
+            argon2i13::OPSLIMIT_MODERATE,
+            argon2i13::MEMLIMIT_INTERACTIVE,
+        ),
+        (
+            "sensitive_ops_moderate_mem",
+            argon2i13::OPSLIMIT_SENSITIVE,
+            argon2i13::MEMLIMIT_MODERATE,
+        ),
+    ];
+    for (name, opslimit, memlimit) in limits {
+        group.bench_function(BenchmarkId::new(name, opslimit.0), |b| {
+            b.iter(|| {
+                let mut key = [0u8; secretbox::KEYBYTES];
+                argon2i13::derive_key(&mut key, PASSWORD.as_bytes(), &salt, opslimit, memlimit)
+                    .unwrap();
+                key
+            })
+        });
+    }
+    group.finish();
+}
+
+fn compression(c: &mut Criterion) {
+    let mut group = c.benchmark_group("compression");
+    for len in INPUT_SIZES {
+        let input = sample_input(len);
+        group.throughput(Throughput::Bytes(len as u64));
+        for level in COMPRESSION_LEVELS {
+            group.bench_with_input(
+                BenchmarkId::new(format!("level_{}", level), len),
+                &input,
+                |b, input| b.iter(|| zstd::encode_all(black_box(input.as_slice()), level).unwrap()),
+            );
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, serialize, deserialize, key_derivation, compression);
+criterion_main!(benches);
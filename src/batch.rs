@@ -0,0 +1,107 @@
+// src/batch.rs
+//! Encoding several files with the same protection and settings, a few at a
+//! time, into one image per file in an output folder.
+use crate::error::Error;
+use crate::qr::processor::{Protection, SerializeOptions};
+use crate::qr::service::RenderOptions;
+use crate::services;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+
+/// Number of files encoded at the same time. Key derivation is CPU and memory
+/// heavy, so running every file at once would only make each one slower.
+pub const MAX_CONCURRENT_JOBS: usize = 4;
+
+#[derive(Debug, Clone)]
+pub enum BatchStatus {
+    Pending,
+    Written(PathBuf),
+    TooLarge(usize),
+    Failed(Error),
+}
+
+#[derive(Debug, Clone)]
+pub struct BatchJob {
+    pub input: PathBuf,
+    pub status: BatchStatus,
+}
+
+#[derive(Debug, Clone)]
+pub struct BatchState {
+    pub output_dir: PathBuf,
+    pub jobs: Vec<BatchJob>,
+}
+
+impl BatchState {
+    pub fn new(inputs: Vec<PathBuf>, output_dir: PathBuf) -> Self {
+        let jobs = inputs
+            .into_iter()
+            .map(|input| BatchJob {
+                input,
+                status: BatchStatus::Pending,
+            })
+            .collect();
+        Self { output_dir, jobs }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.jobs
+            .iter()
+            .all(|job| !matches!(job.status, BatchStatus::Pending))
+    }
+
+    pub fn count(&self, predicate: impl Fn(&BatchStatus) -> bool) -> usize {
+        self.jobs.iter().filter(|job| predicate(&job.status)).count()
+    }
+}
+
+/// `<output_dir>/<file name>.qr.png` for the given input file.
+pub fn output_path(input: &Path, output_dir: &Path) -> PathBuf {
+    let name = input
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "payload".to_string());
+    output_dir.join(format!("{}.qr.png", name))
+}
+
+/// Encodes one file of a batch once a slot in `limiter` becomes free.
+pub async fn encode_file(
+    input: PathBuf,
+    output_dir: PathBuf,
+    protection: Protection,
+    options: SerializeOptions,
+    render: RenderOptions,
+    max_input_len: u64,
+    limiter: Arc<Semaphore>,
+) -> BatchStatus {
+    // The semaphore is never closed, so acquiring only fails if that changes.
+    let _permit = limiter.acquire_owned().await.ok();
+
+    let raw_data = match services::read_input(&input, max_input_len).await {
+        Ok(data) => data,
+        Err(Error::FileTooLarge { size, .. }) => {
+            return BatchStatus::TooLarge(usize::try_from(size).unwrap_or(usize::MAX))
+        }
+        Err(e) => return BatchStatus::Failed(e),
+    };
+
+    let generated = services::generate_qr_from_bytes(
+        raw_data,
+        protection,
+        options,
+        render,
+        services::Progress::default(),
+    );
+    let png = match generated.await {
+        Ok(result) => result.qr_image,
+        Err(Error::PayloadTooLarge { size, .. }) => return BatchStatus::TooLarge(size),
+        Err(e) => return BatchStatus::Failed(e),
+    };
+
+    let target = output_path(&input, &output_dir);
+    match tokio::fs::write(&target, png).await {
+        Ok(()) => BatchStatus::Written(target),
+        Err(e) => BatchStatus::Failed(e.into()),
+    }
+}
@@ -0,0 +1,293 @@
+// src/cli.rs
+//! Command line mode for scripts, used instead of the GUI when arguments are
+//! given.
+use qr_data_exchange::config::AppConfig;
+use qr_data_exchange::qr::processor::{self, DeserializeOptions, Protection};
+use qr_data_exchange::qr::service;
+use qr_data_exchange::selftest;
+use qr_data_exchange::services::{self, EncodeInput};
+use std::ffi::OsString;
+use std::io::BufRead;
+use std::path::PathBuf;
+use zeroize::Zeroizing;
+
+/// Environment variable the password is taken from; without it the first
+/// line of stdin is used.
+const PASSWORD_VAR: &str = "QRDX_PASSWORD";
+
+const USAGE: &str = "Usage: qr-data-exchange [--config <file>] [--output text|terminal <file> | --encrypt <file> <output> | --decrypt <file> <output> | --compare <image> <image> | --selftest]
+
+  --config <file>           read processing defaults from <file> instead of
+                            config.toml in the settings directory; may come
+                            before or after the command, and without one the
+                            GUI starts with them
+  --output text <file>      print the encrypted payload text of <file>
+                            instead of rendering a QR code
+  --output terminal <file>  draw the QR code for <file> in the terminal
+  --text-only <file>        same as --output text
+  --encrypt <file> <output> encrypt <file> of any size into streamed payload
+                            text at <output>, without loading it into memory
+  --decrypt <file> <output> decrypt streamed payload text from <file> into
+                            <output>
+  --compare <image> <image> check whether two QR images carry the same
+                            payload, e.g. a reprint and its original; exits
+                            with 0 if they do, 1 if not and 2 on errors
+  --selftest                encrypt, render and read back a known sample
+                            and report whether this build works
+
+The password is read from QRDX_PASSWORD or the first line of stdin.";
+
+/// How many times the size of a stream `--decrypt` writes at most, so a small
+/// stream can't fill the disk.
+const MAX_STREAM_EXPANSION: u64 = 1024;
+
+/// What the command line mode prints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Output {
+    Text,
+    Terminal,
+}
+
+/// Removes `--config <file>` from `args`, wherever it is, and returns the
+/// file. Fails with the exit code when the file name is missing or the
+/// option is given twice.
+pub fn take_config_path(args: &mut Vec<OsString>) -> Result<Option<PathBuf>, i32> {
+    let Some(index) = args.iter().position(|arg| arg == "--config") else {
+        return Ok(None);
+    };
+    if index + 1 >= args.len() {
+        return Err(usage());
+    }
+
+    let path = args.remove(index + 1);
+    args.remove(index);
+    if args.iter().any(|arg| arg == "--config") {
+        return Err(usage());
+    }
+    Ok(Some(PathBuf::from(path)))
+}
+
+/// Runs the command given on the command line and returns the exit code, or
+/// `None` when there are no arguments and the GUI should start.
+pub fn run(args: Vec<OsString>, config: &AppConfig) -> Option<i32> {
+    let mut args = args.into_iter();
+    let command = args.next()?;
+
+    Some(match command.to_str() {
+        Some("--text-only") => match args.next() {
+            Some(path) => encode(path, Output::Text, config),
+            None => usage(),
+        },
+        Some("--output") => {
+            let output = match args.next().as_ref().and_then(|format| format.to_str()) {
+                Some("text") => Output::Text,
+                Some("terminal") => Output::Terminal,
+                _ => return Some(usage()),
+            };
+            match args.next() {
+                Some(path) => encode(path, output, config),
+                None => usage(),
+            }
+        }
+        Some("--encrypt") => match (args.next(), args.next()) {
+            (Some(input), Some(output)) => encrypt_stream(input, output, config),
+            _ => usage(),
+        },
+        Some("--decrypt") => match (args.next(), args.next()) {
+            (Some(input), Some(output)) => decrypt_stream(input, output, config),
+            _ => usage(),
+        },
+        Some("--compare") => match (args.next(), args.next()) {
+            (Some(a), Some(b)) => compare(a, b),
+            _ => usage(),
+        },
+        Some("--selftest") => run_selftest(),
+        Some("--help" | "-h") => {
+            println!("{}", USAGE);
+            0
+        }
+        _ => usage(),
+    })
+}
+
+fn usage() -> i32 {
+    eprintln!("{}", USAGE);
+    2
+}
+
+/// The password and a runtime for the library calls, or the exit code.
+fn prepare() -> Result<(Zeroizing<String>, tokio::runtime::Runtime), i32> {
+    let password = match read_password() {
+        Ok(password) if !password.is_empty() => password,
+        Ok(_) => {
+            eprintln!("No password given; set {} or pass it on stdin", PASSWORD_VAR);
+            return Err(2);
+        }
+        Err(e) => {
+            eprintln!("Could not read the password: {}", e);
+            return Err(1);
+        }
+    };
+
+    match tokio::runtime::Builder::new_current_thread().build() {
+        Ok(runtime) => Ok((password, runtime)),
+        Err(e) => {
+            eprintln!("{}", e);
+            Err(1)
+        }
+    }
+}
+
+fn encode(path: OsString, output: Output, config: &AppConfig) -> i32 {
+    let (password, runtime) = match prepare() {
+        Ok(prepared) => prepared,
+        Err(code) => return code,
+    };
+
+    let result = runtime.block_on(services::encode_to_string(
+        EncodeInput::File(PathBuf::from(path)),
+        Protection::Password(password),
+        config.serialize_options(),
+        config.max_input_bytes,
+    ));
+
+    let qr_text = match result {
+        Ok(qr_text) => qr_text,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+
+    match output {
+        Output::Text => {
+            println!("{}", qr_text);
+            0
+        }
+        Output::Terminal => print_terminal(&qr_text, config),
+    }
+}
+
+fn encrypt_stream(input: OsString, output: OsString, config: &AppConfig) -> i32 {
+    let (password, runtime) = match prepare() {
+        Ok(prepared) => prepared,
+        Err(code) => return code,
+    };
+
+    match runtime.block_on(services::encrypt_file_streaming(
+        PathBuf::from(input),
+        PathBuf::from(output),
+        password,
+        config.serialize_options(),
+    )) {
+        Ok(_) => 0,
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
+fn decrypt_stream(input: OsString, output: OsString, config: &AppConfig) -> i32 {
+    let input = PathBuf::from(input);
+    let (password, runtime) = match prepare() {
+        Ok(prepared) => prepared,
+        Err(code) => return code,
+    };
+
+    // The whole point of streaming is data beyond the usual limit, so it
+    // grows with the stream instead.
+    let stream_len = std::fs::metadata(&input).map_or(0, |metadata| metadata.len());
+    let options = DeserializeOptions {
+        max_decompressed_len: usize::try_from(stream_len.saturating_mul(MAX_STREAM_EXPANSION))
+            .unwrap_or(usize::MAX)
+            .max(processor::DEFAULT_MAX_DECOMPRESSED_LEN),
+        ..config.deserialize_options()
+    };
+
+    match runtime.block_on(services::decrypt_file_streaming(
+        input,
+        PathBuf::from(output),
+        password,
+        options,
+    )) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
+/// Draws the code on stdout, unless it is wider than the terminal: a wrapped
+/// code can't be scanned.
+fn print_terminal(qr_text: &str, config: &AppConfig) -> i32 {
+    let rendered = match service::generate_qr_terminal(qr_text, config.ec_level) {
+        Ok(rendered) => rendered,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+
+    let width = rendered.lines().next().map_or(0, |line| line.chars().count());
+    if let Some((terminal_size::Width(columns), _)) = terminal_size::terminal_size() {
+        if width > usize::from(columns) {
+            eprintln!(
+                "The QR code is {} columns wide, but the terminal only has {}; \
+                 widen the window or use --output text",
+                width, columns
+            );
+            return 1;
+        }
+    }
+
+    println!("{}", rendered);
+    0
+}
+
+/// Exit codes as for `cmp`; no password is needed.
+fn compare(a: OsString, b: OsString) -> i32 {
+    match service::decode_and_compare(&a.to_string_lossy(), &b.to_string_lossy()) {
+        Ok(true) => {
+            println!("The codes carry the same payload");
+            0
+        }
+        Ok(false) => {
+            println!("The codes carry different payloads");
+            1
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            2
+        }
+    }
+}
+
+/// Prints PASS or FAIL for every step; exits with 1 if any step failed.
+fn run_selftest() -> i32 {
+    let report = selftest::run();
+    for result in &report.steps {
+        let ms = result.duration.as_millis();
+        match result.outcome {
+            Ok(()) => println!("PASS  {} ({} ms)", result.step, ms),
+            Err(ref e) => println!("FAIL  {} ({} ms): {}", result.step, ms, e),
+        }
+    }
+
+    if report.passed() {
+        0
+    } else {
+        1
+    }
+}
+
+fn read_password() -> std::io::Result<Zeroizing<String>> {
+    if let Ok(password) = std::env::var(PASSWORD_VAR) {
+        return Ok(Zeroizing::new(password));
+    }
+
+    let mut line = Zeroizing::new(String::new());
+    std::io::stdin().lock().read_line(&mut line)?;
+    Ok(Zeroizing::new(line.trim_end_matches(['\r', '\n']).to_string()))
+}
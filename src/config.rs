@@ -0,0 +1,227 @@
+// src/config.rs
+//! Processing defaults from `config.toml`, for users who want other values
+//! than the built-in ones. Unlike the settings, the app never writes this
+//! file. Every key is optional:
+//!
+//! ```toml
+//! ec_level = "M"              # L, M, Q or H
+//! compression_level = 19      # zstd, 1 to 22
+//! kdf_ops_limit = 8           # Argon2 passes, 6 to 64
+//! kdf_mem_limit_kib = 262144  # Argon2 memory in KiB, 131072 to 4 GiB
+//! max_input_bytes = 4194304   # largest file encoded into a single code
+//! image_size = 512            # pixels
+//! min_password_length = 12    # characters, 1 to 20
+//! min_password_bits = 50      # estimated entropy, 0 to 128
+//! ```
+//!
+//! A missing or invalid value falls back to its default with a warning; the
+//! other keys still apply. The Argon2 limits can't go below the defaults:
+//! they only make guessing the password harder. Payloads record the limits
+//! they were made with, so the configured ones only matter for decoding
+//! payloads from before format version 7.
+use crate::crypto::crypto_utils::KdfLimits;
+use crate::crypto::password::{PasswordPolicy, MAX_PASSWORD_LENGTH};
+use crate::qr::processor::{DeserializeOptions, SerializeOptions, COMPRESSION_LEVEL};
+use crate::qr::service::{EcLevel, RenderOptions};
+use crate::services;
+use serde::Deserialize;
+use std::ops::RangeInclusive;
+use std::path::Path;
+
+/// Name of the file in the settings directory.
+pub const CONFIG_FILE: &str = "config.toml";
+
+const COMPRESSION_LEVELS: RangeInclusive<i64> = 1..=22;
+const IMAGE_SIZES: RangeInclusive<i64> = 64..=8192;
+/// A policy above this can't be met by any password of up to
+/// [`MAX_PASSWORD_LENGTH`] characters.
+const MAX_PASSWORD_BITS: f64 = 128.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AppConfig {
+    pub ec_level: EcLevel,
+    pub compression_level: i32,
+    pub kdf: KdfLimits,
+    /// Largest input file for a single QR code, in bytes.
+    pub max_input_bytes: u64,
+    /// Preferred side of generated images in pixels.
+    pub image_size: u32,
+    /// What a password has to meet before anything is encrypted with it.
+    pub password_policy: PasswordPolicy,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            ec_level: EcLevel::L,
+            compression_level: COMPRESSION_LEVEL,
+            kdf: KdfLimits::default(),
+            max_input_bytes: services::MAX_INPUT_LEN,
+            image_size: RenderOptions::default().size,
+            password_policy: PasswordPolicy::default(),
+        }
+    }
+}
+
+impl AppConfig {
+    /// Options with the configured compression and Argon2 limits; the rest is
+    /// up to the caller.
+    pub fn serialize_options(&self) -> SerializeOptions {
+        SerializeOptions {
+            compression_level: self.compression_level,
+            kdf: self.kdf,
+            ..SerializeOptions::default()
+        }
+    }
+
+    pub fn deserialize_options(&self) -> DeserializeOptions {
+        DeserializeOptions {
+            kdf: self.kdf,
+            ..DeserializeOptions::default()
+        }
+    }
+
+    pub fn render_options(&self) -> RenderOptions {
+        RenderOptions {
+            size: self.image_size,
+            ec_level: self.ec_level,
+            ..RenderOptions::default()
+        }
+    }
+}
+
+/// The file as written, before validation. Values are kept untyped so that
+/// one of the wrong type only costs that key.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct RawConfig {
+    ec_level: Option<toml::Value>,
+    compression_level: Option<toml::Value>,
+    kdf_ops_limit: Option<toml::Value>,
+    kdf_mem_limit_kib: Option<toml::Value>,
+    max_input_bytes: Option<toml::Value>,
+    image_size: Option<toml::Value>,
+    min_password_length: Option<toml::Value>,
+    min_password_bits: Option<toml::Value>,
+    #[serde(flatten)]
+    unknown: toml::Table,
+}
+
+impl RawConfig {
+    fn validate(self) -> AppConfig {
+        let defaults = AppConfig::default();
+        for key in self.unknown.keys() {
+            tracing::warn!("Ignoring unknown key {} in the config file", key);
+        }
+
+        AppConfig {
+            ec_level: field("ec_level", self.ec_level, defaults.ec_level, |value| {
+                match value.as_str()?.to_ascii_uppercase().as_str() {
+                    "L" => Some(EcLevel::L),
+                    "M" => Some(EcLevel::M),
+                    "Q" => Some(EcLevel::Q),
+                    "H" => Some(EcLevel::H),
+                    _ => None,
+                }
+            }),
+            compression_level: field(
+                "compression_level",
+                self.compression_level,
+                defaults.compression_level,
+                |value| integer_in(value, COMPRESSION_LEVELS),
+            ),
+            kdf: KdfLimits {
+                ops_limit: field("kdf_ops_limit", self.kdf_ops_limit, defaults.kdf.ops_limit, |value| {
+                    let ops = i64::from(defaults.kdf.ops_limit)..=i64::from(KdfLimits::MAX_OPS_LIMIT);
+                    integer_in(value, ops)
+                }),
+                mem_limit_kib: field(
+                    "kdf_mem_limit_kib",
+                    self.kdf_mem_limit_kib,
+                    defaults.kdf.mem_limit_kib,
+                    |value| {
+                        let kib = i64::from(defaults.kdf.mem_limit_kib)
+                            ..=i64::from(KdfLimits::MAX_MEM_LIMIT_KIB);
+                        integer_in(value, kib)
+                    },
+                ),
+            },
+            max_input_bytes: field(
+                "max_input_bytes",
+                self.max_input_bytes,
+                defaults.max_input_bytes,
+                |value| integer_in(value, 1..=i64::MAX),
+            ),
+            image_size: field("image_size", self.image_size, defaults.image_size, |value| {
+                integer_in(value, IMAGE_SIZES)
+            }),
+            password_policy: PasswordPolicy {
+                min_length: field(
+                    "min_password_length",
+                    self.min_password_length,
+                    defaults.password_policy.min_length,
+                    |value| integer_in(value, 1..=MAX_PASSWORD_LENGTH as i64),
+                ),
+                min_entropy_bits: field(
+                    "min_password_bits",
+                    self.min_password_bits,
+                    defaults.password_policy.min_entropy_bits,
+                    |value| {
+                        let bits = value.as_float().or_else(|| value.as_integer().map(|i| i as f64))?;
+                        (0.0..=MAX_PASSWORD_BITS).contains(&bits).then_some(bits)
+                    },
+                ),
+            },
+        }
+    }
+}
+
+/// `parse`s a value that is present, or warns and returns `default` if it
+/// can't.
+fn field<T>(
+    key: &str,
+    value: Option<toml::Value>,
+    default: T,
+    parse: impl FnOnce(&toml::Value) -> Option<T>,
+) -> T {
+    let Some(value) = value else {
+        return default;
+    };
+    parse(&value).unwrap_or_else(|| {
+        tracing::warn!("Ignoring invalid value {} = {} in the config file", key, value);
+        default
+    })
+}
+
+fn integer_in<T: TryFrom<i64>>(value: &toml::Value, range: RangeInclusive<i64>) -> Option<T> {
+    value
+        .as_integer()
+        .filter(|value| range.contains(value))
+        .and_then(|value| T::try_from(value).ok())
+}
+
+/// Validates the contents of a config file, see the module documentation.
+/// Only text that isn't TOML at all fails; bad values fall back one by one.
+pub fn parse(contents: &str) -> Result<AppConfig, toml::de::Error> {
+    toml::from_str::<RawConfig>(contents).map(RawConfig::validate)
+}
+
+/// Loads the config file at `path`. Only a file given `explicit`ly is
+/// expected to exist; anything that can't be read yields the defaults.
+pub fn load(path: &Path, explicit: bool) -> AppConfig {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound && !explicit => {
+            return AppConfig::default()
+        }
+        Err(e) => {
+            tracing::warn!("Could not read config file {}: {}", path.display(), e);
+            return AppConfig::default();
+        }
+    };
+
+    parse(&contents).unwrap_or_else(|e| {
+        tracing::warn!("Ignoring invalid config file {}: {}", path.display(), e);
+        AppConfig::default()
+    })
+}
@@ -1,9 +1,32 @@
 // src/crypto/mod.rs
+pub mod passphrase;
+pub mod password;
+mod wordlist;
+
+// libsodium where it builds; the browser build gets byte-compatible
+// RustCrypto primitives instead.
+#[cfg(not(target_arch = "wasm32"))]
+mod sodium;
+#[cfg(not(target_arch = "wasm32"))]
+use sodium as backend;
+#[cfg(target_arch = "wasm32")]
+mod rust_crypto;
+#[cfg(target_arch = "wasm32")]
+use rust_crypto as backend;
+
 pub mod crypto_utils {
-    use sodiumoxide::crypto::{pwhash, secretbox};
+    use super::backend::{self, randombytes, Nonce, CHACHA_NONCE_LEN};
+    use std::fmt;
     use thiserror::Error;
+    use web_time::Instant;
+    use zeroize::Zeroizing;
 
-    #[derive(Error, Debug)]
+    pub use super::backend::{
+        gen_key, memcmp, Key, PublicKey, Salt, SecretKey, KEY_LEN, MAC_LEN, NONCE_LEN, SALT_LEN,
+        SEAL_LEN,
+    };
+
+    #[derive(Error, Debug, Clone)]
     pub enum CryptoError {
         #[error("Key derivation failed")]
         KeyDerivationFailed,
@@ -15,53 +38,280 @@ pub mod crypto_utils {
         InvalidSalt,
         #[error("Invalid password")]
         InvalidPassword,
+        #[error("This payload was not encrypted for this key pair")]
+        WrongRecipient,
+        #[error("The cryptography library could not be initialized on this system")]
+        InitFailed,
+        #[error("The checksum of the data could not be computed")]
+        DigestFailed,
+    }
+
+    /// Initializes libsodium. Only the first call does the work; later ones
+    /// return its result, so every operation can check cheaply. Applications
+    /// should call this once at startup to report a failure up front.
+    pub fn init() -> Result<(), CryptoError> {
+        static INITIALIZED: std::sync::OnceLock<bool> = std::sync::OnceLock::new();
+        if *INITIALIZED.get_or_init(|| backend::init().is_ok()) {
+            Ok(())
+        } else {
+            Err(CryptoError::InitFailed)
+        }
+    }
+
+    pub fn generate_salt() -> Salt {
+        backend::gen_salt()
+    }
+
+    /// Argon2 cost parameters. Payloads from format version 7 on record
+    /// the ones they were encrypted with; older ones only decrypt with the
+    /// same limits configured.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct KdfLimits {
+        /// Passes over the memory, libsodium's `opslimit`.
+        pub ops_limit: u32,
+        /// Memory in KiB.
+        pub mem_limit_kib: u32,
+    }
+
+    impl KdfLimits {
+        /// libsodium's `OPSLIMIT_MODERATE` / `MEMLIMIT_MODERATE` (128 MiB).
+        pub const MODERATE: KdfLimits = KdfLimits {
+            ops_limit: 6,
+            mem_limit_kib: 128 * 1024,
+        };
+        /// Smallest values libsodium's Argon2i accepts.
+        pub const MIN_OPS_LIMIT: u32 = 3;
+        pub const MIN_MEM_LIMIT_KIB: u32 = 8;
+        /// Largest values a payload may ask a decoder to spend; up to 4 GiB.
+        pub const MAX_OPS_LIMIT: u32 = 64;
+        pub const MAX_MEM_LIMIT_KIB: u32 = 4 * 1024 * 1024;
+
+        /// Whether both limits lie between the `MIN_` and `MAX_` constants.
+        pub fn is_supported(self) -> bool {
+            (Self::MIN_OPS_LIMIT..=Self::MAX_OPS_LIMIT).contains(&self.ops_limit)
+                && (Self::MIN_MEM_LIMIT_KIB..=Self::MAX_MEM_LIMIT_KIB).contains(&self.mem_limit_kib)
+        }
     }
 
-    pub fn init() {
-        sodiumoxide::init().expect("Failed to initialize sodiumoxide");
+    impl Default for KdfLimits {
+        fn default() -> Self {
+            Self::MODERATE
+        }
+    }
+
+    pub fn derive_key(password: &str, salt: &Salt) -> Result<Key, CryptoError> {
+        derive_key_with_keyfile(password, None, salt)
     }
 
-    pub fn generate_salt() -> pwhash::argon2i13::Salt {
-        pwhash::argon2i13::gen_salt()
+    /// Like [`derive_key`], but mixes a keyfile into the KDF input: the
+    /// BLAKE2b hash of the keyfile is appended to the password before Argon2
+    /// runs, so both are needed to recover the key.
+    pub fn derive_key_with_keyfile(
+        password: &str,
+        keyfile: Option<&[u8]>,
+        salt: &Salt,
+    ) -> Result<Key, CryptoError> {
+        derive_key_with_limits(password, keyfile, salt, KdfLimits::MODERATE)
     }
 
-    pub fn derive_key(password: &str, salt: &pwhash::argon2i13::Salt) -> Result<secretbox::Key, CryptoError> {
+    /// Like [`derive_key_with_keyfile`], with other Argon2 limits than
+    /// [`KdfLimits::MODERATE`].
+    pub fn derive_key_with_limits(
+        password: &str,
+        keyfile: Option<&[u8]>,
+        salt: &Salt,
+        limits: KdfLimits,
+    ) -> Result<Key, CryptoError> {
         if password.is_empty() {
             return Err(CryptoError::InvalidPassword);
         }
 
-        let mut key_bytes = [0u8; secretbox::KEYBYTES];
-        pwhash::argon2i13::derive_key(
-            &mut key_bytes,
-            password.as_bytes(),
-            salt,
-            pwhash::argon2i13::OPSLIMIT_MODERATE,
-            pwhash::argon2i13::MEMLIMIT_MODERATE,
-        )
+        let mut input = Zeroizing::new(password.as_bytes().to_vec());
+        if let Some(keyfile) = keyfile {
+            let digest = backend::blake2b_512(keyfile).map_err(|_| CryptoError::KeyDerivationFailed)?;
+            input.extend_from_slice(&digest);
+        }
+
+        let started = Instant::now();
+        let mut key_bytes = Zeroizing::new([0u8; KEY_LEN]);
+        backend::argon2i13(&mut key_bytes[..], &input, salt, limits.ops_limit, limits.mem_limit_kib)
+            .map_err(|_| CryptoError::KeyDerivationFailed)?;
+        tracing::debug!(
+            elapsed_ms = started.elapsed().as_millis() as u64,
+            keyfile = keyfile.is_some(),
+            ops_limit = limits.ops_limit,
+            mem_limit_kib = limits.mem_limit_kib,
+            "derived key"
+        );
+
+        // `Key` wipes itself on drop.
+        Ok(Key(*key_bytes))
+    }
+
+    /// Length of the value returned by [`key_check`].
+    pub const KEY_CHECK_LEN: usize = 8;
+
+    /// Short value derived from `key` with keyed BLAKE2b. Stored next to the
+    /// salt, it tells a wrong password apart from damaged ciphertext. It
+    /// reveals nothing the MAC doesn't: checking a guess still needs Argon2.
+    pub fn key_check(key: &Key) -> Result<[u8; KEY_CHECK_LEN], CryptoError> {
+        let digest = backend::blake2b_128_keyed(b"QRDX key check", &key.0)
             .map_err(|_| CryptoError::KeyDerivationFailed)?;
 
-        Ok(secretbox::Key(key_bytes))
+        let mut check = [0u8; KEY_CHECK_LEN];
+        check.copy_from_slice(&digest[..KEY_CHECK_LEN]);
+        Ok(check)
+    }
+
+    /// Length of the value returned by [`digest`].
+    pub const DIGEST_LEN: usize = 32;
+
+    /// BLAKE2b-256 of `data`. Payloads carry it for the original data, so a
+    /// decoded file can be checked against what was encoded.
+    pub fn digest(data: &[u8]) -> Result<[u8; DIGEST_LEN], CryptoError> {
+        backend::blake2b_256(data).map_err(|_| CryptoError::DigestFailed)
+    }
+
+    pub fn encrypt(data: &[u8], key: &Key) -> Result<Vec<u8>, CryptoError> {
+        encrypt_with_nonce(data, key, &backend::gen_nonce())
     }
 
-    pub fn encrypt(data: &[u8], key: &secretbox::Key) -> Result<Vec<u8>, CryptoError> {
-        let nonce = secretbox::gen_nonce();
-        let ciphertext = secretbox::seal(data, &nonce, key);
+    /// Like [`encrypt`], but with a caller-chosen nonce.
+    ///
+    /// **Never use the same nonce twice with the same key.** Two messages
+    /// sealed that way leak the XOR of their plaintexts and let an attacker
+    /// forge messages. This exists for reproducible test output only.
+    pub fn encrypt_with_nonce(
+        data: &[u8],
+        key: &Key,
+        nonce: &Nonce,
+    ) -> Result<Vec<u8>, CryptoError> {
+        let ciphertext = backend::secretbox_seal(data, nonce, key);
 
         let mut result = nonce.0.to_vec();
         result.extend_from_slice(&ciphertext);
         Ok(result)
     }
 
-    pub fn decrypt(encrypted_data: &[u8], key: &secretbox::Key) -> Result<Vec<u8>, CryptoError> {
-        if encrypted_data.len() < secretbox::NONCEBYTES {
+    pub fn decrypt(encrypted_data: &[u8], key: &Key) -> Result<Vec<u8>, CryptoError> {
+        if encrypted_data.len() < NONCE_LEN {
             return Err(CryptoError::DecryptionFailed);
         }
 
-        let nonce = secretbox::Nonce::from_slice(&encrypted_data[..secretbox::NONCEBYTES])
+        let nonce = Nonce::from_slice(&encrypted_data[..NONCE_LEN])
             .ok_or(CryptoError::DecryptionFailed)?;
 
-        let ciphertext = &encrypted_data[secretbox::NONCEBYTES..];
+        let ciphertext = &encrypted_data[NONCE_LEN..];
+
+        backend::secretbox_open(ciphertext, &nonce, key).map_err(|_| CryptoError::DecryptionFailed)
+    }
+
+    /// Authenticated cipher a password-protected payload is encrypted with.
+    /// Both take the same 32-byte key; the nonce is prepended to the ciphertext.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub enum Cipher {
+        /// XSalsa20-Poly1305 (`secretbox`), the layout of PyNaCl's `SecretBox`.
+        #[default]
+        XSalsa20Poly1305,
+        /// ChaCha20-Poly1305 as in RFC 8439, with a 12-byte nonce.
+        ChaCha20Poly1305,
+    }
+
+    impl Cipher {
+        pub const ALL: [Cipher; 2] = [Cipher::XSalsa20Poly1305, Cipher::ChaCha20Poly1305];
+
+        /// Id stored in the payload header.
+        pub fn id(self) -> u8 {
+            match self {
+                Cipher::XSalsa20Poly1305 => 0,
+                Cipher::ChaCha20Poly1305 => 1,
+            }
+        }
+
+        pub fn from_id(id: u8) -> Option<Self> {
+            Self::ALL.into_iter().find(|cipher| cipher.id() == id)
+        }
+
+        pub fn nonce_len(self) -> usize {
+            match self {
+                Cipher::XSalsa20Poly1305 => NONCE_LEN,
+                Cipher::ChaCha20Poly1305 => CHACHA_NONCE_LEN,
+            }
+        }
+
+        /// Encrypts with a fresh random nonce.
+        pub fn encrypt(self, data: &[u8], key: &Key) -> Result<Vec<u8>, CryptoError> {
+            match self {
+                Cipher::XSalsa20Poly1305 => encrypt(data, key),
+                Cipher::ChaCha20Poly1305 => {
+                    self.encrypt_with_nonce(data, key, &randombytes(CHACHA_NONCE_LEN))
+                }
+            }
+        }
+
+        /// Like [`Cipher::encrypt`], with a caller-chosen nonce of
+        /// [`Cipher::nonce_len`] bytes. The same warning as for
+        /// [`encrypt_with_nonce`] applies.
+        pub fn encrypt_with_nonce(
+            self,
+            data: &[u8],
+            key: &Key,
+            nonce: &[u8],
+        ) -> Result<Vec<u8>, CryptoError> {
+            match self {
+                Cipher::XSalsa20Poly1305 => {
+                    let nonce = Nonce::from_slice(nonce).ok_or(CryptoError::EncryptionFailed)?;
+                    encrypt_with_nonce(data, key, &nonce)
+                }
+                Cipher::ChaCha20Poly1305 => {
+                    let ciphertext = backend::chacha_seal(data, nonce, key)
+                        .map_err(|_| CryptoError::EncryptionFailed)?;
+
+                    let mut result = nonce.to_vec();
+                    result.extend_from_slice(&ciphertext);
+                    Ok(result)
+                }
+            }
+        }
+
+        pub fn decrypt(self, encrypted_data: &[u8], key: &Key) -> Result<Vec<u8>, CryptoError> {
+            match self {
+                Cipher::XSalsa20Poly1305 => decrypt(encrypted_data, key),
+                Cipher::ChaCha20Poly1305 => {
+                    if encrypted_data.len() < CHACHA_NONCE_LEN {
+                        return Err(CryptoError::DecryptionFailed);
+                    }
+                    let (nonce, ciphertext) = encrypted_data.split_at(CHACHA_NONCE_LEN);
+
+                    backend::chacha_open(ciphertext, nonce, key)
+                        .map_err(|_| CryptoError::DecryptionFailed)
+                }
+            }
+        }
+    }
+
+    impl fmt::Display for Cipher {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str(match self {
+                Cipher::XSalsa20Poly1305 => "XSalsa20-Poly1305",
+                Cipher::ChaCha20Poly1305 => "ChaCha20-Poly1305",
+            })
+        }
+    }
+
+    pub fn generate_keypair() -> (PublicKey, SecretKey) {
+        backend::gen_keypair()
+    }
+
+    pub fn seal(data: &[u8], recipient: &PublicKey) -> Vec<u8> {
+        backend::seal(data, recipient)
+    }
 
-        secretbox::open(ciphertext, &nonce, key).map_err(|_| CryptoError::DecryptionFailed)
+    pub fn open_sealed(
+        sealed: &[u8],
+        public_key: &PublicKey,
+        secret_key: &SecretKey,
+    ) -> Result<Vec<u8>, CryptoError> {
+        backend::open_sealed(sealed, public_key, secret_key).map_err(|_| CryptoError::WrongRecipient)
     }
 }
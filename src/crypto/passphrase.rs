@@ -0,0 +1,99 @@
+// src/crypto/passphrase.rs
+//! Random passwords from the system's secure random source.
+//!
+//! Passwords are limited to [`MAX_PASSWORD_LENGTH`] characters for the Python
+//! tool's sake, which is too short for whole words. Word passphrases therefore
+//! use the first four letters of words from the BIP-39 list, each capitalized,
+//! e.g. `TigeOrbiFlasKitcRamp`; the four letters identify the word, so the
+//! phrase can be remembered as the full words.
+use super::backend::randombytes;
+use super::crypto_utils::CryptoError;
+use super::password::MAX_PASSWORD_LENGTH;
+use super::wordlist::WORDS;
+use zeroize::Zeroizing;
+
+/// Least entropy of a style on offer, in bits; five words.
+pub const MIN_ENTROPY_BITS: f64 = 55.0;
+
+/// Letters kept of each word.
+const WORD_PREFIX_LEN: usize = 4;
+const ALPHANUMERIC: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PassphraseStyle {
+    /// This many words.
+    Words(usize),
+    /// This many letters and digits.
+    Alphanumeric(usize),
+}
+
+impl PassphraseStyle {
+    /// The styles on offer; all of them fit into [`MAX_PASSWORD_LENGTH`] and
+    /// have at least [`MIN_ENTROPY_BITS`]. Fewer than five words would fall
+    /// below that.
+    pub const ALL: [PassphraseStyle; 4] = [
+        PassphraseStyle::Words(5),
+        PassphraseStyle::Alphanumeric(12),
+        PassphraseStyle::Alphanumeric(16),
+        PassphraseStyle::Alphanumeric(20),
+    ];
+
+    /// Bits of entropy of a password in this style: the log2 of the number
+    /// of possible passwords.
+    pub fn entropy_bits(self) -> f64 {
+        match self {
+            PassphraseStyle::Words(count) => count as f64 * (WORDS.len() as f64).log2(),
+            PassphraseStyle::Alphanumeric(len) => len as f64 * (ALPHANUMERIC.len() as f64).log2(),
+        }
+    }
+
+    /// Longest password this style can produce.
+    pub fn max_len(self) -> usize {
+        match self {
+            PassphraseStyle::Words(count) => count * WORD_PREFIX_LEN,
+            PassphraseStyle::Alphanumeric(len) => len,
+        }
+    }
+}
+
+impl Default for PassphraseStyle {
+    fn default() -> Self {
+        PassphraseStyle::Words(MAX_PASSWORD_LENGTH / WORD_PREFIX_LEN)
+    }
+}
+
+/// Generates a password in `style`.
+pub fn generate(style: PassphraseStyle) -> Result<Zeroizing<String>, CryptoError> {
+    super::crypto_utils::init()?;
+    let mut password = Zeroizing::new(String::with_capacity(style.max_len()));
+    match style {
+        PassphraseStyle::Words(count) => {
+            for _ in 0..count {
+                let word = WORDS[random_below(WORDS.len())];
+                let mut letters = word.chars().take(WORD_PREFIX_LEN);
+                password.extend(letters.next().map(|c| c.to_ascii_uppercase()));
+                password.extend(letters);
+            }
+        }
+        PassphraseStyle::Alphanumeric(len) => {
+            for _ in 0..len {
+                password.push(char::from(ALPHANUMERIC[random_below(ALPHANUMERIC.len())]));
+            }
+        }
+    }
+    Ok(password)
+}
+
+/// Uniform random number below `bound`, which must be at most 65536.
+/// Values from the uneven top of the range are drawn again.
+fn random_below(bound: usize) -> usize {
+    debug_assert!(bound > 0 && bound <= 1 << 16);
+    let limit = (1 << 16) / bound * bound;
+    loop {
+        let bytes = Zeroizing::new(randombytes(2));
+        let value = usize::from(u16::from_be_bytes([bytes[0], bytes[1]]));
+        if value < limit {
+            return value % bound;
+        }
+    }
+}
@@ -0,0 +1,108 @@
+// src/crypto/password.rs
+//! Minimum requirements for passwords typed in the GUI and the rough strength
+//! estimate behind its meter.
+use thiserror::Error;
+
+/// Maximum password length accepted by the GUI. PyQrDataExchange uses the same
+/// limit, so codes stay exchangeable between both tools.
+pub const MAX_PASSWORD_LENGTH: usize = 20;
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum PolicyViolation {
+    #[error("Password must be at least {0} characters long")]
+    TooShort(usize),
+    #[error("Password is too predictable ({actual:.0} of {required:.0} bits)")]
+    TooWeak { actual: f64, required: f64 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Strength {
+    VeryWeak,
+    Weak,
+    Fair,
+    Strong,
+}
+
+impl Strength {
+    pub fn from_entropy(bits: f64) -> Self {
+        match bits {
+            b if b < 28.0 => Strength::VeryWeak,
+            b if b < 36.0 => Strength::Weak,
+            b if b < 60.0 => Strength::Fair,
+            _ => Strength::Strong,
+        }
+    }
+}
+
+/// Minimum requirements a password has to meet before data is encrypted with it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub min_entropy_bits: f64,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            min_length: 8,
+            min_entropy_bits: 35.0,
+        }
+    }
+}
+
+impl PasswordPolicy {
+    pub fn check(&self, password: &str) -> Result<(), PolicyViolation> {
+        if password.chars().count() < self.min_length {
+            return Err(PolicyViolation::TooShort(self.min_length));
+        }
+
+        let actual = estimate_entropy(password);
+        if actual < self.min_entropy_bits {
+            return Err(PolicyViolation::TooWeak {
+                actual,
+                required: self.min_entropy_bits,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Rough entropy estimate in bits: the size of the character pool in use
+/// raised to the number of characters, where characters that repeat or
+/// continue a run (`aaa`, `abc`, `321`) only count a quarter.
+pub fn estimate_entropy(password: &str) -> f64 {
+    let mut pool = 0u32;
+    let chars: Vec<char> = password.chars().collect();
+
+    if chars.iter().any(|c| c.is_ascii_lowercase()) {
+        pool += 26;
+    }
+    if chars.iter().any(|c| c.is_ascii_uppercase()) {
+        pool += 26;
+    }
+    if chars.iter().any(|c| c.is_ascii_digit()) {
+        pool += 10;
+    }
+    if chars.iter().any(|c| c.is_ascii_punctuation() || *c == ' ') {
+        pool += 33;
+    }
+    if chars.iter().any(|c| !c.is_ascii()) {
+        pool += 100;
+    }
+
+    if pool == 0 {
+        return 0.0;
+    }
+
+    let mut effective_length = 0.0;
+    for (i, c) in chars.iter().enumerate() {
+        let predictable = i > 0 && {
+            let step = *c as i64 - chars[i - 1] as i64;
+            (-1..=1).contains(&step)
+        };
+        effective_length += if predictable { 0.25 } else { 1.0 };
+    }
+
+    effective_length * f64::from(pool).log2()
+}
@@ -0,0 +1,205 @@
+// src/crypto/rust_crypto.rs
+//! Pure-Rust backend for wasm32, where libsodium isn't available. Every
+//! function produces the same bytes as its counterpart in `sodium.rs`.
+use argon2::{Algorithm, Argon2, Params, Version};
+use blake2::digest::{consts::U16, consts::U32, Digest, KeyInit, Mac};
+use blake2::{Blake2b, Blake2b512, Blake2bMac};
+use chacha20poly1305::aead::{Aead, OsRng};
+use chacha20poly1305::ChaCha20Poly1305;
+use crypto_secretbox::XSalsa20Poly1305;
+use std::fmt;
+use zeroize::Zeroize;
+
+pub const SALT_LEN: usize = 16;
+pub const KEY_LEN: usize = 32;
+pub const NONCE_LEN: usize = 24;
+pub const MAC_LEN: usize = 16;
+pub const CHACHA_NONCE_LEN: usize = 12;
+/// Ephemeral public key and MAC in front of a sealed box.
+pub const SEAL_LEN: usize = 32 + MAC_LEN;
+const PUBLIC_KEY_LEN: usize = 32;
+
+/// Defines a fixed-size byte newtype with the parts of sodiumoxide's API the
+/// rest of the crate uses.
+macro_rules! byte_newtype {
+    ($name:ident, $len:expr) => {
+        #[derive(Clone, PartialEq, Eq)]
+        pub struct $name(pub [u8; $len]);
+
+        impl $name {
+            pub fn from_slice(bytes: &[u8]) -> Option<Self> {
+                bytes.try_into().ok().map(Self)
+            }
+        }
+
+        impl AsRef<[u8]> for $name {
+            fn as_ref(&self) -> &[u8] {
+                &self.0
+            }
+        }
+    };
+}
+
+byte_newtype!(Salt, SALT_LEN);
+byte_newtype!(Nonce, NONCE_LEN);
+byte_newtype!(PublicKey, PUBLIC_KEY_LEN);
+byte_newtype!(Key, KEY_LEN);
+byte_newtype!(SecretKey, PUBLIC_KEY_LEN);
+
+impl fmt::Debug for Salt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Salt").field(&self.0).finish()
+    }
+}
+
+impl fmt::Debug for Nonce {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Nonce").field(&self.0).finish()
+    }
+}
+
+impl fmt::Debug for PublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("PublicKey").field(&self.0).finish()
+    }
+}
+
+// Like sodiumoxide's, secret keys wipe themselves and never print.
+impl Drop for Key {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl Drop for SecretKey {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl fmt::Debug for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Key(****)")
+    }
+}
+
+impl fmt::Debug for SecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SecretKey(****)")
+    }
+}
+
+pub fn init() -> Result<(), ()> {
+    Ok(())
+}
+
+pub fn randombytes(len: usize) -> Vec<u8> {
+    let mut bytes = vec![0u8; len];
+    getrandom::getrandom(&mut bytes).expect("No secure random source available");
+    bytes
+}
+
+fn random_array<const N: usize>() -> [u8; N] {
+    let mut bytes = [0u8; N];
+    getrandom::getrandom(&mut bytes).expect("No secure random source available");
+    bytes
+}
+
+/// Compares in constant time, like libsodium's `sodium_memcmp`.
+pub fn memcmp(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+pub fn gen_salt() -> Salt {
+    Salt(random_array())
+}
+
+pub fn gen_key() -> Key {
+    Key(random_array())
+}
+
+pub fn gen_nonce() -> Nonce {
+    Nonce(random_array())
+}
+
+/// Argon2i v1.3 with one lane, as libsodium runs it.
+pub fn argon2i13(
+    out: &mut [u8],
+    input: &[u8],
+    salt: &Salt,
+    ops_limit: u32,
+    mem_limit_kib: u32,
+) -> Result<(), ()> {
+    let params = Params::new(mem_limit_kib, ops_limit, 1, Some(out.len())).map_err(|_| ())?;
+    Argon2::new(Algorithm::Argon2i, Version::V0x13, params)
+        .hash_password_into(input, &salt.0, out)
+        .map_err(|_| ())
+}
+
+/// Unkeyed BLAKE2b-512.
+pub fn blake2b_512(data: &[u8]) -> Result<[u8; 64], ()> {
+    Ok(Blake2b512::digest(data).into())
+}
+
+/// Unkeyed BLAKE2b-256.
+pub fn blake2b_256(data: &[u8]) -> Result<[u8; 32], ()> {
+    Ok(Blake2b::<U32>::digest(data).into())
+}
+
+/// BLAKE2b-128 keyed with `key`.
+pub fn blake2b_128_keyed(data: &[u8], key: &[u8]) -> Result<[u8; 16], ()> {
+    let mut mac = <Blake2bMac<U16> as KeyInit>::new_from_slice(key).map_err(|_| ())?;
+    mac.update(data);
+    Ok(mac.finalize().into_bytes().into())
+}
+
+/// XSalsa20-Poly1305; returns MAC || ciphertext, as `crypto_secretbox_easy`.
+pub fn secretbox_seal(data: &[u8], nonce: &Nonce, key: &Key) -> Vec<u8> {
+    XSalsa20Poly1305::new(&key.0.into())
+        .encrypt(&nonce.0.into(), data)
+        .expect("Plaintext exceeds the XSalsa20 stream")
+}
+
+pub fn secretbox_open(sealed: &[u8], nonce: &Nonce, key: &Key) -> Result<Vec<u8>, ()> {
+    XSalsa20Poly1305::new(&key.0.into())
+        .decrypt(&nonce.0.into(), sealed)
+        .map_err(|_| ())
+}
+
+/// ChaCha20-Poly1305 (IETF); returns ciphertext || MAC.
+pub fn chacha_seal(data: &[u8], nonce: &[u8], key: &Key) -> Result<Vec<u8>, ()> {
+    let nonce: [u8; CHACHA_NONCE_LEN] = nonce.try_into().map_err(|_| ())?;
+    ChaCha20Poly1305::new(&key.0.into())
+        .encrypt(&nonce.into(), data)
+        .map_err(|_| ())
+}
+
+pub fn chacha_open(sealed: &[u8], nonce: &[u8], key: &Key) -> Result<Vec<u8>, ()> {
+    let nonce: [u8; CHACHA_NONCE_LEN] = nonce.try_into().map_err(|_| ())?;
+    ChaCha20Poly1305::new(&key.0.into())
+        .decrypt(&nonce.into(), sealed)
+        .map_err(|_| ())
+}
+
+pub fn gen_keypair() -> (PublicKey, SecretKey) {
+    let secret_key = crypto_box::SecretKey::generate(&mut OsRng);
+    (
+        PublicKey(*secret_key.public_key().as_bytes()),
+        SecretKey(secret_key.to_bytes()),
+    )
+}
+
+/// `crypto_box_seal`.
+pub fn seal(data: &[u8], recipient: &PublicKey) -> Vec<u8> {
+    crypto_box::PublicKey::from(recipient.0)
+        .seal(&mut OsRng, data)
+        .expect("Plaintext exceeds the XSalsa20 stream")
+}
+
+/// The public key is derived from the secret key here; the parameter only
+/// mirrors `crypto_box_seal_open`.
+pub fn open_sealed(sealed: &[u8], _public_key: &PublicKey, secret_key: &SecretKey) -> Result<Vec<u8>, ()> {
+    crypto_box::SecretKey::from(secret_key.0)
+        .unseal(sealed)
+        .map_err(|_| ())
+}
@@ -0,0 +1,102 @@
+// src/crypto/sodium.rs
+//! libsodium backend, used everywhere but in the browser.
+use sodiumoxide::crypto::aead::chacha20poly1305_ietf as chacha;
+use sodiumoxide::crypto::{box_, generichash, pwhash::argon2i13, sealedbox, secretbox};
+
+pub use box_::{PublicKey, SecretKey};
+pub use secretbox::{Key, Nonce};
+pub use argon2i13::Salt;
+pub use sodiumoxide::randombytes::randombytes;
+pub use sodiumoxide::utils::memcmp;
+
+pub const SALT_LEN: usize = argon2i13::SALTBYTES;
+pub const KEY_LEN: usize = secretbox::KEYBYTES;
+pub const NONCE_LEN: usize = secretbox::NONCEBYTES;
+pub const MAC_LEN: usize = secretbox::MACBYTES;
+pub const CHACHA_NONCE_LEN: usize = chacha::NONCEBYTES;
+pub const SEAL_LEN: usize = sealedbox::SEALBYTES;
+
+pub fn init() -> Result<(), ()> {
+    sodiumoxide::init()
+}
+
+pub fn gen_salt() -> Salt {
+    argon2i13::gen_salt()
+}
+
+pub fn gen_key() -> Key {
+    secretbox::gen_key()
+}
+
+pub fn gen_nonce() -> Nonce {
+    secretbox::gen_nonce()
+}
+
+/// Argon2i v1.3 with `ops_limit` passes over `mem_limit_kib` KiB.
+pub fn argon2i13(
+    out: &mut [u8],
+    input: &[u8],
+    salt: &Salt,
+    ops_limit: u32,
+    mem_limit_kib: u32,
+) -> Result<(), ()> {
+    argon2i13::derive_key(
+        out,
+        input,
+        salt,
+        argon2i13::OpsLimit(ops_limit as usize),
+        argon2i13::MemLimit(mem_limit_kib as usize * 1024),
+    )
+    .map(|_| ())
+}
+
+/// Unkeyed BLAKE2b-512.
+pub fn blake2b_512(data: &[u8]) -> Result<[u8; 64], ()> {
+    let digest = generichash::hash(data, Some(64), None)?;
+    digest.as_ref().try_into().map_err(|_| ())
+}
+
+/// Unkeyed BLAKE2b-256.
+pub fn blake2b_256(data: &[u8]) -> Result<[u8; 32], ()> {
+    let digest = generichash::hash(data, Some(32), None)?;
+    digest.as_ref().try_into().map_err(|_| ())
+}
+
+/// BLAKE2b-128 keyed with `key`.
+pub fn blake2b_128_keyed(data: &[u8], key: &[u8]) -> Result<[u8; 16], ()> {
+    let digest = generichash::hash(data, Some(16), Some(key))?;
+    digest.as_ref().try_into().map_err(|_| ())
+}
+
+/// XSalsa20-Poly1305; returns MAC || ciphertext.
+pub fn secretbox_seal(data: &[u8], nonce: &Nonce, key: &Key) -> Vec<u8> {
+    secretbox::seal(data, nonce, key)
+}
+
+pub fn secretbox_open(sealed: &[u8], nonce: &Nonce, key: &Key) -> Result<Vec<u8>, ()> {
+    secretbox::open(sealed, nonce, key)
+}
+
+/// ChaCha20-Poly1305 (IETF); returns ciphertext || MAC.
+pub fn chacha_seal(data: &[u8], nonce: &[u8], key: &Key) -> Result<Vec<u8>, ()> {
+    let nonce = chacha::Nonce::from_slice(nonce).ok_or(())?;
+    Ok(chacha::seal(data, None, &nonce, &chacha::Key(key.0)))
+}
+
+pub fn chacha_open(sealed: &[u8], nonce: &[u8], key: &Key) -> Result<Vec<u8>, ()> {
+    let nonce = chacha::Nonce::from_slice(nonce).ok_or(())?;
+    chacha::open(sealed, None, &nonce, &chacha::Key(key.0))
+}
+
+pub fn gen_keypair() -> (PublicKey, SecretKey) {
+    box_::gen_keypair()
+}
+
+/// `crypto_box_seal`.
+pub fn seal(data: &[u8], recipient: &PublicKey) -> Vec<u8> {
+    sealedbox::seal(data, recipient)
+}
+
+pub fn open_sealed(sealed: &[u8], public_key: &PublicKey, secret_key: &SecretKey) -> Result<Vec<u8>, ()> {
+    sealedbox::open(sealed, public_key, secret_key)
+}
@@ -0,0 +1,110 @@
+// src/error.rs
+use crate::crypto::crypto_utils::CryptoError;
+#[cfg(feature = "gui")]
+use crate::keys::KeyError;
+use crate::qr::archive::ArchiveError;
+use crate::qr::chunk::ChunkError;
+use crate::qr::fountain::FountainError;
+use crate::qr::processor::QrProcessorError;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::qr::stream::StreamError;
+#[cfg(feature = "gui")]
+use crate::qr::service::QrServiceError;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use thiserror::Error;
+
+/// Top-level error of the library API.
+///
+/// Cloneable so it can travel through GUI messages; IO errors are shared
+/// behind an [`Arc`] for that reason.
+#[derive(Error, Debug, Clone)]
+pub enum Error {
+    #[error(transparent)]
+    Crypto(#[from] CryptoError),
+    #[error(transparent)]
+    Processor(#[from] QrProcessorError),
+    #[cfg(feature = "gui")]
+    #[error(transparent)]
+    Service(#[from] QrServiceError),
+    #[cfg(feature = "gui")]
+    #[error(transparent)]
+    Key(#[from] KeyError),
+    #[error(transparent)]
+    Archive(#[from] ArchiveError),
+    #[error(transparent)]
+    Chunk(#[from] ChunkError),
+    #[error(transparent)]
+    Fountain(#[from] FountainError),
+    #[error("Only {recovered} of {total} blocks of the payload could be recovered; more frames are needed")]
+    IncompleteFrames { recovered: usize, total: usize },
+    #[error("IO error: {0}")]
+    Io(Arc<std::io::Error>),
+    #[error("{} does not exist", path.display())]
+    NotFound { path: PathBuf },
+    /// The folder of the file is gone as well, or the drive reports that it
+    /// isn't ready.
+    #[error("{} can't be reached; is the drive or network share it is on still connected?", path.display())]
+    DriveUnavailable { path: PathBuf },
+    #[error("No permission to read {}", path.display())]
+    PermissionDenied { path: PathBuf },
+    #[error("Payload is too large for a single QR code ({size} of at most {limit} bytes)")]
+    PayloadTooLarge { size: usize, limit: usize },
+    #[error("File is too large ({size} of at most {limit} bytes)")]
+    FileTooLarge { size: u64, limit: u64 },
+    #[error("Could not write {}: {source}", path.display())]
+    SaveFailed {
+        path: PathBuf,
+        source: Arc<std::io::Error>,
+    },
+}
+
+/// `ERROR_NOT_READY`, e.g. for a card reader without a card.
+#[cfg(windows)]
+const ERROR_NOT_READY: i32 = 21;
+
+impl Error {
+    /// Sorts an error from opening or reading `path` by its cause, so callers
+    /// can tell the user what to do. Other causes stay [`Error::Io`].
+    pub fn read_failed(path: impl AsRef<Path>, e: io::Error) -> Self {
+        let path = path.as_ref().to_path_buf();
+        #[cfg(windows)]
+        if e.raw_os_error() == Some(ERROR_NOT_READY) {
+            return Error::DriveUnavailable { path };
+        }
+
+        match e.kind() {
+            io::ErrorKind::NotFound => {
+                let folder_exists = path
+                    .parent()
+                    .is_none_or(|dir| dir.as_os_str().is_empty() || dir.exists());
+                if folder_exists {
+                    Error::NotFound { path }
+                } else {
+                    Error::DriveUnavailable { path }
+                }
+            }
+            io::ErrorKind::PermissionDenied => Error::PermissionDenied { path },
+            _ => e.into(),
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(Arc::new(e))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl From<StreamError> for Error {
+    fn from(e: StreamError) -> Self {
+        match e {
+            StreamError::Processor(e) => e.into(),
+            StreamError::Io(e) => e.into(),
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
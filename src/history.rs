@@ -0,0 +1,155 @@
+// src/history.rs
+//! Generated and decoded payloads of the session. Entries hold the payload
+//! text, which is encrypted, and some metadata, never a password or
+//! plaintext. They are only written to disk when the user opts in.
+use crate::settings;
+use qr_data_exchange::qr::pdf;
+use qr_data_exchange::services;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::Mutex;
+
+const HISTORY_FILE: &str = "history.toml";
+
+/// Generation handed to the next [`save`] or [`remove`].
+static NEXT_GENERATION: AtomicU64 = AtomicU64::new(1);
+
+/// Generation of the last write that reached the disk. Holding the lock
+/// keeps writes apart, and a write older than the last one is dropped, so
+/// an older history never replaces a newer one and a save that runs late
+/// can't bring back a cleared history.
+static LAST_WRITTEN: Mutex<u64> = Mutex::const_new(0);
+
+/// Oldest entries are dropped beyond this many.
+pub const MAX_HISTORY_ENTRIES: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Direction {
+    Encode,
+    Decode,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// Unix seconds.
+    pub timestamp: i64,
+    pub direction: Direction,
+    /// File name of the input file or scanned image, if there was one.
+    pub source: Option<String>,
+    /// Length of the payload text in bytes.
+    pub payload_len: usize,
+    /// [`pdf::fingerprint`] of the payload text.
+    pub fingerprint: String,
+    pub payload: String,
+}
+
+impl HistoryEntry {
+    pub fn new(direction: Direction, source: Option<String>, payload: String) -> Self {
+        Self {
+            timestamp: time::OffsetDateTime::now_utc().unix_timestamp(),
+            direction,
+            source,
+            payload_len: payload.len(),
+            fingerprint: pdf::fingerprint(&payload),
+            payload,
+        }
+    }
+}
+
+/// Newest first, at most [`MAX_HISTORY_ENTRIES`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct History {
+    pub entries: Vec<HistoryEntry>,
+}
+
+impl History {
+    pub fn push(&mut self, entry: HistoryEntry) {
+        self.entries.insert(0, entry);
+        self.entries.truncate(MAX_HISTORY_ENTRIES);
+    }
+}
+
+fn history_path() -> Option<PathBuf> {
+    settings::config_dir().map(|dir| dir.join(HISTORY_FILE))
+}
+
+/// Loads the stored history; a missing or unreadable file yields an empty one.
+pub fn load() -> History {
+    let Some(path) = history_path() else {
+        return History::default();
+    };
+
+    match std::fs::read_to_string(&path) {
+        Ok(contents) => toml::from_str(&contents).unwrap_or_else(|e| {
+            tracing::warn!("Ignoring invalid history file {}: {}", path.display(), e);
+            History::default()
+        }),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => History::default(),
+        Err(e) => {
+            tracing::warn!("Could not read history file {}: {}", path.display(), e);
+            History::default()
+        }
+    }
+}
+
+/// Writes the history, readable by the user only. Failures are only logged,
+/// as for the settings.
+///
+/// The write takes its place in line when this is called, not when the
+/// future first runs.
+pub fn save(history: History) -> impl Future<Output = ()> {
+    let generation = NEXT_GENERATION.fetch_add(1, Ordering::Relaxed);
+    write(generation, Some(history))
+}
+
+/// Deletes the stored history, after the user cleared it or turned
+/// persistence off. Ordered with [`save`] like the saves themselves.
+pub fn remove() -> impl Future<Output = ()> {
+    let generation = NEXT_GENERATION.fetch_add(1, Ordering::Relaxed);
+    write(generation, None)
+}
+
+/// Saves `history`, or removes the stored one for `None`, unless a later
+/// generation was written already.
+async fn write(generation: u64, history: Option<History>) {
+    let mut last_written = LAST_WRITTEN.lock().await;
+    if *last_written > generation {
+        tracing::debug!(generation, last = *last_written, "dropping stale history write");
+        return;
+    }
+    *last_written = generation;
+
+    let Some(path) = history_path() else {
+        return;
+    };
+
+    let Some(history) = history else {
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => tracing::warn!("Could not delete history file {}: {}", path.display(), e),
+        }
+        return;
+    };
+
+    let contents = match toml::to_string(&history) {
+        Ok(contents) => contents,
+        Err(e) => {
+            tracing::warn!("Could not serialize the history: {}", e);
+            return;
+        }
+    };
+
+    if let Some(parent) = path.parent() {
+        if let Err(e) = tokio::fs::create_dir_all(parent).await {
+            tracing::warn!("Could not create {}: {}", parent.display(), e);
+            return;
+        }
+    }
+    if let Err(e) = services::save_private_bytes_atomic(&path, contents.as_bytes()).await {
+        tracing::warn!("Could not write history file: {}", e);
+    }
+}
@@ -0,0 +1,1133 @@
+// src/i18n.rs
+use crate::history::{Direction, HistoryEntry};
+use qr_data_exchange::batch::BatchStatus;
+use qr_data_exchange::crypto::crypto_utils::CryptoError;
+use qr_data_exchange::crypto::passphrase::PassphraseStyle;
+use qr_data_exchange::crypto::password::PolicyViolation;
+use qr_data_exchange::keys::KeyError;
+use qr_data_exchange::qr::archive::ArchiveError;
+use qr_data_exchange::qr::chunk::{self, ChunkError};
+use qr_data_exchange::qr::fountain::FountainError;
+use qr_data_exchange::qr::processor::{QrProcessorError, Stage};
+use qr_data_exchange::qr::service::{QrCapacity, QrDimensions, QrServiceError};
+use qr_data_exchange::selftest::{SelfTestError, SelfTestStep, StepResult};
+use qr_data_exchange::services::{LevelAnalysis, SizeEstimate};
+use qr_data_exchange::Error;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Lang {
+    English,
+    German,
+}
+
+/// The system language until the user picks one; the choice is then kept in
+/// the settings.
+impl Default for Lang {
+    fn default() -> Self {
+        Lang::system()
+    }
+}
+
+impl Lang {
+    pub const ALL: [Lang; 2] = [Lang::English, Lang::German];
+
+    /// German for any German locale (`de`, `de-AT`, `de_CH.UTF-8`, ...),
+    /// English for everything else.
+    pub fn system() -> Self {
+        match sys_locale::get_locale() {
+            Some(locale) if locale.to_ascii_lowercase().starts_with("de") => Lang::German,
+            _ => Lang::English,
+        }
+    }
+
+    pub fn strings(self) -> &'static Strings {
+        match self {
+            Lang::English => &EN,
+            Lang::German => &DE,
+        }
+    }
+
+    pub fn passphrase_style(self, style: PassphraseStyle) -> String {
+        match (self, style) {
+            (Lang::English, PassphraseStyle::Words(count)) => format!("{} words", count),
+            (Lang::English, PassphraseStyle::Alphanumeric(len)) => format!("{} characters", len),
+            (Lang::German, PassphraseStyle::Words(count)) => format!("{} Wörter", count),
+            (Lang::German, PassphraseStyle::Alphanumeric(len)) => format!("{} Zeichen", len),
+        }
+    }
+
+    pub fn entropy_bits(self, bits: f64) -> String {
+        match self {
+            Lang::English => format!("{:.0} bits of entropy", bits),
+            Lang::German => format!("{:.0} Bit Entropie", bits),
+        }
+    }
+
+    pub fn password_hint(self, max_length: usize) -> String {
+        match self {
+            Lang::English => format!(
+                "At most {} characters, as in PyQrDataExchange. Prefer several words.",
+                max_length
+            ),
+            Lang::German => format!(
+                "Höchstens {} Zeichen, wie bei PyQrDataExchange. Am besten mehrere Wörter.",
+                max_length
+            ),
+        }
+    }
+
+    pub fn animation_part(self, index: usize, count: usize) -> String {
+        match self {
+            Lang::English => format!("Part {}/{}", index, count),
+            Lang::German => format!("Teil {}/{}", index, count),
+        }
+    }
+
+    /// Caption printed under a QR code in an exported PDF; `part` is the
+    /// 1-based part and the number of parts of a split payload.
+    pub fn pdf_caption(
+        self,
+        created: &str,
+        filename: Option<&str>,
+        fingerprint: &str,
+        part: Option<(usize, usize)>,
+    ) -> Vec<String> {
+        let (created_label, file_label, fingerprint_label) = match self {
+            Lang::English => ("Created", "File", "Fingerprint"),
+            Lang::German => ("Erstellt", "Datei", "Fingerabdruck"),
+        };
+
+        let mut lines = vec![format!("{}: {}", created_label, created)];
+        if let Some(filename) = filename {
+            lines.push(format!("{}: {}", file_label, filename));
+        }
+        lines.push(format!("{}: {}", fingerprint_label, fingerprint));
+        if let Some((index, count)) = part {
+            lines.push(match self {
+                Lang::English => format!("Part {} of {}", index, count),
+                Lang::German => format!("Teil {} von {}", index, count),
+            });
+        }
+        lines
+    }
+
+    pub fn confirm_overwrite(self, path: &Path) -> String {
+        match self {
+            Lang::English => format!("{} already exists. Replace it?", path.display()),
+            Lang::German => format!("{} existiert bereits. Ersetzen?", path.display()),
+        }
+    }
+
+    pub fn qr_capacity(self, capacity: &QrCapacity) -> String {
+        match self {
+            Lang::English => format!(
+                "Version {}, {}/{} bytes",
+                capacity.version, capacity.used_bytes, capacity.capacity_bytes
+            ),
+            Lang::German => format!(
+                "Version {}, {}/{} Bytes",
+                capacity.version, capacity.used_bytes, capacity.capacity_bytes
+            ),
+        }
+    }
+
+    pub fn qr_dimensions(self, dimensions: &QrDimensions) -> String {
+        match self {
+            Lang::English => format!(
+                "{0} × {0} px, {1} px per module",
+                dimensions.side, dimensions.module_size
+            ),
+            Lang::German => format!(
+                "{0} × {0} px, {1} px pro Modul",
+                dimensions.side, dimensions.module_size
+            ),
+        }
+    }
+
+    pub fn preview_size(self, len: usize) -> String {
+        match self {
+            Lang::English => format!("Size: {} bytes", len),
+            Lang::German => format!("Größe: {} Bytes", len),
+        }
+    }
+
+    /// Shown instead of a file name when several files are packed together.
+    pub fn files_selected(self, count: usize) -> String {
+        match self {
+            Lang::English => format!("{} files, packed as an archive", count),
+            Lang::German => format!("{} Dateien, als Archiv gepackt", count),
+        }
+    }
+
+    /// What a running job is doing.
+    pub fn stage(self, stage: Stage) -> &'static str {
+        match (self, stage) {
+            (Lang::English, Stage::Reading) => "Reading the input…",
+            (Lang::English, Stage::DerivingKey) => "Deriving the key…",
+            (Lang::English, Stage::Compressing) => "Compressing…",
+            (Lang::English, Stage::Encrypting) => "Encrypting…",
+            (Lang::English, Stage::Rendering) => "Rendering the code…",
+            (Lang::English, Stage::Decrypting) => "Decrypting…",
+            (Lang::English, Stage::Decompressing) => "Decompressing…",
+            (Lang::German, Stage::Reading) => "Eingabe wird gelesen…",
+            (Lang::German, Stage::DerivingKey) => "Schlüssel wird abgeleitet…",
+            (Lang::German, Stage::Compressing) => "Wird komprimiert…",
+            (Lang::German, Stage::Encrypting) => "Wird verschlüsselt…",
+            (Lang::German, Stage::Rendering) => "QR-Code wird erzeugt…",
+            (Lang::German, Stage::Decrypting) => "Wird entschlüsselt…",
+            (Lang::German, Stage::Decompressing) => "Wird entpackt…",
+        }
+    }
+
+    pub fn parts_collected(self, collected: usize, count: usize) -> String {
+        match self {
+            Lang::English => format!("{}/{} parts collected", collected, count),
+            Lang::German => format!("{}/{} Teile gesammelt", collected, count),
+        }
+    }
+
+    /// Progress of collecting fountain frames.
+    pub fn blocks_recovered(self, recovered: usize, total: usize) -> String {
+        match self {
+            Lang::English => format!(
+                "{}/{} blocks recovered; keep scanning frames in any order",
+                recovered, total
+            ),
+            Lang::German => format!(
+                "{}/{} Blöcke wiederhergestellt; weitere Bilder in beliebiger Reihenfolge scannen",
+                recovered, total
+            ),
+        }
+    }
+
+    pub fn parts_missing(self, missing: &[usize]) -> String {
+        let list = missing.iter().map(usize::to_string).collect::<Vec<_>>().join(", ");
+        match self {
+            Lang::English => format!("Missing: {}", list),
+            Lang::German => format!("Es fehlen: {}", list),
+        }
+    }
+
+    /// Where the last generated code was saved without asking.
+    pub fn auto_saved(self, paths: &[PathBuf]) -> String {
+        match (self, paths) {
+            (Lang::English, [path]) => format!("Saved to {}", path.display()),
+            (Lang::German, [path]) => format!("Gespeichert unter {}", path.display()),
+            (Lang::English, [first, ..]) => format!(
+                "{} images saved to {}",
+                paths.len(),
+                first.parent().unwrap_or(first).display()
+            ),
+            (Lang::German, [first, ..]) => format!(
+                "{} Bilder gespeichert in {}",
+                paths.len(),
+                first.parent().unwrap_or(first).display()
+            ),
+            (_, []) => String::new(),
+        }
+    }
+
+    pub fn archive_contents(self, count: usize) -> String {
+        match self {
+            Lang::English => format!("Archive with {} files:", count),
+            Lang::German => format!("Archiv mit {} Dateien:", count),
+        }
+    }
+
+    pub fn archive_entry(self, name: &str, len: usize) -> String {
+        match self {
+            Lang::English => format!("{} ({} bytes)", name, len),
+            Lang::German => format!("{} ({} Bytes)", name, len),
+        }
+    }
+
+    /// Digest of decrypted data, or a note that an older payload carried none.
+    pub fn checksum(self, digest_hex: Option<&str>) -> String {
+        match (self, digest_hex) {
+            (Lang::English, Some(hex)) => format!("Checksum (BLAKE2b-256): {}", hex),
+            (Lang::German, Some(hex)) => format!("Prüfsumme (BLAKE2b-256): {}", hex),
+            (Lang::English, None) => "Checksum: not verified (older payload)".to_string(),
+            (Lang::German, None) => "Prüfsumme: nicht geprüft (älteres Format)".to_string(),
+        }
+    }
+
+    pub fn preview_type(self, mime_type: Option<&str>) -> String {
+        match (self, mime_type) {
+            (Lang::English, Some(mime_type)) => format!("Type: {}", mime_type),
+            (Lang::German, Some(mime_type)) => format!("Typ: {}", mime_type),
+            (Lang::English, None) => "Type: unknown binary data".to_string(),
+            (Lang::German, None) => "Typ: unbekannte Binärdaten".to_string(),
+        }
+    }
+
+    /// Remaining validity of a decrypted payload, in days, hours or minutes.
+    pub fn remaining_validity(self, seconds: i64) -> String {
+        let minutes = seconds.max(0) / 60;
+        let (hours, days) = (minutes / 60, minutes / (24 * 60));
+        match self {
+            Lang::English if days > 0 => format!("Valid for another {} d {} h", days, hours % 24),
+            Lang::English if hours > 0 => format!("Valid for another {} h {} min", hours, minutes % 60),
+            Lang::English => format!("Valid for another {} min", minutes),
+            Lang::German if days > 0 => format!("Noch {} T {} h gültig", days, hours % 24),
+            Lang::German if hours > 0 => format!("Noch {} h {} min gültig", hours, minutes % 60),
+            Lang::German => format!("Noch {} min gültig", minutes),
+        }
+    }
+
+    pub fn batch_status(self, status: &BatchStatus) -> String {
+        match (self, status) {
+            (_, BatchStatus::Pending) => self.strings().batch_pending.to_string(),
+            (Lang::English, BatchStatus::Written(path)) => format!("written to {}", path.display()),
+            (Lang::German, BatchStatus::Written(path)) => format!("gespeichert in {}", path.display()),
+            (Lang::English, BatchStatus::TooLarge(size)) => format!("too large ({} bytes)", size),
+            (Lang::German, BatchStatus::TooLarge(size)) => format!("zu groß ({} Bytes)", size),
+            (Lang::English, BatchStatus::Failed(e)) => format!("failed: {}", self.error(e)),
+            (Lang::German, BatchStatus::Failed(e)) => format!("fehlgeschlagen: {}", self.error(e)),
+        }
+    }
+
+    /// One line of the self-test report: outcome, step, duration and error.
+    pub fn self_test_result(self, result: &StepResult) -> String {
+        let step = match (self, result.step) {
+            (Lang::English, step) => step.to_string(),
+            (Lang::German, SelfTestStep::Payload) => "Ver- und Entschlüsseln".to_string(),
+            (Lang::German, SelfTestStep::QrImage) => "QR-Code erzeugen und lesen".to_string(),
+        };
+        let ms = result.duration.as_millis();
+
+        match (self, &result.outcome) {
+            (Lang::English, Ok(())) => format!("PASS  {} ({} ms)", step, ms),
+            (Lang::German, Ok(())) => format!("OK  {} ({} ms)", step, ms),
+            (Lang::English, Err(SelfTestError::Failed(e))) => {
+                format!("FAIL  {} ({} ms): {}", step, ms, self.error(e))
+            }
+            (Lang::German, Err(SelfTestError::Failed(e))) => {
+                format!("FEHLER  {} ({} ms): {}", step, ms, self.error(e))
+            }
+            (Lang::English, Err(SelfTestError::Mismatch)) => format!(
+                "FAIL  {} ({} ms): the recovered data differs from the original",
+                step, ms
+            ),
+            (Lang::German, Err(SelfTestError::Mismatch)) => format!(
+                "FEHLER  {} ({} ms): die gelesenen Daten weichen vom Original ab",
+                step, ms
+            ),
+        }
+    }
+
+    /// Time (UTC), direction, source, payload size and fingerprint.
+    pub fn history_entry(self, entry: &HistoryEntry) -> String {
+        let time = time::OffsetDateTime::from_unix_timestamp(entry.timestamp)
+            .map(|t| format!("{} {:02}:{:02} UTC", t.date(), t.hour(), t.minute()))
+            .unwrap_or_default();
+        let source = entry.source.as_deref().unwrap_or("-");
+        match (self, entry.direction) {
+            (Lang::English, Direction::Encode) => format!(
+                "{}  encoded  {}  {} chars  {}",
+                time, source, entry.payload_len, entry.fingerprint
+            ),
+            (Lang::English, Direction::Decode) => format!(
+                "{}  decoded  {}  {} chars  {}",
+                time, source, entry.payload_len, entry.fingerprint
+            ),
+            (Lang::German, Direction::Encode) => format!(
+                "{}  erzeugt  {}  {} Zeichen  {}",
+                time, source, entry.payload_len, entry.fingerprint
+            ),
+            (Lang::German, Direction::Decode) => format!(
+                "{}  entschlüsselt  {}  {} Zeichen  {}",
+                time, source, entry.payload_len, entry.fingerprint
+            ),
+        }
+    }
+
+    pub fn batch_summary(self, written: usize, too_large: usize, failed: usize) -> String {
+        match self {
+            Lang::English => format!(
+                "Done: {} written, {} too large, {} failed",
+                written, too_large, failed
+            ),
+            Lang::German => format!(
+                "Fertig: {} gespeichert, {} zu groß, {} fehlgeschlagen",
+                written, too_large, failed
+            ),
+        }
+    }
+
+    pub fn batch_progress(self, done: usize, total: usize) -> String {
+        match self {
+            Lang::English => format!("Processing {}/{}...", done, total),
+            Lang::German => format!("Verarbeite {}/{}...", done, total),
+        }
+    }
+
+    pub fn analyzed_input(self, len: usize) -> String {
+        match self {
+            Lang::English => format!("Input: {} bytes", len),
+            Lang::German => format!("Eingabe: {} Bytes", len),
+        }
+    }
+
+    pub fn level_analysis(self, analysis: &LevelAnalysis) -> String {
+        match self {
+            Lang::English => format!(
+                "Level {:>2}: {:>9} bytes compressed, {:>9} payload, {} QR {}",
+                analysis.level,
+                analysis.compressed_len,
+                analysis.payload_len,
+                analysis.codes,
+                if analysis.codes == 1 { "code" } else { "codes" }
+            ),
+            Lang::German => format!(
+                "Stufe {:>2}: {:>9} Bytes komprimiert, {:>9} Nutzlast, {} QR-{}",
+                analysis.level,
+                analysis.compressed_len,
+                analysis.payload_len,
+                analysis.codes,
+                if analysis.codes == 1 { "Code" } else { "Codes" }
+            ),
+        }
+    }
+
+    pub fn size_estimate(self, estimate: &SizeEstimate) -> String {
+        match self {
+            Lang::English => format!(
+                "Estimated QR payload: ~{} / {} bytes",
+                estimate.payload_len, estimate.limit
+            ),
+            Lang::German => format!(
+                "Geschätzte QR-Nutzlast: ~{} / {} Bytes",
+                estimate.payload_len, estimate.limit
+            ),
+        }
+    }
+
+    /// Library errors carry English messages; German ones are built from the variant.
+    pub fn error(self, error: &Error) -> String {
+        match self {
+            Lang::English => error.to_string(),
+            Lang::German => error_de(error),
+        }
+    }
+}
+
+impl fmt::Display for Lang {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Lang::English => "English",
+            Lang::German => "Deutsch",
+        })
+    }
+}
+
+/// Fixed labels of the UI in one language.
+pub struct Strings {
+    pub language: &'static str,
+    pub theme: &'static str,
+    pub image_size: &'static str,
+    pub error_correction: &'static str,
+    pub rerender: &'static str,
+    pub verifying_qr: &'static str,
+    pub verified_scannable: &'static str,
+    pub not_scannable: &'static str,
+    pub cipher: &'static str,
+    pub light: &'static str,
+    pub dark: &'static str,
+    pub protect_with: &'static str,
+    pub password_option: &'static str,
+    pub recipient_option: &'static str,
+    pub show_public_key: &'static str,
+    pub password_label: &'static str,
+    pub show_password: &'static str,
+    pub generate_password: &'static str,
+    pub generated_password: &'static str,
+    pub use_password: &'static str,
+    pub regenerate: &'static str,
+    pub recipient_key_label: &'static str,
+    pub own_public_key_label: &'static str,
+    pub copy: &'static str,
+    pub keyfile_label: &'static str,
+    pub no_keyfile: &'static str,
+    pub keyfile_unused: &'static str,
+    pub logo_label: &'static str,
+    pub no_logo: &'static str,
+    pub output_dir_label: &'static str,
+    pub no_output_dir: &'static str,
+    pub auto_save_png: &'static str,
+    pub logo_size: &'static str,
+    pub quiet_zone: &'static str,
+    pub browse: &'static str,
+    pub clear: &'static str,
+    pub encode_label: &'static str,
+    pub file_option: &'static str,
+    pub text_option: &'static str,
+    pub encoding: &'static str,
+    pub binary_qr: &'static str,
+    pub animated: &'static str,
+    pub animation_without_logo: &'static str,
+    pub fountain: &'static str,
+    pub filename_label: &'static str,
+    pub recent_files: &'static str,
+    pub note_placeholder: &'static str,
+    pub read_qr: &'static str,
+    pub open_qr_image: &'static str,
+    pub discard_scan: &'static str,
+    pub add_part: &'static str,
+    pub read_string: &'static str,
+    pub scan_clipboard: &'static str,
+    #[cfg(feature = "screen")]
+    pub scan_screen: &'static str,
+    #[cfg(feature = "screen")]
+    pub all_displays: &'static str,
+    pub batch: &'static str,
+    pub processing: &'static str,
+    pub generate_qr: &'static str,
+    pub very_weak: &'static str,
+    pub weak: &'static str,
+    pub fair: &'static str,
+    pub strong: &'static str,
+    pub generated_qr_title: &'static str,
+    pub animation_title: &'static str,
+    pub save_gif: &'static str,
+    pub export_pdf: &'static str,
+    pub open_in_viewer: &'static str,
+    pub save_text: &'static str,
+    pub text_files: &'static str,
+    pub pdf_files: &'static str,
+    pub close: &'static str,
+    pub cancel: &'static str,
+    pub read_title: &'static str,
+    pub text_to_convert: &'static str,
+    pub decrypt_and_save: &'static str,
+    pub decrypt_to_clipboard: &'static str,
+    pub load_text_file: &'static str,
+    pub preview_title: &'static str,
+    pub save: &'static str,
+    pub discard: &'static str,
+    pub copy_to_clipboard: &'static str,
+    pub inspect: &'static str,
+    pub hide_inspector: &'static str,
+    pub copy_hex: &'static str,
+    pub copy_base64: &'static str,
+    pub save_as_file: &'static str,
+    pub extract_to_folder: &'static str,
+    pub analyze_compression: &'static str,
+    pub analyzing: &'static str,
+    pub use_level: &'static str,
+    pub overwrite_title: &'static str,
+    pub valid_for: &'static str,
+    pub valid_hour: &'static str,
+    pub valid_day: &'static str,
+    pub valid_week: &'static str,
+    pub valid_never: &'static str,
+    pub batch_title: &'static str,
+    pub history: &'static str,
+    pub history_empty: &'static str,
+    pub show_qr: &'static str,
+    pub clear_history: &'static str,
+    pub persist_history: &'static str,
+    pub self_test_title: &'static str,
+    pub self_test_running: &'static str,
+    pub batch_output: &'static str,
+    pub batch_pending: &'static str,
+    pub qr_images: &'static str,
+    pub select_keyfile: &'static str,
+    pub select_logo: &'static str,
+    pub png_images: &'static str,
+    pub select_batch_files: &'static str,
+    pub select_output_dir: &'static str,
+    pub copied_to_clipboard: &'static str,
+    pub saved: &'static str,
+    pub qr_generated: &'static str,
+    pub cancelled: &'static str,
+}
+
+const EN: Strings = Strings {
+    language: "Language:",
+    theme: "Theme:",
+    image_size: "Image size:",
+    error_correction: "Error correction:",
+    rerender: "Render again",
+    verifying_qr: "Checking that the code can be scanned...",
+    verified_scannable: "Verified scannable",
+    not_scannable: "This image did not scan back. Choose a larger image size or a lower error correction level, or encode less data.",
+    cipher: "Cipher:",
+    light: "Light",
+    dark: "Dark",
+    protect_with: "Protect with:",
+    password_option: "Password",
+    recipient_option: "Recipient keys",
+    show_public_key: "My public key",
+    password_label: "Password [1-20]:",
+    show_password: "Show",
+    generate_password: "Generate",
+    generated_password: "Write this password down; it is only shown here:",
+    use_password: "Use",
+    regenerate: "New",
+    recipient_key_label: "Recipient keys:",
+    own_public_key_label: "My public key:",
+    copy: "Copy",
+    keyfile_label: "Keyfile:",
+    no_keyfile: "(none)",
+    keyfile_unused: "Not used with recipient keys",
+    logo_label: "Logo:",
+    no_logo: "(none)",
+    output_dir_label: "Output folder:",
+    no_output_dir: "(none)",
+    auto_save_png: "Save PNG automatically",
+    logo_size: "Logo size (%):",
+    quiet_zone: "Border (modules):",
+    browse: "Browse",
+    clear: "Clear",
+    encode_label: "Encode:",
+    file_option: "File",
+    text_option: "Text",
+    encoding: "Encoding:",
+    binary_qr: "Raw bytes",
+    animated: "Animated sequence",
+    animation_without_logo: "Animations are rendered without the logo.",
+    fountain: "Tolerate missed frames (fountain code)",
+    filename_label: "Filename:",
+    recent_files: "Recent files...",
+    note_placeholder: "Text to encode...",
+    read_qr: "Read QR",
+    open_qr_image: "Open QR image…",
+    discard_scan: "Discard scan",
+    add_part: "Add part",
+    read_string: "Read String",
+    scan_clipboard: "Scan clipboard image",
+    #[cfg(feature = "screen")]
+    scan_screen: "Scan screen",
+    #[cfg(feature = "screen")]
+    all_displays: "All displays",
+    batch: "Batch...",
+    processing: "Processing...",
+    generate_qr: "Generate QR",
+    very_weak: "Very weak",
+    weak: "Weak",
+    fair: "Fair",
+    strong: "Strong",
+    generated_qr_title: "Generated QR code",
+    animation_title: "Animated QR sequence",
+    save_gif: "Save GIF",
+    export_pdf: "Export PDF",
+    open_in_viewer: "Open in image viewer",
+    save_text: "Save text",
+    text_files: "Text files",
+    pdf_files: "PDF files",
+    close: "Close",
+    cancel: "Cancel",
+    read_title: "QR Data Read",
+    text_to_convert: "Text to convert:",
+    decrypt_and_save: "Decrypt and Save",
+    decrypt_to_clipboard: "Decrypt to Clipboard",
+    load_text_file: "Load text file",
+    preview_title: "Decrypted data",
+    save: "Save",
+    discard: "Discard",
+    copy_to_clipboard: "Copy to clipboard",
+    inspect: "Inspect",
+    hide_inspector: "Hide hex view",
+    copy_hex: "Copy as hex",
+    copy_base64: "Copy as base64",
+    save_as_file: "Save as file",
+    extract_to_folder: "Extract to folder…",
+    analyze_compression: "Analyze compression",
+    analyzing: "Analyzing…",
+    use_level: "Use",
+    overwrite_title: "Replace file?",
+    valid_for: "Valid for",
+    valid_hour: "1 hour",
+    valid_day: "24 hours",
+    valid_week: "7 days",
+    valid_never: "Never expires",
+    batch_title: "Batch Encode",
+    history: "History",
+    history_empty: "Nothing generated or decoded yet.",
+    show_qr: "Show QR",
+    clear_history: "Clear history",
+    persist_history: "Keep the history across restarts",
+    self_test_title: "Self-test",
+    self_test_running: "Running the self-test...",
+    batch_output: "Output:",
+    batch_pending: "pending...",
+    qr_images: "Images and PDFs",
+    select_keyfile: "Select keyfile",
+    select_logo: "Select logo",
+    png_images: "PNG images",
+    select_batch_files: "Select files for the batch",
+    select_output_dir: "Select output folder",
+    copied_to_clipboard: "Copied to clipboard.",
+    saved: "Saved.",
+    qr_generated: "QR code generated.",
+    cancelled: "Cancelled.",
+};
+
+const DE: Strings = Strings {
+    language: "Sprache:",
+    theme: "Design:",
+    image_size: "Bildgröße:",
+    error_correction: "Fehlerkorrektur:",
+    rerender: "Neu erzeugen",
+    verifying_qr: "Prüfe, ob der Code lesbar ist...",
+    verified_scannable: "Lesbarkeit geprüft",
+    not_scannable: "Dieses Bild ließ sich nicht zurücklesen. Wähle eine größere Bildgröße oder eine niedrigere Fehlerkorrektur, oder kodiere weniger Daten.",
+    cipher: "Verfahren:",
+    light: "Hell",
+    dark: "Dunkel",
+    protect_with: "Schützen mit:",
+    password_option: "Passwort",
+    recipient_option: "Empfängerschlüssel",
+    show_public_key: "Mein öffentlicher Schlüssel",
+    password_label: "Passwort [1-20]:",
+    show_password: "Anzeigen",
+    generate_password: "Erzeugen",
+    generated_password: "Schreib dir dieses Passwort auf; es wird nur hier angezeigt:",
+    use_password: "Übernehmen",
+    regenerate: "Neu",
+    recipient_key_label: "Empfänger:",
+    own_public_key_label: "Mein Schlüssel:",
+    copy: "Kopieren",
+    keyfile_label: "Keyfile:",
+    no_keyfile: "(keins)",
+    keyfile_unused: "Wird mit Empfängerschlüsseln nicht verwendet",
+    logo_label: "Logo:",
+    no_logo: "(keins)",
+    output_dir_label: "Zielordner:",
+    no_output_dir: "(keiner)",
+    auto_save_png: "PNG automatisch speichern",
+    logo_size: "Logogröße (%):",
+    quiet_zone: "Rand (Module):",
+    browse: "Durchsuchen",
+    clear: "Entfernen",
+    encode_label: "Kodieren:",
+    file_option: "Datei",
+    text_option: "Text",
+    encoding: "Kodierung:",
+    binary_qr: "Rohe Bytes",
+    animated: "Animierte Folge",
+    animation_without_logo: "Animationen werden ohne Logo erzeugt.",
+    fountain: "Verpasste Bilder tolerieren (Fountain-Code)",
+    filename_label: "Dateiname:",
+    recent_files: "Zuletzt verwendet...",
+    note_placeholder: "Zu kodierender Text...",
+    read_qr: "QR lesen",
+    open_qr_image: "QR-Bild öffnen…",
+    discard_scan: "Scan verwerfen",
+    add_part: "Teil hinzufügen",
+    read_string: "Text lesen",
+    scan_clipboard: "Bild aus Zwischenablage scannen",
+    #[cfg(feature = "screen")]
+    scan_screen: "Bildschirm scannen",
+    #[cfg(feature = "screen")]
+    all_displays: "Alle Bildschirme",
+    batch: "Stapel...",
+    processing: "Verarbeite...",
+    generate_qr: "QR erzeugen",
+    very_weak: "Sehr schwach",
+    weak: "Schwach",
+    fair: "Mittel",
+    strong: "Stark",
+    generated_qr_title: "Generierter QR-Code",
+    animation_title: "Animierte QR-Folge",
+    save_gif: "GIF speichern",
+    export_pdf: "Als PDF exportieren",
+    open_in_viewer: "Im Bildbetrachter öffnen",
+    save_text: "Text speichern",
+    text_files: "Textdateien",
+    pdf_files: "PDF-Dateien",
+    close: "Schließen",
+    cancel: "Abbrechen",
+    read_title: "QR-Daten lesen",
+    text_to_convert: "Zu konvertierender Text:",
+    decrypt_and_save: "Entschlüsseln und speichern",
+    decrypt_to_clipboard: "In die Zwischenablage entschlüsseln",
+    load_text_file: "Textdatei laden",
+    preview_title: "Entschlüsselte Daten",
+    save: "Speichern",
+    discard: "Verwerfen",
+    copy_to_clipboard: "In die Zwischenablage kopieren",
+    inspect: "Untersuchen",
+    hide_inspector: "Hex-Ansicht ausblenden",
+    copy_hex: "Als Hex kopieren",
+    copy_base64: "Als Base64 kopieren",
+    save_as_file: "Als Datei speichern",
+    extract_to_folder: "In Ordner entpacken…",
+    analyze_compression: "Komprimierung analysieren",
+    analyzing: "Analysiere…",
+    use_level: "Verwenden",
+    overwrite_title: "Datei ersetzen?",
+    valid_for: "Gültig für",
+    valid_hour: "1 Stunde",
+    valid_day: "24 Stunden",
+    valid_week: "7 Tage",
+    valid_never: "Unbegrenzt",
+    batch_title: "Stapelkodierung",
+    history: "Verlauf",
+    history_empty: "Noch nichts erzeugt oder entschlüsselt.",
+    show_qr: "QR anzeigen",
+    clear_history: "Verlauf löschen",
+    persist_history: "Verlauf über Neustarts hinweg behalten",
+    self_test_title: "Selbsttest",
+    self_test_running: "Selbsttest läuft...",
+    batch_output: "Zielordner:",
+    batch_pending: "wartet...",
+    qr_images: "Bilder und PDFs",
+    select_keyfile: "Keyfile auswählen",
+    select_logo: "Logo auswählen",
+    png_images: "PNG-Bilder",
+    select_batch_files: "Dateien für den Batch auswählen",
+    select_output_dir: "Zielordner auswählen",
+    copied_to_clipboard: "In die Zwischenablage kopiert.",
+    saved: "Gespeichert.",
+    qr_generated: "QR-Code erzeugt.",
+    cancelled: "Abgebrochen.",
+};
+
+/// A confirmation shown as a toast, translated when it is rendered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Notice {
+    CopiedToClipboard,
+    Saved,
+    QrGenerated,
+    Cancelled,
+}
+
+impl Notice {
+    pub fn describe(self, lang: Lang) -> &'static str {
+        match self {
+            Notice::CopiedToClipboard => lang.strings().copied_to_clipboard,
+            Notice::Saved => lang.strings().saved,
+            Notice::QrGenerated => lang.strings().qr_generated,
+            Notice::Cancelled => lang.strings().cancelled,
+        }
+    }
+}
+
+/// An error shown as a toast, translated when it is rendered.
+#[derive(Debug, Clone)]
+pub enum UiError {
+    PasswordMissing,
+    FileMissing,
+    /// The path to read a code from is neither an image nor a PDF.
+    NotScannable(PathBuf),
+    TextMissing,
+    InvalidRecipientKey,
+    PasswordPolicy(PolicyViolation),
+    Generate(Error),
+    ReadQr(Error),
+    Decrypt(Error),
+    Keyfile(Error),
+    KeyPair(Error),
+    Save(Error),
+    Extract(Error),
+    OpenViewer(Error),
+    LoadText(Error),
+    Analyze(Error),
+    Startup(Error),
+}
+
+impl UiError {
+    pub fn describe(&self, lang: Lang) -> String {
+        match lang {
+            Lang::English => self.describe_en(),
+            Lang::German => self.describe_de(),
+        }
+    }
+
+    fn describe_en(&self) -> String {
+        let lang = Lang::English;
+        match self {
+            UiError::PasswordMissing => "Please enter a password.".to_string(),
+            UiError::FileMissing => "Please select a file.".to_string(),
+            UiError::NotScannable(path) => format!(
+                "{} is not an image; QR codes are read from PNG, JPEG, BMP, WebP, GIF and PDF files.",
+                path.display()
+            ),
+            UiError::TextMissing => "Please enter some text.".to_string(),
+            UiError::InvalidRecipientKey => "Please enter one or more valid public keys, separated by spaces.".to_string(),
+            UiError::PasswordPolicy(PolicyViolation::TooShort(min)) => {
+                format!("The password must be at least {} characters long.", min)
+            }
+            UiError::PasswordPolicy(PolicyViolation::TooWeak { .. }) => {
+                "The password is too easy to guess.".to_string()
+            }
+            UiError::Generate(Error::Io(e)) => format!("Error reading the file: {}", e),
+            UiError::Generate(
+                e @ (Error::NotFound { .. } | Error::DriveUnavailable { .. } | Error::PermissionDenied { .. }),
+            ) => lang.error(e),
+            UiError::Generate(Error::PayloadTooLarge { size, .. }) => {
+                format!("The file is too large at {} bytes.", size)
+            }
+            UiError::Generate(e @ Error::Service(_)) => {
+                format!("QR generation failed: {}", lang.error(e))
+            }
+            UiError::Generate(e) => format!("Encryption failed: {}", lang.error(e)),
+            UiError::ReadQr(e) => format!("Error reading the QR code: {}", lang.error(e)),
+            UiError::Decrypt(e) => format!("Decryption failed: {}", lang.error(e)),
+            UiError::Keyfile(e) => format!("Error reading the keyfile: {}", lang.error(e)),
+            UiError::KeyPair(e) => format!("Error loading the key pair: {}", lang.error(e)),
+            UiError::Save(e) => format!("Error saving the file: {}", lang.error(e)),
+            UiError::Extract(e) => format!("Error extracting the archive: {}", lang.error(e)),
+            UiError::OpenViewer(e) => format!("Could not open the image viewer: {}", lang.error(e)),
+            UiError::LoadText(e) => format!("Error loading the text file: {}", lang.error(e)),
+            UiError::Analyze(e) => format!("Error analyzing the input: {}", lang.error(e)),
+            UiError::Startup(e) => format!(
+                "Nothing can be encrypted or decrypted on this system: {}",
+                lang.error(e)
+            ),
+        }
+    }
+
+    fn describe_de(&self) -> String {
+        let lang = Lang::German;
+        match self {
+            UiError::PasswordMissing => "Bitte gib ein Passwort ein.".to_string(),
+            UiError::FileMissing => "Bitte wähle eine Datei aus.".to_string(),
+            UiError::NotScannable(path) => format!(
+                "{} ist kein Bild; QR-Codes werden aus PNG-, JPEG-, BMP-, WebP-, GIF- und PDF-Dateien gelesen.",
+                path.display()
+            ),
+            UiError::TextMissing => "Bitte gib einen Text ein.".to_string(),
+            UiError::InvalidRecipientKey => {
+                "Bitte gib einen oder mehrere gültige öffentliche Schlüssel ein, getrennt durch Leerzeichen.".to_string()
+            }
+            UiError::PasswordPolicy(PolicyViolation::TooShort(min)) => {
+                format!("Das Passwort muss mindestens {} Zeichen lang sein.", min)
+            }
+            UiError::PasswordPolicy(PolicyViolation::TooWeak { .. }) => {
+                "Das Passwort ist zu leicht zu erraten.".to_string()
+            }
+            UiError::Generate(Error::Io(e)) => format!("Fehler beim Lesen der Datei: {}", e),
+            UiError::Generate(
+                e @ (Error::NotFound { .. } | Error::DriveUnavailable { .. } | Error::PermissionDenied { .. }),
+            ) => lang.error(e),
+            UiError::Generate(Error::PayloadTooLarge { size, .. }) => {
+                format!("Die Datei ist mit {} Bytes zu groß.", size)
+            }
+            UiError::Generate(e @ Error::Service(_)) => {
+                format!("Fehler bei der QR-Generierung: {}", lang.error(e))
+            }
+            UiError::Generate(e) => format!("Fehler bei der Verschlüsselung: {}", lang.error(e)),
+            UiError::ReadQr(e) => format!("Fehler beim Lesen des QR-Codes: {}", lang.error(e)),
+            UiError::Decrypt(e) => format!("Entschlüsselung fehlgeschlagen: {}", lang.error(e)),
+            UiError::Keyfile(e) => format!("Fehler beim Lesen des Keyfiles: {}", lang.error(e)),
+            UiError::KeyPair(e) => {
+                format!("Fehler beim Laden des Schlüsselpaars: {}", lang.error(e))
+            }
+            UiError::Save(e) => format!("Fehler beim Speichern der Datei: {}", lang.error(e)),
+            UiError::Extract(e) => format!("Fehler beim Entpacken des Archivs: {}", lang.error(e)),
+            UiError::OpenViewer(e) => {
+                format!("Der Bildbetrachter ließ sich nicht öffnen: {}", lang.error(e))
+            }
+            UiError::LoadText(e) => format!("Fehler beim Laden der Textdatei: {}", lang.error(e)),
+            UiError::Analyze(e) => format!("Fehler bei der Analyse der Eingabe: {}", lang.error(e)),
+            UiError::Startup(e) => format!(
+                "Auf diesem System kann nichts ver- oder entschlüsselt werden: {}",
+                lang.error(e)
+            ),
+        }
+    }
+}
+
+fn error_de(error: &Error) -> String {
+    match error {
+        Error::Crypto(e) => crypto_error_de(e),
+        Error::Processor(e) => match e {
+            QrProcessorError::Crypto(e) => crypto_error_de(e),
+            QrProcessorError::Compression(e) => format!("Komprimierungsfehler: {}", e),
+            QrProcessorError::Serialization(e) => format!("Serialisierungsfehler: {}", e),
+            QrProcessorError::Base64(e) => format!(
+                "Der Text ist kein gültiges Base64 ({}); vermutlich unvollständig gescannt, bitte erneut scannen.",
+                e
+            ),
+            QrProcessorError::Base45(e) => format!(
+                "Der Text ist kein gültiges Base45 ({}); vermutlich unvollständig gescannt, bitte erneut scannen.",
+                e
+            ),
+            QrProcessorError::Malformed(e) => format!(
+                "Der Text enthält keine gültigen Daten ({}); bitte erneut scannen.",
+                e
+            ),
+            QrProcessorError::InvalidSalt(len) => format!(
+                "Das Salt der Daten hat {} statt 16 Bytes; bitte erneut scannen.",
+                len
+            ),
+            QrProcessorError::WrongPassword => "Falsches Passwort oder Keyfile.".to_string(),
+            QrProcessorError::Corrupted => {
+                "Passwort oder Schlüssel stimmen, aber die Daten sind beschädigt oder wurden verändert."
+                    .to_string()
+            }
+            QrProcessorError::NoRecipients => {
+                "Es wird mindestens ein Empfängerschlüssel benötigt.".to_string()
+            }
+            QrProcessorError::Expired { .. } => {
+                "Diese Daten sind abgelaufen und lassen sich nicht mehr entschlüsseln.".to_string()
+            }
+            QrProcessorError::AuthenticationFailed => {
+                "Falsches Passwort, oder die Daten sind beschädigt oder wurden verändert."
+                    .to_string()
+            }
+            QrProcessorError::KeyfileRequired => {
+                "Diese Daten wurden mit einem Keyfile verschlüsselt; wähle das Keyfile aus."
+                    .to_string()
+            }
+            QrProcessorError::RecipientKeyRequired => {
+                "Diese Daten wurden für einen öffentlichen Schlüssel verschlüsselt.".to_string()
+            }
+            QrProcessorError::PasswordRequired => {
+                "Diese Daten sind mit einem Passwort geschützt, nicht mit einem Schlüssel."
+                    .to_string()
+            }
+            QrProcessorError::UnknownMode(mode) => format!("Unbekannter Modus {}", mode),
+            QrProcessorError::DecompressedTooLarge { limit } => format!(
+                "Die entpackten Daten überschreiten die Grenze von {} Bytes.",
+                limit
+            ),
+            QrProcessorError::UnknownCipher(cipher) => format!("Unbekanntes Verschlüsselungsverfahren {}", cipher),
+            QrProcessorError::EmptyInput => {
+                "Es gibt nichts zu entschlüsseln; füge zuerst Daten ein oder scanne sie.".to_string()
+            }
+            QrProcessorError::NotAPayload => {
+                "Das sieht nicht nach QR-Data-Exchange-Daten aus.".to_string()
+            }
+            QrProcessorError::UnsupportedVersion(version) => format!(
+                "Formatversion {} wird von dieser Version der App nicht unterstützt.",
+                version
+            ),
+            QrProcessorError::Cancelled => "Abgebrochen.".to_string(),
+            QrProcessorError::IntegrityCheckFailed => {
+                "Die entschlüsselten Daten passen nicht zu ihrer Prüfsumme; sie wurden unterwegs beschädigt.".to_string()
+            }
+            QrProcessorError::NotForStreams(option) => {
+                format!("Als Stream verschlüsselte Dateien unterstützen {} nicht.", option)
+            }
+        },
+        Error::Service(e) => match e {
+            QrServiceError::GenerationFailed(e) => format!("QR-Erzeugung fehlgeschlagen: {}", e),
+            QrServiceError::ImageReadError(e) => format!("Bild nicht lesbar: {}", e),
+            QrServiceError::QrCodeNotFound { attempts } => {
+                format!("Kein QR-Code im Bild gefunden ({} Varianten versucht).", attempts)
+            }
+            QrServiceError::NotText => {
+                "Der QR-Code enthält Binärdaten, keinen Text.".to_string()
+            }
+            QrServiceError::LogoTooLarge { max_percent } => {
+                format!("Das Logo bedeckt mehr als {}% des Codes.", max_percent)
+            }
+            QrServiceError::LowContrast { ratio, min_ratio } => format!(
+                "Die Farben heben sich zu wenig ab ({:.1}:1, mindestens {}:1 nötig).",
+                ratio, min_ratio
+            ),
+            QrServiceError::NoClipboardImage => {
+                "Die Zwischenablage enthält kein Bild.".to_string()
+            }
+            QrServiceError::ClipboardError(e) => format!("Fehler der Zwischenablage: {}", e),
+            QrServiceError::NoDisplay => "Kein Bildschirm zum Aufnehmen gefunden.".to_string(),
+            QrServiceError::ScreenCaptureError(e) => {
+                format!("Bildschirmaufnahme fehlgeschlagen: {}", e)
+            }
+            QrServiceError::CompareFailed { path, source } => format!(
+                "{} ließ sich nicht dekodieren: {}",
+                path,
+                error_de(&Error::Service((**source).clone()))
+            ),
+        },
+        Error::Key(e) => match e {
+            KeyError::NoDataDir => "Kein Datenverzeichnis auf diesem System.".to_string(),
+            KeyError::NoLocalKey => {
+                "Auf diesem Rechner wurde noch kein Schlüsselpaar erstellt.".to_string()
+            }
+            KeyError::InvalidPublicKey => "Kein gültiger öffentlicher Schlüssel.".to_string(),
+            KeyError::InvalidKeyFile => "Die gespeicherte Schlüsseldatei ist beschädigt.".to_string(),
+            KeyError::Io(e) => format!("Ein-/Ausgabefehler: {}", e),
+            KeyError::Crypto(e) => crypto_error_de(e),
+        },
+        Error::Archive(e) => match e {
+            ArchiveError::Malformed(detail) => format!("Das Archiv ist beschädigt: {}", detail),
+            ArchiveError::Empty => "Das Archiv ist leer.".to_string(),
+            ArchiveError::DuplicateName(name) => {
+                format!("Das Archiv enthält {} mehrfach.", name)
+            }
+            ArchiveError::UnsafeName(name) => format!(
+                "{:?} wird nicht entpackt: Der Name zeigt aus dem Zielordner heraus.",
+                name
+            ),
+            ArchiveError::Exists(name) => format!("{} existiert bereits.", name),
+            ArchiveError::Io { name, message } => {
+                format!("{} konnte nicht geschrieben werden: {}", name, message)
+            }
+        },
+        Error::Chunk(e) => match e {
+            ChunkError::NotAChunk => "Das ist kein Teil einer aufgeteilten Nachricht.".to_string(),
+            ChunkError::Inconsistent => {
+                "Die Teile gehören zu verschiedenen Nachrichten.".to_string()
+            }
+            ChunkError::Missing(index) => format!("Teil {} fehlt.", index),
+            ChunkError::TooManyParts(count) => format!(
+                "Eine aufgeteilte Nachricht hat höchstens {} Teile, diese gibt {} an.",
+                chunk::MAX_PARTS,
+                count
+            ),
+        },
+        Error::Fountain(e) => match e {
+            FountainError::NotAFrame => "Das ist kein Fountain-Frame.".to_string(),
+            FountainError::UnsupportedVersion(version) => format!(
+                "Fountain-Version {} wird von dieser Version der App nicht unterstützt.",
+                version
+            ),
+            FountainError::Inconsistent => {
+                "Der Frame gehört zu einer anderen Nachricht.".to_string()
+            }
+            FountainError::Corrupted => "Die zusammengesetzte Nachricht ist beschädigt.".to_string(),
+        },
+        Error::IncompleteFrames { recovered, total } => format!(
+            "Nur {} von {} Blöcken der Nachricht konnten wiederhergestellt werden; es werden mehr Frames gebraucht.",
+            recovered, total
+        ),
+        Error::Io(e) => format!("Ein-/Ausgabefehler: {}", e),
+        Error::NotFound { path } => format!("{} existiert nicht.", path.display()),
+        Error::DriveUnavailable { path } => format!(
+            "{} ist nicht erreichbar; ist das Laufwerk oder die Netzwerkfreigabe noch verbunden?",
+            path.display()
+        ),
+        Error::PermissionDenied { path } => {
+            format!("Keine Berechtigung, {} zu lesen.", path.display())
+        }
+        Error::PayloadTooLarge { size, limit } => format!(
+            "Die Daten sind zu groß für einen QR-Code ({} von höchstens {} Bytes).",
+            size, limit
+        ),
+        Error::FileTooLarge { size, limit } => format!(
+            "Die Datei ist zu groß ({} von höchstens {} Bytes).",
+            size, limit
+        ),
+        Error::SaveFailed { path, source } => {
+            format!("{} konnte nicht geschrieben werden: {}", path.display(), source)
+        }
+    }
+}
+
+fn crypto_error_de(error: &CryptoError) -> String {
+    match error {
+        CryptoError::KeyDerivationFailed => "Schlüsselableitung fehlgeschlagen".to_string(),
+        CryptoError::EncryptionFailed => "Verschlüsselung fehlgeschlagen".to_string(),
+        CryptoError::DecryptionFailed => {
+            "Falsches Passwort oder beschädigte Daten".to_string()
+        }
+        CryptoError::InvalidSalt => "Ungültige Salt-Länge".to_string(),
+        CryptoError::InvalidPassword => "Ungültiges Passwort".to_string(),
+        CryptoError::WrongRecipient => {
+            "Diese Daten wurden nicht für dieses Schlüsselpaar verschlüsselt".to_string()
+        }
+        CryptoError::InitFailed => {
+            "Die Kryptografie-Bibliothek ließ sich auf diesem System nicht initialisieren".to_string()
+        }
+        CryptoError::DigestFailed => {
+            "Die Prüfsumme der Daten ließ sich nicht berechnen".to_string()
+        }
+    }
+}
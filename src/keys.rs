@@ -0,0 +1,112 @@
+// src/keys.rs
+use crate::crypto::crypto_utils::{self, CryptoError, PublicKey, SecretKey};
+use base64::{engine::general_purpose, Engine};
+use directories::ProjectDirs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use thiserror::Error;
+use zeroize::Zeroizing;
+
+/// Prefix of a shareable public key string.
+pub const PUBLIC_KEY_PREFIX: &str = "qrdx-pk:";
+
+const SECRET_KEY_FILE: &str = "recipient.key";
+
+#[derive(Error, Debug, Clone)]
+pub enum KeyError {
+    #[error("No data directory available on this platform")]
+    NoDataDir,
+    #[error("No key pair has been created on this computer yet")]
+    NoLocalKey,
+    #[error("Not a valid public key")]
+    InvalidPublicKey,
+    #[error("The stored key file is damaged")]
+    InvalidKeyFile,
+    #[error("IO error: {0}")]
+    Io(Arc<std::io::Error>),
+    #[error(transparent)]
+    Crypto(#[from] CryptoError),
+}
+
+impl From<std::io::Error> for KeyError {
+    fn from(e: std::io::Error) -> Self {
+        KeyError::Io(Arc::new(e))
+    }
+}
+
+pub fn encode_public_key(public_key: &PublicKey) -> String {
+    format!(
+        "{}{}",
+        PUBLIC_KEY_PREFIX,
+        general_purpose::URL_SAFE_NO_PAD.encode(public_key.0)
+    )
+}
+
+pub fn decode_public_key(input: &str) -> Result<PublicKey, KeyError> {
+    let body = input
+        .trim()
+        .strip_prefix(PUBLIC_KEY_PREFIX)
+        .ok_or(KeyError::InvalidPublicKey)?;
+    let bytes = general_purpose::URL_SAFE_NO_PAD
+        .decode(body)
+        .map_err(|_| KeyError::InvalidPublicKey)?;
+
+    PublicKey::from_slice(&bytes).ok_or(KeyError::InvalidPublicKey)
+}
+
+/// Decodes one or more public keys separated by whitespace or commas.
+pub fn decode_public_keys(input: &str) -> Result<Vec<PublicKey>, KeyError> {
+    let keys = input
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|key| !key.is_empty())
+        .map(decode_public_key)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if keys.is_empty() {
+        return Err(KeyError::InvalidPublicKey);
+    }
+    Ok(keys)
+}
+
+fn secret_key_path() -> Result<PathBuf, KeyError> {
+    let dirs = ProjectDirs::from("", "vaultecki", "qr-data-exchange").ok_or(KeyError::NoDataDir)?;
+    Ok(dirs.data_dir().join(SECRET_KEY_FILE))
+}
+
+/// Loads the local key pair used to open payloads sealed to our public key.
+pub async fn load_keypair() -> Result<(PublicKey, SecretKey), KeyError> {
+    let bytes = match tokio::fs::read(secret_key_path()?).await {
+        Ok(bytes) => Zeroizing::new(bytes),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Err(KeyError::NoLocalKey),
+        Err(e) => return Err(e.into()),
+    };
+
+    let secret_key = SecretKey::from_slice(&bytes).ok_or(KeyError::InvalidKeyFile)?;
+    Ok((secret_key.public_key(), secret_key))
+}
+
+/// Loads the local key pair, creating and storing a new one on first use.
+pub async fn load_or_generate_keypair() -> Result<(PublicKey, SecretKey), KeyError> {
+    match load_keypair().await {
+        Err(KeyError::NoLocalKey) => {}
+        other => return other,
+    }
+
+    crypto_utils::init()?;
+    let (public_key, secret_key) = crypto_utils::generate_keypair();
+
+    let path = secret_key_path()?;
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+
+    let mut options = tokio::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+
+    let mut file = options.open(&path).await?;
+    tokio::io::AsyncWriteExt::write_all(&mut file, &secret_key.0).await?;
+
+    Ok((public_key, secret_key))
+}
@@ -0,0 +1,23 @@
+// src/lib.rs
+//! Without the default `gui` feature only the payload format (`crypto`,
+//! `qr::processor` and the multi-code schemes) is built, which also compiles
+//! to wasm32.
+#[cfg(feature = "gui")]
+pub mod batch;
+#[cfg(feature = "gui")]
+pub mod config;
+pub mod crypto;
+pub mod error;
+#[cfg(feature = "gui")]
+pub mod keys;
+#[cfg(feature = "gui")]
+pub mod preview;
+pub mod qr;
+#[cfg(feature = "gui")]
+pub mod selftest;
+#[cfg(feature = "gui")]
+pub mod services;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use error::Error;
@@ -1,264 +1,2748 @@
 // src/main.rs
+mod cli;
+mod history;
+mod i18n;
+mod scan_session;
+mod settings;
+mod toast;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use history::{Direction, History, HistoryEntry};
+use i18n::{Lang, Notice, UiError};
 use iced::{
-    widget::{button, column, container, row, text, text_input, Column},
-    Alignment, Element, Length, Task, Theme,
+    widget::{
+        button, checkbox, column, container, image, pick_list, progress_bar, radio, row, stack,
+        text, scrollable, text_editor, text_input, Column, Row, Space,
+    },
+    window, Alignment, Color, Element, Length, Size, Subscription, Task, Theme,
 };
-use std::path::PathBuf;
-
-mod crypto;
-mod qr;
+use qr_data_exchange::batch::{self, BatchState, BatchStatus};
+use qr_data_exchange::config::{self, AppConfig};
+use qr_data_exchange::crypto::crypto_utils::{self, Cipher, CryptoError};
+use qr_data_exchange::crypto::passphrase::{self, PassphraseStyle};
+use qr_data_exchange::crypto::password::{self, Strength};
+use qr_data_exchange::preview::{self, DataPreview};
+use qr_data_exchange::qr::archive;
+use qr_data_exchange::qr::chunk::Chunk;
+use qr_data_exchange::qr::fountain::FountainDecoder;
+use qr_data_exchange::qr::pdf::{self, PdfPage};
+use qr_data_exchange::qr::processor::{
+    self, DeserializeOptions, PayloadMetadata, PayloadMode, Protection, QrDataProcessor,
+    SerializeOptions, Stage, TextEncoding,
+};
+use qr_data_exchange::qr::scan::ScanSession;
+use qr_data_exchange::qr::service::{
+    EcLevel, QrLogo, QrStyle, RenderOptions, DEFAULT_QUIET_ZONE, PRINT_IMAGE_SIZE,
+};
+#[cfg(feature = "screen")]
+use qr_data_exchange::qr::service::ScreenMonitor;
+use qr_data_exchange::selftest::{self, SelfTestReport};
+use qr_data_exchange::services::{
+    self, AnimationLayout, CompressionAnalysis, EncodeInput, PayloadShape, Progress, QrAnimation,
+    QrGenerationResult, SizeEstimate,
+};
+use qr_data_exchange::{keys, Error};
+use settings::Settings;
+use toast::Toasts;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio_util::sync::CancellationToken;
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::EnvFilter;
+use zeroize::Zeroizing;
 
 fn main() -> iced::Result {
-    tracing_subscriber::fmt::init();
-    iced::application("QR Data Exchange", QrApp::update, QrApp::view)
+    // Logs go to stderr, so stdout stays clean for the command line mode.
+    // RUST_LOG=debug also shows how long each step of the pipeline takes.
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .with_env_filter(
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
+        )
+        .with_span_events(FmtSpan::CLOSE)
+        .init();
+
+    let mut args: Vec<_> = std::env::args_os().skip(1).collect();
+    let config = match cli::take_config_path(&mut args) {
+        Ok(Some(path)) => config::load(&path, true),
+        Ok(None) => settings::config_dir()
+            .map_or_else(AppConfig::default, |dir| config::load(&dir.join(config::CONFIG_FILE), false)),
+        Err(code) => std::process::exit(code),
+    };
+    // Once for the whole process; if it fails, every operation that needs
+    // libsodium reports that instead of the app crashing.
+    let crypto = crypto_utils::init();
+    if let Err(ref e) = crypto {
+        tracing::error!("{}", e);
+    }
+    if let Some(code) = cli::run(args, &config) {
+        std::process::exit(code);
+    }
+
+    // A daemon rather than an application, so the QR code and the read
+    // dialog can have windows of their own; it exits with the main window.
+    let result = iced::daemon(QrApp::title, QrApp::update, QrApp::view)
         .theme(QrApp::theme)
-        .run_with(QrApp::new)
+        .subscription(QrApp::subscription)
+        .run_with(move || QrApp::new(config, crypto));
+
+    let _ = std::fs::remove_file(viewer_temp_path());
+    result
+}
+
+/// PNG handed to the external image viewer. There is one per process; it is
+/// overwritten on every open and removed when the app exits.
+fn viewer_temp_path() -> PathBuf {
+    std::env::temp_dir().join(format!("qr-data-exchange-{}.png", std::process::id()))
+}
+
+/// Initial window sizes, in logical pixels. The QR and read windows fit
+/// their content; all of them can be resized.
+const MAIN_WINDOW_SIZE: Size = Size::new(1024.0, 900.0);
+const QR_WINDOW_SIZE: Size = Size::new(480.0, 760.0);
+const READ_WINDOW_SIZE: Size = Size::new(680.0, 640.0);
+/// The hex inspector shows a fixed number of pixels per row, so that only
+/// the rows in view have to be laid out.
+const HEX_ROW_HEIGHT: f32 = 16.0;
+const HEX_FONT_SIZE: f32 = 12.0;
+const HEX_VIEW_HEIGHT: f32 = 240.0;
+/// Name suggested when saving decrypted data; payloads carry no file name.
+const DECRYPTED_FILE_STEM: &str = "decrypted";
+
+fn open_window(size: Size) -> (window::Id, Task<Message>) {
+    let (id, open) = window::open(window::Settings {
+        size,
+        ..window::Settings::default()
+    });
+    (id, open.discard())
 }
 
+/// Decrypted data and what the payload said about it.
+type Decryption = Result<(Zeroizing<Vec<u8>>, PayloadMetadata), Error>;
+
 #[derive(Debug, Clone)]
 enum Message {
-    PasswordChanged(String),
+    WindowClosed(window::Id),
+    DismissToast(u64),
+    ToastTick(Instant),
+    LanguageChanged(Lang),
+    ThemeChanged(Theme),
+    PasswordChanged(Zeroizing<String>),
+    /// Opens the generator, or draws a new password when it is open.
+    GeneratePassword,
+    TogglePasswordVisibility(bool),
+    PassphraseStyleChanged(StyleChoice),
+    CopyGeneratedPassword,
+    UseGeneratedPassword,
+    DiscardGeneratedPassword,
+    ProtectionModeChanged(ProtectionMode),
+    RecipientKeyChanged(String),
+    ShowOwnPublicKey,
+    OwnPublicKeyLoaded(Result<String, Error>),
+    CopyOwnPublicKey,
     FilenameChanged(String),
-    BrowseFile,
+    InputModeChanged(InputMode),
+    NoteEdited(text_editor::Action),
+    EncodingChanged(TextEncoding),
+    BinaryToggled(bool),
+    AnimatedToggled(bool),
+    ImageSizeChanged(u32),
+    QuietZoneChanged(u32),
+    BrowseLogo,
+    LogoSelected(Option<PathBuf>),
+    ClearLogo,
+    LogoSizeChanged(u8),
+    BrowseOutputDir,
+    OutputDirSelected(Option<PathBuf>),
+    ClearOutputDir,
+    AutoSaveToggled(bool),
+    /// PNGs of a generated code written to the output folder.
+    AutoSaved(Result<Vec<PathBuf>, Error>),
+    CipherChanged(Cipher),
+    ValidityChanged(Validity),
+    FountainToggled(bool),
+    BrowseInputFile,
     FileSelected(Option<PathBuf>),
+    /// Several files picked at once; they are packed into an archive.
+    FilesSelected(Option<Vec<PathBuf>>),
+    SizeEstimated(Option<SizeEstimate>),
+    AnalyzeCompression,
+    CompressionAnalyzed(Result<CompressionAnalysis, Error>),
+    CompressionLevelChosen(i32),
+    BrowseKeyfile,
+    KeyfileLoaded(Option<Result<Keyfile, Error>>),
+    ClearKeyfile,
     GenerateQr,
-    QrGenerated(Result<QrGenerationResult, String>),
+    /// Stops the running encode or decode.
+    CancelProcessing,
+    StageReached(Stage),
+    QrGenerated(Result<QrGenerationResult, Error>),
     ReadQrFromFile,
+    BrowseQrImage,
+    QrImageSelected(Option<PathBuf>),
     ReadQrFromString,
-    QrReadFromImage(Result<String, String>),
+    ReadQrFromClipboard,
+    #[cfg(feature = "screen")]
+    ScanScreen,
+    #[cfg(feature = "screen")]
+    MonitorsListed(Result<Vec<ScreenMonitor>, Error>),
+    /// Display to scan, `None` for all of them.
+    #[cfg(feature = "screen")]
+    ScreenChanged(Option<u32>),
+    /// Name of the scanned image file, if any, and the payload text.
+    QrReadFromImage(Option<String>, Result<String, Error>),
     ShowQrDisplay(QrGenerationResult),
     CloseQrDisplay,
+    DisplayEcLevelChanged(EcChoice),
+    DisplaySizeChanged(u32),
+    /// Renders the shown payload again with the display's EC level and size.
+    RerenderQr,
+    QrRerendered(Result<QrGenerationResult, Error>),
+    /// PNG that was read back and whether it yielded the payload text.
+    QrVerified(Vec<u8>, Result<bool, Error>),
+    OpenInViewer,
+    ViewerOpened(Result<(), Error>),
+    SaveQrText,
+    /// `true` once the file was written, `false` if the user cancelled.
+    QrTextSaved(Result<bool, Error>),
+    AnimationGenerated(Result<QrAnimation, Error>),
+    AnimationTick,
+    SaveAnimation,
+    /// Like [`Message::QrTextSaved`].
+    AnimationSaved(Result<bool, Error>),
+    ExportPdf(PdfSource),
+    /// Like [`Message::QrTextSaved`].
+    PdfExported(Result<bool, Error>),
+    CloseAnimation,
     ShowReadWindow(Option<String>),
     CloseReadWindow,
+    DiscardScan,
+    /// Adds the part of a split payload in the read window's text field to
+    /// the parts collected so far.
+    AddPart,
     DecryptInput(String),
+    LoadQrTextFile,
+    QrTextFileLoaded(Option<Result<String, Error>>),
     DecryptAndSave,
-    DecryptResult(Result<Vec<u8>, String>),
-    SaveDecryptedFile(Vec<u8>),
-    FileSaved(Result<(), String>),
+    DecryptToClipboard,
+    DecryptResult(Decryption),
+    ClipboardDecryptResult(Decryption),
+    SavePreview,
+    DiscardPreview,
+    ViewerAction(text_editor::Action),
+    CopyDecryptedText,
+    ToggleInspector,
+    InspectorScrolled(scrollable::Viewport),
+    CopyDecryptedHex,
+    CopyDecryptedBase64,
+    /// The data and the extension to suggest for it.
+    SaveDecryptedFile(Zeroizing<Vec<u8>>, &'static str),
+    /// `true` once the file was written, `false` if the user cancelled.
+    FileSaved(Result<bool, Error>),
+    ExtractArchive,
+    /// `true` once the files were written, `false` if the user cancelled.
+    ArchiveExtracted(Result<bool, Error>),
+    BatchEncode,
+    BatchSelected(Option<(Vec<PathBuf>, PathBuf)>),
+    BatchFileFinished(usize, BatchStatus),
+    CloseBatch,
+    ShowHistory,
+    CloseHistory,
+    ReopenHistoryEntry(usize),
+    HistoryRendered(Result<QrGenerationResult, Error>),
+    CopyHistoryEntry(usize),
+    ClearHistory,
+    PersistHistoryToggled(bool),
+    RunSelfTest,
+    SelfTestFinished(SelfTestReport),
+    CloseSelfTest,
+    /// Escape pressed in a window while no widget used it.
+    EscapePressed(window::Id),
 }
 
-#[derive(Debug, Clone)]
-struct QrGenerationResult {
-    qr_text: String,
-    qr_image: Vec<u8>, // PNG bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ProtectionMode {
+    Password,
+    Recipient,
+}
+
+/// How long a generated payload can be decrypted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Validity {
+    Hour,
+    Day,
+    Week,
+    Never,
+}
+
+impl Validity {
+    fn seconds(self) -> Option<i64> {
+        match self {
+            Validity::Hour => Some(60 * 60),
+            Validity::Day => Some(24 * 60 * 60),
+            Validity::Week => Some(7 * 24 * 60 * 60),
+            Validity::Never => None,
+        }
+    }
+}
+
+/// Side lengths offered for generated QR images, in pixels.
+const IMAGE_SIZES: [u32; 4] = [256, 512, PRINT_IMAGE_SIZE, 2048];
+
+/// Quiet zones offered, in modules on each side.
+const QUIET_ZONES: [u32; 4] = [2, DEFAULT_QUIET_ZONE, 8, 12];
+
+/// Logo sides offered, in percent of the code's side. The largest keeps the
+/// logo within `MAX_LOGO_AREA`.
+const LOGO_SIZES: [u8; 4] = [15, 25, 35, 44];
+
+/// Which of the open QR views a PDF export is made from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PdfSource {
+    QrDisplay,
+    Animation,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InputMode {
+    File,
+    Text,
+}
+
+/// The open windows. Closing a child window only drops its state; closing the
+/// main window closes the children and exits.
+#[derive(Debug)]
+struct Windows {
+    main: window::Id,
+    qr_display: Option<window::Id>,
+    read: Option<window::Id>,
 }
 
 struct QrApp {
-    password: String,
+    windows: Windows,
+    settings: Settings,
+    config: AppConfig,
+    password: Zeroizing<String>,
+    protection_mode: ProtectionMode,
+    recipient_key: String,
+    own_public_key: Option<String>,
     filename: String,
-    qr_display: Option<QrGenerationResult>,
+    /// Files packed into one archive instead of encoding `filename`.
+    archive_files: Vec<PathBuf>,
+    input_mode: InputMode,
+    note: text_editor::Content,
+    encoding: TextEncoding,
+    /// Single codes hold the packed payload as raw bytes instead of its text.
+    binary: bool,
+    image_size: u32,
+    quiet_zone: u32,
+    /// PNG placed in the middle of generated codes.
+    logo: Option<PathBuf>,
+    /// Percent of the code's side.
+    logo_size: u8,
+    /// Displays found at startup, for the scan screen choice.
+    #[cfg(feature = "screen")]
+    monitors: Vec<ScreenMonitor>,
+    #[cfg(feature = "screen")]
+    screen: Option<u32>,
+    cipher: Cipher,
+    validity: Validity,
+    animated: bool,
+    fountain: bool,
+    keyfile: Option<Keyfile>,
+    /// Whether the password field shows what was typed. Reset whenever a
+    /// code is put on screen for someone else to scan.
+    show_password: bool,
+    generator: Option<PassphraseGenerator>,
+    size_estimate: Option<SizeEstimate>,
+    compression: CompressionState,
+    qr_display: Option<QrDisplayState>,
+    animation: Option<AnimationState>,
     read_window: Option<ReadWindowState>,
-    error_message: Option<String>,
+    /// Parts of a split payload scanned one by one, kept across restarts.
+    scan: Option<ScanSession>,
+    /// Fountain frames scanned so far. Not stored, as the animation keeps
+    /// showing new frames for as long as it runs.
+    fountain_scan: Option<FountainDecoder>,
+    batch: Option<BatchState>,
+    /// Files the last generated code was saved to automatically.
+    auto_saved: Vec<PathBuf>,
+    /// [`Self::output_name`] when the running generation started.
+    generating_name: Option<String>,
+    self_test: Option<SelfTestState>,
+    history: History,
+    show_history: bool,
+    toasts: Toasts,
     is_processing: bool,
+    /// The running encode or decode, see [`Message::CancelProcessing`].
+    job: Option<Job>,
+    /// Last stage the job reported.
+    stage: Option<Stage>,
+}
+
+struct Job {
+    handle: iced::task::Handle,
+    cancel: CancellationToken,
+    /// The stages the job goes through, for the progress bar.
+    stages: &'static [Stage],
+}
+
+#[derive(Clone)]
+struct Keyfile {
+    path: PathBuf,
+    contents: Zeroizing<Vec<u8>>,
+}
+
+impl std::fmt::Debug for Keyfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Keyfile").field("path", &self.path).finish_non_exhaustive()
+    }
+}
+
+/// The generated code on display, with the settings to render it again.
+#[derive(Debug, Clone)]
+struct QrDisplayState {
+    result: QrGenerationResult,
+    ec_level: EcLevel,
+    size: u32,
+    rendering: bool,
+    /// Whether the image reads back as the payload; `None` while checking.
+    scannable: Option<bool>,
+}
+
+/// Error correction level offered on the QR display, shown with how much of
+/// the code may be damaged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct EcChoice(EcLevel);
+
+const EC_LEVELS: [EcChoice; 4] = [
+    EcChoice(EcLevel::L),
+    EcChoice(EcLevel::M),
+    EcChoice(EcLevel::Q),
+    EcChoice(EcLevel::H),
+];
+
+impl std::fmt::Display for EcChoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self.0 {
+            EcLevel::L => "L (7 %)",
+            EcLevel::M => "M (15 %)",
+            EcLevel::Q => "Q (25 %)",
+            EcLevel::H => "H (30 %)",
+        })
+    }
+}
+
+/// Entry of the recent files list; shown by its full path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct RecentFile(PathBuf);
+
+impl std::fmt::Display for RecentFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.display().fmt(f)
+    }
+}
+
+/// Entry of the display list; `None` scans every display.
+#[cfg(feature = "screen")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ScreenChoice {
+    id: Option<u32>,
+    label: String,
+}
+
+#[cfg(feature = "screen")]
+impl std::fmt::Display for ScreenChoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.label)
+    }
+}
+
+/// Entry of the passphrase style list, labelled in the UI language.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct StyleChoice {
+    style: PassphraseStyle,
+    label: String,
+}
+
+impl std::fmt::Display for StyleChoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.label)
+    }
+}
+
+/// A generated password, shown in the clear until it is used or discarded.
+struct PassphraseGenerator {
+    style: PassphraseStyle,
+    password: Zeroizing<String>,
+}
+
+impl PassphraseGenerator {
+    fn new(style: PassphraseStyle) -> Result<Self, CryptoError> {
+        Ok(Self {
+            style,
+            password: passphrase::generate(style)?,
+        })
+    }
+}
+
+/// The "Analyze" comparison of compression levels for the current input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum CompressionState {
+    Idle,
+    Running,
+    Done(CompressionAnalysis),
 }
 
 #[derive(Debug, Clone)]
+struct AnimationState {
+    animation: QrAnimation,
+    frames: Vec<image::Handle>,
+    current: usize,
+}
+
+#[derive(Debug, Clone)]
+enum SelfTestState {
+    Running,
+    Finished(SelfTestReport),
+}
+
+#[derive(Debug)]
 struct ReadWindowState {
     qr_text: String,
-    password: String,
+    /// Name of the image the text was scanned from, for the history.
+    source: Option<String>,
+    /// Decrypted data waiting for the user to save or discard it.
+    decrypted: Option<Decrypted>,
+}
+
+struct Decrypted {
+    data: Zeroizing<Vec<u8>>,
+    preview: DataPreview,
+    expires_at: Option<i64>,
+    /// Verified digest as hex; `None` for payloads that carried none.
+    digest_hex: Option<String>,
+    /// Suggested in the save dialog, from the detected type.
+    extension: &'static str,
+    /// Read-only view of the whole text, for small UTF-8 data. The editor
+    /// keeps its own copy, which isn't wiped but dropped with the preview.
+    viewer: Option<text_editor::Content>,
+    /// Whether the hex inspector is open.
+    inspecting: bool,
+    /// Scroll position of the hex inspector in pixels.
+    hex_offset: f32,
+    /// Names and sizes of the files, for an archive.
+    archive: Option<Vec<(String, usize)>>,
+}
+
+impl std::fmt::Debug for Decrypted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Decrypted").field("len", &self.data.len()).finish_non_exhaustive()
+    }
 }
 
 impl QrApp {
-    fn new() -> (Self, Task<Message>) {
-        (
-            Self {
-                password: String::new(),
-                filename: String::new(),
+    fn new(config: AppConfig, crypto: Result<(), CryptoError>) -> (Self, Task<Message>) {
+        let (main, open_main) = open_window(MAIN_WINDOW_SIZE);
+        let mut toasts = Toasts::default();
+        if let Err(e) = crypto {
+            toasts.error(UiError::Startup(e.into()));
+        }
+        let settings = settings::load();
+        let history = if settings.persist_history {
+            history::load()
+        } else {
+            History::default()
+        };
+        let mut app = Self {
+            windows: Windows {
+                main,
                 qr_display: None,
-                read_window: None,
-                error_message: None,
-                is_processing: false,
+                read: None,
             },
-            Task::none(),
-        )
+            settings,
+            config,
+            password: Zeroizing::new(String::new()),
+            protection_mode: ProtectionMode::Password,
+            recipient_key: String::new(),
+            own_public_key: None,
+            filename: String::new(),
+            archive_files: Vec::new(),
+            input_mode: InputMode::File,
+            note: text_editor::Content::new(),
+            encoding: TextEncoding::default(),
+            binary: false,
+            image_size: config.image_size,
+            quiet_zone: DEFAULT_QUIET_ZONE,
+            logo: None,
+            logo_size: 25,
+            #[cfg(feature = "screen")]
+            monitors: Vec::new(),
+            #[cfg(feature = "screen")]
+            screen: None,
+            cipher: Cipher::default(),
+            validity: Validity::Never,
+            animated: false,
+            fountain: false,
+            keyfile: None,
+            show_password: false,
+            generator: None,
+            size_estimate: None,
+            compression: CompressionState::Idle,
+            qr_display: None,
+            animation: None,
+            read_window: None,
+            scan: scan_session::load(),
+            fountain_scan: None,
+            batch: None,
+            auto_saved: Vec::new(),
+            generating_name: None,
+            self_test: None,
+            history,
+            show_history: false,
+            toasts,
+            is_processing: false,
+            job: None,
+            stage: None,
+        };
+        // A scan interrupted by a crash or restart picks up where it stopped.
+        let resume = if app.scan.is_some() {
+            app.open_read_window(String::new(), None)
+        } else {
+            Task::none()
+        };
+        #[cfg(feature = "screen")]
+        let startup = Task::batch([
+            open_main,
+            resume,
+            Task::perform(services::list_monitors(), Message::MonitorsListed),
+        ]);
+        #[cfg(not(feature = "screen"))]
+        let startup = Task::batch([open_main, resume]);
+        (app, startup)
+    }
+
+    fn title(&self, id: window::Id) -> String {
+        let t = self.settings.language.strings();
+        if Some(id) == self.windows.qr_display {
+            t.generated_qr_title.to_string()
+        } else if Some(id) == self.windows.read {
+            t.read_title.to_string()
+        } else {
+            "QR Data Exchange".to_string()
+        }
     }
 
     fn update(&mut self, message: Message) -> Task<Message> {
         match message {
+            Message::WindowClosed(id) if id == self.windows.main => {
+                let children = [self.windows.qr_display.take(), self.windows.read.take()];
+                Task::batch(children.into_iter().flatten().map(window::close))
+                    .chain(iced::exit())
+            }
+            Message::DismissToast(id) => {
+                self.toasts.dismiss(id);
+                Task::none()
+            }
+            Message::ToastTick(now) => {
+                self.toasts.expire(now);
+                Task::none()
+            }
+            Message::WindowClosed(id) => {
+                if Some(id) == self.windows.qr_display {
+                    self.windows.qr_display = None;
+                    self.qr_display = None;
+                } else if Some(id) == self.windows.read {
+                    self.windows.read = None;
+                    self.read_window = None;
+                }
+                Task::none()
+            }
+            Message::LanguageChanged(language) => {
+                self.settings.language = language;
+                Task::future(settings::save(self.settings.clone())).discard()
+            }
+            Message::ThemeChanged(theme) => {
+                self.settings.set_theme(&theme);
+                Task::future(settings::save(self.settings.clone())).discard()
+            }
             Message::PasswordChanged(password) => {
-                if password.len() <= 20 {
+                if password.chars().count() <= password::MAX_PASSWORD_LENGTH {
                     self.password = password;
                 }
                 Task::none()
             }
-            Message::FilenameChanged(filename) => {
-                self.filename = filename;
+            Message::TogglePasswordVisibility(visible) => {
+                self.show_password = visible;
+                Task::none()
+            }
+            Message::GeneratePassword => {
+                let style = self.generator.as_ref().map(|g| g.style).unwrap_or_default();
+                self.regenerate_password(style);
+                Task::none()
+            }
+            Message::PassphraseStyleChanged(choice) => {
+                self.regenerate_password(choice.style);
+                Task::none()
+            }
+            Message::CopyGeneratedPassword => match self.generator {
+                Some(ref generator) => {
+                    self.toasts.info(Notice::CopiedToClipboard);
+                    iced::clipboard::write(generator.password.to_string())
+                }
+                None => Task::none(),
+            },
+            Message::UseGeneratedPassword => {
+                if let Some(generator) = self.generator.take() {
+                    self.password = generator.password;
+                }
                 Task::none()
             }
-            Message::BrowseFile => Task::perform(
+            Message::DiscardGeneratedPassword => {
+                self.generator = None;
+                Task::none()
+            }
+            Message::ProtectionModeChanged(mode) => {
+                self.protection_mode = mode;
+                self.estimate_size()
+            }
+            Message::RecipientKeyChanged(key) => {
+                self.recipient_key = key;
+                self.estimate_size()
+            }
+            Message::ShowOwnPublicKey => Task::perform(
                 async {
-                    rfd::AsyncFileDialog::new()
-                        .add_filter("All files", &["*"])
-                        .add_filter("PNG files", &["png"])
-                        .pick_file()
+                    keys::load_or_generate_keypair()
                         .await
-                        .map(|f| f.path().to_path_buf())
+                        .map(|(public_key, _)| keys::encode_public_key(&public_key))
+                        .map_err(Error::from)
                 },
-                Message::FileSelected,
+                Message::OwnPublicKeyLoaded,
             ),
+            Message::OwnPublicKeyLoaded(Ok(public_key)) => {
+                self.own_public_key = Some(public_key);
+                Task::none()
+            }
+            Message::OwnPublicKeyLoaded(Err(e)) => {
+                self.toasts.error(UiError::KeyPair(e));
+                Task::none()
+            }
+            Message::CopyOwnPublicKey => match self.own_public_key {
+                Some(ref public_key) => iced::clipboard::write(public_key.clone()),
+                None => Task::none(),
+            },
+            Message::FilenameChanged(filename) => {
+                self.filename = filename;
+                self.archive_files.clear();
+                self.estimate_size()
+            }
+            Message::InputModeChanged(mode) => {
+                self.input_mode = mode;
+                self.estimate_size()
+            }
+            Message::NoteEdited(action) => {
+                let is_edit = action.is_edit();
+                self.note.perform(action);
+                if is_edit {
+                    self.estimate_size()
+                } else {
+                    Task::none()
+                }
+            }
+            Message::EncodingChanged(encoding) => {
+                self.encoding = encoding;
+                self.estimate_size()
+            }
+            Message::BinaryToggled(enabled) => {
+                self.binary = enabled;
+                self.estimate_size()
+            }
+            Message::CipherChanged(cipher) => {
+                self.cipher = cipher;
+                Task::none()
+            }
+            Message::ValidityChanged(validity) => {
+                self.validity = validity;
+                self.estimate_size()
+            }
+            Message::ImageSizeChanged(size) => {
+                self.image_size = size;
+                Task::none()
+            }
+            Message::QuietZoneChanged(modules) => {
+                self.quiet_zone = modules;
+                Task::none()
+            }
+            Message::BrowseLogo => {
+                let t = self.settings.language.strings();
+                let (title, filter) = (t.select_logo, t.png_images);
+                Task::perform(
+                    async move {
+                        rfd::AsyncFileDialog::new()
+                            .set_title(title)
+                            .add_filter(filter, &["png"])
+                            .pick_file()
+                            .await
+                            .map(|file| file.path().to_path_buf())
+                    },
+                    Message::LogoSelected,
+                )
+            }
+            // A logo raises the error correction level, which lowers the
+            // capacity the estimate is measured against.
+            Message::LogoSelected(Some(path)) => {
+                self.logo = Some(path);
+                self.estimate_size()
+            }
+            Message::LogoSelected(None) => Task::none(),
+            Message::ClearLogo => {
+                self.logo = None;
+                self.estimate_size()
+            }
+            Message::LogoSizeChanged(percent) => {
+                self.logo_size = percent;
+                Task::none()
+            }
+            Message::BrowseOutputDir => {
+                let title = self.settings.language.strings().select_output_dir;
+                let mut dialog = rfd::AsyncFileDialog::new().set_title(title);
+                if let Some(ref dir) = self.settings.output_dir {
+                    dialog = dialog.set_directory(dir);
+                }
+                Task::perform(
+                    async move { dialog.pick_folder().await.map(|dir| dir.path().to_path_buf()) },
+                    Message::OutputDirSelected,
+                )
+            }
+            Message::OutputDirSelected(Some(dir)) => {
+                self.settings.output_dir = Some(dir);
+                Task::future(settings::save(self.settings.clone())).discard()
+            }
+            Message::OutputDirSelected(None) => Task::none(),
+            Message::ClearOutputDir => {
+                self.settings.output_dir = None;
+                self.settings.auto_save_png = false;
+                Task::future(settings::save(self.settings.clone())).discard()
+            }
+            Message::AutoSaveToggled(enabled) => {
+                self.settings.auto_save_png = enabled;
+                Task::future(settings::save(self.settings.clone())).discard()
+            }
+            Message::AutoSaved(Ok(paths)) => {
+                self.auto_saved = paths;
+                Task::none()
+            }
+            Message::AutoSaved(Err(e)) => {
+                self.toasts.error(UiError::Save(e));
+                Task::none()
+            }
+            Message::AnimatedToggled(enabled) => {
+                self.animated = enabled;
+                Task::none()
+            }
+            Message::FountainToggled(enabled) => {
+                self.fountain = enabled;
+                Task::none()
+            }
+            Message::BrowseInputFile => {
+                // No filter: any file can be encoded, and an "all files" filter
+                // isn't shown the same way on every platform.
+                let mut dialog = rfd::AsyncFileDialog::new();
+                if let Some(dir) = &self.settings.input_dir {
+                    dialog = dialog.set_directory(dir);
+                }
+                Task::perform(
+                    async move {
+                        dialog
+                            .pick_files()
+                            .await
+                            .map(|files| files.iter().map(|f| f.path().to_path_buf()).collect())
+                    },
+                    Message::FilesSelected,
+                )
+            }
+            Message::FilesSelected(Some(mut paths)) if paths.len() == 1 => {
+                Task::done(Message::FileSelected(paths.pop()))
+            }
+            Message::FilesSelected(Some(paths)) if !paths.is_empty() => {
+                self.filename.clear();
+                self.settings.input_dir = paths[0].parent().map(Path::to_path_buf);
+                self.archive_files = paths;
+                self.size_estimate = None;
+                self.compression = CompressionState::Idle;
+                Task::future(settings::save(self.settings.clone())).discard()
+            }
+            Message::FilesSelected(_) => Task::none(),
             Message::FileSelected(Some(path)) => {
                 self.filename = path.to_string_lossy().to_string();
-                Task::none()
+                self.archive_files.clear();
+                self.settings.input_dir = path.parent().map(Path::to_path_buf);
+                self.settings.remember_file(&path);
+                Task::batch([
+                    self.estimate_size(),
+                    Task::future(settings::save(self.settings.clone())).discard(),
+                ])
             }
             Message::FileSelected(None) => Task::none(),
+            Message::SizeEstimated(estimate) => {
+                self.size_estimate = estimate;
+                Task::none()
+            }
+            Message::AnalyzeCompression => {
+                let input = match self.input_mode {
+                    InputMode::File if self.filename.is_empty() => {
+                        self.toasts.error(UiError::FileMissing);
+                        return Task::none();
+                    }
+                    InputMode::File => EncodeInput::File(PathBuf::from(&self.filename)),
+                    InputMode::Text => EncodeInput::Bytes(self.note_text().into_bytes()),
+                };
+                let shape = self.payload_shape();
+                self.compression = CompressionState::Running;
+                Task::perform(
+                    services::analyze_compression(input, shape, services::MAX_ANIMATION_INPUT_LEN),
+                    Message::CompressionAnalyzed,
+                )
+            }
+            Message::CompressionAnalyzed(Ok(analysis)) => {
+                // Dropped if the input changed in the meantime.
+                if self.compression == CompressionState::Running {
+                    self.compression = CompressionState::Done(analysis);
+                }
+                Task::none()
+            }
+            Message::CompressionAnalyzed(Err(e)) => {
+                self.compression = CompressionState::Idle;
+                self.toasts.error(UiError::Analyze(e));
+                Task::none()
+            }
+            Message::CompressionLevelChosen(level) => {
+                // For this session only; the config file is never written.
+                self.config.compression_level = level;
+                Task::none()
+            }
+            Message::BrowseKeyfile => {
+                let title = self.settings.language.strings().select_keyfile;
+                Task::perform(
+                    async move {
+                        let file = rfd::AsyncFileDialog::new()
+                            .set_title(title)
+                            .pick_file()
+                            .await?;
+                        let path = file.path().to_path_buf();
+                        Some(
+                            tokio::fs::read(&path)
+                                .await
+                                .map_err(|e| Error::read_failed(&path, e))
+                                .map(|contents| Keyfile {
+                                    path,
+                                    contents: Zeroizing::new(contents),
+                                }),
+                        )
+                    },
+                    Message::KeyfileLoaded,
+                )
+            }
+            Message::KeyfileLoaded(Some(Ok(keyfile))) => {
+                self.keyfile = Some(keyfile);
+                Task::none()
+            }
+            Message::KeyfileLoaded(Some(Err(e))) => {
+                self.toasts.error(UiError::Keyfile(e));
+                Task::none()
+            }
+            Message::KeyfileLoaded(None) => Task::none(),
+            Message::ClearKeyfile => {
+                self.keyfile = None;
+                Task::none()
+            }
             Message::GenerateQr => {
-                if self.password.is_empty() {
-                    self.error_message = Some("Bitte gib ein Passwort ein.".to_string());
+                if self.is_processing {
                     return Task::none();
                 }
-                if self.filename.is_empty() {
-                    self.error_message = Some("Bitte wähle eine Datei aus.".to_string());
+                let protection = match self.protection() {
+                    Ok(protection) => protection,
+                    Err(e) => {
+                        self.toasts.error(e);
+                        return Task::none();
+                    }
+                };
+                if self.input_mode == InputMode::File
+                    && self.filename.is_empty()
+                    && self.archive_files.is_empty()
+                {
+                    self.toasts.error(UiError::FileMissing);
+                    return Task::none();
+                }
+                if self.input_mode == InputMode::File {
+                    if let Err(e) = self.check_input_files() {
+                        self.toasts.error(UiError::Generate(e));
+                        return Task::none();
+                    }
+                }
+                if self.input_mode == InputMode::Text && self.note_text().is_empty() {
+                    self.toasts.error(UiError::TextMissing);
                     return Task::none();
                 }
 
-                let filename = self.filename.clone();
-                let password = self.password.clone();
-                self.is_processing = true;
-                self.error_message = None;
+                let archive = self.input_mode == InputMode::File && !self.archive_files.is_empty();
+                let options = SerializeOptions {
+                    archive,
+                    ..self.serialize_options()
+                };
+                let render = self.render_options();
+                let layout = if self.fountain {
+                    AnimationLayout::Fountain
+                } else {
+                    AnimationLayout::Sequence
+                };
+                let cancel = CancellationToken::new();
+                let (progress, stages) = Progress::channel(cancel.clone());
+                self.auto_saved.clear();
+                self.generating_name = Some(self.output_name());
 
-                Task::perform(
-                    async move { generate_qr_async(filename, password).await },
-                    Message::QrGenerated,
-                )
+                let task = match self.input_mode {
+                    InputMode::File if archive && self.animated => {
+                        let files = self.archive_files.clone();
+                        Task::perform(
+                            async move {
+                                progress.enter(Stage::Reading)?;
+                                let raw_data = services::pack_files(
+                                    files,
+                                    services::MAX_ANIMATION_INPUT_LEN,
+                                )
+                                .await?;
+                                services::generate_qr_animation_from_bytes(
+                                    raw_data, protection, options, layout, render, progress,
+                                )
+                                .await
+                            },
+                            Message::AnimationGenerated,
+                        )
+                    }
+                    InputMode::File if archive => {
+                        let files = self.archive_files.clone();
+                        let max_input_len = self.config.max_input_bytes;
+                        Task::perform(
+                            async move {
+                                progress.enter(Stage::Reading)?;
+                                let raw_data = services::pack_files(files, max_input_len).await?;
+                                services::generate_qr_from_bytes(
+                                    raw_data, protection, options, render, progress,
+                                )
+                                .await
+                            },
+                            Message::QrGenerated,
+                        )
+                    }
+                    InputMode::File if self.animated => {
+                        let filename = self.filename.clone();
+                        Task::perform(
+                            async move {
+                                services::generate_qr_animation_async(
+                                    filename,
+                                    protection,
+                                    options,
+                                    layout,
+                                    render,
+                                    services::MAX_ANIMATION_INPUT_LEN,
+                                    progress,
+                                )
+                                .await
+                            },
+                            Message::AnimationGenerated,
+                        )
+                    }
+                    InputMode::Text if self.animated => {
+                        let raw_data = self.note_text().into_bytes();
+                        Task::perform(
+                            async move {
+                                services::generate_qr_animation_from_bytes(
+                                    raw_data, protection, options, layout, render, progress,
+                                )
+                                .await
+                            },
+                            Message::AnimationGenerated,
+                        )
+                    }
+                    InputMode::File => {
+                        let filename = self.filename.clone();
+                        let max_input_len = self.config.max_input_bytes;
+                        Task::perform(
+                            async move {
+                                services::generate_qr_async(
+                                    filename,
+                                    protection,
+                                    options,
+                                    render,
+                                    max_input_len,
+                                    progress,
+                                )
+                                .await
+                            },
+                            Message::QrGenerated,
+                        )
+                    }
+                    InputMode::Text => {
+                        let raw_data = self.note_text().into_bytes();
+                        Task::perform(
+                            async move {
+                                services::generate_qr_from_bytes(
+                                    raw_data, protection, options, render, progress,
+                                )
+                                .await
+                            },
+                            Message::QrGenerated,
+                        )
+                    }
+                };
+                self.start_job(task, cancel, stages, &Stage::ENCODE)
+            }
+            Message::CancelProcessing => {
+                // Aborting the task doesn't stop its blocking work, which
+                // sees the cancelled token before its next step and stops
+                // there; a step already running finishes in the background
+                // and its result is dropped with the aborted task.
+                if let Some(job) = self.job.take() {
+                    job.cancel.cancel();
+                    job.handle.abort();
+                    self.toasts.info(Notice::Cancelled);
+                }
+                self.finish_job();
+                Task::none()
+            }
+            Message::StageReached(stage) => {
+                if self.job.is_some() {
+                    self.stage = Some(stage);
+                }
+                Task::none()
             }
             Message::QrGenerated(Ok(result)) => {
-                self.is_processing = false;
-                Task::done(Message::ShowQrDisplay(result))
+                self.finish_job();
+                self.toasts.info(Notice::QrGenerated);
+                let entry =
+                    HistoryEntry::new(Direction::Encode, self.input_name(), result.qr_text.clone());
+                let auto_save = self.auto_save(vec![result.qr_image.clone()]);
+                Task::batch([
+                    self.record(entry),
+                    auto_save,
+                    Task::done(Message::ShowQrDisplay(result)),
+                ])
             }
             Message::QrGenerated(Err(e)) => {
-                self.is_processing = false;
-                self.error_message = Some(e);
+                self.finish_job();
+                self.toasts.error(UiError::Generate(e));
                 Task::none()
             }
             Message::ShowQrDisplay(result) => {
-                self.qr_display = Some(result);
-                Task::none()
+                self.show_password = false;
+                let render = self.render_options();
+                self.qr_display = Some(QrDisplayState {
+                    result,
+                    ec_level: render.ec_level,
+                    size: render.size,
+                    rendering: false,
+                    scannable: None,
+                });
+                let verify = self.verify_qr_display();
+                let window = match self.windows.qr_display {
+                    Some(id) => window::gain_focus(id),
+                    None => {
+                        let (id, open) = open_window(QR_WINDOW_SIZE);
+                        self.windows.qr_display = Some(id);
+                        open
+                    }
+                };
+                Task::batch([window, verify])
             }
             Message::CloseQrDisplay => {
                 self.qr_display = None;
+                match self.windows.qr_display.take() {
+                    Some(id) => window::close(id),
+                    None => Task::none(),
+                }
+            }
+            Message::DisplayEcLevelChanged(EcChoice(ec_level)) => {
+                if let Some(ref mut display) = self.qr_display {
+                    display.ec_level = ec_level;
+                }
                 Task::none()
             }
-            Message::ReadQrFromFile => {
-                if self.password.is_empty() {
-                    self.error_message = Some("Bitte gib ein Passwort ein.".to_string());
+            Message::DisplaySizeChanged(size) => {
+                if let Some(ref mut display) = self.qr_display {
+                    display.size = size;
+                }
+                Task::none()
+            }
+            Message::RerenderQr => {
+                let render = self.render_options();
+                let Some(ref mut display) = self.qr_display else {
+                    return Task::none();
+                };
+                // The payload stays as it is; only the image is made again.
+                display.rendering = true;
+                let render = RenderOptions {
+                    ec_level: display.ec_level,
+                    size: display.size,
+                    ..render
+                };
+                Task::perform(
+                    services::render_qr_text_async(display.result.qr_text.clone(), render),
+                    Message::QrRerendered,
+                )
+            }
+            Message::QrRerendered(result) => {
+                let Some(ref mut display) = self.qr_display else {
                     return Task::none();
+                };
+                display.rendering = false;
+                match result {
+                    Ok(result) => {
+                        display.result = result;
+                        display.scannable = None;
+                        self.verify_qr_display()
+                    }
+                    Err(e) => {
+                        self.toasts.error(UiError::Generate(e));
+                        Task::none()
+                    }
                 }
-                if self.filename.is_empty() {
-                    self.error_message = Some("Bitte wähle eine Datei aus.".to_string());
+            }
+            Message::QrVerified(png, result) => {
+                let Some(ref mut display) = self.qr_display else {
+                    return Task::none();
+                };
+                // The image may have been rendered again in the meantime.
+                if display.result.qr_image != png {
                     return Task::none();
                 }
-
-                let filename = self.filename.clone();
+                display.scannable = Some(result.unwrap_or_else(|e| {
+                    tracing::warn!("Generated QR code could not be read back: {}", e);
+                    false
+                }));
+                Task::none()
+            }
+            Message::OpenInViewer => {
+                let Some(display) = self.qr_display.as_ref() else {
+                    return Task::none();
+                };
+                let png = display.result.qr_image.clone();
                 Task::perform(
-                    async move { read_qr_from_image(filename).await },
-                    Message::QrReadFromImage,
+                    async move {
+                        let path = viewer_temp_path();
+                        tokio::fs::write(&path, png).await?;
+                        open::that_detached(&path)?;
+                        Ok(())
+                    },
+                    Message::ViewerOpened,
                 )
             }
-            Message::QrReadFromImage(Ok(text)) => Task::done(Message::ShowReadWindow(Some(text))),
-            Message::QrReadFromImage(Err(e)) => {
-                self.error_message = Some(e);
+            Message::ViewerOpened(Ok(())) => Task::none(),
+            Message::ViewerOpened(Err(e)) => {
+                self.toasts.error(UiError::OpenViewer(e));
                 Task::none()
             }
-            Message::ReadQrFromString => {
-                if self.password.is_empty() {
-                    self.error_message = Some("Bitte gib ein Passwort ein.".to_string());
+            Message::SaveQrText => {
+                let Some(display) = self.qr_display.as_ref() else {
                     return Task::none();
+                };
+                let qr_text = display.result.qr_text.clone();
+                let strings = self.settings.language.strings();
+                Task::perform(
+                    async move {
+                        match rfd::AsyncFileDialog::new()
+                            .add_filter(strings.text_files, &["txt", "qrdx"])
+                            .set_file_name("qr.txt")
+                            .save_file()
+                            .await
+                        {
+                            Some(file) => services::save_bytes_atomic(file.path(), qr_text.as_bytes())
+                                .await
+                                .map(|()| true),
+                            None => Ok(false),
+                        }
+                    },
+                    Message::QrTextSaved,
+                )
+            }
+            Message::QrTextSaved(Ok(saved))
+            | Message::AnimationSaved(Ok(saved))
+            | Message::PdfExported(Ok(saved)) => {
+                if saved {
+                    self.toasts.info(Notice::Saved);
                 }
-                Task::done(Message::ShowReadWindow(None))
+                Task::none()
             }
-            Message::ShowReadWindow(qr_text) => {
-                self.read_window = Some(ReadWindowState {
-                    qr_text: qr_text.unwrap_or_default(),
-                    password: self.password.clone(),
-                });
+            Message::QrTextSaved(Err(e)) => {
+                self.toasts.error(UiError::Save(e));
                 Task::none()
             }
-            Message::CloseReadWindow => {
-                self.read_window = None;
+            Message::AnimationGenerated(Ok(animation)) => {
+                self.finish_job();
+                self.toasts.info(Notice::QrGenerated);
+                let entry = HistoryEntry::new(
+                    Direction::Encode,
+                    self.input_name(),
+                    animation.qr_text.clone(),
+                );
+                let record = self.record(entry);
+                let auto_save = self.auto_save(animation.frames.clone());
+                let frames = animation
+                    .frames
+                    .iter()
+                    .map(|png| image::Handle::from_bytes(png.clone()))
+                    .collect();
+                self.show_password = false;
+                self.animation = Some(AnimationState {
+                    animation,
+                    frames,
+                    current: 0,
+                });
+                Task::batch([record, auto_save])
+            }
+            Message::AnimationGenerated(Err(e)) => {
+                self.finish_job();
+                self.toasts.error(UiError::Generate(e));
                 Task::none()
             }
-            Message::DecryptInput(text) => {
-                if let Some(ref mut window) = self.read_window {
-                    window.qr_text = text;
+            Message::AnimationTick => {
+                if let Some(ref mut state) = self.animation {
+                    state.current = (state.current + 1) % state.frames.len().max(1);
                 }
                 Task::none()
             }
-            Message::DecryptAndSave => {
-                if let Some(ref window) = self.read_window {
-                    let qr_text = window.qr_text.clone();
-                    let password = window.password.clone();
-
+            Message::SaveAnimation => match self.animation {
+                Some(ref state) => {
+                    let gif = state.animation.gif.clone();
                     Task::perform(
-                        async move { decrypt_qr_data(qr_text, password).await },
-                        Message::DecryptResult,
+                        async move {
+                            match rfd::AsyncFileDialog::new()
+                                .add_filter("GIF", &["gif"])
+                                .set_file_name("qr.gif")
+                                .save_file()
+                                .await
+                            {
+                                Some(file) => tokio::fs::write(file.path(), gif)
+                                    .await
+                                    .map(|()| true)
+                                    .map_err(Error::from),
+                                None => Ok(false),
+                            }
+                        },
+                        Message::AnimationSaved,
                     )
-                } else {
-                    Task::none()
                 }
-            }
-            Message::DecryptResult(Ok(data)) => Task::done(Message::SaveDecryptedFile(data)),
-            Message::DecryptResult(Err(e)) => {
-                self.error_message = Some(e);
+                None => Task::none(),
+            },
+            Message::AnimationSaved(Err(e)) => {
+                self.toasts.error(UiError::Save(e));
                 Task::none()
             }
-            Message::SaveDecryptedFile(data) => Task::perform(
-                async move {
-                    if let Some(file) = rfd::AsyncFileDialog::new().save_file().await {
-                        tokio::fs::write(file.path(), data)
+            Message::ExportPdf(source) => {
+                let Some(pages) = self.pdf_pages(source) else {
+                    return Task::none();
+                };
+                let strings = self.settings.language.strings();
+                Task::perform(
+                    async move {
+                        match rfd::AsyncFileDialog::new()
+                            .add_filter(strings.pdf_files, &["pdf"])
+                            .set_file_name("qr.pdf")
+                            .save_file()
                             .await
-                            .map_err(|e| e.to_string())
-                    } else {
-                        Ok(())
+                        {
+                            Some(file) => {
+                                services::export_qr_pdf(
+                                    "QR Data Exchange".to_string(),
+                                    pages,
+                                    file.path().to_path_buf(),
+                                )
+                                .await
+                                .map(|()| true)
+                            }
+                            None => Ok(false),
+                        }
+                    },
+                    Message::PdfExported,
+                )
+            }
+            Message::PdfExported(Err(e)) => {
+                self.toasts.error(UiError::Save(e));
+                Task::none()
+            }
+            Message::CloseAnimation => {
+                self.animation = None;
+                Task::none()
+            }
+            Message::ReadQrFromFile => {
+                if self.protection_mode == ProtectionMode::Password && self.password.is_empty() {
+                    self.toasts.error(UiError::PasswordMissing);
+                    return Task::none();
+                }
+                if self.filename.is_empty() {
+                    self.toasts.error(UiError::FileMissing);
+                    return Task::none();
+                }
+                let path = PathBuf::from(&self.filename);
+                if !services::is_scannable(&path) {
+                    self.toasts.error(UiError::NotScannable(path));
+                    return Task::none();
+                }
+
+                let filename = self.filename.clone();
+                let source = self.input_name();
+                let is_pdf = services::is_pdf(&path);
+                Task::perform(
+                    async move {
+                        if is_pdf {
+                            services::read_qr_from_pdf(filename).await
+                        } else {
+                            services::read_qr_from_image(filename).await
+                        }
+                    },
+                    move |result| Message::QrReadFromImage(source.clone(), result),
+                )
+            }
+            Message::BrowseQrImage => {
+                let strings = self.settings.language.strings();
+                let mut extensions = services::QR_IMAGE_EXTENSIONS.to_vec();
+                extensions.push("pdf");
+                let mut dialog = rfd::AsyncFileDialog::new().add_filter(strings.qr_images, &extensions);
+                if let Some(dir) = &self.settings.qr_image_dir {
+                    dialog = dialog.set_directory(dir);
+                }
+                Task::perform(
+                    async move { dialog.pick_file().await.map(|file| file.path().to_path_buf()) },
+                    Message::QrImageSelected,
+                )
+            }
+            Message::QrImageSelected(Some(path)) => {
+                self.settings.qr_image_dir = path.parent().map(Path::to_path_buf);
+                self.filename = path.to_string_lossy().to_string();
+                self.archive_files.clear();
+                self.size_estimate = None;
+                self.compression = CompressionState::Idle;
+                Task::batch([
+                    Task::future(settings::save(self.settings.clone())).discard(),
+                    Task::done(Message::ReadQrFromFile),
+                ])
+            }
+            Message::QrImageSelected(None) => Task::none(),
+            Message::ReadQrFromClipboard => {
+                if self.protection_mode == ProtectionMode::Password && self.password.is_empty() {
+                    self.toasts.error(UiError::PasswordMissing);
+                    return Task::none();
+                }
+
+                Task::perform(services::read_qr_from_clipboard(), |result| {
+                    Message::QrReadFromImage(None, result)
+                })
+            }
+            #[cfg(feature = "screen")]
+            Message::ScanScreen => {
+                if self.protection_mode == ProtectionMode::Password && self.password.is_empty() {
+                    self.toasts.error(UiError::PasswordMissing);
+                    return Task::none();
+                }
+
+                Task::perform(services::read_qr_from_screen(self.screen), |result| {
+                    Message::QrReadFromImage(None, result)
+                })
+            }
+            #[cfg(feature = "screen")]
+            Message::MonitorsListed(result) => {
+                match result {
+                    Ok(monitors) => self.monitors = monitors,
+                    Err(e) => tracing::warn!("Could not list displays: {}", e),
+                }
+                Task::none()
+            }
+            #[cfg(feature = "screen")]
+            Message::ScreenChanged(screen) => {
+                self.screen = screen;
+                Task::none()
+            }
+            Message::QrReadFromImage(source, Ok(text)) if Chunk::parse(&text).is_ok() => {
+                self.add_scanned_part(&text, source)
+            }
+            Message::QrReadFromImage(source, Ok(text)) if FountainDecoder::is_frame(&text) => {
+                self.add_fountain_frame(&text, source)
+            }
+            Message::QrReadFromImage(source, Ok(text)) => self.open_read_window(text, source),
+            Message::QrReadFromImage(_, Err(e)) => {
+                self.toasts.error(UiError::ReadQr(e));
+                Task::none()
+            }
+            Message::ReadQrFromString => {
+                if self.protection_mode == ProtectionMode::Password && self.password.is_empty() {
+                    self.toasts.error(UiError::PasswordMissing);
+                    return Task::none();
+                }
+                Task::done(Message::ShowReadWindow(None))
+            }
+            Message::ShowReadWindow(qr_text) => {
+                self.open_read_window(qr_text.unwrap_or_default(), None)
+            }
+            Message::CloseReadWindow => self.close_read_window(),
+            Message::DiscardScan => {
+                self.scan = None;
+                self.fountain_scan = None;
+                Task::future(scan_session::remove()).discard()
+            }
+            Message::AddPart => match self.read_window {
+                Some(ref state) if FountainDecoder::is_frame(&state.qr_text) => {
+                    let frame = state.qr_text.clone();
+                    self.add_fountain_frame(&frame, None)
+                }
+                Some(ref state) => {
+                    let code = state.qr_text.clone();
+                    self.add_scanned_part(&code, None)
+                }
+                None => Task::none(),
+            },
+            Message::DecryptInput(text) => {
+                if let Some(ref mut window) = self.read_window {
+                    window.qr_text = text;
+                }
+                Task::none()
+            }
+            Message::LoadQrTextFile => {
+                let strings = self.settings.language.strings();
+                Task::perform(
+                    async move {
+                        let file = rfd::AsyncFileDialog::new()
+                            .add_filter(strings.text_files, &["txt", "qrdx"])
+                            .pick_file()
+                            .await?;
+                        Some(services::load_payload_text(file.path().to_path_buf()).await)
+                    },
+                    Message::QrTextFileLoaded,
+                )
+            }
+            Message::QrTextFileLoaded(Some(Ok(text))) => {
+                if let Some(ref mut window) = self.read_window {
+                    window.qr_text = text;
+                }
+                Task::none()
+            }
+            Message::QrTextFileLoaded(Some(Err(e))) => {
+                self.toasts.error(UiError::LoadText(e));
+                Task::none()
+            }
+            Message::QrTextFileLoaded(None) => Task::none(),
+            Message::DecryptAndSave => self.decrypt(Message::DecryptResult),
+            Message::DecryptToClipboard => self.decrypt(Message::ClipboardDecryptResult),
+            Message::DecryptResult(Ok((data, metadata))) => {
+                self.finish_job();
+                self.show_preview(data, metadata);
+                self.record_decode()
+            }
+            Message::DecryptResult(Err(e)) | Message::ClipboardDecryptResult(Err(e)) => {
+                self.finish_job();
+                self.toasts.error(UiError::Decrypt(e));
+                Task::none()
+            }
+            Message::ClipboardDecryptResult(Ok((data, metadata))) => {
+                self.finish_job();
+                let record = self.record_decode();
+                match std::str::from_utf8(&data) {
+                    Ok(text) if !metadata.is_archive => {
+                        self.toasts.info(Notice::CopiedToClipboard);
+                        Task::batch([record, iced::clipboard::write(text.to_string())])
                     }
-                },
-                Message::FileSaved,
-            ),
-            Message::FileSaved(Ok(())) => {
-                self.read_window = None;
+                    // Binary data can't be pasted anywhere, so offer to save it instead.
+                    _ => {
+                        self.show_preview(data, metadata);
+                        record
+                    }
+                }
+            }
+            Message::SavePreview => match self.read_window {
+                Some(ReadWindowState {
+                    decrypted: Some(ref decrypted),
+                    ..
+                }) => Task::done(Message::SaveDecryptedFile(
+                    decrypted.data.clone(),
+                    decrypted.extension,
+                )),
+                _ => Task::none(),
+            },
+            Message::SaveDecryptedFile(data, extension) => {
+                let lang = self.settings.language;
+                Task::perform(
+                    async move {
+                        let Some(file) = rfd::AsyncFileDialog::new()
+                            .set_file_name(format!("{}.{}", DECRYPTED_FILE_STEM, extension))
+                            .save_file()
+                            .await
+                        else {
+                            return Ok(false);
+                        };
+                        // Not every platform's save dialog asks before replacing a file.
+                        let path = file.path();
+                        let exists = tokio::fs::try_exists(path).await.unwrap_or(true);
+                        if exists && !confirm_overwrite(path, lang).await {
+                            return Ok(false);
+                        }
+
+                        services::save_bytes_atomic(path, &data).await.map(|()| true)
+                    },
+                    Message::FileSaved,
+                )
+            }
+            Message::ExtractArchive => {
+                let Some(decrypted) = self.read_window.as_ref().and_then(|w| w.decrypted.as_ref())
+                else {
+                    return Task::none();
+                };
+                let data = decrypted.data.clone();
+                Task::perform(
+                    async move {
+                        let Some(folder) = rfd::AsyncFileDialog::new().pick_folder().await else {
+                            return Ok(false);
+                        };
+                        services::extract_archive(data, folder.path().to_path_buf())
+                            .await
+                            .map(|_| true)
+                    },
+                    Message::ArchiveExtracted,
+                )
+            }
+            Message::ArchiveExtracted(Ok(true)) => {
+                self.toasts.info(Notice::Saved);
+                self.close_read_window()
+            }
+            Message::ArchiveExtracted(Ok(false)) => Task::none(),
+            Message::ArchiveExtracted(Err(e)) => {
+                self.toasts.error(UiError::Extract(e));
                 Task::none()
             }
+            Message::ViewerAction(action) => {
+                if let Some(viewer) = self
+                    .read_window
+                    .as_mut()
+                    .and_then(|window| window.decrypted.as_mut())
+                    .and_then(|decrypted| decrypted.viewer.as_mut())
+                {
+                    // Selecting and scrolling only; the text stays as decrypted.
+                    if !action.is_edit() {
+                        viewer.perform(action);
+                    }
+                }
+                Task::none()
+            }
+            Message::CopyDecryptedText => {
+                let text = self
+                    .read_window
+                    .as_ref()
+                    .and_then(|window| window.decrypted.as_ref())
+                    .and_then(|decrypted| preview::viewer_text(&decrypted.data));
+                match text {
+                    Some(text) => {
+                        self.toasts.info(Notice::CopiedToClipboard);
+                        iced::clipboard::write(text.to_string())
+                    }
+                    None => Task::none(),
+                }
+            }
+            Message::ToggleInspector => {
+                if let Some(decrypted) = self.decrypted_mut() {
+                    decrypted.inspecting = !decrypted.inspecting;
+                    decrypted.hex_offset = 0.0;
+                }
+                Task::none()
+            }
+            Message::InspectorScrolled(viewport) => {
+                if let Some(decrypted) = self.decrypted_mut() {
+                    decrypted.hex_offset = viewport.absolute_offset().y;
+                }
+                Task::none()
+            }
+            Message::CopyDecryptedHex | Message::CopyDecryptedBase64 => {
+                let Some(decrypted) = self.read_window.as_ref().and_then(|w| w.decrypted.as_ref())
+                else {
+                    return Task::none();
+                };
+                let text = if matches!(message, Message::CopyDecryptedHex) {
+                    preview::to_hex(&decrypted.data).to_string()
+                } else {
+                    STANDARD.encode(&decrypted.data[..])
+                };
+                self.toasts.info(Notice::CopiedToClipboard);
+                iced::clipboard::write(text)
+            }
+            Message::DiscardPreview => {
+                if let Some(ref mut window) = self.read_window {
+                    window.decrypted = None;
+                }
+                Task::none()
+            }
+            Message::FileSaved(Ok(true)) => {
+                self.toasts.info(Notice::Saved);
+                self.close_read_window()
+            }
+            Message::FileSaved(Ok(false)) => Task::none(),
             Message::FileSaved(Err(e)) => {
-                self.error_message = Some(e);
+                self.toasts.error(UiError::Save(e));
+                Task::none()
+            }
+            Message::BatchEncode => {
+                if let Err(e) = self.protection() {
+                    self.toasts.error(e);
+                    return Task::none();
+                }
+                let strings = self.settings.language.strings();
+                let default_output_dir = self.settings.output_dir.clone();
+                Task::perform(
+                    async move {
+                        let files = rfd::AsyncFileDialog::new()
+                            .set_title(strings.select_batch_files)
+                            .pick_files()
+                            .await?;
+                        let mut output_dialog =
+                            rfd::AsyncFileDialog::new().set_title(strings.select_output_dir);
+                        if let Some(dir) = default_output_dir {
+                            output_dialog = output_dialog.set_directory(dir);
+                        }
+                        let output_dir = output_dialog.pick_folder().await?;
+                        Some((
+                            files.iter().map(|f| f.path().to_path_buf()).collect(),
+                            output_dir.path().to_path_buf(),
+                        ))
+                    },
+                    Message::BatchSelected,
+                )
+            }
+            Message::BatchSelected(Some((files, output_dir))) => {
+                let protection = match self.protection() {
+                    Ok(protection) => protection,
+                    Err(e) => {
+                        self.toasts.error(e);
+                        return Task::none();
+                    }
+                };
+
+                let limiter = Arc::new(tokio::sync::Semaphore::new(batch::MAX_CONCURRENT_JOBS));
+                let tasks: Vec<_> = files
+                    .iter()
+                    .enumerate()
+                    .map(|(index, input)| {
+                        let job = batch::encode_file(
+                            input.clone(),
+                            output_dir.clone(),
+                            protection.clone(),
+                            self.serialize_options(),
+                            self.render_options(),
+                            self.config.max_input_bytes,
+                            limiter.clone(),
+                        );
+                        Task::perform(job, move |status| {
+                            Message::BatchFileFinished(index, status)
+                        })
+                    })
+                    .collect();
+
+                self.batch = Some(BatchState::new(files, output_dir));
+                Task::batch(tasks)
+            }
+            Message::BatchSelected(None) => Task::none(),
+            Message::BatchFileFinished(index, status) => {
+                if let Some(job) = self
+                    .batch
+                    .as_mut()
+                    .and_then(|batch| batch.jobs.get_mut(index))
+                {
+                    job.status = status;
+                }
+                Task::none()
+            }
+            Message::CloseBatch => {
+                self.batch = None;
+                Task::none()
+            }
+            Message::ShowHistory => {
+                self.show_history = true;
+                Task::none()
+            }
+            Message::CloseHistory => {
+                self.show_history = false;
+                Task::none()
+            }
+            Message::ReopenHistoryEntry(index) => match self.history.entries.get(index) {
+                Some(entry) => Task::perform(
+                    services::render_qr_text_async(entry.payload.clone(), self.render_options()),
+                    Message::HistoryRendered,
+                ),
+                None => Task::none(),
+            },
+            Message::HistoryRendered(Ok(result)) => Task::done(Message::ShowQrDisplay(result)),
+            Message::HistoryRendered(Err(e)) => {
+                self.toasts.error(UiError::Generate(e));
+                Task::none()
+            }
+            Message::CopyHistoryEntry(index) => match self.history.entries.get(index) {
+                Some(entry) => {
+                    self.toasts.info(Notice::CopiedToClipboard);
+                    iced::clipboard::write(entry.payload.clone())
+                }
+                None => Task::none(),
+            },
+            Message::ClearHistory => {
+                self.history = History::default();
+                Task::future(history::remove()).discard()
+            }
+            Message::PersistHistoryToggled(enabled) => {
+                self.settings.persist_history = enabled;
+                let history = if enabled {
+                    Task::future(history::save(self.history.clone())).discard()
+                } else {
+                    Task::future(history::remove()).discard()
+                };
+                let settings = Task::future(settings::save(self.settings.clone())).discard();
+                Task::batch([settings, history])
+            }
+            Message::RunSelfTest => {
+                if matches!(self.self_test, Some(SelfTestState::Running)) {
+                    return Task::none();
+                }
+                self.self_test = Some(SelfTestState::Running);
+                Task::perform(
+                    async {
+                        tokio::task::spawn_blocking(selftest::run)
+                            .await
+                            .expect("self-test panicked")
+                    },
+                    Message::SelfTestFinished,
+                )
+            }
+            Message::SelfTestFinished(report) => {
+                self.self_test = Some(SelfTestState::Finished(report));
+                Task::none()
+            }
+            Message::CloseSelfTest => {
+                self.self_test = None;
+                Task::none()
+            }
+            Message::EscapePressed(id) if Some(id) == self.windows.read => self.close_read_window(),
+            Message::EscapePressed(id) if Some(id) == self.windows.qr_display => {
+                Task::done(Message::CloseQrDisplay)
+            }
+            Message::EscapePressed(_) => {
+                // The main window: close its panels, not the app.
+                self.animation = None;
+                self.batch = None;
+                self.show_history = false;
+                self.self_test = None;
+                self.generator = None;
                 Task::none()
             }
         }
     }
 
-    fn view(&self) -> Element<Message> {
+    /// The secret new payloads are encrypted with, validated for encoding.
+    fn protection(&self) -> Result<Protection, UiError> {
+        match self.protection_mode {
+            ProtectionMode::Password => {
+                if self.password.is_empty() {
+                    return Err(UiError::PasswordMissing);
+                }
+                self.config
+                    .password_policy
+                    .check(&self.password)
+                    .map_err(UiError::PasswordPolicy)?;
+                Ok(Protection::Password(self.password.clone()))
+            }
+            ProtectionMode::Recipient => keys::decode_public_keys(&self.recipient_key)
+                .map(Protection::Recipients)
+                .map_err(|_| UiError::InvalidRecipientKey),
+        }
+    }
+
+    /// Layout of the payload the current settings produce, for estimates.
+    fn payload_shape(&self) -> PayloadShape {
+        let mode = match self.protection_mode {
+            ProtectionMode::Password => PayloadMode::Password,
+            ProtectionMode::Recipient => PayloadMode::Recipient,
+        };
+        PayloadShape {
+            mode,
+            recipients: keys::decode_public_keys(&self.recipient_key).map_or(1, |k| k.len()),
+            encoding: self.encoding,
+            binary: self.binary,
+            expires: self.validity.seconds().is_some(),
+            ec_level: self.render_options().effective_ec_level(),
+        }
+    }
+
+    /// Refreshes the payload size estimate for the current input. A
+    /// compression analysis of the previous input no longer applies.
+    fn estimate_size(&mut self) -> Task<Message> {
+        let shape = self.payload_shape();
+        self.compression = CompressionState::Idle;
+
+        match self.input_mode {
+            // Packing reads every file; the size only shows up on generating.
+            InputMode::File if self.filename.is_empty() || !self.archive_files.is_empty() => {
+                self.size_estimate = None;
+                Task::none()
+            }
+            InputMode::File => {
+                let filename = self.filename.clone();
+                Task::perform(
+                    async move { services::estimate_file_size(filename, shape).await.ok() },
+                    Message::SizeEstimated,
+                )
+            }
+            InputMode::Text => {
+                let note = self.note_text();
+                self.size_estimate =
+                    services::estimate_size(note.as_bytes(), note.len() as u64, shape).ok();
+                Task::none()
+            }
+        }
+    }
+
+    /// One page per QR code of the given view, captioned with today's date,
+    /// the input file name and a fingerprint of the whole payload.
+    fn pdf_pages(&self, source: PdfSource) -> Option<Vec<PdfPage>> {
+        let (qr_text, payloads) = match source {
+            PdfSource::QrDisplay => {
+                let result = &self.qr_display.as_ref()?.result;
+                (&result.qr_text, std::slice::from_ref(&result.qr_text))
+            }
+            PdfSource::Animation => {
+                let state = self.animation.as_ref()?;
+                (&state.animation.qr_text, state.animation.chunks.as_slice())
+            }
+        };
+
+        let lang = self.settings.language;
+        let created = time::OffsetDateTime::now_utc().date().to_string();
+        let filename = self.input_name();
+        let fingerprint = pdf::fingerprint(qr_text);
+        let count = payloads.len();
+
+        Some(
+            payloads
+                .iter()
+                .enumerate()
+                .map(|(index, payload)| PdfPage {
+                    payload: payload.clone(),
+                    caption: lang.pdf_caption(
+                        &created,
+                        filename.as_deref(),
+                        &fingerprint,
+                        (count > 1).then_some((index + 1, count)),
+                    ),
+                })
+                .collect(),
+        )
+    }
+
+    fn serialize_options(&self) -> SerializeOptions {
+        SerializeOptions {
+            encoding: self.encoding,
+            cipher: self.cipher,
+            keyfile: self.keyfile.as_ref().map(|k| k.contents.clone()),
+            expires_at: self
+                .validity
+                .seconds()
+                .map(|seconds| processor::unix_now() + seconds),
+            ..self.config.serialize_options()
+        }
+    }
+
+    fn regenerate_password(&mut self, style: PassphraseStyle) {
+        match PassphraseGenerator::new(style) {
+            Ok(generator) => self.generator = Some(generator),
+            Err(e) => self.toasts.error(UiError::Generate(e.into())),
+        }
+    }
+
+    fn open_read_window(&mut self, qr_text: String, source: Option<String>) -> Task<Message> {
+        self.show_password = false;
+        self.read_window = Some(ReadWindowState {
+            qr_text,
+            source,
+            decrypted: None,
+        });
+        match self.windows.read {
+            Some(id) => window::gain_focus(id),
+            None => {
+                let (id, open) = open_window(READ_WINDOW_SIZE);
+                self.windows.read = Some(id);
+                open
+            }
+        }
+    }
+
+    /// Runs `work` as the current job. It reports to the [`Progress`] that
+    /// came with `stages`, which arrive as [`Message::StageReached`].
+    fn start_job(
+        &mut self,
+        work: Task<Message>,
+        cancel: CancellationToken,
+        stages: UnboundedReceiver<Stage>,
+        order: &'static [Stage],
+    ) -> Task<Message> {
+        let stages = iced::futures::stream::unfold(stages, |mut stages| async move {
+            stages.recv().await.map(|stage| (stage, stages))
+        });
+        let (task, handle) = Task::batch([Task::run(stages, Message::StageReached), work]).abortable();
+        self.is_processing = true;
+        self.stage = None;
+        self.job = Some(Job {
+            handle,
+            cancel,
+            stages: order,
+        });
+        task
+    }
+
+    fn finish_job(&mut self) {
+        self.is_processing = false;
+        self.job = None;
+        self.stage = None;
+    }
+
+    /// Decrypts the text of the read window; `done` receives the result.
+    fn decrypt(&mut self, done: fn(Decryption) -> Message) -> Task<Message> {
+        let Some(ref window) = self.read_window else {
+            return Task::none();
+        };
+        if self.is_processing {
+            return Task::none();
+        }
+        let qr_text = window.qr_text.trim().to_string();
+        if let Err(e) = QrDataProcessor::check_payload(&qr_text) {
+            self.toasts.error(UiError::Decrypt(e.into()));
+            return Task::none();
+        }
+        let password = self.password.clone();
+        let options = self.deserialize_options();
+        let cancel = CancellationToken::new();
+        let (progress, stages) = Progress::channel(cancel.clone());
+
+        let work = Task::perform(
+            async move { services::decrypt_qr_data(qr_text, password, options, progress).await },
+            done,
+        );
+        self.start_job(work, cancel, stages, &Stage::DECODE)
+    }
+
+    /// Adds a scanned part of a split payload to the scan session. Once all
+    /// parts are in, the payload goes to the read window and the stored
+    /// session is deleted.
+    fn add_scanned_part(&mut self, code: &str, source: Option<String>) -> Task<Message> {
+        let scan = self.scan.get_or_insert_with(ScanSession::default);
+        let added = match scan.add(code) {
+            Ok(added) => added,
+            Err(e) => {
+                self.toasts.error(UiError::ReadQr(e.into()));
+                return Task::none();
+            }
+        };
+        // A part pasted into the read window moves into the list of parts.
+        if let Some(ref mut state) = self.read_window {
+            if state.qr_text.trim() == code.trim() {
+                state.qr_text.clear();
+            }
+        }
+
+        if scan.is_complete() {
+            return match scan.join() {
+                Ok(payload) => {
+                    self.scan = None;
+                    Task::batch([
+                        Task::future(scan_session::remove()).discard(),
+                        self.open_read_window(payload, source),
+                    ])
+                }
+                Err(e) => {
+                    self.toasts.error(UiError::ReadQr(e.into()));
+                    Task::none()
+                }
+            };
+        }
+
+        let save = if added {
+            Task::future(scan_session::save(scan.clone())).discard()
+        } else {
+            Task::none()
+        };
+        Task::batch([save, self.show_scan_progress()])
+    }
+
+    /// Adds a scanned fountain frame. Once enough frames are in, the payload
+    /// goes to the read window.
+    fn add_fountain_frame(&mut self, frame: &str, source: Option<String>) -> Task<Message> {
+        let decoder = self.fountain_scan.get_or_insert_with(FountainDecoder::new);
+        let payload = match decoder.add(frame) {
+            Ok(payload) => payload,
+            Err(e) => {
+                self.toasts.error(UiError::ReadQr(e.into()));
+                return Task::none();
+            }
+        };
+        if let Some(ref mut state) = self.read_window {
+            if state.qr_text.trim() == frame.trim() {
+                state.qr_text.clear();
+            }
+        }
+
+        match payload {
+            Some(payload) => {
+                self.fountain_scan = None;
+                self.open_read_window(payload, source)
+            }
+            None => self.show_scan_progress(),
+        }
+    }
+
+    /// Brings up the read window, which lists what was scanned so far.
+    fn show_scan_progress(&mut self) -> Task<Message> {
+        match self.windows.read {
+            Some(id) => window::gain_focus(id),
+            None => self.open_read_window(String::new(), None),
+        }
+    }
+
+    fn close_read_window(&mut self) -> Task<Message> {
+        self.read_window = None;
+        match self.windows.read.take() {
+            Some(id) => window::close(id),
+            None => Task::none(),
+        }
+    }
+
+    fn decrypted_mut(&mut self) -> Option<&mut Decrypted> {
+        self.read_window.as_mut().and_then(|window| window.decrypted.as_mut())
+    }
+
+    fn show_preview(&mut self, data: Zeroizing<Vec<u8>>, metadata: PayloadMetadata) {
+        let archive = if metadata.is_archive {
+            match archive::unpack(&data) {
+                Ok(entries) => Some(
+                    entries
+                        .into_iter()
+                        .map(|entry| (entry.name, entry.data.len()))
+                        .collect(),
+                ),
+                Err(e) => {
+                    self.toasts.error(UiError::Extract(e.into()));
+                    return;
+                }
+            }
+        } else {
+            None
+        };
+
+        if let Some(ref mut window) = self.read_window {
+            window.decrypted = Some(Decrypted {
+                preview: DataPreview::new(&data),
+                viewer: if archive.is_none() {
+                    preview::viewer_text(&data).map(text_editor::Content::with_text)
+                } else {
+                    None
+                },
+                data,
+                expires_at: metadata.expires_at,
+                digest_hex: metadata.digest_hex(),
+                extension: metadata.extension(),
+                inspecting: false,
+                hex_offset: 0.0,
+                archive,
+            });
+        }
+    }
+
+    /// Makes sure the input file, or each file to pack, is still there, so a
+    /// mistyped path is reported before anything is read or encrypted.
+    fn check_input_files(&self) -> Result<(), Error> {
+        let paths = if self.archive_files.is_empty() {
+            vec![PathBuf::from(&self.filename)]
+        } else {
+            self.archive_files.clone()
+        };
+        for path in paths {
+            std::fs::metadata(&path).map_err(|e| Error::read_failed(&path, e))?;
+        }
+        Ok(())
+    }
+
+    /// File name of the selected input file, or the names of the files packed
+    /// together; `None` when encoding typed text.
+    fn input_name(&self) -> Option<String> {
+        match self.input_mode {
+            InputMode::File if !self.archive_files.is_empty() => Some(
+                self.archive_files
+                    .iter()
+                    .filter_map(|path| path.file_name())
+                    .map(|name| name.to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+            InputMode::File => Path::new(&self.filename)
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string()),
+            InputMode::Text => None,
+        }
+    }
+
+    /// Name the saved images of a code start with: the input file name, or a
+    /// fixed name for archives and typed text.
+    fn output_name(&self) -> String {
+        match self.input_mode {
+            InputMode::File if !self.archive_files.is_empty() => "archive".to_string(),
+            InputMode::File => Path::new(&self.filename)
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "payload".to_string()),
+            InputMode::Text => "note".to_string(),
+        }
+    }
+
+    /// Writes the PNGs of a generated code into the output folder when the
+    /// user turned that on, see [`Message::AutoSaved`]. They are named after
+    /// the input the generation started with, not the one selected now.
+    fn auto_save(&mut self, pngs: Vec<Vec<u8>>) -> Task<Message> {
+        let name = self.generating_name.take();
+        match (&self.settings.output_dir, name) {
+            (Some(dir), Some(name)) if self.settings.auto_save_png => Task::perform(
+                services::auto_save_pngs(dir.clone(), name, pngs),
+                Message::AutoSaved,
+            ),
+            _ => Task::none(),
+        }
+    }
+
+    /// Adds `entry` to the history and stores it if the user asked for that.
+    fn record(&mut self, entry: HistoryEntry) -> Task<Message> {
+        self.history.push(entry);
+        if self.settings.persist_history {
+            Task::future(history::save(self.history.clone())).discard()
+        } else {
+            Task::none()
+        }
+    }
+
+    /// Records the payload text of the read window after it was decrypted.
+    fn record_decode(&mut self) -> Task<Message> {
+        let Some(ref window) = self.read_window else {
+            return Task::none();
+        };
+        let entry = HistoryEntry::new(
+            Direction::Decode,
+            window.source.clone(),
+            window.qr_text.trim().to_string(),
+        );
+        self.record(entry)
+    }
+
+    fn render_options(&self) -> RenderOptions {
+        RenderOptions {
+            size: self.image_size,
+            style: QrStyle {
+                quiet_zone: self.quiet_zone,
+                logo: self.logo.clone().map(|path| QrLogo {
+                    path,
+                    relative_size: f32::from(self.logo_size) / 100.0,
+                }),
+                ..QrStyle::default()
+            },
+            binary: self.binary,
+            ..self.config.render_options()
+        }
+    }
+
+    /// Reads the shown code back from its PNG, see [`Message::QrVerified`].
+    fn verify_qr_display(&self) -> Task<Message> {
+        let Some(ref display) = self.qr_display else {
+            return Task::none();
+        };
+        let png = display.result.qr_image.clone();
+        let qr_text = display.result.qr_text.clone();
+        Task::perform(
+            async move {
+                let result = services::verify_qr_image(png.clone(), qr_text).await;
+                (png, result)
+            },
+            |(png, result)| Message::QrVerified(png, result),
+        )
+    }
+
+    fn deserialize_options(&self) -> DeserializeOptions {
+        DeserializeOptions {
+            keyfile: self.keyfile.as_ref().map(|k| k.contents.clone()),
+            ..self.config.deserialize_options()
+        }
+    }
+
+    /// The note as typed, without the trailing newline the editor always appends.
+    fn note_text(&self) -> String {
+        let mut note = self.note.text();
+        if note.ends_with('\n') {
+            note.pop();
+        }
+        note
+    }
+
+    fn view(&self, id: window::Id) -> Element<'_, Message> {
+        let lang = self.settings.language;
+        let content = if id == self.windows.main {
+            self.main_view()
+        } else {
+            let content = if Some(id) == self.windows.qr_display {
+                self.qr_display.as_ref().map(|display| qr_display_view(display, lang))
+            } else {
+                self.read_window
+                    .as_ref()
+                    .map(|state| {
+                        read_window_view(
+                            state,
+                            self.scan.as_ref(),
+                            self.fountain_scan.as_ref(),
+                            self.progress_view(),
+                            lang,
+                        )
+                    })
+            };
+            scrollable(column![].push_maybe(content).padding(20))
+                .height(Length::Fill)
+                .into()
+        };
+
+        stack![content, self.toasts_view()].into()
+    }
+
+    /// "Scan screen" button, with a display choice when there is more than one.
+    #[cfg(feature = "screen")]
+    fn screen_scan_controls(&self) -> Option<Element<'_, Message>> {
+        let t = self.settings.language.strings();
+        let scan = button(t.scan_screen).on_press(Message::ScanScreen);
+        if self.monitors.len() < 2 {
+            return Some(scan.into());
+        }
+
+        let choices: Vec<ScreenChoice> = std::iter::once(ScreenChoice {
+            id: None,
+            label: t.all_displays.to_string(),
+        })
+        .chain(self.monitors.iter().map(|monitor| ScreenChoice {
+            id: Some(monitor.id),
+            label: monitor.name.clone(),
+        }))
+        .collect();
+        let selected = choices.iter().find(|choice| choice.id == self.screen).cloned();
+
+        Some(
+            row![
+                scan,
+                pick_list(choices, selected, |choice| Message::ScreenChanged(choice.id)),
+            ]
+            .spacing(5)
+            .into(),
+        )
+    }
+
+    #[cfg(not(feature = "screen"))]
+    fn screen_scan_controls(&self) -> Option<Element<'_, Message>> {
+        None
+    }
+
+    /// Stage and progress of the running job, with a button to cancel it.
+    fn progress_view(&self) -> Option<Element<'_, Message>> {
+        let job = self.job.as_ref()?;
+        let lang = self.settings.language;
+        let done = self
+            .stage
+            .and_then(|stage| job.stages.iter().position(|&s| s == stage))
+            .unwrap_or(0);
+        let label = match self.stage {
+            Some(stage) => lang.stage(stage),
+            None => lang.strings().processing,
+        };
+        Some(
+            row![
+                text(label),
+                progress_bar(0.0..=job.stages.len() as f32, done as f32)
+                    .width(Length::Fixed(120.0))
+                    .height(Length::Fixed(6.0)),
+                button(lang.strings().cancel).on_press(Message::CancelProcessing),
+            ]
+            .spacing(10)
+            .align_y(Alignment::Center)
+            .into(),
+        )
+    }
+
+    /// The "Analyze" button and, once it ran, compressed size and number of
+    /// codes per level.
+    fn compression_view(&self, lang: Lang) -> Element<'_, Message> {
+        let t = lang.strings();
+        let running = self.compression == CompressionState::Running;
+        let mut content = column![row![
+            text("").width(Length::Fixed(120.0)),
+            button(if running { t.analyzing } else { t.analyze_compression })
+                .on_press_maybe((!running).then_some(Message::AnalyzeCompression)),
+        ]
+        .spacing(10)]
+        .spacing(4);
+
+        if let CompressionState::Done(ref analysis) = self.compression {
+            content = content.push(row![
+                text("").width(Length::Fixed(120.0)),
+                text(lang.analyzed_input(analysis.input_len)).size(12),
+            ]
+            .spacing(10));
+            for level in &analysis.levels {
+                let chosen = level.level == self.config.compression_level;
+                content = content.push(
+                    row![
+                        text("").width(Length::Fixed(120.0)),
+                        text(lang.level_analysis(level))
+                            .font(iced::Font::MONOSPACE)
+                            .size(12)
+                            .width(Length::Fixed(420.0)),
+                        button(text(t.use_level).size(12))
+                            .on_press_maybe((!chosen).then_some(Message::CompressionLevelChosen(level.level))),
+                    ]
+                    .spacing(10)
+                    .align_y(Alignment::Center),
+                );
+            }
+        }
+
+        content.into()
+    }
+
+    /// Toasts in the lower right corner, shown in every window.
+    fn toasts_view(&self) -> Element<'_, Message> {
+        let lang = self.settings.language;
+        let toasts = self.toasts.iter().map(|toast| {
+            let error = toast.is_error();
+            button(text(toast.describe(lang)))
+                .on_press(Message::DismissToast(toast.id))
+                .width(Length::Fixed(320.0))
+                .padding(10)
+                .style(move |theme: &Theme, _status| {
+                    let palette = theme.extended_palette();
+                    let pair = if error { palette.danger.base } else { palette.success.base };
+                    button::Style {
+                        background: Some(pair.color.into()),
+                        text_color: pair.text,
+                        border: iced::Border {
+                            radius: 5.0.into(),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    }
+                })
+                .into()
+        });
+
+        container(Column::with_children(toasts).spacing(10))
+            .align_right(Length::Fill)
+            .align_bottom(Length::Fill)
+            .padding(20)
+            .into()
+    }
+
+    fn main_view(&self) -> Element<'_, Message> {
+        let lang = self.settings.language;
+        let t = lang.strings();
+
         let main_content = column![
-            text("PyQrDataExchange").size(24),
             row![
-                text("Password [1-20]:").width(Length::Fixed(120.0)),
+                text("PyQrDataExchange").size(24).width(Length::Fill),
+                text(t.theme),
+                pick_list(Theme::ALL, Some(self.settings.theme()), Message::ThemeChanged),
+                light_dark_toggle(&self.settings.theme(), lang),
+                text(t.language),
+                pick_list(Lang::ALL, Some(lang), Message::LanguageChanged),
+            ]
+            .spacing(10)
+            .align_y(Alignment::Center),
+            row![
+                text(t.protect_with).width(Length::Fixed(120.0)),
+                radio(
+                    t.password_option,
+                    ProtectionMode::Password,
+                    Some(self.protection_mode),
+                    Message::ProtectionModeChanged
+                ),
+                radio(
+                    t.recipient_option,
+                    ProtectionMode::Recipient,
+                    Some(self.protection_mode),
+                    Message::ProtectionModeChanged
+                ),
+                button(t.show_public_key).on_press(Message::ShowOwnPublicKey),
+            ]
+            .spacing(10)
+            .align_y(Alignment::Center),
+            row![
+                text(t.password_label).width(Length::Fixed(120.0)),
                 text_input("", &self.password)
-                    .on_input(Message::PasswordChanged)
-                    .secure(true)
+                    .on_input(|password| Message::PasswordChanged(Zeroizing::new(password)))
+                    .on_submit(Message::GenerateQr)
+                    .secure(!self.show_password)
                     .width(Length::Fixed(150.0)),
+                checkbox(t.show_password, self.show_password)
+                    .on_toggle(Message::TogglePasswordVisibility),
+                button(t.generate_password).on_press(Message::GeneratePassword),
+                text(t.cipher),
+                pick_list(Cipher::ALL, Some(self.cipher), Message::CipherChanged),
+            ]
+            .spacing(10)
+            .align_y(Alignment::Center),
+            password_strength_view(&self.password, lang),
+        ]
+            .spacing(20);
+
+        let main_content = match self.generator {
+            Some(ref generator) => main_content.push(generator_view(generator, lang)),
+            None => main_content,
+        };
+
+        let main_content = if self.protection_mode == ProtectionMode::Recipient {
+            main_content.push(
+                row![
+                    text(t.recipient_key_label).width(Length::Fixed(120.0)),
+                    text_input("qrdx-pk:... qrdx-pk:...", &self.recipient_key)
+                        .on_input(Message::RecipientKeyChanged)
+                        .on_submit(Message::GenerateQr)
+                        .width(Length::Fixed(400.0)),
+                ]
+                .spacing(10)
+                .align_y(Alignment::Center),
+            )
+        } else {
+            main_content
+        };
+
+        let main_content = if let Some(ref public_key) = self.own_public_key {
+            main_content.push(
+                row![
+                    text(t.own_public_key_label).width(Length::Fixed(120.0)),
+                    text_input("", public_key).width(Length::Fixed(400.0)),
+                    button(t.copy).on_press(Message::CopyOwnPublicKey),
+                ]
+                .spacing(10)
+                .align_y(Alignment::Center),
+            )
+        } else {
+            main_content
+        };
+
+        let main_content = if self.archive_files.is_empty() || self.input_mode == InputMode::Text {
+            main_content.push(self.compression_view(lang))
+        } else {
+            main_content
+        };
+
+        let main_content = column![
+            main_content,
+            row![
+                text(t.keyfile_label).width(Length::Fixed(120.0)),
+                text(
+                    self.keyfile
+                        .as_ref()
+                        .map(|k| k.path.to_string_lossy().into_owned())
+                        .unwrap_or_else(|| t.no_keyfile.to_string())
+                )
+                .width(Length::Fixed(250.0)),
+                // Recipient payloads are sealed to the keys alone.
+                button(t.browse).on_press_maybe(
+                    (self.protection_mode == ProtectionMode::Password).then_some(Message::BrowseKeyfile)
+                ),
+                button(t.clear).on_press_maybe(self.keyfile.as_ref().map(|_| Message::ClearKeyfile)),
+            ]
+            .push_maybe(
+                (self.protection_mode == ProtectionMode::Recipient && self.keyfile.is_some())
+                    .then(|| text(t.keyfile_unused))
+            )
+            .spacing(10)
+            .align_y(Alignment::Center),
+            row![
+                text(t.logo_label).width(Length::Fixed(120.0)),
+                text(
+                    self.logo
+                        .as_ref()
+                        .and_then(|path| path.file_name())
+                        .map(|name| name.to_string_lossy().into_owned())
+                        .unwrap_or_else(|| t.no_logo.to_string())
+                )
+                .width(Length::Fixed(250.0)),
+                button(t.browse).on_press(Message::BrowseLogo),
+                button(t.clear).on_press_maybe(self.logo.as_ref().map(|_| Message::ClearLogo)),
+                text(t.logo_size),
+                pick_list(LOGO_SIZES, Some(self.logo_size), Message::LogoSizeChanged),
+                text(t.quiet_zone),
+                pick_list(QUIET_ZONES, Some(self.quiet_zone), Message::QuietZoneChanged),
+            ]
+            .spacing(10)
+            .align_y(Alignment::Center),
+            row![
+                text(t.output_dir_label).width(Length::Fixed(120.0)),
+                text(
+                    self.settings
+                        .output_dir
+                        .as_ref()
+                        .map(|dir| dir.to_string_lossy().into_owned())
+                        .unwrap_or_else(|| t.no_output_dir.to_string())
+                )
+                .width(Length::Fixed(250.0)),
+                button(t.browse).on_press(Message::BrowseOutputDir),
+                button(t.clear).on_press_maybe(
+                    self.settings.output_dir.as_ref().map(|_| Message::ClearOutputDir)
+                ),
+                checkbox(t.auto_save_png, self.settings.auto_save_png).on_toggle_maybe(
+                    self.settings.output_dir.as_ref().map(|_| Message::AutoSaveToggled)
+                ),
+            ]
+            .spacing(10)
+            .align_y(Alignment::Center),
+            row![
+                text(t.valid_for).width(Length::Fixed(120.0)),
+                radio(t.valid_hour, Validity::Hour, Some(self.validity), Message::ValidityChanged),
+                radio(t.valid_day, Validity::Day, Some(self.validity), Message::ValidityChanged),
+                radio(t.valid_week, Validity::Week, Some(self.validity), Message::ValidityChanged),
+                radio(t.valid_never, Validity::Never, Some(self.validity), Message::ValidityChanged),
+            ]
+            .spacing(10)
+            .align_y(Alignment::Center),
+            row![
+                text(t.encode_label).width(Length::Fixed(120.0)),
+                radio(t.file_option, InputMode::File, Some(self.input_mode), Message::InputModeChanged),
+                radio(t.text_option, InputMode::Text, Some(self.input_mode), Message::InputModeChanged),
+                text(t.encoding),
+                pick_list(TextEncoding::ALL, Some(self.encoding), Message::EncodingChanged),
+                checkbox(t.binary_qr, self.binary).on_toggle(Message::BinaryToggled),
+                checkbox(t.animated, self.animated).on_toggle(Message::AnimatedToggled),
+                text(t.image_size),
+                pick_list(IMAGE_SIZES, Some(self.image_size), Message::ImageSizeChanged),
             ]
             .spacing(10)
             .align_y(Alignment::Center),
             row![
-                text("Filename:").width(Length::Fixed(120.0)),
-                text_input("", &self.filename)
-                    .on_input(Message::FilenameChanged)
-                    .width(Length::Fixed(250.0)),
-                button("Browse").on_press(Message::BrowseFile),
+                text(t.filename_label).width(Length::Fixed(120.0)),
+                if self.archive_files.is_empty() {
+                    Element::from(
+                        text_input("", &self.filename)
+                            .on_input(Message::FilenameChanged)
+                            .on_submit(Message::GenerateQr)
+                            .width(Length::Fixed(250.0)),
+                    )
+                } else {
+                    text(lang.files_selected(self.archive_files.len()))
+                        .width(Length::Fixed(250.0))
+                        .into()
+                },
+                button(t.browse).on_press(Message::BrowseInputFile),
             ]
             .spacing(10)
             .align_y(Alignment::Center),
+        ]
+            .spacing(20);
+
+        let main_content = if self.settings.recent_files.is_empty() {
+            main_content
+        } else {
+            let recent: Vec<RecentFile> = self
+                .settings
+                .recent_files
+                .iter()
+                .cloned()
+                .map(RecentFile)
+                .collect();
+            main_content.push(
+                row![
+                    text("").width(Length::Fixed(120.0)),
+                    pick_list(recent, None::<RecentFile>, |file| {
+                        Message::FileSelected(Some(file.0))
+                    })
+                    .placeholder(t.recent_files)
+                    .width(Length::Fixed(400.0)),
+                ]
+                .spacing(10),
+            )
+        };
+
+        let main_content = if self.animated {
+            main_content.push(
+                row![
+                    text("").width(Length::Fixed(120.0)),
+                    checkbox(t.fountain, self.fountain).on_toggle(Message::FountainToggled),
+                ]
+                .push_maybe(self.logo.as_ref().map(|_| text(t.animation_without_logo)))
+                .spacing(10),
+            )
+        } else {
+            main_content
+        };
+
+        let main_content = if self.input_mode == InputMode::Text {
+            main_content.push(
+                text_editor(&self.note)
+                    .placeholder(t.note_placeholder)
+                    .on_action(Message::NoteEdited)
+                    .height(Length::Fixed(120.0))
+                    .width(520.0),
+            )
+        } else {
+            main_content
+        };
+
+        let main_content = if self.auto_saved.is_empty() {
+            main_content
+        } else {
+            main_content.push(row![
+                text("").width(Length::Fixed(120.0)),
+                text(lang.auto_saved(&self.auto_saved)),
+            ]
+            .spacing(10))
+        };
+
+        let main_content = if let Some(estimate) = self.size_estimate {
+            main_content.push(row![
+                text("").width(Length::Fixed(120.0)),
+                text(lang.size_estimate(&estimate)).style(move |theme: &Theme| text::Style {
+                    color: (!estimate.fits()).then(|| theme.palette().danger),
+                }),
+            ]
+            .spacing(10))
+        } else {
+            main_content
+        };
+
+        let main_content = column![
+            main_content,
             row![
-                button("Read QR").on_press(Message::ReadQrFromFile),
-                button("Read String").on_press(Message::ReadQrFromString),
+                button(t.read_qr).on_press(Message::ReadQrFromFile),
+                button(t.open_qr_image).on_press(Message::BrowseQrImage),
+                button(t.read_string).on_press(Message::ReadQrFromString),
+                button(t.scan_clipboard).on_press(Message::ReadQrFromClipboard),
+            ]
+            .push_maybe(self.screen_scan_controls())
+            .push(button(t.batch).on_press(Message::BatchEncode))
+            .push(button(t.history).on_press(Message::ShowHistory))
+            .push(
                 button(if self.is_processing {
-                    "Processing..."
+                    t.processing
                 } else {
-                    "Generate QR"
+                    t.generate_qr
                 })
                 .on_press_maybe(if self.is_processing {
                     None
                 } else {
                     Some(Message::GenerateQr)
                 }),
-            ]
+            )
+            .push_maybe(self.progress_view())
             .spacing(10),
         ]
             .spacing(20)
@@ -266,43 +2750,260 @@ impl QrApp {
 
         let mut content = Column::new().push(main_content);
 
-        if let Some(ref error) = self.error_message {
-            content = content.push(
-                container(text(error).style(|theme: &Theme| text::Style {
-                    color: Some(theme.palette().danger),
-                }))
-                    .padding(10),
-            );
+        // Open panels sit side by side and wrap onto new lines when the window
+        // is too narrow for them.
+        let mut panels = Row::new().spacing(20);
+
+        if let Some(ref animation) = self.animation {
+            panels = panels.push(animation_view(animation, lang));
         }
 
-        if let Some(ref qr_result) = self.qr_display {
-            content = content.push(qr_display_view(qr_result));
+        if let Some(ref batch_state) = self.batch {
+            panels = panels.push(batch_view(batch_state, lang));
         }
 
-        if let Some(ref read_state) = self.read_window {
-            content = content.push(read_window_view(read_state));
+        if self.show_history {
+            panels = panels.push(history_view(&self.history, self.settings.persist_history, lang));
         }
 
-        container(content)
-            .width(Length::Fill)
+        if let Some(ref self_test) = self.self_test {
+            panels = panels.push(self_test_view(self_test, lang));
+        }
+
+        content = content.push(container(panels.wrap()).padding(20));
+
+        scrollable(container(content).width(Length::Fill))
             .height(Length::Fill)
             .into()
     }
 
-    fn theme(&self) -> Theme {
-        Theme::default()
+    fn theme(&self, _window: window::Id) -> Theme {
+        self.settings.theme()
     }
+
+    fn subscription(&self) -> Subscription<Message> {
+        // Dropping a file on the window works like picking it with Browse.
+        let file_drops = iced::event::listen_with(|event, _status, _window| match event {
+            iced::Event::Window(iced::window::Event::FileDropped(path)) => {
+                Some(Message::FileSelected(Some(path)))
+            }
+            _ => None,
+        });
+
+        let animation = match self.animation {
+            Some(ref state) => {
+                let interval = Duration::from_millis(1000 / u64::from(state.animation.fps.max(1)));
+                iced::time::every(interval).map(|_| Message::AnimationTick)
+            }
+            None => Subscription::none(),
+        };
+
+        // Not offered as a button: Ctrl+Shift+D runs the self-test, for when
+        // support asks whether this build works.
+        let self_test = iced::keyboard::on_key_press(|key, modifiers| match key.as_ref() {
+            iced::keyboard::Key::Character("d" | "D")
+                if modifiers.command() && modifiers.shift() =>
+            {
+                Some(Message::RunSelfTest)
+            }
+            _ => None,
+        });
+
+        // A focused text field takes the first Escape to give up focus; only
+        // presses no widget used close anything.
+        let escape = iced::event::listen_with(|event, status, window| match event {
+            iced::Event::Keyboard(iced::keyboard::Event::KeyPressed {
+                key: iced::keyboard::Key::Named(iced::keyboard::key::Named::Escape),
+                ..
+            }) if status == iced::event::Status::Ignored => Some(Message::EscapePressed(window)),
+            _ => None,
+        });
+
+        let toast_ticks = if self.toasts.is_empty() {
+            Subscription::none()
+        } else {
+            iced::time::every(Duration::from_millis(500)).map(Message::ToastTick)
+        };
+
+        Subscription::batch([
+            file_drops,
+            animation,
+            self_test,
+            escape,
+            window::close_events().map(Message::WindowClosed),
+            toast_ticks,
+        ])
+    }
+}
+
+fn generator_view(generator: &PassphraseGenerator, lang: Lang) -> Element<'_, Message> {
+    let t = lang.strings();
+    let styles: Vec<StyleChoice> = PassphraseStyle::ALL
+        .into_iter()
+        .map(|style| StyleChoice {
+            style,
+            label: lang.passphrase_style(style),
+        })
+        .collect();
+    let selected = StyleChoice {
+        style: generator.style,
+        label: lang.passphrase_style(generator.style),
+    };
+
+    column![
+        row![
+            text("").width(Length::Fixed(120.0)),
+            text(t.generated_password).size(12),
+        ]
+        .spacing(10),
+        row![
+            text("").width(Length::Fixed(120.0)),
+            text(generator.password.as_str()).font(iced::Font::MONOSPACE).size(18),
+            text(lang.entropy_bits(generator.style.entropy_bits())).size(12),
+        ]
+        .spacing(10)
+        .align_y(Alignment::Center),
+        row![
+            text("").width(Length::Fixed(120.0)),
+            pick_list(styles, Some(selected), Message::PassphraseStyleChanged),
+            button(t.regenerate).on_press(Message::GeneratePassword),
+            button(t.copy_to_clipboard).on_press(Message::CopyGeneratedPassword),
+            button(t.use_password).on_press(Message::UseGeneratedPassword),
+            button(t.discard).on_press(Message::DiscardGeneratedPassword),
+        ]
+        .spacing(10)
+        .align_y(Alignment::Center),
+    ]
+    .spacing(6)
+    .into()
+}
+
+fn password_strength_view(password: &str, lang: Lang) -> Element<'_, Message> {
+    let t = lang.strings();
+    let entropy = password::estimate_entropy(password);
+    let strength = Strength::from_entropy(entropy);
+    let (label, color) = match strength {
+        Strength::VeryWeak => (t.very_weak, Color::from_rgb(0.8, 0.1, 0.1)),
+        Strength::Weak => (t.weak, Color::from_rgb(0.9, 0.5, 0.1)),
+        Strength::Fair => (t.fair, Color::from_rgb(0.8, 0.7, 0.1)),
+        Strength::Strong => (t.strong, Color::from_rgb(0.1, 0.6, 0.2)),
+    };
+
+    column![
+        row![
+            text("").width(Length::Fixed(120.0)),
+            progress_bar(0.0..=80.0, entropy.min(80.0) as f32)
+                .width(Length::Fixed(150.0))
+                .height(Length::Fixed(6.0))
+                .style(move |theme: &Theme| progress_bar::Style {
+                    background: theme.extended_palette().background.strong.color.into(),
+                    bar: color.into(),
+                    border: iced::Border::default(),
+                }),
+            text(if password.is_empty() { "" } else { label }).size(12),
+        ]
+        .spacing(10)
+        .align_y(Alignment::Center),
+        row![
+            text("").width(Length::Fixed(120.0)),
+            text(lang.password_hint(password::MAX_PASSWORD_LENGTH)).size(12),
+        ]
+        .spacing(10),
+    ]
+    .spacing(4)
+    .into()
 }
 
-fn qr_display_view(result: &QrGenerationResult) -> Element<Message> {
+async fn confirm_overwrite(path: &Path, lang: Lang) -> bool {
+    let answer = rfd::AsyncMessageDialog::new()
+        .set_level(rfd::MessageLevel::Warning)
+        .set_title(lang.strings().overwrite_title)
+        .set_description(lang.confirm_overwrite(path))
+        .set_buttons(rfd::MessageButtons::YesNo)
+        .show()
+        .await;
+
+    answer == rfd::MessageDialogResult::Yes
+}
+
+/// Switches between iced's plain light and dark themes, from whichever theme
+/// is active.
+fn light_dark_toggle(theme: &Theme, lang: Lang) -> Element<'static, Message> {
+    let t = lang.strings();
+    let (label, target) = if theme.extended_palette().is_dark {
+        (t.light, Theme::Light)
+    } else {
+        (t.dark, Theme::Dark)
+    };
+
+    button(label).on_press(Message::ThemeChanged(target)).into()
+}
+
+/// Largest side of a QR code shown on screen, in logical pixels.
+const QR_DISPLAY_SIDE: u32 = 400;
+
+fn qr_display_view(display: &QrDisplayState, lang: Lang) -> Element<'_, Message> {
+    let t = lang.strings();
+    let result = &display.result;
     let qr_image = iced::widget::image::Handle::from_bytes(result.qr_image.clone());
+    // A whole number of screen pixels per module keeps the preview sharp.
+    let modules = result.dimensions.modules;
+    let display_side = modules * (QR_DISPLAY_SIDE / modules).max(1);
+
+    column![
+        text(t.generated_qr_title).size(20),
+        text_input("", &result.qr_text).width(Length::Fixed(400.0)),
+        iced::widget::image(qr_image)
+            .width(Length::Fixed(display_side as f32))
+            .filter_method(image::FilterMethod::Nearest),
+        text(lang.qr_capacity(&result.capacity)),
+        text(lang.qr_dimensions(&result.dimensions)),
+        match display.scannable {
+            None => text(t.verifying_qr),
+            Some(true) => text(t.verified_scannable).style(text::success),
+            Some(false) => text(t.not_scannable).style(text::danger),
+        },
+        row![
+            text(t.error_correction),
+            pick_list(EC_LEVELS, Some(EcChoice(display.ec_level)), Message::DisplayEcLevelChanged),
+            text(t.image_size),
+            pick_list(IMAGE_SIZES, Some(display.size), Message::DisplaySizeChanged),
+            button(if display.rendering { t.processing } else { t.rerender })
+                .on_press_maybe((!display.rendering).then_some(Message::RerenderQr)),
+        ]
+        .spacing(10)
+        .align_y(Alignment::Center),
+        row![
+            button(t.open_in_viewer).on_press(Message::OpenInViewer),
+            button(t.save_text).on_press(Message::SaveQrText),
+            button(t.export_pdf).on_press(Message::ExportPdf(PdfSource::QrDisplay)),
+            button(t.close).on_press(Message::CloseQrDisplay),
+        ]
+        .spacing(10),
+    ]
+    .spacing(10)
+    .into()
+}
+
+fn animation_view(state: &AnimationState, lang: Lang) -> Element<'_, Message> {
+    let t = lang.strings();
+
+    let frame = match state.frames.get(state.current) {
+        Some(handle) => Element::from(iced::widget::image(handle.clone()).width(Length::Fixed(400.0))),
+        None => text("").into(),
+    };
 
     container(
         column![
-            text("Generierter QR-Code").size(20),
-            text_input("", &result.qr_text).width(Length::Fixed(400.0)),
-            iced::widget::image(qr_image).width(Length::Fixed(400.0)),
-            button("Close").on_press(Message::CloseQrDisplay),
+            text(t.animation_title).size(20),
+            frame,
+            text(lang.animation_part(state.current + 1, state.frames.len())),
+            row![
+                button(t.save_gif).on_press(Message::SaveAnimation),
+                button(t.export_pdf).on_press(Message::ExportPdf(PdfSource::Animation)),
+                button(t.close).on_press(Message::CloseAnimation),
+            ]
+            .spacing(10),
         ]
             .spacing(10)
             .padding(20),
@@ -319,19 +3020,214 @@ fn qr_display_view(result: &QrGenerationResult) -> Element<Message> {
         .into()
 }
 
-fn read_window_view(state: &ReadWindowState) -> Element<Message> {
-    container(
-        column![
-            text("QR Data Read").size(20),
-            text("Text to convert:"),
+fn read_window_view<'a>(
+    state: &'a ReadWindowState,
+    scan: Option<&ScanSession>,
+    fountain: Option<&FountainDecoder>,
+    progress: Option<Element<'a, Message>>,
+    lang: Lang,
+) -> Element<'a, Message> {
+    let t = lang.strings();
+    let idle = progress.is_none();
+    // Parts go into the list first; decrypting waits until the payload is
+    // whole. An empty field still decrypts, as before, unless parts are
+    // being collected.
+    let is_part =
+        Chunk::parse(&state.qr_text).is_ok() || FountainDecoder::is_frame(&state.qr_text);
+    let collecting = scan.is_some() || fountain.is_some();
+    let can_decrypt = idle && !is_part && (!collecting || !state.qr_text.trim().is_empty());
+
+    let mut content = column![
+        text(t.read_title).size(20),
+        text(t.text_to_convert),
+        row![
             text_input("", &state.qr_text)
                 .on_input(Message::DecryptInput)
+                .on_submit(if is_part { Message::AddPart } else { Message::DecryptAndSave })
                 .width(Length::Fixed(400.0)),
+            button(t.load_text_file).on_press(Message::LoadQrTextFile),
+        ]
+        .spacing(10),
+        row![
+            button(t.add_part).on_press_maybe(is_part.then_some(Message::AddPart)),
+            button(t.decrypt_and_save).on_press_maybe(can_decrypt.then_some(Message::DecryptAndSave)),
+            button(t.decrypt_to_clipboard)
+                .on_press_maybe(can_decrypt.then_some(Message::DecryptToClipboard)),
+            button(t.close).on_press(Message::CloseReadWindow),
+        ]
+        .spacing(10),
+    ]
+    .push_maybe(progress)
+    .spacing(10);
+
+    if let Some(scan) = scan {
+        content = content.push(
+            column![
+                text(lang.parts_collected(scan.parts.len(), scan.count)),
+                Row::with_children(
+                    scan.parts
+                        .keys()
+                        .map(|&index| text(lang.animation_part(index, scan.count)).into()),
+                )
+                .spacing(10)
+                .wrap(),
+                text(lang.parts_missing(&scan.missing())),
+            ]
+            .spacing(6),
+        );
+    }
+    if let Some(fountain) = fountain {
+        let (recovered, total) = fountain.progress();
+        content = content.push(text(lang.blocks_recovered(recovered, total)));
+    }
+    if collecting {
+        content = content.push(
             row![
-                button("Decrypt and Save").on_press(Message::DecryptAndSave),
-                button("Close").on_press(Message::CloseReadWindow),
+                button(t.open_qr_image).on_press(Message::BrowseQrImage),
+                button(t.scan_clipboard).on_press(Message::ReadQrFromClipboard),
+                button(t.discard_scan).on_press(Message::DiscardScan),
             ]
             .spacing(10),
+        );
+    }
+
+    if let Some(ref decrypted) = state.decrypted {
+        content = content.push(preview_view(decrypted, lang));
+    }
+
+    content.into()
+}
+
+fn preview_view(decrypted: &Decrypted, lang: Lang) -> Element<'_, Message> {
+    let t = lang.strings();
+    let preview = &decrypted.preview;
+
+    let mut content = column![
+        text(t.preview_title).size(16),
+        text(lang.preview_size(preview.len)),
+        text(lang.preview_type(preview.mime_type)),
+        text(lang.checksum(decrypted.digest_hex.as_deref())).font(iced::Font::MONOSPACE),
+    ]
+    .spacing(6);
+
+    if let Some(expires_at) = decrypted.expires_at {
+        content = content.push(text(lang.remaining_validity(expires_at - processor::unix_now())));
+    }
+
+    let buttons = if let Some(ref entries) = decrypted.archive {
+        let names = entries
+            .iter()
+            .map(|(name, len)| text(lang.archive_entry(name, *len)).font(iced::Font::MONOSPACE).into());
+        content = content.push(text(lang.archive_contents(entries.len()))).push(
+            container(scrollable(Column::with_children(names).spacing(2)))
+                .padding(6)
+                .max_height(200.0)
+                .width(Length::Fixed(400.0)),
+        );
+        row![
+            button(t.extract_to_folder).on_press(Message::ExtractArchive),
+            button(t.discard).on_press(Message::DiscardPreview),
+        ]
+    } else if let Some(ref viewer) = decrypted.viewer {
+        content = content.push(
+            text_editor(viewer)
+                .on_action(Message::ViewerAction)
+                .font(iced::Font::MONOSPACE)
+                .height(Length::Fixed(200.0))
+                .width(400.0),
+        );
+        row![
+            button(t.copy_to_clipboard).on_press(Message::CopyDecryptedText),
+            button(t.save_as_file).on_press(Message::SavePreview),
+            button(t.discard).on_press(Message::DiscardPreview),
+        ]
+    } else {
+        if let Some(ref lines) = preview.text {
+            content = content.push(
+                container(text(lines.as_str()).font(iced::Font::MONOSPACE))
+                    .padding(6)
+                    .width(Length::Fixed(400.0)),
+            );
+        }
+        if decrypted.inspecting {
+            content = content.push(hex_view(decrypted)).push(
+                row![
+                    button(t.copy_hex).on_press(Message::CopyDecryptedHex),
+                    button(t.copy_base64).on_press(Message::CopyDecryptedBase64),
+                ]
+                .spacing(10),
+            );
+        }
+        row![
+            button(t.save).on_press(Message::SavePreview),
+            button(if decrypted.inspecting { t.hide_inspector } else { t.inspect })
+                .on_press(Message::ToggleInspector),
+            button(t.discard).on_press(Message::DiscardPreview),
+        ]
+    };
+
+    content.push(buttons.spacing(10)).into()
+}
+
+/// Hex dump of the decrypted data. Only the rows in view are laid out; the
+/// others are stood in for by empty space of the same height.
+fn hex_view(decrypted: &Decrypted) -> Element<'_, Message> {
+    let rows = preview::hex_row_count(decrypted.data.len());
+    let first = ((decrypted.hex_offset / HEX_ROW_HEIGHT) as usize).min(rows);
+    let last = (first + (HEX_VIEW_HEIGHT / HEX_ROW_HEIGHT).ceil() as usize + 1).min(rows);
+
+    let visible = (first..last).map(|row| {
+        text(preview::hex_row(&decrypted.data, row).to_string())
+            .font(iced::Font::MONOSPACE)
+            .size(HEX_FONT_SIZE)
+            .line_height(text::LineHeight::Absolute(HEX_ROW_HEIGHT.into()))
+            .wrapping(text::Wrapping::None)
+            .into()
+    });
+    let content = column![Space::with_height(first as f32 * HEX_ROW_HEIGHT)]
+        .extend(visible)
+        .push(Space::with_height((rows - last) as f32 * HEX_ROW_HEIGHT));
+
+    container(
+        scrollable(content)
+            .on_scroll(Message::InspectorScrolled)
+            .height(Length::Fixed(HEX_VIEW_HEIGHT))
+            .width(Length::Fill),
+    )
+    .padding(6)
+    .into()
+}
+
+fn batch_view(state: &BatchState, lang: Lang) -> Element<'_, Message> {
+    let t = lang.strings();
+    let mut rows = Column::new().spacing(4);
+    for job in &state.jobs {
+        let name = job
+            .input
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let status = lang.batch_status(&job.status);
+        rows = rows.push(row![text(name).width(Length::Fixed(200.0)), text(status)].spacing(10));
+    }
+
+    let summary = if state.is_finished() {
+        let written = state.count(|s| matches!(s, BatchStatus::Written(_)));
+        let too_large = state.count(|s| matches!(s, BatchStatus::TooLarge(_)));
+        let failed = state.count(|s| matches!(s, BatchStatus::Failed(_)));
+        lang.batch_summary(written, too_large, failed)
+    } else {
+        let done = state.count(|s| !matches!(s, BatchStatus::Pending));
+        lang.batch_progress(done, state.jobs.len())
+    };
+
+    container(
+        column![
+            text(t.batch_title).size(20),
+            text(format!("{} {}", t.batch_output, state.output_dir.display())),
+            rows,
+            text(summary),
+            button(t.close).on_press(Message::CloseBatch),
         ]
             .spacing(10)
             .padding(20),
@@ -348,37 +3244,79 @@ fn read_window_view(state: &ReadWindowState) -> Element<Message> {
         .into()
 }
 
-// Async functions for business logic
-async fn generate_qr_async(filename: String, password: String) -> Result<QrGenerationResult, String> {
-    const MAX_QR_BYTES: usize = 2953;
-
-    let raw_data = tokio::fs::read(&filename)
-        .await
-        .map_err(|e| format!("Fehler beim Lesen der Datei: {}", e))?;
-
-    let qr_text = qr::processor::QrDataProcessor::serialize(&raw_data, &password)
-        .map_err(|e| format!("Fehler bei der Verschlüsselung: {}", e))?;
-
-    if qr_text.len() >= MAX_QR_BYTES {
-        return Err(format!(
-            "Die Datei ist mit {} Bytes zu groß.",
-            qr_text.len()
-        ));
+fn self_test_view(state: &SelfTestState, lang: Lang) -> Element<'_, Message> {
+    let t = lang.strings();
+    let mut lines = Column::new().spacing(4);
+    match state {
+        SelfTestState::Running => lines = lines.push(text(t.self_test_running)),
+        SelfTestState::Finished(report) => {
+            for result in &report.steps {
+                lines = lines.push(text(lang.self_test_result(result)).font(iced::Font::MONOSPACE));
+            }
+        }
     }
 
-    let qr_image = qr::service::generate_qr_image(&qr_text)
-        .map_err(|e| format!("Fehler bei der QR-Generierung: {}", e))?;
-
-    Ok(QrGenerationResult { qr_text, qr_image })
+    container(
+        column![
+            text(t.self_test_title).size(20),
+            lines,
+            button(t.close).on_press(Message::CloseSelfTest),
+        ]
+            .spacing(10)
+            .padding(20),
+    )
+        .style(|theme: &Theme| container::Style {
+            background: Some(theme.palette().background.into()),
+            border: iced::Border {
+                color: theme.palette().primary,
+                width: 2.0,
+                radius: 5.0.into(),
+            },
+            ..Default::default()
+        })
+        .into()
 }
 
-async fn read_qr_from_image(filename: String) -> Result<String, String> {
-    qr::service::read_qr_from_image(&filename)
-        .map_err(|e| format!("Fehler beim Lesen des QR-Codes: {}", e))
-}
+fn history_view(history: &History, persist: bool, lang: Lang) -> Element<'_, Message> {
+    let t = lang.strings();
+    let mut rows = Column::new().spacing(4);
+    if history.entries.is_empty() {
+        rows = rows.push(text(t.history_empty));
+    }
+    for (index, entry) in history.entries.iter().enumerate() {
+        rows = rows.push(
+            row![
+                text(lang.history_entry(entry)).font(iced::Font::MONOSPACE),
+                button(t.show_qr).on_press(Message::ReopenHistoryEntry(index)),
+                button(t.copy).on_press(Message::CopyHistoryEntry(index)),
+            ]
+            .spacing(10)
+            .align_y(Alignment::Center),
+        );
+    }
 
-async fn decrypt_qr_data(qr_text: String, password: String) -> Result<Vec<u8>, String> {
-    qr::processor::QrDataProcessor::deserialize(&qr_text, &password)
-        .map_err(|e| format!("Entschlüsselung fehlgeschlagen: {}", e))
+    container(
+        column![
+            text(t.history).size(20),
+            rows,
+            checkbox(t.persist_history, persist).on_toggle(Message::PersistHistoryToggled),
+            row![
+                button(t.clear_history).on_press(Message::ClearHistory),
+                button(t.close).on_press(Message::CloseHistory),
+            ]
+            .spacing(10),
+        ]
+            .spacing(10)
+            .padding(20),
+    )
+        .style(|theme: &Theme| container::Style {
+            background: Some(theme.palette().background.into()),
+            border: iced::Border {
+                color: theme.palette().primary,
+                width: 2.0,
+                radius: 5.0.into(),
+            },
+            ..Default::default()
+        })
+        .into()
 }
-
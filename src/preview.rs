@@ -0,0 +1,110 @@
+// src/preview.rs
+//! Summary of decrypted data, shown before it is written anywhere.
+use crate::qr::processor::ContentType;
+use std::fmt::{self, Write};
+use zeroize::Zeroizing;
+
+/// Only data up to this size is checked for being readable text.
+pub const PREVIEW_TEXT_LIMIT: usize = 64 * 1024;
+/// Number of lines of a text shown in the preview.
+pub const PREVIEW_LINES: usize = 8;
+/// Text up to this size is shown in full, instead of only the preview lines.
+pub const VIEWER_TEXT_LIMIT: usize = 16 * 1024;
+
+/// `data` as text, if it is UTF-8 and small enough to be shown in full.
+pub fn viewer_text(data: &[u8]) -> Option<&str> {
+    (data.len() <= VIEWER_TEXT_LIMIT)
+        .then_some(data)
+        .and_then(|data| std::str::from_utf8(data).ok())
+}
+
+/// Bytes per row of a [`hex_row`] dump.
+pub const HEX_ROW_LEN: usize = 16;
+
+/// Number of rows a hex dump of `len` bytes has.
+pub fn hex_row_count(len: usize) -> usize {
+    len.div_ceil(HEX_ROW_LEN)
+}
+
+/// Row `row` of a hex dump: the offset, the bytes in hex and as ASCII, e.g.
+/// `00000010  48 65 6c 6c 6f ...  |Hello...|`. Rows past the end are empty.
+pub fn hex_row(data: &[u8], row: usize) -> Zeroizing<String> {
+    let start = (row * HEX_ROW_LEN).min(data.len());
+    let bytes = &data[start..(start + HEX_ROW_LEN).min(data.len())];
+    let mut line = Zeroizing::new(String::with_capacity(80));
+    if bytes.is_empty() {
+        return line;
+    }
+
+    let _ = write!(line, "{:08x} ", start);
+    for i in 0..HEX_ROW_LEN {
+        // An extra space after eight bytes, as in `hexdump -C`.
+        if i % 8 == 0 {
+            line.push(' ');
+        }
+        match bytes.get(i) {
+            Some(b) => {
+                let _ = write!(line, "{:02x} ", b);
+            }
+            None => line.push_str("   "),
+        }
+    }
+    line.push_str(" |");
+    line.extend(bytes.iter().map(|&b| {
+        if b.is_ascii_graphic() || b == b' ' {
+            b as char
+        } else {
+            '.'
+        }
+    }));
+    line.push('|');
+    line
+}
+
+/// `data` as one string of lowercase hex digits.
+pub fn to_hex(data: &[u8]) -> Zeroizing<String> {
+    let mut hex = Zeroizing::new(String::with_capacity(data.len() * 2));
+    for b in data {
+        let _ = write!(hex, "{:02x}", b);
+    }
+    hex
+}
+
+#[derive(Clone)]
+pub struct DataPreview {
+    pub len: usize,
+    /// MIME type guessed from the magic bytes, `text/plain` for UTF-8 text.
+    pub mime_type: Option<&'static str>,
+    /// The first [`PREVIEW_LINES`] lines, for small UTF-8 data.
+    pub text: Option<Zeroizing<String>>,
+}
+
+impl fmt::Debug for DataPreview {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DataPreview")
+            .field("len", &self.len)
+            .field("mime_type", &self.mime_type)
+            .finish_non_exhaustive()
+    }
+}
+
+impl DataPreview {
+    pub fn new(data: &[u8]) -> Self {
+        let text = (data.len() <= PREVIEW_TEXT_LIMIT)
+            .then_some(data)
+            .and_then(|data| std::str::from_utf8(data).ok())
+            .map(|text| {
+                Zeroizing::new(text.lines().take(PREVIEW_LINES).collect::<Vec<_>>().join("\n"))
+            });
+
+        let mime_type = ContentType::detect(data)
+            .map(|content_type| content_type.mime_type)
+            .or(text.as_ref().map(|_| "text/plain"));
+
+        Self {
+            len: data.len(),
+            mime_type,
+            text,
+        }
+    }
+}
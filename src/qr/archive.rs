@@ -0,0 +1,169 @@
+// src/qr/archive.rs
+//! Several files packed into the data of one payload.
+//!
+//! The archive is a MessagePack array of `[name, data]` pairs, with `data` as
+//! `bin`. Names use `/` as separator and are relative; [`extract`] refuses any
+//! that would leave the target directory. The payload marks archives with a
+//! flag (see [`crate::qr::processor`]), so plain data is never mistaken for
+//! one.
+use super::bin;
+use serde::{Deserialize, Serialize};
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::{Component, Path, PathBuf};
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ArchiveError {
+    #[error("The archive is damaged: {0}")]
+    Malformed(String),
+    #[error("The archive is empty")]
+    Empty,
+    #[error("The archive contains {0} more than once")]
+    DuplicateName(String),
+    #[error("Refusing to extract {0:?}: the name points outside the target folder")]
+    UnsafeName(String),
+    #[error("{0} already exists")]
+    Exists(String),
+    #[error("Could not write {name}: {message}")]
+    Io { name: String, message: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchiveEntry {
+    pub name: String,
+    #[serde(with = "bin")]
+    pub data: Vec<u8>,
+}
+
+/// Packs `entries` into the data of one payload. Names are checked as in
+/// [`extract`], so an archive this produces can always be unpacked.
+pub fn pack(entries: &[ArchiveEntry]) -> Result<Vec<u8>, ArchiveError> {
+    check_entries(entries)?;
+    rmp_serde::to_vec(entries).map_err(|e| ArchiveError::Malformed(e.to_string()))
+}
+
+/// Reads the entries back from decrypted data.
+pub fn unpack(data: &[u8]) -> Result<Vec<ArchiveEntry>, ArchiveError> {
+    let entries: Vec<ArchiveEntry> =
+        rmp_serde::from_slice(data).map_err(|e| ArchiveError::Malformed(e.to_string()))?;
+    check_entries(&entries)?;
+    Ok(entries)
+}
+
+fn check_entries(entries: &[ArchiveEntry]) -> Result<(), ArchiveError> {
+    if entries.is_empty() {
+        return Err(ArchiveError::Empty);
+    }
+    let mut names = std::collections::HashSet::new();
+    for entry in entries {
+        let parts = name_parts(&entry.name)?;
+        if !names.insert(parts.join("/")) {
+            return Err(ArchiveError::DuplicateName(entry.name.clone()));
+        }
+    }
+    Ok(())
+}
+
+/// Splits a name into its path components. Only plain components are allowed:
+/// no `..`, `.`, empty parts, absolute paths, drive letters or backslashes.
+fn name_parts(name: &str) -> Result<Vec<&str>, ArchiveError> {
+    let unsafe_name = || ArchiveError::UnsafeName(name.to_string());
+    if name.is_empty() || name.contains(['\\', '\0']) {
+        return Err(unsafe_name());
+    }
+    let parts: Vec<&str> = name.split('/').collect();
+    let plain = |part: &&str| !part.is_empty() && *part != "." && *part != ".." && !part.contains(':');
+    if !parts.iter().all(plain) {
+        return Err(unsafe_name());
+    }
+    Ok(parts)
+}
+
+/// Where `name` goes below `dir`, or [`ArchiveError::UnsafeName`] if it would
+/// end up anywhere else.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn entry_path(dir: &Path, name: &str) -> Result<PathBuf, ArchiveError> {
+    let mut path = dir.to_path_buf();
+    for part in name_parts(name)? {
+        let mut components = Path::new(part).components();
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(_)), None) => path.push(part),
+            _ => return Err(ArchiveError::UnsafeName(name.to_string())),
+        }
+    }
+    Ok(path)
+}
+
+/// Writes the entries below `dir`, creating subfolders as needed. All names
+/// and the folders on their way are checked before anything is created, and
+/// existing files are never overwritten. Returns the paths written.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn extract(entries: &[ArchiveEntry], dir: &Path) -> Result<Vec<PathBuf>, ArchiveError> {
+    use std::io::Write;
+
+    check_entries(entries)?;
+    let paths = entries
+        .iter()
+        .map(|entry| entry_path(dir, &entry.name))
+        .collect::<Result<Vec<_>, _>>()?;
+    let root = dir.canonicalize().map_err(|e| io_error(&dir.display().to_string(), e))?;
+    for entry in entries {
+        check_folders(dir, &root, &entry.name)?;
+    }
+
+    let mut written = Vec::with_capacity(entries.len());
+    for (entry, path) in entries.iter().zip(paths) {
+        let parent = path.parent().unwrap_or(dir);
+        std::fs::create_dir_all(parent).map_err(|e| io_error(&entry.name, e))?;
+        // Checked again in case the folders changed since.
+        let parent = parent.canonicalize().map_err(|e| io_error(&entry.name, e))?;
+        if !parent.starts_with(&root) {
+            return Err(ArchiveError::UnsafeName(entry.name.clone()));
+        }
+
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::AlreadyExists => ArchiveError::Exists(entry.name.clone()),
+                _ => io_error(&entry.name, e),
+            })?;
+        file.write_all(&entry.data).map_err(|e| io_error(&entry.name, e))?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+/// Walks the folders of `name` below `dir` that already exist and refuses a
+/// symlink among them that leads outside `root`, the canonical `dir`.
+/// Nothing is created, so a refused name leaves no folders behind.
+#[cfg(not(target_arch = "wasm32"))]
+fn check_folders(dir: &Path, root: &Path, name: &str) -> Result<(), ArchiveError> {
+    let parts = name_parts(name)?;
+    let mut path = dir.to_path_buf();
+    for part in &parts[..parts.len() - 1] {
+        path.push(part);
+        match std::fs::symlink_metadata(&path) {
+            Ok(metadata) if metadata.file_type().is_symlink() => {
+                let target = path.canonicalize().map_err(|e| io_error(name, e))?;
+                if !target.starts_with(root) {
+                    return Err(ArchiveError::UnsafeName(name.to_string()));
+                }
+            }
+            Ok(_) => {}
+            // Nothing further down exists either.
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => break,
+            Err(e) => return Err(io_error(name, e)),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn io_error(name: &str, e: std::io::Error) -> ArchiveError {
+    ArchiveError::Io {
+        name: name.to_string(),
+        message: e.to_string(),
+    }
+}
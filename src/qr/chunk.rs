@@ -0,0 +1,112 @@
+// src/qr/chunk.rs
+//! Splitting of payloads that are too large for one QR code into parts.
+//!
+//! Every part is `QRDXC<index>/<count>:<text>` with a 1-based index, so a
+//! scanner can show how far along it is and put parts back together in any
+//! order. The header only uses characters of the QR alphanumeric set, which
+//! keeps base45 payloads compact.
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+/// Scheme in front of every part of a split payload.
+pub const CHUNK_SCHEME: &str = "QRDXC";
+
+/// Length of the payload text carried by one part. Small enough that a phone
+/// can reliably scan each frame off a monitor.
+pub const DEFAULT_CHUNK_LEN: usize = 800;
+
+/// Most parts a split payload may have. The largest animation the app
+/// generates, 32 MiB of incompressible input, takes about 95 000 parts of
+/// [`DEFAULT_CHUNK_LEN`]; larger counts in scanned text are refused, so
+/// nobody walks or stores billions of slots for them.
+pub const MAX_PARTS: usize = 100_000;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ChunkError {
+    #[error("This is not a part of a split payload")]
+    NotAChunk,
+    #[error("The parts belong to different payloads")]
+    Inconsistent,
+    #[error("Part {0} is missing")]
+    Missing(usize),
+    #[error("A split payload has at most {MAX_PARTS} parts, this one claims {0}")]
+    TooManyParts(usize),
+}
+
+/// One parsed part, borrowing its text from the scanned input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Chunk<'a> {
+    pub index: usize,
+    pub count: usize,
+    pub text: &'a str,
+}
+
+impl<'a> Chunk<'a> {
+    pub fn parse(input: &'a str) -> Result<Self, ChunkError> {
+        let input = input.trim();
+        let rest = input
+            .get(..CHUNK_SCHEME.len())
+            .filter(|scheme| scheme.eq_ignore_ascii_case(CHUNK_SCHEME))
+            .map(|_| &input[CHUNK_SCHEME.len()..])
+            .ok_or(ChunkError::NotAChunk)?;
+
+        let (header, text) = rest.split_once(':').ok_or(ChunkError::NotAChunk)?;
+        let (index, count) = header.split_once('/').ok_or(ChunkError::NotAChunk)?;
+        let index: usize = index.parse().map_err(|_| ChunkError::NotAChunk)?;
+        let count: usize = count.parse().map_err(|_| ChunkError::NotAChunk)?;
+
+        if index == 0 || index > count {
+            return Err(ChunkError::NotAChunk);
+        }
+        if count > MAX_PARTS {
+            return Err(ChunkError::TooManyParts(count));
+        }
+
+        Ok(Self { index, count, text })
+    }
+}
+
+/// Splits `payload` into parts of at most `max_len` characters of payload text each.
+pub fn split(payload: &str, max_len: usize) -> Vec<String> {
+    let max_len = max_len.max(1);
+    let chars: Vec<char> = payload.chars().collect();
+    let mut parts: Vec<String> = chars
+        .chunks(max_len)
+        .map(|part| part.iter().collect())
+        .collect();
+    if parts.is_empty() {
+        parts.push(String::new());
+    }
+
+    let count = parts.len();
+
+    parts
+        .iter()
+        .enumerate()
+        .map(|(i, part)| format!("{}{}/{}:{}", CHUNK_SCHEME, i + 1, count, part))
+        .collect()
+}
+
+/// Puts the payload back together from its parts, which may come in any
+/// order and contain duplicates.
+pub fn join<S: AsRef<str>>(parts: &[S]) -> Result<String, ChunkError> {
+    let mut count = None;
+    let mut texts: BTreeMap<usize, &str> = BTreeMap::new();
+
+    for part in parts {
+        let chunk = Chunk::parse(part.as_ref())?;
+        if *count.get_or_insert(chunk.count) != chunk.count {
+            return Err(ChunkError::Inconsistent);
+        }
+
+        match texts.insert(chunk.index, chunk.text) {
+            Some(text) if text != chunk.text => return Err(ChunkError::Inconsistent),
+            _ => {}
+        }
+    }
+
+    let count = count.ok_or(ChunkError::Missing(1))?;
+    (1..=count)
+        .map(|index| texts.get(&index).copied().ok_or(ChunkError::Missing(index)))
+        .collect()
+}
@@ -0,0 +1,404 @@
+// src/qr/fountain.rs
+//! Rateless (LT) encoding of a payload over many QR codes.
+//!
+//! The payload text is cut into `K` blocks of equal size, the last one padded
+//! with zeros. Every frame carries the XOR of a pseudo-random subset of the
+//! blocks, picked from the frame's seed with a robust soliton degree
+//! distribution, so a receiver can rebuild the payload from slightly more than
+//! `K` frames received in any order, no matter which ones it missed.
+//!
+//! A frame is `QRDXF<version>:` (see [`FOUNTAIN_SCHEME`]) followed by the
+//! base45 encoding of `total_len: u32 | block_size: u16 | seed: u32 | data`,
+//! all big endian. Which blocks a frame combines depends only on its seed and
+//! `K`, so [`Rng`] and [`degree_cdf`] must not change within a version.
+use super::chunk;
+use std::collections::HashSet;
+use thiserror::Error;
+
+/// Scheme in front of every fountain frame, followed by the version and `:`.
+pub const FOUNTAIN_SCHEME: &str = "QRDXF";
+/// Version of the frame layout and block selection written by [`FountainEncoder`].
+pub const FOUNTAIN_VERSION: u32 = 1;
+
+/// Block size that keeps a base45 frame at roughly the size of a
+/// [`DEFAULT_CHUNK_LEN`](super::chunk::DEFAULT_CHUNK_LEN) chunk.
+pub const DEFAULT_BLOCK_SIZE: usize = 512;
+
+/// Longest payload a frame may announce: as much as the most parts of a
+/// split payload carry. Frame headers are scanned text, so anything larger
+/// is refused before the decoder allocates for it.
+pub const MAX_PAYLOAD_LEN: usize = chunk::MAX_PARTS * chunk::DEFAULT_CHUNK_LEN;
+
+/// Most blocks a frame may announce, enough for [`MAX_PAYLOAD_LEN`] in
+/// blocks of [`DEFAULT_BLOCK_SIZE`].
+pub const MAX_BLOCKS: usize = MAX_PAYLOAD_LEN / DEFAULT_BLOCK_SIZE;
+
+/// Elimination keeps a bit per pending frame and unknown block, so it only
+/// runs once peeling has left at most this many blocks unknown.
+const MAX_ELIMINATION_BLOCKS: usize = 1024;
+
+const HEADER_LEN: usize = 10;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum FountainError {
+    #[error("This is not a fountain-coded frame")]
+    NotAFrame,
+    #[error("Fountain frame version {0} is not supported by this version of the app")]
+    UnsupportedVersion(u32),
+    #[error("The frame belongs to a different payload")]
+    Inconsistent,
+    #[error("The reassembled payload is damaged")]
+    Corrupted,
+}
+
+/// SplitMix64, seeded with the frame seed.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u32) -> Self {
+        Self(u64::from(seed))
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Cumulative robust soliton distribution over the degrees `1..=k`.
+fn degree_cdf(k: usize) -> Vec<f64> {
+    const C: f64 = 0.1;
+    const DELTA: f64 = 0.5;
+
+    let kf = k as f64;
+    let r = C * (kf / DELTA).ln() * kf.sqrt();
+    let spike = (kf / r).floor() as usize;
+
+    let weights: Vec<f64> = (1..=k)
+        .map(|d| {
+            let df = d as f64;
+            let ideal = if d == 1 { 1.0 / kf } else { 1.0 / (df * (df - 1.0)) };
+            let robust = match d.cmp(&spike) {
+                std::cmp::Ordering::Less => r / (df * kf),
+                std::cmp::Ordering::Equal => r * (r / DELTA).ln() / kf,
+                std::cmp::Ordering::Greater => 0.0,
+            };
+            (ideal + robust).max(0.0)
+        })
+        .collect();
+
+    let total: f64 = weights.iter().sum();
+    let mut sum = 0.0;
+    weights
+        .iter()
+        .map(|w| {
+            sum += w / total;
+            sum
+        })
+        .collect()
+}
+
+/// Indices of the blocks combined in the frame with `seed`.
+fn blocks_for(seed: u32, cdf: &[f64]) -> Vec<usize> {
+    let k = cdf.len();
+    let mut rng = Rng::new(seed);
+
+    let x = rng.unit();
+    let degree = cdf.iter().position(|&p| x < p).unwrap_or(k - 1) + 1;
+
+    let mut indices: Vec<usize> = (0..k).collect();
+    for i in 0..degree {
+        let j = i + rng.below(k - i);
+        indices.swap(i, j);
+    }
+    indices.truncate(degree);
+    indices
+}
+
+fn xor_into(target: &mut [u8], source: &[u8]) {
+    for (t, s) in target.iter_mut().zip(source) {
+        *t ^= s;
+    }
+}
+
+pub struct FountainEncoder {
+    blocks: Vec<Vec<u8>>,
+    total_len: usize,
+    block_size: usize,
+    cdf: Vec<f64>,
+}
+
+impl FountainEncoder {
+    pub fn new(payload: &str, block_size: usize) -> Self {
+        let block_size = block_size.clamp(1, usize::from(u16::MAX));
+        let bytes = payload.as_bytes();
+
+        let mut blocks: Vec<Vec<u8>> = bytes.chunks(block_size).map(<[u8]>::to_vec).collect();
+        if blocks.is_empty() {
+            blocks.push(Vec::new());
+        }
+        for block in &mut blocks {
+            block.resize(block_size, 0);
+        }
+
+        Self {
+            cdf: degree_cdf(blocks.len()),
+            blocks,
+            total_len: bytes.len(),
+            block_size,
+        }
+    }
+
+    pub fn block_count(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Number of frames worth showing in a loop: enough that most receivers
+    /// can decode even after missing a good share of them.
+    pub fn recommended_frame_count(&self) -> usize {
+        self.block_count() * 2 + 4
+    }
+
+    pub fn frame(&self, seed: u32) -> String {
+        let mut data = vec![0u8; self.block_size];
+        for index in blocks_for(seed, &self.cdf) {
+            xor_into(&mut data, &self.blocks[index]);
+        }
+
+        let mut packed = Vec::with_capacity(HEADER_LEN + data.len());
+        packed.extend_from_slice(&(self.total_len as u32).to_be_bytes());
+        packed.extend_from_slice(&(self.block_size as u16).to_be_bytes());
+        packed.extend_from_slice(&seed.to_be_bytes());
+        packed.extend_from_slice(&data);
+
+        format!(
+            "{}{}:{}",
+            FOUNTAIN_SCHEME,
+            FOUNTAIN_VERSION,
+            base45::encode(&packed)
+        )
+    }
+
+    pub fn frames(&self, count: usize) -> Vec<String> {
+        (0..count as u32).map(|seed| self.frame(seed)).collect()
+    }
+}
+
+struct Frame {
+    total_len: usize,
+    block_size: usize,
+    seed: u32,
+    data: Vec<u8>,
+}
+
+impl Frame {
+    fn parse(input: &str) -> Result<Self, FountainError> {
+        let input = input.trim();
+        let rest = input
+            .get(..FOUNTAIN_SCHEME.len())
+            .filter(|scheme| scheme.eq_ignore_ascii_case(FOUNTAIN_SCHEME))
+            .map(|_| &input[FOUNTAIN_SCHEME.len()..])
+            .ok_or(FountainError::NotAFrame)?;
+
+        let (version, body) = rest.split_once(':').ok_or(FountainError::NotAFrame)?;
+        let version: u32 = version.parse().map_err(|_| FountainError::NotAFrame)?;
+        if version != FOUNTAIN_VERSION {
+            return Err(FountainError::UnsupportedVersion(version));
+        }
+
+        let packed = base45::decode(body).map_err(|_| FountainError::NotAFrame)?;
+        if packed.len() < HEADER_LEN {
+            return Err(FountainError::NotAFrame);
+        }
+
+        let total_len = u32::from_be_bytes([packed[0], packed[1], packed[2], packed[3]]) as usize;
+        let block_size = usize::from(u16::from_be_bytes([packed[4], packed[5]]));
+        let seed = u32::from_be_bytes([packed[6], packed[7], packed[8], packed[9]]);
+        let data = packed[HEADER_LEN..].to_vec();
+
+        if block_size == 0 || data.len() != block_size {
+            return Err(FountainError::NotAFrame);
+        }
+        if total_len > MAX_PAYLOAD_LEN || total_len.div_ceil(block_size) > MAX_BLOCKS {
+            return Err(FountainError::NotAFrame);
+        }
+
+        Ok(Self {
+            total_len,
+            block_size,
+            seed,
+            data,
+        })
+    }
+}
+
+/// Collects fountain frames until the payload can be rebuilt.
+#[derive(Default)]
+pub struct FountainDecoder {
+    params: Option<(usize, usize)>,
+    cdf: Vec<f64>,
+    blocks: Vec<Option<Vec<u8>>>,
+    pending: Vec<(Vec<usize>, Vec<u8>)>,
+    seen: HashSet<u32>,
+}
+
+impl FountainDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether `input` looks like a fountain frame, without decoding it.
+    pub fn is_frame(input: &str) -> bool {
+        input
+            .trim()
+            .get(..FOUNTAIN_SCHEME.len())
+            .is_some_and(|scheme| scheme.eq_ignore_ascii_case(FOUNTAIN_SCHEME))
+    }
+
+    /// Recovered and total number of blocks; `(0, 0)` before the first frame.
+    pub fn progress(&self) -> (usize, usize) {
+        let solved = self.blocks.iter().filter(|b| b.is_some()).count();
+        (solved, self.blocks.len())
+    }
+
+    /// Adds one scanned frame and returns the payload once every block is known.
+    pub fn add(&mut self, input: &str) -> Result<Option<String>, FountainError> {
+        let frame = Frame::parse(input)?;
+
+        match self.params {
+            None => {
+                let k = frame.total_len.div_ceil(frame.block_size).max(1);
+                self.params = Some((frame.total_len, frame.block_size));
+                self.cdf = degree_cdf(k);
+                self.blocks = vec![None; k];
+            }
+            Some(params) if params != (frame.total_len, frame.block_size) => {
+                return Err(FountainError::Inconsistent);
+            }
+            Some(_) => {}
+        }
+
+        if self.seen.insert(frame.seed) {
+            let indices = blocks_for(frame.seed, &self.cdf);
+            self.pending.push((indices, frame.data));
+            self.peel();
+            if self.blocks.iter().any(Option::is_none) {
+                self.eliminate();
+            }
+        }
+
+        self.payload()
+    }
+
+    /// Removes known blocks from pending frames until no frame reduces to a
+    /// single unknown block any more.
+    fn peel(&mut self) {
+        loop {
+            let mut solved_any = false;
+
+            for (indices, data) in &mut self.pending {
+                indices.retain(|&index| match &self.blocks[index] {
+                    Some(block) => {
+                        xor_into(data, block);
+                        false
+                    }
+                    None => true,
+                });
+
+                if let [index] = indices[..] {
+                    self.blocks[index] = Some(std::mem::take(data));
+                    indices.clear();
+                    solved_any = true;
+                }
+            }
+
+            self.pending.retain(|(indices, _)| !indices.is_empty());
+            if !solved_any {
+                break;
+            }
+        }
+    }
+
+    /// Solves the pending frames as a linear system over GF(2) once there are
+    /// enough of them. Peeling alone often stalls with small block counts,
+    /// where it would need far more than `K` frames.
+    fn eliminate(&mut self) {
+        let unknown: Vec<usize> = (0..self.blocks.len())
+            .filter(|&index| self.blocks[index].is_none())
+            .collect();
+        if self.pending.len() < unknown.len() || unknown.len() > MAX_ELIMINATION_BLOCKS {
+            return;
+        }
+
+        let mut rows: Vec<(Vec<bool>, Vec<u8>)> = self
+            .pending
+            .iter()
+            .map(|(indices, data)| {
+                let columns = unknown.iter().map(|index| indices.contains(index)).collect();
+                (columns, data.clone())
+            })
+            .collect();
+
+        let mut pivot_row = 0;
+        for column in 0..unknown.len() {
+            let Some(found) = (pivot_row..rows.len()).find(|&row| rows[row].0[column]) else {
+                continue;
+            };
+            rows.swap(pivot_row, found);
+
+            let (pivot_columns, pivot_data) = rows[pivot_row].clone();
+            for (row, (columns, data)) in rows.iter_mut().enumerate() {
+                if row != pivot_row && columns[column] {
+                    for (c, p) in columns.iter_mut().zip(&pivot_columns) {
+                        *c ^= p;
+                    }
+                    xor_into(data, &pivot_data);
+                }
+            }
+            pivot_row += 1;
+        }
+
+        let mut solved_any = false;
+        for (columns, data) in rows {
+            let mut set = columns.iter().enumerate().filter(|(_, &c)| c);
+            if let (Some((column, _)), None) = (set.next(), set.next()) {
+                self.blocks[unknown[column]] = Some(data);
+                solved_any = true;
+            }
+        }
+
+        if solved_any {
+            self.peel();
+        }
+    }
+
+    fn payload(&self) -> Result<Option<String>, FountainError> {
+        let Some((total_len, _)) = self.params else {
+            return Ok(None);
+        };
+        if self.blocks.iter().any(Option::is_none) {
+            return Ok(None);
+        }
+
+        let mut bytes: Vec<u8> = self.blocks.iter().flatten().flatten().copied().collect();
+        if bytes.len() < total_len {
+            return Err(FountainError::Corrupted);
+        }
+        bytes.truncate(total_len);
+
+        String::from_utf8(bytes)
+            .map(Some)
+            .map_err(|_| FountainError::Corrupted)
+    }
+}
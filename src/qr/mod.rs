@@ -1,3 +1,13 @@
 // src/qr/mod.rs
+pub mod archive;
+mod bin;
+pub mod chunk;
+pub mod fountain;
+#[cfg(feature = "gui")]
+pub mod pdf;
 pub mod processor;
+pub mod scan;
+#[cfg(feature = "gui")]
 pub mod service;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod stream;
@@ -0,0 +1,298 @@
+// src/qr/pdf.rs
+//! Printable A4 pages with one QR code each, and reading the codes back from
+//! a scan of such pages.
+//!
+//! Each code is embedded as a black and white image with
+//! [`SAMPLES_PER_MODULE`] samples per module and interpolation turned off, so
+//! it stays sharp at any printer resolution and scans when printed at 100%.
+//!
+//! Reading doesn't rasterize pages. It decodes the images embedded in them,
+//! which is all a document scanner's PDF consists of, and all that
+//! [`generate_qr_pdf`] writes: JPEG (`DCTDecode`) and uncompressed or
+//! `FlateDecode` gray or RGB samples without predictors, and bilevel samples.
+//! CCITT, JBIG2 and JPEG 2000 images are skipped, as are codes drawn with
+//! vector graphics; such pages have to be printed and scanned first.
+use crate::qr::service::{self, QrServiceError, ReadOptions};
+use image::{DynamicImage, GrayImage, RgbImage};
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream};
+use printpdf::{
+    BuiltinFont, ColorBits, ColorSpace, Image, ImageTransform, ImageXObject, Mm, PdfDocument, Px,
+};
+use qrcode::{Color as Module, EcLevel, QrCode};
+use sodiumoxide::crypto::generichash;
+use std::path::Path;
+
+const PAGE_WIDTH: f32 = 210.0;
+const PAGE_HEIGHT: f32 = 297.0;
+const TOP_MARGIN: f32 = 30.0;
+/// Largest side of a code including its quiet zone, in mm.
+const MAX_CODE_SIDE: f32 = 150.0;
+/// Small codes are not blown up beyond this module size, in mm.
+const MAX_MODULE_SIZE: f32 = 4.0;
+const QUIET_ZONE_MODULES: usize = 4;
+/// Image samples per module side. Printing would be as sharp with one; more
+/// let [`read_qr_from_pdf`] find the code in the page without a scanner.
+pub const SAMPLES_PER_MODULE: usize = 4;
+const CAPTION_FONT_SIZE: f32 = 11.0;
+const CAPTION_LINE_HEIGHT: f32 = 6.0;
+/// Number of digest bytes shown by [`fingerprint`].
+const FINGERPRINT_BYTES: usize = 8;
+
+/// One page: a QR code with caption lines printed below it.
+#[derive(Debug, Clone)]
+pub struct PdfPage {
+    pub payload: String,
+    pub caption: Vec<String>,
+}
+
+/// Short BLAKE2b fingerprint of `payload`, e.g. `3f2a-9c01-77be-d4e0`, so a
+/// printout can be matched against the payload it was made from.
+pub fn fingerprint(payload: &str) -> String {
+    let hex: String = generichash::hash(payload.as_bytes(), Some(generichash::DIGEST_MIN), None)
+        .map(|digest| {
+            digest.as_ref()[..FINGERPRINT_BYTES]
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    hex.as_bytes()
+        .chunks(4)
+        .map(|group| String::from_utf8_lossy(group))
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Lays out every page centered on A4 and returns the PDF document.
+pub fn generate_qr_pdf(title: &str, pages: &[PdfPage]) -> Result<Vec<u8>, QrServiceError> {
+    let (doc, first_page, first_layer) =
+        PdfDocument::new(title, Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Layer 1");
+    let font = doc
+        .add_builtin_font(BuiltinFont::Helvetica)
+        .map_err(|e| QrServiceError::GenerationFailed(e.to_string()))?;
+
+    for (index, page) in pages.iter().enumerate() {
+        let (page_ref, layer_ref) = if index == 0 {
+            (first_page, first_layer)
+        } else {
+            doc.add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Layer 1")
+        };
+        let layer = doc.get_page(page_ref).get_layer(layer_ref);
+
+        let code = QrCode::with_error_correction_level(&page.payload, EcLevel::L)
+            .map_err(|e| QrServiceError::GenerationFailed(e.to_string()))?;
+        let width = code.width();
+        let side = width + 2 * QUIET_ZONE_MODULES;
+        let module = (MAX_CODE_SIDE / side as f32).min(MAX_MODULE_SIZE);
+        let left = (PAGE_WIDTH - side as f32 * module) / 2.0 + QUIET_ZONE_MODULES as f32 * module;
+        let top = PAGE_HEIGHT - TOP_MARGIN - QUIET_ZONE_MODULES as f32 * module;
+
+        // PDF coordinates start at the bottom left, at the quiet zone here.
+        let side_mm = side as f32 * module;
+        let samples = side * SAMPLES_PER_MODULE;
+        Image::from(code_image(&code)).add_to_layer(
+            layer.clone(),
+            ImageTransform {
+                translate_x: Some(Mm((PAGE_WIDTH - side_mm) / 2.0)),
+                translate_y: Some(Mm(top + QUIET_ZONE_MODULES as f32 * module - side_mm)),
+                dpi: Some(samples as f32 * 25.4 / side_mm),
+                ..ImageTransform::default()
+            },
+        );
+
+        let mut baseline = top - (width + QUIET_ZONE_MODULES) as f32 * module - CAPTION_LINE_HEIGHT;
+        for line in &page.caption {
+            layer.use_text(
+                line.as_str(),
+                CAPTION_FONT_SIZE,
+                Mm(left),
+                Mm(baseline),
+                &font,
+            );
+            baseline -= CAPTION_LINE_HEIGHT;
+        }
+    }
+
+    doc.save_to_bytes()
+        .map_err(|e| QrServiceError::GenerationFailed(e.to_string()))
+}
+
+/// `code` with its quiet zone as 1-bit gray samples, 1 being white and every
+/// row starting on a byte boundary.
+fn code_image(code: &QrCode) -> ImageXObject {
+    let width = code.width();
+    let side = (width + 2 * QUIET_ZONE_MODULES) * SAMPLES_PER_MODULE;
+    let row_len = side.div_ceil(8);
+    let colors = code.to_colors();
+
+    let mut data = vec![u8::MAX; row_len * side];
+    for y in 0..side {
+        for x in 0..side {
+            let module_x = (x / SAMPLES_PER_MODULE).checked_sub(QUIET_ZONE_MODULES);
+            let module_y = (y / SAMPLES_PER_MODULE).checked_sub(QUIET_ZONE_MODULES);
+            let dark = match (module_x, module_y) {
+                (Some(mx), Some(my)) if mx < width && my < width => {
+                    colors[my * width + mx] == Module::Dark
+                }
+                _ => false,
+            };
+            if dark {
+                data[y * row_len + x / 8] &= !(0x80 >> (x % 8));
+            }
+        }
+    }
+
+    ImageXObject {
+        width: Px(side),
+        height: Px(side),
+        color_space: ColorSpace::Greyscale,
+        bits_per_component: ColorBits::Bit1,
+        interpolate: false,
+        image_data: data,
+        image_filter: None,
+        smask: None,
+        clipping_bbox: None,
+    }
+}
+
+/// Text of every QR code on the pages of a scanned PDF, in page order and
+/// without duplicates. Pages without a readable code are skipped; only when
+/// no page has one is that an error.
+#[tracing::instrument(level = "debug", skip(options))]
+pub fn read_qr_from_pdf(path: &Path, options: ReadOptions) -> Result<Vec<String>, QrServiceError> {
+    let document =
+        Document::load(path).map_err(|e| QrServiceError::ImageReadError(e.to_string()))?;
+
+    let mut codes: Vec<String> = Vec::new();
+    let mut attempts = 0;
+    for (number, page_id) in document.get_pages() {
+        let mut found = false;
+        for img in page_images(&document, page_id) {
+            match service::read_all_qr_from_image(img.to_luma8(), options) {
+                Ok(page_codes) => {
+                    found = true;
+                    for code in page_codes {
+                        if !codes.contains(&code) {
+                            codes.push(code);
+                        }
+                    }
+                }
+                Err(QrServiceError::QrCodeNotFound { attempts: tried }) => attempts += tried,
+                Err(e) => return Err(e),
+            }
+        }
+        if !found {
+            tracing::debug!(page = number, "no QR code on page");
+        }
+    }
+
+    if codes.is_empty() {
+        return Err(QrServiceError::QrCodeNotFound { attempts });
+    }
+    Ok(codes)
+}
+
+/// The images a page draws directly, in the order of its resources. Images
+/// in a format that can't be decoded here are skipped.
+fn page_images(document: &Document, page_id: ObjectId) -> Vec<DynamicImage> {
+    let (own, inherited) = document.get_page_resources(page_id);
+    let resources = own
+        .into_iter()
+        .chain(inherited.into_iter().filter_map(|id| document.get_dictionary(id).ok()));
+
+    let mut images = Vec::new();
+    for resources in resources {
+        let Some(xobjects) = resources
+            .get(b"XObject")
+            .and_then(|xobjects| document.dereference(xobjects))
+            .and_then(|(_, xobjects)| xobjects.as_dict())
+            .ok()
+        else {
+            continue;
+        };
+
+        for (_, xobject) in xobjects.iter() {
+            let Ok((_, Object::Stream(stream))) = document.dereference(xobject) else {
+                continue;
+            };
+            if stream.dict.get(b"Subtype").and_then(Object::as_name_str).ok() != Some("Image") {
+                continue;
+            }
+            match decode_image(document, stream) {
+                Some(img) => images.push(img),
+                None => tracing::debug!(filters = ?stream.filters().ok(), "skipping unsupported PDF image"),
+            }
+        }
+    }
+    images
+}
+
+fn decode_image(document: &Document, stream: &Stream) -> Option<DynamicImage> {
+    let filters = stream.filters().unwrap_or_default();
+    let samples = match filters.as_slice() {
+        [jpeg] if jpeg == "DCTDecode" => {
+            return image::load_from_memory_with_format(&stream.content, image::ImageFormat::Jpeg)
+                .ok();
+        }
+        [] => stream.content.clone(),
+        [flate] if flate == "FlateDecode" => {
+            // lopdf refuses to decompress image streams itself.
+            let mut plain = stream.clone();
+            plain.dict.remove(b"Subtype");
+            plain.decompressed_content().ok()?
+        }
+        _ => return None,
+    };
+
+    let dict = &stream.dict;
+    let width = u32::try_from(integer(document, dict, b"Width")?).ok()?;
+    let height = u32::try_from(integer(document, dict, b"Height")?).ok()?;
+    let bits = integer(document, dict, b"BitsPerComponent")?;
+
+    match (components(document, dict)?, bits) {
+        (1, 8) => GrayImage::from_raw(width, height, samples).map(DynamicImage::ImageLuma8),
+        (3, 8) => RgbImage::from_raw(width, height, samples).map(DynamicImage::ImageRgb8),
+        // Bilevel scans; rows start on a byte boundary and 1 is white.
+        (1, 1) => {
+            let row_len = (width as usize).div_ceil(8);
+            if samples.len() < row_len * height as usize {
+                return None;
+            }
+            Some(DynamicImage::ImageLuma8(GrayImage::from_fn(width, height, |x, y| {
+                let byte = samples[y as usize * row_len + x as usize / 8];
+                let white = byte & (0x80 >> (x % 8)) != 0;
+                image::Luma([if white { u8::MAX } else { 0 }])
+            })))
+        }
+        _ => None,
+    }
+}
+
+fn integer(document: &Document, dict: &Dictionary, key: &[u8]) -> Option<i64> {
+    let (_, value) = document.dereference(dict.get(key).ok()?).ok()?;
+    value.as_i64().ok()
+}
+
+/// Colour components of an image with a gray or RGB colour space, including
+/// ICC-based ones.
+fn components(document: &Document, dict: &Dictionary) -> Option<i64> {
+    let (_, space) = document.dereference(dict.get(b"ColorSpace").ok()?).ok()?;
+    match space {
+        Object::Name(name) => match name.as_slice() {
+            b"DeviceGray" | b"CalGray" => Some(1),
+            b"DeviceRGB" | b"CalRGB" => Some(3),
+            _ => None,
+        },
+        Object::Array(space) => match space.first()?.as_name_str().ok()? {
+            "ICCBased" => {
+                let (_, profile) = document.dereference(space.get(1)?).ok()?;
+                integer(document, &profile.as_stream().ok()?.dict, b"N")
+            }
+            "CalGray" => Some(1),
+            "CalRGB" => Some(3),
+            _ => None,
+        },
+        _ => None,
+    }
+}
@@ -1,11 +1,81 @@
 // src/qr/processor.rs
-use crate::crypto::crypto_utils;
-use base64::{engine::general_purpose, Engine};
+//! Encoding of raw bytes into the text payload carried by a QR code.
+//!
+//! Wire format:
+//!
+//! 1. `raw` is compressed with zstd (level 16 unless configured otherwise).
+//! 2. A 32-byte key is derived from the password with Argon2i v1.3
+//!    (`OPSLIMIT_MODERATE` / `MEMLIMIT_MODERATE` unless configured otherwise,
+//!    16-byte random salt). The limits are recorded in the payload, so the
+//!    decoder needs no configuration; payloads before version 7 didn't, and
+//!    are decoded with [`DeserializeOptions::kdf`].
+//! 3. The compressed data is sealed with XSalsa20-Poly1305 (`secretbox`); the
+//!    24-byte nonce is prepended to the ciphertext, which is the same combined
+//!    layout PyNaCl's `SecretBox.encrypt` returns. Opt-in, ChaCha20-Poly1305
+//!    (RFC 8439) is used instead, with its 12-byte nonce prepended the same way.
+//! 4. `QrData { salt, encrypted, flags, mode, cipher, check, keys, kdf_ops,
+//!    kdf_mem_kib }` is packed with
+//!    MessagePack and the result is encoded as standard base64 with padding, or — opt-in — as
+//!    unpadded URL-safe base64 or as base45 marked by a leading [`BASE45_PREFIX`]. `check` holds the first 8 bytes of a
+//!    BLAKE2b hash keyed with the derived key, so a wrong password is reported
+//!    as such instead of as damaged data. Byte fields are written as arrays of
+//!    integers but also read as MessagePack `bin`, which most other MessagePack
+//!    libraries, msgpack-python's included, write for bytes.
+//! 5. The text is prefixed with `QRDX<version>:` (see [`PAYLOAD_SCHEME`]) so
+//!    scanners show what the code is and decoders know the format version.
+//!    Payloads without the prefix are treated as version 1.
+//!
+//! With an expiry set, the compressed data is preceded by the deadline as a
+//! big-endian `i64` of unix seconds before it is encrypted, and bit 1 of
+//! `flags` is set. Clearing the bit doesn't remove the deadline: the data then
+//! fails to decompress.
+//!
+//! Bit 2 of `flags` marks data that is several files packed into one archive
+//! (see [`crate::qr::archive`]). It only tells the decoder to unpack the data
+//! rather than save it as one file.
+//!
+//! Bit 3 of `flags` marks a BLAKE2b-256 digest of the raw data in front of the
+//! compressed data, after the deadline if there is one. The decoder compares
+//! it with the decompressed data, which catches damage the MAC can't see,
+//! e.g. a bug in compression, and lets the recipient check a saved file
+//! against a digest passed on separately. Payloads carry it from version 6 on.
+//!
+//! Payloads for a recipient's public key (`mode` 1) skip steps 2 and 3: the
+//! compressed data is sealed to the X25519 key with `crypto_box_seal` and
+//! `salt` and `check` stay empty. For several recipients (`mode` 2) the data is
+//! encrypted as in step 3 with a random key instead, and that key is sealed to
+//! each recipient in turn; the sealed copies are stored in `keys`.
+//!
+//! `kdf_ops` and `kdf_mem_kib` hold the Argon2 limits of step 2 (passes and
+//! KiB); both are 0 for recipient payloads, which derive no key.
+//!
+//! On encode the struct is written as a nine-element array and the byte fields
+//! as arrays of integers. On decode both the array and the map form
+//! (`{"salt": .., "encrypted": ..}`) are accepted, the fields after
+//! `encrypted` may be missing (as in codes from older versions), and the byte fields may also
+//! use the MessagePack `bin` type, which is what Python's `msgpack.packb`
+//! produces for `bytes`. Field names therefore only matter for the map form.
+//!
+//! A byte-mode QR code may also hold the packed struct from step 4 as it is,
+//! without the text encoding and the prefix of step 5, which saves the third
+//! base64 adds ([`QrDataProcessor::payload_to_bytes`]). The packed struct
+//! starts with a MessagePack array or map header, which is never valid UTF-8,
+//! so scanners tell it apart from payload text
+//! ([`QrDataProcessor::payload_from_bytes`]). Both forms carry the same
+//! payload.
+use super::bin;
+use crate::crypto::crypto_utils::{self, memcmp, Cipher, KdfLimits, PublicKey, Salt, SecretKey};
+use base64::alphabet;
+use base64::engine::{general_purpose, DecodePaddingMode, GeneralPurpose};
+use base64::Engine;
 use serde::{Deserialize, Serialize};
-use sodiumoxide::crypto::pwhash;
+use std::borrow::Cow;
+use std::fmt;
+use std::io::Read;
 use thiserror::Error;
+use zeroize::Zeroizing;
 
-#[derive(Error, Debug)]
+#[derive(Error, Debug, Clone)]
 pub enum QrProcessorError {
     #[error("Crypto error: {0}")]
     Crypto(#[from] crypto_utils::CryptoError),
@@ -13,59 +83,1158 @@ pub enum QrProcessorError {
     Compression(String),
     #[error("Serialization error: {0}")]
     Serialization(String),
-    #[error("Base64 error: {0}")]
+    #[error("The text is not valid base64 ({0}); it was probably scanned incompletely, re-scan the code")]
     Base64(#[from] base64::DecodeError),
+    #[error("The text is not valid base45 ({0}); it was probably scanned incompletely, re-scan the code")]
+    Base45(#[from] base45::DecodeError),
+    #[error("The text doesn't contain a valid payload ({0}); re-scan the code")]
+    Malformed(String),
+    #[error("The payload has a salt of {0} bytes instead of 16; re-scan the code")]
+    InvalidSalt(usize),
+    #[error("Wrong password or keyfile")]
+    WrongPassword,
+    #[error("The password or key is correct, but the data is damaged or was tampered with")]
+    Corrupted,
+    #[error("Wrong password, or the data is damaged or was tampered with")]
+    AuthenticationFailed,
+    #[error("At least one recipient key is needed")]
+    NoRecipients,
+    #[error("This payload has expired and can no longer be decrypted")]
+    Expired { expires_at: i64 },
+    #[error("This payload was encrypted with a keyfile; select the keyfile to decrypt it")]
+    KeyfileRequired,
+    #[error("This payload was encrypted for a public key; decrypt it with the matching key pair")]
+    RecipientKeyRequired,
+    #[error("This payload is protected by a password, not by a public key")]
+    PasswordRequired,
+    #[error("Unknown payload mode {0}")]
+    UnknownMode(u8),
+    #[error("Unknown cipher {0}")]
+    UnknownCipher(u8),
+    #[error("The decompressed data is larger than the limit of {limit} bytes")]
+    DecompressedTooLarge { limit: usize },
+    #[error("There is nothing to decrypt; paste or scan a payload first")]
+    EmptyInput,
+    #[error("This doesn't look like a QR Data Exchange payload")]
+    NotAPayload,
+    #[error("Payload format version {0} is not supported by this version of the app")]
+    UnsupportedVersion(u32),
+    #[error("Cancelled")]
+    Cancelled,
+    #[error("The decrypted data doesn't match the checksum it was encoded with; it was damaged on the way")]
+    IntegrityCheckFailed,
+    #[error("Files encrypted as a stream can't use {0}")]
+    NotForStreams(&'static str),
 }
 
+/// Steps of encoding and decoding a payload. Reading the input and rendering
+/// the code happen outside this module; they are listed so callers can
+/// report them alongside.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Reading,
+    DerivingKey,
+    Compressing,
+    Encrypting,
+    Rendering,
+    Decrypting,
+    Decompressing,
+}
+
+impl Stage {
+    /// The stages of generating a code, in order.
+    pub const ENCODE: [Stage; 5] = [
+        Stage::Reading,
+        Stage::DerivingKey,
+        Stage::Compressing,
+        Stage::Encrypting,
+        Stage::Rendering,
+    ];
+    /// The stages of decrypting a payload, in order.
+    pub const DECODE: [Stage; 3] = [Stage::DerivingKey, Stage::Decrypting, Stage::Decompressing];
+}
+
+/// Called before each [`Stage`] starts; an error, usually
+/// [`QrProcessorError::Cancelled`], stops the work there.
+pub type OnStage<'a> = &'a mut dyn FnMut(Stage) -> Result<(), QrProcessorError>;
+
+fn no_stages(_: Stage) -> Result<(), QrProcessorError> {
+    Ok(())
+}
+
+/// Scheme in front of every payload, followed by the format version and `:`.
+/// Upper case so that base45 payloads stay within the QR alphanumeric set.
+pub const PAYLOAD_SCHEME: &str = "QRDX";
+/// Format version written by [`QrDataProcessor::serialize`]. Version 1 is the
+/// bare, unprefixed layout without `flags` and `mode`; version 2 has no `cipher`
+/// and version 3 no `check`; version 4 has no `keys` and version 5 no digest;
+/// version 6 doesn't record the Argon2 limits. Decoding refuses fields a
+/// payload's version doesn't have, and from version 6 on requires the digest.
+pub const FORMAT_VERSION: u32 = 7;
+
+const BASE64_CHARSET: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/=";
+const BASE45_CHARSET: &str = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+/// The one place payload text is cleaned up before decoding: surrounding
+/// whitespace goes, and so do line breaks and tabs anywhere, as mail clients
+/// and chat apps insert them. Inner spaces stay, base45 uses them. Padding
+/// is left alone; both base64 decoders accept text with or without it.
+fn normalize_payload(input: &str) -> Cow<'_, str> {
+    let input = input.trim();
+    if input.contains(['\r', '\n', '\t']) {
+        Cow::Owned(input.chars().filter(|c| !matches!(c, '\r' | '\n' | '\t')).collect())
+    } else {
+        Cow::Borrowed(input)
+    }
+}
+
+/// Splits normalized `input` into format version and encoded body. Rejects
+/// text that can't be a payload at all before any decoding is attempted.
+fn split_scheme(input: &str) -> Result<(u32, &str), QrProcessorError> {
+    if input.is_empty() {
+        return Err(QrProcessorError::EmptyInput);
+    }
+
+    let has_scheme = input
+        .get(..PAYLOAD_SCHEME.len())
+        .is_some_and(|s| s.eq_ignore_ascii_case(PAYLOAD_SCHEME));
+    if has_scheme {
+        let rest = &input[PAYLOAD_SCHEME.len()..];
+        let (version, body) = rest.split_once(':').ok_or(QrProcessorError::NotAPayload)?;
+        let version: u32 = version.parse().map_err(|_| QrProcessorError::NotAPayload)?;
+        if version == 0 || version > FORMAT_VERSION {
+            return Err(QrProcessorError::UnsupportedVersion(version));
+        }
+        return Ok((version, body));
+    }
+
+    let plausible = match input.strip_prefix(BASE45_PREFIX) {
+        Some(body) => body.chars().all(|c| BASE45_CHARSET.contains(c)),
+        None => input.chars().all(|c| BASE64_CHARSET.contains(c)),
+    };
+    if !plausible {
+        return Err(QrProcessorError::NotAPayload);
+    }
+
+    Ok((1, input))
+}
+
+/// Default for [`SerializeOptions::compression_level`].
+pub const COMPRESSION_LEVEL: i32 = 16;
+
+/// Set in [`QrData::flags`] when the key was derived from password and keyfile.
+pub(crate) const FLAG_KEYFILE: u8 = 0b0000_0001;
+/// Set in [`QrData::flags`] when the plaintext starts with an expiry timestamp.
+pub(crate) const FLAG_EXPIRES: u8 = 0b0000_0010;
+/// Set in [`QrData::flags`] when the data is several files packed with
+/// [`crate::qr::archive::pack`].
+const FLAG_ARCHIVE: u8 = 0b0000_0100;
+/// Set in [`QrData::flags`] when the plaintext carries a digest of the raw data.
+const FLAG_DIGEST: u8 = 0b0000_1000;
+const EXPIRY_LEN: usize = 8;
+
+/// Marks a base45 payload. It is part of the QR alphanumeric set but not of the
+/// base64 alphabet, so bare base64 payloads stay unambiguous.
+pub const BASE45_PREFIX: char = '%';
+
+/// Base64 decoders for either alphabet that accept the text with or without
+/// padding.
+const STANDARD_ANY_PAD: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    general_purpose::PAD.with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+const URL_SAFE_ANY_PAD: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::URL_SAFE,
+    general_purpose::NO_PAD.with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// Text encoding of the packed payload.
+///
+/// Base45 only uses characters of the QR alphanumeric set, which the `qrcode`
+/// crate packs at 5.5 bits per character instead of 8, so the same payload
+/// fits into a smaller QR version than its base64 form.
+///
+/// The alphabet isn't recorded in the payload: decoding tells the two base64
+/// variants apart by their characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextEncoding {
+    #[default]
+    Base64,
+    /// URL and filename safe base64 (`-` and `_`) without padding, so the text
+    /// can go into a link as it is.
+    Base64Url,
+    Base45,
+}
+
+impl TextEncoding {
+    pub const ALL: [TextEncoding; 3] =
+        [TextEncoding::Base64, TextEncoding::Base64Url, TextEncoding::Base45];
+
+    fn encode(self, packed: &[u8]) -> String {
+        match self {
+            TextEncoding::Base64 => general_purpose::STANDARD.encode(packed),
+            TextEncoding::Base64Url => general_purpose::URL_SAFE_NO_PAD.encode(packed),
+            TextEncoding::Base45 => format!("{}{}", BASE45_PREFIX, base45::encode(packed)),
+        }
+    }
+
+    fn decode(input: &str) -> Result<Vec<u8>, QrProcessorError> {
+        match input.strip_prefix(BASE45_PREFIX) {
+            Some(body) => Ok(base45::decode(body)?),
+            None if input.contains(['-', '_']) => Ok(URL_SAFE_ANY_PAD.decode(input)?),
+            // Also covers URL-safe text that happens to contain neither.
+            None => Ok(STANDARD_ANY_PAD.decode(input)?),
+        }
+    }
+
+    /// Length of [`TextEncoding::encode`]'s output for `len` packed bytes.
+    fn encoded_len(self, len: usize) -> usize {
+        match self {
+            TextEncoding::Base64 => len.div_ceil(3) * 4,
+            TextEncoding::Base64Url => (len * 4).div_ceil(3),
+            TextEncoding::Base45 => 1 + len / 2 * 3 + len % 2 * 2,
+        }
+    }
+}
+
+impl fmt::Display for TextEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TextEncoding::Base64 => "Base64",
+            TextEncoding::Base64Url => "Base64 (URL-safe)",
+            TextEncoding::Base45 => "Base45 (compact)",
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SerializeOptions {
+    pub encoding: TextEncoding,
+    /// Cipher for password-protected payloads; ignored for recipient payloads.
+    pub cipher: Cipher,
+    /// Contents of a keyfile that is required in addition to the password.
+    pub keyfile: Option<Zeroizing<Vec<u8>>>,
+    /// Unix time in seconds after which the payload refuses to decrypt.
+    pub expires_at: Option<i64>,
+    /// zstd level, 1 to 22. The browser build always uses its fastest level.
+    pub compression_level: i32,
+    /// Argon2 limits for password-protected payloads.
+    pub kdf: KdfLimits,
+    /// The data is an archive from [`crate::qr::archive::pack`].
+    pub archive: bool,
+}
+
+impl Default for SerializeOptions {
+    fn default() -> Self {
+        Self {
+            encoding: TextEncoding::default(),
+            cipher: Cipher::default(),
+            keyfile: None,
+            expires_at: None,
+            compression_level: COMPRESSION_LEVEL,
+            kdf: KdfLimits::default(),
+            archive: false,
+        }
+    }
+}
+
+/// Default for [`DeserializeOptions::max_decompressed_len`]; far above what a
+/// single QR code or even a long animation can legitimately carry.
+pub const DEFAULT_MAX_DECOMPRESSED_LEN: usize = 32 * 1024 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct DeserializeOptions {
+    pub keyfile: Option<Zeroizing<Vec<u8>>>,
+    /// Decompression stops with an error beyond this many bytes, so a tiny
+    /// payload can't expand into gigabytes.
+    pub max_decompressed_len: usize,
+    /// Argon2 limits for payloads that don't record theirs, those from
+    /// before format version 7.
+    pub kdf: KdfLimits,
+}
+
+impl Default for DeserializeOptions {
+    fn default() -> Self {
+        Self {
+            keyfile: None,
+            max_decompressed_len: DEFAULT_MAX_DECOMPRESSED_LEN,
+            kdf: KdfLimits::default(),
+        }
+    }
+}
+
+/// What a decrypted payload carried besides the data.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PayloadMetadata {
+    /// Unix time in seconds after which the payload refuses to decrypt.
+    pub expires_at: Option<i64>,
+    /// The data is an archive to be unpacked with
+    /// [`crate::qr::archive::unpack`].
+    pub is_archive: bool,
+    /// Type of the data as told by its magic bytes; `None` for archives and
+    /// for data no known type matches, such as plain text.
+    pub content_type: Option<ContentType>,
+    /// BLAKE2b-256 of the data, which matched the one the payload carried;
+    /// `None` for payloads from before digests, whose data is unverified.
+    pub digest: Option<[u8; crypto_utils::DIGEST_LEN]>,
+}
+
+/// Extension suggested for data of unknown type.
+pub const FALLBACK_EXTENSION: &str = "bin";
+
+impl PayloadMetadata {
+    /// Extension, without the dot, to suggest when saving the data.
+    pub fn extension(&self) -> &'static str {
+        self.content_type.map_or(FALLBACK_EXTENSION, |content_type| content_type.extension)
+    }
+
+    /// The verified digest as lowercase hex.
+    pub fn digest_hex(&self) -> Option<String> {
+        self.digest
+            .map(|digest| digest.iter().map(|byte| format!("{:02x}", byte)).collect())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContentType {
+    pub mime_type: &'static str,
+    pub extension: &'static str,
+}
+
+impl ContentType {
+    /// Looks at the first bytes of `data`. Payloads store no file names, so
+    /// this is all a recipient has to go by.
+    pub fn detect(data: &[u8]) -> Option<Self> {
+        infer::get(data).map(|kind| ContentType {
+            mime_type: kind.mime_type(),
+            extension: kind.extension(),
+        })
+    }
+}
+
+/// The secret a payload is encrypted with.
+#[derive(Clone)]
+pub enum Protection {
+    Password(Zeroizing<String>),
+    /// Any of the holders of the matching secret keys can decrypt the payload.
+    Recipients(Vec<PublicKey>),
+}
+
+impl fmt::Debug for Protection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Protection::Password(_) => f.write_str("Password(..)"),
+            Protection::Recipients(public_keys) => {
+                f.debug_tuple("Recipients").field(public_keys).finish()
+            }
+        }
+    }
+}
+
+/// How the key protecting a payload is obtained.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayloadMode {
+    Password,
+    Recipient,
+}
+
+impl PayloadMode {
+    fn id(self) -> u8 {
+        match self {
+            PayloadMode::Password => 0,
+            PayloadMode::Recipient => 1,
+        }
+    }
+
+    fn from_id(id: u8) -> Result<Self, QrProcessorError> {
+        match id {
+            0 => Ok(PayloadMode::Password),
+            1 | MODE_RECIPIENTS => Ok(PayloadMode::Recipient),
+            other => Err(QrProcessorError::UnknownMode(other)),
+        }
+    }
+}
+
+/// Wire mode of a payload sealed to several recipients; it is opened like a
+/// [`PayloadMode::Recipient`] payload.
+const MODE_RECIPIENTS: u8 = 2;
+
+/// Byte fields are written as arrays of integers, as they always were, but
+/// also read from `bin`, which other MessagePack libraries write for bytes.
 #[derive(Serialize, Deserialize)]
 struct QrData {
+    #[serde(deserialize_with = "bin::deserialize")]
     salt: Vec<u8>,
+    #[serde(deserialize_with = "bin::deserialize")]
     encrypted: Vec<u8>,
+    #[serde(default)]
+    flags: u8,
+    #[serde(default)]
+    mode: u8,
+    #[serde(default)]
+    cipher: u8,
+    #[serde(default, deserialize_with = "bin::deserialize")]
+    check: Vec<u8>,
+    #[serde(default, deserialize_with = "bin::deserialize_list")]
+    keys: Vec<Vec<u8>>,
+    #[serde(default)]
+    kdf_ops: u32,
+    #[serde(default)]
+    kdf_mem_kib: u32,
+}
+
+impl QrData {
+    fn to_packed(&self) -> Result<Vec<u8>, QrProcessorError> {
+        rmp_serde::to_vec(self).map_err(|e| QrProcessorError::Serialization(e.to_string()))
+    }
+
+    fn pack(&self, encoding: TextEncoding) -> Result<String, QrProcessorError> {
+        Ok(format!(
+            "{}{}:{}",
+            PAYLOAD_SCHEME,
+            FORMAT_VERSION,
+            encoding.encode(&self.to_packed()?)
+        ))
+    }
+
+    fn unpack(input_string: &str) -> Result<Self, QrProcessorError> {
+        Self::unpack_versioned(input_string).map(|(_, qr_data)| qr_data)
+    }
+
+    /// Like [`unpack`](Self::unpack), also returning the format version.
+    fn unpack_versioned(input_string: &str) -> Result<(u32, Self), QrProcessorError> {
+        let input = normalize_payload(input_string);
+        let (version, body) = split_scheme(&input)?;
+        let packed = TextEncoding::decode(body)?;
+
+        let qr_data: QrData =
+            rmp_serde::from_slice(&packed).map_err(|e| QrProcessorError::Malformed(e.to_string()))?;
+        qr_data.check_version(version)?;
+        Ok((version, qr_data))
+    }
+
+    /// Refuses fields the format `version` doesn't have yet, and a version 6
+    /// or later payload without its digest, which would otherwise decode
+    /// without being checked.
+    fn check_version(&self, version: u32) -> Result<(), QrProcessorError> {
+        let unexpected = |field: &str| {
+            Err(QrProcessorError::Malformed(format!("{} in a version {} payload", field, version)))
+        };
+        if version < 2 && (self.flags != 0 || self.mode != 0) {
+            return unexpected("flags or mode");
+        }
+        if version < 3 && self.cipher != 0 {
+            return unexpected("cipher");
+        }
+        if version < 4 && !self.check.is_empty() {
+            return unexpected("check value");
+        }
+        if version < 5 && !self.keys.is_empty() {
+            return unexpected("recipient keys");
+        }
+        let has_kdf = self.kdf_ops != 0 || self.kdf_mem_kib != 0;
+        if version < 7 && has_kdf {
+            return unexpected("Argon2 limits");
+        }
+        if version >= 7 && has_kdf != (self.mode == PayloadMode::Password.id()) {
+            return Err(QrProcessorError::Malformed(
+                "Argon2 limits don't match the payload mode".to_string(),
+            ));
+        }
+        match (version >= 6, self.flags & FLAG_DIGEST != 0) {
+            (false, true) => unexpected("digest"),
+            (true, false) => Err(QrProcessorError::Malformed("missing digest".to_string())),
+            _ => Ok(()),
+        }
+    }
+
+    /// The Argon2 limits a password payload was encrypted with, or
+    /// `fallback` when it doesn't record them. Limits outside what
+    /// [`KdfLimits::is_supported`] allows are refused, so a payload can't
+    /// make the decoder spend unbounded time and memory.
+    fn kdf_limits(&self, fallback: KdfLimits) -> Result<KdfLimits, QrProcessorError> {
+        if self.kdf_ops == 0 && self.kdf_mem_kib == 0 {
+            return Ok(fallback);
+        }
+        let limits = KdfLimits {
+            ops_limit: self.kdf_ops,
+            mem_limit_kib: self.kdf_mem_kib,
+        };
+        if !limits.is_supported() {
+            return Err(QrProcessorError::Malformed(format!(
+                "unsupported Argon2 limits ({} passes, {} KiB)",
+                limits.ops_limit, limits.mem_limit_kib
+            )));
+        }
+        Ok(limits)
+    }
+}
+
+/// Writes a frame with a content checksum, which decompression verifies on
+/// top of the authentication tag.
+#[cfg(not(target_arch = "wasm32"))]
+fn zstd_encode(data: &[u8], level: i32) -> Result<Vec<u8>, QrProcessorError> {
+    use std::io::Write;
+
+    let encode = || {
+        let mut encoder = zstd::Encoder::new(Vec::new(), level)?;
+        encoder.include_checksum(true)?;
+        encoder.write_all(data)?;
+        encoder.finish()
+    };
+    encode().map_err(|e| QrProcessorError::Compression(e.to_string()))
+}
+
+/// ruzstd only implements its fastest level, so the browser build compresses
+/// less tightly than the desktop app; both read each other's frames. With its
+/// default `hash` feature, ruzstd writes a content checksum as well.
+#[cfg(target_arch = "wasm32")]
+fn zstd_encode(data: &[u8], _level: i32) -> Result<Vec<u8>, QrProcessorError> {
+    use ruzstd::encoding::{compress_to_vec, CompressionLevel};
+
+    Ok(compress_to_vec(data, CompressionLevel::Fastest))
+}
+
+/// Decodes the whole frame before returning. libzstd verifies the content
+/// checksum, if the frame has one, and fails on a frame that ends early.
+#[cfg(not(target_arch = "wasm32"))]
+fn decompress(compressed: &[u8], limit: usize) -> Result<Vec<u8>, QrProcessorError> {
+    let decoder =
+        zstd::Decoder::new(compressed).map_err(|e| QrProcessorError::Compression(e.to_string()))?;
+    read_limited(decoder, limit)
+}
+
+/// ruzstd leaves both checks to the caller.
+#[cfg(target_arch = "wasm32")]
+fn decompress(compressed: &[u8], limit: usize) -> Result<Vec<u8>, QrProcessorError> {
+    let mut decoder = ruzstd::decoding::StreamingDecoder::new(compressed)
+        .map_err(|e| QrProcessorError::Compression(e.to_string()))?;
+    let decompressed = read_limited(&mut decoder, limit)?;
+
+    let frame = &decoder.decoder;
+    if !frame.is_finished() {
+        return Err(QrProcessorError::Compression("the frame ends early".to_string()));
+    }
+    if let Some(expected) = frame.get_checksum_from_data() {
+        if frame.get_calculated_checksum() != Some(expected) {
+            return Err(QrProcessorError::Compression("checksum mismatch".to_string()));
+        }
+    }
+    Ok(decompressed)
+}
+
+/// Compresses `raw_data` and puts the expiry, if any, in front of it. Returns
+/// the plaintext to encrypt and the flags describing it.
+fn seal_plaintext(
+    raw_data: &[u8],
+    options: &SerializeOptions,
+) -> Result<(Vec<u8>, u8), QrProcessorError> {
+    let compressed = zstd_encode(raw_data, options.compression_level)?;
+    tracing::debug!(compressed_len = compressed.len(), "compressed");
+    let mut flags = FLAG_DIGEST;
+    if options.archive {
+        flags |= FLAG_ARCHIVE;
+    }
+
+    let mut plaintext = Vec::with_capacity(EXPIRY_LEN + crypto_utils::DIGEST_LEN + compressed.len());
+    if let Some(expires_at) = options.expires_at {
+        plaintext.extend_from_slice(&expires_at.to_be_bytes());
+        flags |= FLAG_EXPIRES;
+    }
+    plaintext.extend_from_slice(&crypto_utils::digest(raw_data)?);
+    plaintext.extend_from_slice(&compressed);
+    Ok((plaintext, flags))
+}
+
+/// Reverses [`seal_plaintext`]; fails with [`QrProcessorError::Expired`] once
+/// the deadline has passed and with [`QrProcessorError::IntegrityCheckFailed`]
+/// when the data doesn't match its digest.
+fn open_plaintext(
+    decrypted: &[u8],
+    flags: u8,
+    options: &DeserializeOptions,
+) -> Result<(Vec<u8>, PayloadMetadata), QrProcessorError> {
+    let (expires_at, compressed) = if flags & FLAG_EXPIRES != 0 {
+        if decrypted.len() < EXPIRY_LEN {
+            return Err(QrProcessorError::Malformed("missing expiry".to_string()));
+        }
+        let (expiry, rest) = decrypted.split_at(EXPIRY_LEN);
+        let expires_at = i64::from_be_bytes(expiry.try_into().expect("split at EXPIRY_LEN"));
+        if unix_now() >= expires_at {
+            return Err(QrProcessorError::Expired { expires_at });
+        }
+        (Some(expires_at), rest)
+    } else {
+        (None, decrypted)
+    };
+
+    let (expected_digest, compressed) = if flags & FLAG_DIGEST != 0 {
+        if compressed.len() < crypto_utils::DIGEST_LEN {
+            return Err(QrProcessorError::Malformed("missing digest".to_string()));
+        }
+        let (digest, rest) = compressed.split_at(crypto_utils::DIGEST_LEN);
+        (Some(digest), rest)
+    } else {
+        (None, compressed)
+    };
+
+    let decompressed = decompress(compressed, options.max_decompressed_len)?;
+    tracing::debug!(output_len = decompressed.len(), "decompressed");
+
+    let digest = match expected_digest {
+        Some(expected) => {
+            let digest = crypto_utils::digest(&decompressed)?;
+            if !memcmp(expected, &digest) {
+                return Err(QrProcessorError::IntegrityCheckFailed);
+            }
+            Some(digest)
+        }
+        None => None,
+    };
+
+    let is_archive = flags & FLAG_ARCHIVE != 0;
+    let content_type = if is_archive { None } else { ContentType::detect(&decompressed) };
+    Ok((
+        decompressed,
+        PayloadMetadata {
+            expires_at,
+            is_archive,
+            content_type,
+            digest,
+        },
+    ))
+}
+
+/// Current unix time in seconds.
+pub fn unix_now() -> i64 {
+    web_time::SystemTime::now()
+        .duration_since(web_time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Reads at most `limit` decompressed bytes; anyone can hand us a payload, so
+/// the output size must not be left to the input.
+fn read_limited(decoder: impl Read, limit: usize) -> Result<Vec<u8>, QrProcessorError> {
+    let mut decompressed = Vec::new();
+    decoder
+        .take(limit as u64 + 1)
+        .read_to_end(&mut decompressed)
+        .map_err(|e| QrProcessorError::Compression(e.to_string()))?;
+
+    if decompressed.len() > limit {
+        return Err(QrProcessorError::DecompressedTooLarge { limit });
+    }
+    Ok(decompressed)
+}
+
+/// Fast zstd level used for size estimates. It compresses a little worse than
+/// [`COMPRESSION_LEVEL`], so estimates err on the large side.
+const ESTIMATE_COMPRESSION_LEVEL: i32 = 3;
+
+/// Estimated compressed size of `total_len` bytes of input, extrapolated from
+/// `sample`, which should be a prefix of the input.
+pub fn estimate_compressed_len(sample: &[u8], total_len: u64) -> Result<usize, QrProcessorError> {
+    if sample.is_empty() {
+        return Ok(0);
+    }
+
+    let compressed = zstd_encode(sample, ESTIMATE_COMPRESSION_LEVEL)?;
+
+    let scaled = compressed.len() as u64 * total_len.max(sample.len() as u64) / sample.len() as u64;
+    Ok(usize::try_from(scaled).unwrap_or(usize::MAX))
+}
+
+/// Length of all of `data` compressed at `level`, as a payload would carry it.
+pub fn compressed_len(data: &[u8], level: i32) -> Result<usize, QrProcessorError> {
+    Ok(zstd_encode(data, level)?.len())
+}
+
+/// MessagePack size of a byte field written as an array of integers. Values
+/// below 128 take one byte and the others two; salt and ciphertext are
+/// uniformly random, so half of them land on each side.
+fn packed_bytes_len(len: usize) -> usize {
+    let header = match len {
+        0..=15 => 1,
+        16..=65535 => 3,
+        _ => 5,
+    };
+    header + len + len.div_ceil(2)
+}
+
+/// Length of the text payload for `compressed_len` bytes of compressed input
+/// and its digest, following the layout
+/// [`QrDataProcessor::serialize_protected`] writes.
+/// `recipients` only matters for [`PayloadMode::Recipient`]; `expires` tells
+/// whether [`SerializeOptions::expires_at`] is set.
+pub fn estimate_payload_len(
+    compressed_len: usize,
+    mode: PayloadMode,
+    recipients: usize,
+    encoding: TextEncoding,
+    expires: bool,
+) -> usize {
+    let scheme_len = PAYLOAD_SCHEME.len() + FORMAT_VERSION.to_string().len() + 1;
+    let packed_len = estimate_packed_len(compressed_len, mode, recipients, expires);
+    scheme_len + encoding.encoded_len(packed_len)
+}
+
+/// Like [`estimate_payload_len`], for the packed bytes of
+/// [`QrDataProcessor::payload_to_bytes`].
+pub fn estimate_packed_len(
+    compressed_len: usize,
+    mode: PayloadMode,
+    recipients: usize,
+    expires: bool,
+) -> usize {
+    let expiry_len = if expires { EXPIRY_LEN } else { 0 };
+    let plaintext_len = expiry_len + crypto_utils::DIGEST_LEN + compressed_len;
+    // Argon2 passes fit a positive fixint, the memory in KiB usually needs
+    // a `uint32`; recipient payloads write two zero fixints.
+    let (salt_len, encrypted_len, check_len, keys, kdf_len) = match mode {
+        PayloadMode::Password => (
+            crypto_utils::SALT_LEN,
+            crypto_utils::NONCE_LEN + crypto_utils::MAC_LEN + plaintext_len,
+            crypto_utils::KEY_CHECK_LEN,
+            0,
+            1 + 5,
+        ),
+        PayloadMode::Recipient if recipients > 1 => (
+            0,
+            crypto_utils::NONCE_LEN + crypto_utils::MAC_LEN + plaintext_len,
+            0,
+            recipients,
+            2,
+        ),
+        PayloadMode::Recipient => (0, crypto_utils::SEAL_LEN + plaintext_len, 0, 0, 2),
+    };
+
+    // fixarray header, salt, encrypted, flags, mode and cipher as positive
+    // fixints, check, the keys array with one sealed key per recipient and
+    // the Argon2 limits
+    let keys_header = if keys < 16 { 1 } else { 3 };
+    1 + packed_bytes_len(salt_len)
+        + packed_bytes_len(encrypted_len)
+        + 3
+        + packed_bytes_len(check_len)
+        + keys_header
+        + keys * packed_bytes_len(crypto_utils::SEAL_LEN + crypto_utils::KEY_LEN)
+        + kdf_len
 }
 
 pub struct QrDataProcessor;
 
 impl QrDataProcessor {
     pub fn serialize(raw_data: &[u8], password: &str) -> Result<String, QrProcessorError> {
-        crypto_utils::init();
+        Self::serialize_with(raw_data, password, &SerializeOptions::default())
+    }
+
+    pub fn serialize_with(
+        raw_data: &[u8],
+        password: &str,
+        options: &SerializeOptions,
+    ) -> Result<String, QrProcessorError> {
+        crypto_utils::init()?;
+
+        Self::serialize_password(
+            raw_data,
+            password,
+            options,
+            &crypto_utils::generate_salt(),
+            None,
+            &mut no_stages,
+        )
+    }
+
+    /// Like [`serialize_with`](Self::serialize_with), but with a fixed salt
+    /// and nonce, so the same input always produces the same payload. Meant
+    /// for snapshot tests and for diffing generated codes.
+    ///
+    /// **Do not use this for real data.** Encrypting two different inputs
+    /// with the same password, salt and nonce reuses the XSalsa20 key stream:
+    /// anyone holding both codes learns the XOR of the two plaintexts and can
+    /// forge payloads. A fixed salt also lets an attacker precompute password
+    /// guesses. Use [`serialize_with`](Self::serialize_with), which draws both
+    /// from the system's secure random source.
+    ///
+    /// Ciphers with a shorter nonce use its leading bytes.
+    pub fn serialize_deterministic(
+        raw_data: &[u8],
+        password: &str,
+        options: &SerializeOptions,
+        salt: [u8; crypto_utils::SALT_LEN],
+        nonce: [u8; crypto_utils::NONCE_LEN],
+    ) -> Result<String, QrProcessorError> {
+        crypto_utils::init()?;
+
+        Self::serialize_password(
+            raw_data,
+            password,
+            options,
+            &Salt(salt),
+            Some(&nonce[..options.cipher.nonce_len()]),
+            &mut no_stages,
+        )
+    }
 
-        let salt = crypto_utils::generate_salt();
-        let key = crypto_utils::derive_key(password, &salt)?;
+    #[tracing::instrument(
+        name = "serialize",
+        level = "debug",
+        skip_all,
+        fields(input_len = raw_data.len(), cipher = %options.cipher, deterministic = nonce.is_some())
+    )]
+    fn serialize_password(
+        raw_data: &[u8],
+        password: &str,
+        options: &SerializeOptions,
+        salt: &Salt,
+        nonce: Option<&[u8]>,
+        on_stage: OnStage,
+    ) -> Result<String, QrProcessorError> {
+        on_stage(Stage::DerivingKey)?;
+        let key = crypto_utils::derive_key_with_limits(
+            password,
+            options.keyfile.as_ref().map(|k| k.as_slice()),
+            salt,
+            options.kdf,
+        )?;
 
-        let compressed = zstd::encode_all(raw_data, 16)
-            .map_err(|e| QrProcessorError::Compression(e.to_string()))?;
+        on_stage(Stage::Compressing)?;
+        let (plaintext, flags) = seal_plaintext(raw_data, options)?;
 
-        let encrypted = crypto_utils::encrypt(&compressed, &key)?;
+        on_stage(Stage::Encrypting)?;
+        let encrypted = match nonce {
+            Some(nonce) => options.cipher.encrypt_with_nonce(&plaintext, &key, nonce)?,
+            None => options.cipher.encrypt(&plaintext, &key)?,
+        };
 
         let qr_data = QrData {
             salt: salt.0.to_vec(),
             encrypted,
+            flags: flags | if options.keyfile.is_some() { FLAG_KEYFILE } else { 0 },
+            mode: PayloadMode::Password.id(),
+            cipher: options.cipher.id(),
+            check: crypto_utils::key_check(&key)?.to_vec(),
+            keys: Vec::new(),
+            kdf_ops: options.kdf.ops_limit,
+            kdf_mem_kib: options.kdf.mem_limit_kib,
+        };
+
+        let payload = qr_data.pack(options.encoding)?;
+        tracing::debug!(payload_len = payload.len(), "packed");
+        Ok(payload)
+    }
+
+    pub fn serialize_protected(
+        raw_data: &[u8],
+        protection: &Protection,
+        options: &SerializeOptions,
+    ) -> Result<String, QrProcessorError> {
+        Self::serialize_protected_staged(raw_data, protection, options, &mut no_stages)
+    }
+
+    /// Like [`serialize_protected`](Self::serialize_protected), but calls
+    /// `on_stage` before each step.
+    pub fn serialize_protected_staged(
+        raw_data: &[u8],
+        protection: &Protection,
+        options: &SerializeOptions,
+        on_stage: OnStage,
+    ) -> Result<String, QrProcessorError> {
+        crypto_utils::init()?;
+
+        match protection {
+            Protection::Password(password) => Self::serialize_password(
+                raw_data,
+                password,
+                options,
+                &crypto_utils::generate_salt(),
+                None,
+                on_stage,
+            ),
+            Protection::Recipients(public_keys) => {
+                Self::serialize_recipients(raw_data, public_keys, options, on_stage)
+            }
+        }
+    }
+
+    pub fn serialize_for_recipient(
+        raw_data: &[u8],
+        recipient: &PublicKey,
+    ) -> Result<String, QrProcessorError> {
+        Self::serialize_for_recipient_with(raw_data, recipient, &SerializeOptions::default())
+    }
+
+    /// Seals the payload to `recipient`, so only the holder of the matching
+    /// secret key can open it. `options.keyfile` and `options.cipher` are
+    /// ignored in this mode.
+    pub fn serialize_for_recipient_with(
+        raw_data: &[u8],
+        recipient: &PublicKey,
+        options: &SerializeOptions,
+    ) -> Result<String, QrProcessorError> {
+        Self::serialize_for_recipients_with(raw_data, std::slice::from_ref(recipient), options)
+    }
+
+    /// Encrypts the payload so that the holder of any of the secret keys
+    /// matching `recipients` can open it. A single recipient gets the smaller
+    /// layout of [`serialize_for_recipient_with`](Self::serialize_for_recipient_with);
+    /// `options.keyfile` is ignored.
+    pub fn serialize_for_recipients_with(
+        raw_data: &[u8],
+        recipients: &[PublicKey],
+        options: &SerializeOptions,
+    ) -> Result<String, QrProcessorError> {
+        crypto_utils::init()?;
+
+        Self::serialize_recipients(raw_data, recipients, options, &mut no_stages)
+    }
+
+    #[tracing::instrument(
+        name = "serialize",
+        level = "debug",
+        skip_all,
+        fields(input_len = raw_data.len(), mode = "recipient", recipients = recipients.len())
+    )]
+    fn serialize_recipients(
+        raw_data: &[u8],
+        recipients: &[PublicKey],
+        options: &SerializeOptions,
+        on_stage: OnStage,
+    ) -> Result<String, QrProcessorError> {
+        on_stage(Stage::Compressing)?;
+        let (plaintext, flags) = seal_plaintext(raw_data, options)?;
+
+        on_stage(Stage::Encrypting)?;
+        let qr_data = match recipients {
+            [] => return Err(QrProcessorError::NoRecipients),
+            [recipient] => QrData {
+                salt: Vec::new(),
+                encrypted: crypto_utils::seal(&plaintext, recipient),
+                flags,
+                mode: PayloadMode::Recipient.id(),
+                cipher: 0,
+                check: Vec::new(),
+                keys: Vec::new(),
+                kdf_ops: 0,
+                kdf_mem_kib: 0,
+            },
+            recipients => {
+                let key = crypto_utils::gen_key();
+                QrData {
+                    salt: Vec::new(),
+                    encrypted: options.cipher.encrypt(&plaintext, &key)?,
+                    flags,
+                    mode: MODE_RECIPIENTS,
+                    cipher: options.cipher.id(),
+                    check: Vec::new(),
+                    keys: recipients
+                        .iter()
+                        .map(|recipient| crypto_utils::seal(&key.0, recipient))
+                        .collect(),
+                    kdf_ops: 0,
+                    kdf_mem_kib: 0,
+                }
+            }
         };
 
-        let packed = rmp_serde::to_vec(&qr_data)
-            .map_err(|e| QrProcessorError::Serialization(e.to_string()))?;
+        qr_data.pack(options.encoding)
+    }
+
+    /// Turns the raw content of a scanned code into payload text. UTF-8 content
+    /// is returned as it is. Anything else is taken as the packed `QrData`
+    /// without a text encoding, as [`payload_to_bytes`](Self::payload_to_bytes)
+    /// writes it, and re-encoded as base64 payload text.
+    pub fn payload_from_bytes(scanned: &[u8]) -> Result<String, QrProcessorError> {
+        if let Ok(text) = std::str::from_utf8(scanned) {
+            return Ok(text.to_string());
+        }
+
+        let qr_data: QrData =
+            rmp_serde::from_slice(scanned).map_err(|e| QrProcessorError::Malformed(e.to_string()))?;
+        qr_data.pack(TextEncoding::Base64)
+    }
+
+    /// The packed `QrData` of `payload` without prefix and text encoding, for
+    /// a byte-mode QR code; about a quarter shorter than base64 text.
+    /// [`payload_from_bytes`](Self::payload_from_bytes) turns it back into
+    /// text. The packed form carries no version, so only payloads in the
+    /// current [`FORMAT_VERSION`] have one; older ones are refused.
+    pub fn payload_to_bytes(payload: &str) -> Result<Vec<u8>, QrProcessorError> {
+        match QrData::unpack_versioned(payload)? {
+            (FORMAT_VERSION, qr_data) => qr_data.to_packed(),
+            (version, _) => Err(QrProcessorError::UnsupportedVersion(version)),
+        }
+    }
+
+    /// Like [`deserialize_with`](Self::deserialize_with), for the raw content
+    /// of a scanned code; see [`payload_from_bytes`](Self::payload_from_bytes).
+    pub fn deserialize_bytes(
+        scanned: &[u8],
+        password: &str,
+        options: &DeserializeOptions,
+    ) -> Result<Vec<u8>, QrProcessorError> {
+        Self::deserialize_with(&Self::payload_from_bytes(scanned)?, password, options)
+    }
+
+    /// Cheap check that `input_string` looks like a payload this app can read.
+    pub fn check_payload(input_string: &str) -> Result<(), QrProcessorError> {
+        split_scheme(&normalize_payload(input_string)).map(|_| ())
+    }
 
-        Ok(general_purpose::STANDARD.encode(packed))
+    /// Tells which secret is needed to decrypt `input_string` without decrypting it.
+    pub fn payload_mode(input_string: &str) -> Result<PayloadMode, QrProcessorError> {
+        PayloadMode::from_id(QrData::unpack(input_string)?.mode)
     }
 
     pub fn deserialize(input_string: &str, password: &str) -> Result<Vec<u8>, QrProcessorError> {
-        crypto_utils::init();
+        Self::deserialize_with(input_string, password, &DeserializeOptions::default())
+    }
+
+    pub fn deserialize_with(
+        input_string: &str,
+        password: &str,
+        options: &DeserializeOptions,
+    ) -> Result<Vec<u8>, QrProcessorError> {
+        Self::deserialize_with_metadata(input_string, password, options).map(|(data, _)| data)
+    }
+
+    /// Like [`deserialize_with`](Self::deserialize_with), but also returns
+    /// what the payload carried besides the data, such as its expiry.
+    pub fn deserialize_with_metadata(
+        input_string: &str,
+        password: &str,
+        options: &DeserializeOptions,
+    ) -> Result<(Vec<u8>, PayloadMetadata), QrProcessorError> {
+        Self::deserialize_with_metadata_staged(input_string, password, options, &mut no_stages)
+    }
+
+    /// Like [`deserialize_with_metadata`](Self::deserialize_with_metadata),
+    /// but calls `on_stage` before each step.
+    #[tracing::instrument(
+        name = "deserialize",
+        level = "debug",
+        skip_all,
+        fields(input_len = input_string.len())
+    )]
+    pub fn deserialize_with_metadata_staged(
+        input_string: &str,
+        password: &str,
+        options: &DeserializeOptions,
+        on_stage: OnStage,
+    ) -> Result<(Vec<u8>, PayloadMetadata), QrProcessorError> {
+        crypto_utils::init()?;
+
+        let qr_data = QrData::unpack(input_string)?;
+        tracing::debug!(encrypted_len = qr_data.encrypted.len(), cipher = qr_data.cipher, "unpacked");
+
+        if PayloadMode::from_id(qr_data.mode)? != PayloadMode::Password {
+            return Err(QrProcessorError::RecipientKeyRequired);
+        }
+
+        let salt = Salt::from_slice(&qr_data.salt)
+            .ok_or(QrProcessorError::InvalidSalt(qr_data.salt.len()))?;
+
+        let keyfile = if qr_data.flags & FLAG_KEYFILE != 0 {
+            let keyfile = options.keyfile.as_ref().ok_or(QrProcessorError::KeyfileRequired)?;
+            Some(keyfile.as_slice())
+        } else {
+            None
+        };
+
+        let cipher =
+            Cipher::from_id(qr_data.cipher).ok_or(QrProcessorError::UnknownCipher(qr_data.cipher))?;
+
+        let kdf = qr_data.kdf_limits(options.kdf)?;
+
+        on_stage(Stage::DerivingKey)?;
+        let key = crypto_utils::derive_key_with_limits(password, keyfile, &salt, kdf)?;
+
+        // Codes from before version 4 have no check value; a failed MAC is
+        // then all we know.
+        let has_check = !qr_data.check.is_empty();
+        if has_check && !memcmp(&qr_data.check, &crypto_utils::key_check(&key)?) {
+            return Err(QrProcessorError::WrongPassword);
+        }
+
+        on_stage(Stage::Decrypting)?;
+        let decrypted = cipher.decrypt(&qr_data.encrypted, &key).map_err(|e| match e {
+            crypto_utils::CryptoError::DecryptionFailed if has_check => QrProcessorError::Corrupted,
+            crypto_utils::CryptoError::DecryptionFailed => QrProcessorError::AuthenticationFailed,
+            e => e.into(),
+        })?;
 
-        let packed = general_purpose::STANDARD.decode(input_string)?;
+        on_stage(Stage::Decompressing)?;
+        open_plaintext(&decrypted, qr_data.flags, options)
+    }
+
+    pub fn deserialize_with_keypair(
+        input_string: &str,
+        public_key: &PublicKey,
+        secret_key: &SecretKey,
+        options: &DeserializeOptions,
+    ) -> Result<Vec<u8>, QrProcessorError> {
+        Self::deserialize_with_keypair_metadata(input_string, public_key, secret_key, options)
+            .map(|(data, _)| data)
+    }
 
-        let qr_data: QrData = rmp_serde::from_slice(&packed)
-            .map_err(|e| QrProcessorError::Serialization(e.to_string()))?;
+    /// Like [`deserialize_with_keypair`](Self::deserialize_with_keypair), but
+    /// also returns what the payload carried besides the data.
+    pub fn deserialize_with_keypair_metadata(
+        input_string: &str,
+        public_key: &PublicKey,
+        secret_key: &SecretKey,
+        options: &DeserializeOptions,
+    ) -> Result<(Vec<u8>, PayloadMetadata), QrProcessorError> {
+        Self::deserialize_with_keypair_staged(input_string, public_key, secret_key, options, &mut no_stages)
+    }
+
+    /// Like [`deserialize_with_keypair_metadata`](Self::deserialize_with_keypair_metadata),
+    /// but calls `on_stage` before each step.
+    #[tracing::instrument(
+        name = "deserialize",
+        level = "debug",
+        skip_all,
+        fields(input_len = input_string.len(), mode = "recipient")
+    )]
+    pub fn deserialize_with_keypair_staged(
+        input_string: &str,
+        public_key: &PublicKey,
+        secret_key: &SecretKey,
+        options: &DeserializeOptions,
+        on_stage: OnStage,
+    ) -> Result<(Vec<u8>, PayloadMetadata), QrProcessorError> {
+        crypto_utils::init()?;
 
-        let salt = pwhash::argon2i13::Salt::from_slice(&qr_data.salt)
-            .ok_or(crypto_utils::CryptoError::InvalidSalt)?;
+        let qr_data = QrData::unpack(input_string)?;
 
-        let key = crypto_utils::derive_key(password, &salt)?;
+        if PayloadMode::from_id(qr_data.mode)? != PayloadMode::Recipient {
+            return Err(QrProcessorError::PasswordRequired);
+        }
 
-        let decrypted = crypto_utils::decrypt(&qr_data.encrypted, &key)?;
+        on_stage(Stage::Decrypting)?;
+        let decrypted = if qr_data.mode == MODE_RECIPIENTS {
+            let cipher = Cipher::from_id(qr_data.cipher)
+                .ok_or(QrProcessorError::UnknownCipher(qr_data.cipher))?;
+            // Each sealed key is tried in turn; only ours opens.
+            let key = qr_data
+                .keys
+                .iter()
+                .find_map(|sealed| crypto_utils::open_sealed(sealed, public_key, secret_key).ok())
+                .map(Zeroizing::new)
+                .ok_or(crypto_utils::CryptoError::WrongRecipient)?;
+            let key = crypto_utils::Key::from_slice(&key)
+                .ok_or_else(|| QrProcessorError::Malformed("invalid content key".to_string()))?;
 
-        let decompressed = zstd::decode_all(&decrypted[..])
-            .map_err(|e| QrProcessorError::Compression(e.to_string()))?;
+            cipher.decrypt(&qr_data.encrypted, &key).map_err(|e| match e {
+                crypto_utils::CryptoError::DecryptionFailed => QrProcessorError::Corrupted,
+                e => e.into(),
+            })?
+        } else {
+            crypto_utils::open_sealed(&qr_data.encrypted, public_key, secret_key)?
+        };
 
-        Ok(decompressed)
+        on_stage(Stage::Decompressing)?;
+        open_plaintext(&decrypted, qr_data.flags, options)
     }
 }
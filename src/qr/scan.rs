@@ -0,0 +1,60 @@
+// src/qr/scan.rs
+//! Parts of a split payload collected one scan at a time, in any order,
+//! until the payload is whole.
+use crate::qr::chunk::{self, Chunk, ChunkError};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScanSession {
+    /// Number of parts of the payload.
+    pub count: usize,
+    /// The parts as scanned, header included, by their 1-based index.
+    pub parts: BTreeMap<usize, String>,
+}
+
+impl ScanSession {
+    /// Adds a scanned part. Returns whether it was new; a part that doesn't
+    /// fit the ones collected so far is refused as inconsistent.
+    pub fn add(&mut self, code: &str) -> Result<bool, ChunkError> {
+        let part = Chunk::parse(code)?;
+        if self.parts.is_empty() {
+            self.count = part.count;
+        } else if part.count != self.count {
+            return Err(ChunkError::Inconsistent);
+        }
+
+        match self.parts.get(&part.index) {
+            Some(known) if Chunk::parse(known)?.text != part.text => Err(ChunkError::Inconsistent),
+            Some(_) => Ok(false),
+            None => {
+                self.parts.insert(part.index, code.trim().to_string());
+                Ok(true)
+            }
+        }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.count > 0 && self.parts.len() == self.count
+    }
+
+    /// Indices of the parts still to scan.
+    pub fn missing(&self) -> Vec<usize> {
+        (1..=self.count).filter(|index| !self.parts.contains_key(index)).collect()
+    }
+
+    /// The payload, once all parts are in.
+    pub fn join(&self) -> Result<String, ChunkError> {
+        chunk::join(&self.parts.values().collect::<Vec<_>>())
+    }
+
+    /// Whether a stored session still makes sense: the count is within
+    /// [`chunk::MAX_PARTS`], and every part parses and sits under its own index.
+    pub fn is_valid(&self) -> bool {
+        self.count <= chunk::MAX_PARTS
+            && self.parts.iter().all(|(&index, code)| {
+                Chunk::parse(code)
+                    .is_ok_and(|part| part.index == index && part.count == self.count)
+            })
+    }
+}
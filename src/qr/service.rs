@@ -1,52 +1,1023 @@
 // src/qr/service.rs
-use qrcode::{QrCode, EcLevel};
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{imageops, Delay, Frame, GrayImage, Luma, Rgba, RgbaImage};
+use qrcode::render::{unicode, Renderer};
+use qrcode::{QrCode, Version};
+use std::path::PathBuf;
 use thiserror::Error;
 
-#[derive(Error, Debug)]
+pub use qrcode::EcLevel;
+
+/// Byte capacity of the largest QR code (version 40, error correction L).
+pub const MAX_QR_BYTES: usize = 2953;
+/// Character capacity of the largest QR code in alphanumeric mode.
+pub const MAX_QR_ALPHANUMERIC_CHARS: usize = 4296;
+
+const ALPHANUMERIC_CHARSET: &str = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+/// Byte capacity of a version 40 code at `ec_level`; [`MAX_QR_BYTES`] at L.
+pub fn max_qr_bytes(ec_level: EcLevel) -> usize {
+    match ec_level {
+        EcLevel::L => MAX_QR_BYTES,
+        EcLevel::M => 2331,
+        EcLevel::Q => 1663,
+        EcLevel::H => 1273,
+    }
+}
+
+/// Alphanumeric capacity of a version 40 code at `ec_level`;
+/// [`MAX_QR_ALPHANUMERIC_CHARS`] at L.
+pub fn max_qr_alphanumeric_chars(ec_level: EcLevel) -> usize {
+    match ec_level {
+        EcLevel::L => MAX_QR_ALPHANUMERIC_CHARS,
+        EcLevel::M => 3391,
+        EcLevel::Q => 2420,
+        EcLevel::H => 1852,
+    }
+}
+
+/// Largest length `data` may have to still fit into a single QR code at
+/// `ec_level`.
+pub fn max_payload_len(data: &str, ec_level: EcLevel) -> usize {
+    if data.chars().all(|c| ALPHANUMERIC_CHARSET.contains(c)) {
+        max_qr_alphanumeric_chars(ec_level)
+    } else {
+        max_qr_bytes(ec_level)
+    }
+}
+
+#[derive(Error, Debug, Clone)]
 pub enum QrServiceError {
     #[error("QR code generation failed: {0}")]
     GenerationFailed(String),
     #[error("Image read error: {0}")]
     ImageReadError(String),
-    #[error("No QR code found in image")]
-    QrCodeNotFound,
+    #[error("No QR code found in image ({attempts} variants tried)")]
+    QrCodeNotFound { attempts: usize },
+    #[error("The QR code contains binary data, not text")]
+    NotText,
+    #[error("The logo covers more than {max_percent}% of the code")]
+    LogoTooLarge { max_percent: u32 },
+    #[error("The colors contrast too little for scanners ({ratio:.1}:1, at least {min_ratio}:1)")]
+    LowContrast { ratio: f32, min_ratio: f32 },
+    #[error("The clipboard contains no image")]
+    NoClipboardImage,
+    #[error("Clipboard error: {0}")]
+    ClipboardError(String),
+    #[error("No display to capture")]
+    NoDisplay,
+    #[error("Screen capture failed: {0}")]
+    ScreenCaptureError(String),
+    #[error("Could not decode {path}: {source}")]
+    CompareFailed {
+        path: String,
+        source: Box<QrServiceError>,
+    },
+}
+
+/// Modules of the quiet zone on each side of a rendered code, as the QR
+/// standard asks for.
+pub const DEFAULT_QUIET_ZONE: u32 = 4;
+/// Smaller modules are hard to resolve for printers and phone cameras.
+pub const DEFAULT_MIN_MODULE_SIZE: u32 = 4;
+/// Smallest module side on paper when rendering for a given DPI, in mm.
+pub const MIN_PRINT_MODULE_MM: f32 = 0.5;
+
+/// Image side length for codes meant to be printed.
+pub const PRINT_IMAGE_SIZE: u32 = 1024;
+
+/// Largest share of the code area a logo may cover. Error correction level
+/// H restores up to 30% of the codewords, which leaves some margin for
+/// damage and blur.
+pub const MAX_LOGO_AREA: f32 = 0.2;
+
+/// Smallest contrast ratio between light and dark modules, as WCAG defines
+/// it for text. Dark modules on a lighter background come out below 1 and
+/// are refused as well; most scanners only look for dark codes.
+pub const MIN_CONTRAST_RATIO: f32 = 3.0;
+
+/// Image placed in the middle of a code.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QrLogo {
+    pub path: PathBuf,
+    /// Side of the logo as a share of the code's side, quiet zone excluded.
+    /// Its square must stay within [`MAX_LOGO_AREA`].
+    pub relative_size: f32,
+}
+
+/// Look of a rendered code.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QrStyle {
+    /// RGB color of dark modules.
+    pub dark: [u8; 3],
+    /// RGB color of light modules and the quiet zone.
+    pub light: [u8; 3],
+    /// Light modules around the code on each side.
+    pub quiet_zone: u32,
+    /// Rendering with a logo switches to error correction level H, whatever
+    /// [`RenderOptions::ec_level`] says.
+    pub logo: Option<QrLogo>,
+}
+
+impl Default for QrStyle {
+    fn default() -> Self {
+        Self {
+            dark: [0, 0, 0],
+            light: [255, 255, 255],
+            quiet_zone: DEFAULT_QUIET_ZONE,
+            logo: None,
+        }
+    }
+}
+
+impl QrStyle {
+    /// Black on white without a logo, which renders as grayscale.
+    fn is_plain(&self) -> bool {
+        self.dark == [0, 0, 0] && self.light == [255, 255, 255] && self.logo.is_none()
+    }
+
+    /// Relative luminance of the light color over that of the dark one, from
+    /// 21 for black on white down to below 1 for codes drawn light on dark.
+    pub fn contrast_ratio(&self) -> f32 {
+        (relative_luminance(self.light) + 0.05) / (relative_luminance(self.dark) + 0.05)
+    }
+}
+
+/// Luminance of an sRGB color as WCAG 2 computes it, from 0 to 1.
+fn relative_luminance(color: [u8; 3]) -> f32 {
+    let [r, g, b] = color.map(|channel| {
+        let c = f32::from(channel) / 255.0;
+        if c <= 0.039_28 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    });
+    0.2126 * r + 0.7152 * g + 0.0722 * b
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderOptions {
+    /// Preferred side length of the image in pixels, quiet zone included.
+    /// Modules are whole pixels, so the image may come out slightly smaller,
+    /// or larger where `min_module_size` requires it.
+    pub size: u32,
+    /// Smallest module side in pixels. Dense codes grow beyond `size` rather
+    /// than going below it.
+    pub min_module_size: u32,
+    /// Print resolution. When set, modules are also kept at least
+    /// [`MIN_PRINT_MODULE_MM`] wide when printed at this resolution.
+    pub dpi: Option<u32>,
+    /// Colors, quiet zone and logo. Animations only use the quiet zone, see
+    /// [`render_animation_frames`].
+    pub style: QrStyle,
+    /// Higher levels survive more damage but hold less data, see
+    /// [`max_qr_bytes`]; payloads near [`MAX_QR_BYTES`] only fit at level L.
+    pub ec_level: EcLevel,
+    /// Put the packed payload into the code as raw bytes instead of its text.
+    /// Only [`crate::services`] acts on this; the functions here render
+    /// whatever they are given.
+    pub binary: bool,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            size: 512,
+            min_module_size: DEFAULT_MIN_MODULE_SIZE,
+            dpi: None,
+            style: QrStyle::default(),
+            ec_level: EcLevel::L,
+            binary: false,
+        }
+    }
+}
+
+impl RenderOptions {
+    /// The configured level, or H when there is a logo to make up for.
+    pub fn effective_ec_level(&self) -> EcLevel {
+        if self.style.logo.is_some() {
+            EcLevel::H
+        } else {
+            self.ec_level
+        }
+    }
+
+    /// Module side in pixels for a code `modules` wide, quiet zone included.
+    fn module_size(&self, modules: u32) -> u32 {
+        let print_min = self
+            .dpi
+            .map_or(0, |dpi| (MIN_PRINT_MODULE_MM * dpi as f32 / 25.4).ceil() as u32);
+
+        (self.size / modules.max(1))
+            .max(self.min_module_size)
+            .max(print_min)
+            .max(1)
+    }
+}
+
+/// Pixel layout of a rendered code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QrDimensions {
+    /// Modules per side, quiet zone included.
+    pub modules: u32,
+    /// Pixels per module side.
+    pub module_size: u32,
+    /// Image side length in pixels.
+    pub side: u32,
+}
+
+/// QR version of a code and how much of its capacity the data takes up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QrCapacity {
+    /// 1 to 40.
+    pub version: i16,
+    /// Data bytes used, mode and length headers included.
+    pub used_bytes: usize,
+    /// Data bytes the version holds at the code's error correction level.
+    pub capacity_bytes: usize,
+}
+
+/// A rendered code together with its version, fill level and pixel layout.
+#[derive(Debug, Clone)]
+pub struct QrImage {
+    pub png: Vec<u8>,
+    pub capacity: QrCapacity,
+    pub dimensions: QrDimensions,
+}
+
+pub fn generate_qr_image(data: &str, options: &RenderOptions) -> Result<QrImage, QrServiceError> {
+    generate_qr_image_bytes(data.as_bytes(), options)
+}
+
+/// Like [`generate_qr_image`], for arbitrary bytes, which go into byte mode
+/// unless they happen to be text a denser mode can hold.
+#[tracing::instrument(level = "debug", skip_all, fields(data_len = data.len(), size = options.size))]
+pub fn generate_qr_image_bytes(data: &[u8], options: &RenderOptions) -> Result<QrImage, QrServiceError> {
+    let capacity = qr_capacity_at(data, options.effective_ec_level())?;
+    tracing::debug!(
+        version = capacity.version,
+        used_bytes = capacity.used_bytes,
+        capacity_bytes = capacity.capacity_bytes,
+        "picked QR version"
+    );
+
+    let (png, dimensions) = if options.style.is_plain() {
+        let (img, dimensions) = render_qr(data, options)?;
+        (encode_png(&img)?, dimensions)
+    } else {
+        let (img, dimensions) = render_styled(data, options)?;
+        (encode_png(&img)?, dimensions)
+    };
+    tracing::debug!(
+        side = dimensions.side,
+        module_size = dimensions.module_size,
+        png_len = png.len(),
+        "rendered QR code"
+    );
+
+    Ok(QrImage {
+        png,
+        capacity,
+        dimensions,
+    })
 }
 
-pub fn generate_qr_image(data: &str) -> Result<Vec<u8>, QrServiceError> {
-    let code = QrCode::with_error_correction_level(data, EcLevel::L)
+/// Picks the QR version for `data` the same way rendering does and reports
+/// how full it is.
+pub fn qr_capacity(data: &str) -> Result<QrCapacity, QrServiceError> {
+    qr_capacity_at(data.as_bytes(), EcLevel::L)
+}
+
+fn qr_capacity_at(data: &[u8], ec_level: EcLevel) -> Result<QrCapacity, QrServiceError> {
+    let to_error = |e: qrcode::types::QrError| QrServiceError::GenerationFailed(e.to_string());
+
+    // The encoded bits come back padded to the full capacity, so the data is
+    // encoded once more for the version without the padding.
+    let padded = qrcode::bits::encode_auto(data, ec_level).map_err(to_error)?;
+    let mut bits = qrcode::bits::Bits::new(padded.version());
+    bits.push_optimal_data(data).map_err(to_error)?;
+
+    let version = match padded.version() {
+        Version::Normal(version) | Version::Micro(version) => version,
+    };
+
+    Ok(QrCapacity {
+        version,
+        used_bytes: bits.len().div_ceil(8),
+        capacity_bytes: padded.max_len(ec_level).map_err(to_error)? / 8,
+    })
+}
+
+/// Encodes `data` and lays it out for `options`.
+fn layout_qr(
+    data: &[u8],
+    options: &RenderOptions,
+) -> Result<(QrCode, QrDimensions), QrServiceError> {
+    let code = QrCode::with_error_correction_level(data, options.effective_ec_level())
         .map_err(|e| QrServiceError::GenerationFailed(e.to_string()))?;
 
-    // Render as image buffer
-    let image_buffer = code
-        .render::<image::Luma<u8>>()
-        .max_dimensions(512, 512)
+    let modules = code.width() as u32 + 2 * options.style.quiet_zone;
+    let module_size = options.module_size(modules);
+
+    Ok((
+        code,
+        QrDimensions {
+            modules,
+            module_size,
+            side: modules * module_size,
+        },
+    ))
+}
+
+/// Renders black on white, ignoring the style's colors and logo.
+fn render_qr(
+    data: &[u8],
+    options: &RenderOptions,
+) -> Result<(GrayImage, QrDimensions), QrServiceError> {
+    let (code, dimensions) = layout_qr(data, options)?;
+
+    let img = Renderer::<Luma<u8>>::new(&code.to_colors(), code.width(), options.style.quiet_zone)
+        .module_dimensions(dimensions.module_size, dimensions.module_size)
+        .build();
+
+    Ok((img, dimensions))
+}
+
+/// Renders in the style's colors and puts its logo, if any, in the middle.
+fn render_styled(
+    data: &[u8],
+    options: &RenderOptions,
+) -> Result<(RgbaImage, QrDimensions), QrServiceError> {
+    let style = &options.style;
+    let ratio = style.contrast_ratio();
+    if ratio < MIN_CONTRAST_RATIO {
+        return Err(QrServiceError::LowContrast {
+            ratio,
+            min_ratio: MIN_CONTRAST_RATIO,
+        });
+    }
+    let (code, dimensions) = layout_qr(data, options)?;
+    let [dr, dg, db] = style.dark;
+    let [lr, lg, lb] = style.light;
+
+    let mut img = Renderer::<Rgba<u8>>::new(&code.to_colors(), code.width(), style.quiet_zone)
+        .module_dimensions(dimensions.module_size, dimensions.module_size)
+        .dark_color(Rgba([dr, dg, db, 255]))
+        .light_color(Rgba([lr, lg, lb, 255]))
         .build();
 
-    // Convert to PNG bytes
+    if let Some(logo) = &style.logo {
+        place_logo(&mut img, logo, code.width() as u32, &dimensions, style.light)?;
+    }
+
+    Ok((img, dimensions))
+}
+
+/// Clears a square of whole modules in the middle of the code to the light
+/// color and draws the logo into it, scaled to fit.
+fn place_logo(
+    img: &mut RgbaImage,
+    logo: &QrLogo,
+    code_width: u32,
+    dimensions: &QrDimensions,
+    light: [u8; 3],
+) -> Result<(), QrServiceError> {
+    if logo.relative_size.powi(2) > MAX_LOGO_AREA {
+        return Err(QrServiceError::LogoTooLarge {
+            max_percent: (MAX_LOGO_AREA * 100.0).round() as u32,
+        });
+    }
+
+    let logo_image = image::open(&logo.path)
+        .map_err(|e| QrServiceError::ImageReadError(e.to_string()))?;
+
+    // Odd, so the cleared square sits exactly on the center module, and
+    // rounded down, so it stays within the checked area.
+    let modules = ((logo.relative_size.max(0.0) * code_width as f32) as u32).saturating_sub(1) | 1;
+    let side = modules * dimensions.module_size;
+    let offset = i64::from((dimensions.side - side) / 2);
+
+    let [r, g, b] = light;
+    let background = RgbaImage::from_pixel(side, side, Rgba([r, g, b, 255]));
+    imageops::replace(img, &background, offset, offset);
+
+    let scaled = logo_image
+        .resize(side, side, imageops::FilterType::Lanczos3)
+        .into_rgba8();
+    imageops::overlay(
+        img,
+        &scaled,
+        offset + i64::from((side - scaled.width()) / 2),
+        offset + i64::from((side - scaled.height()) / 2),
+    );
+
+    Ok(())
+}
+
+/// Renders `data` for a terminal with half-block characters, two modules per
+/// character cell so the code comes out square, quiet zone included.
+///
+/// Light modules are the drawn ones, so the code scans on the usual terminal
+/// with light text on a dark background.
+pub fn generate_qr_terminal(data: &str, ec_level: EcLevel) -> Result<String, QrServiceError> {
+    let code = QrCode::with_error_correction_level(data, ec_level)
+        .map_err(|e| QrServiceError::GenerationFailed(e.to_string()))?;
+
+    Ok(code
+        .render::<unicode::Dense1x2>()
+        .dark_color(unicode::Dense1x2::Light)
+        .light_color(unicode::Dense1x2::Dark)
+        .quiet_zone(true)
+        .build())
+}
+
+pub fn encode_png<P>(img: &image::ImageBuffer<P, Vec<u8>>) -> Result<Vec<u8>, QrServiceError>
+where
+    P: image::PixelWithColorType<Subpixel = u8>,
+{
     let mut buffer = Vec::new();
-    let img = image::DynamicImage::ImageLuma8(image_buffer);
     img.write_to(&mut std::io::Cursor::new(&mut buffer), image::ImageFormat::Png)
         .map_err(|e| QrServiceError::GenerationFailed(e.to_string()))?;
 
     Ok(buffer)
 }
 
+/// Height of the strip below each animation frame that marks its position.
+const INDICATOR_HEIGHT: u32 = 12;
+
+/// Narrowest segment of the position strip. With more chunks than fit at
+/// this width, the strip becomes one track with a marker of this width.
+const MIN_SEGMENT_WIDTH: u32 = 6;
+
+/// Renders one frame per chunk, all of the same size: the QR code centered on
+/// a white canvas above a strip with one segment per chunk, the current one
+/// filled, so the viewer can see which part is showing.
+///
+/// Frames are always black on white and carry no logo, whatever the style
+/// says: a logo raises the error correction level, which would spread the
+/// payload over more frames, and scanners lock onto plain frames faster.
+pub fn render_animation_frames(
+    chunks: &[String],
+    options: &RenderOptions,
+) -> Result<Vec<GrayImage>, QrServiceError> {
+    let codes = chunks
+        .iter()
+        .map(|chunk| render_qr(chunk.as_bytes(), options).map(|(img, _)| img))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let width = codes.iter().map(|code| code.width()).max().unwrap_or(0);
+    let height = codes.iter().map(|code| code.height()).max().unwrap_or(0);
+
+    let frames = codes
+        .iter()
+        .enumerate()
+        .map(|(index, code)| {
+            let mut frame = GrayImage::from_pixel(width, height + INDICATOR_HEIGHT, Luma([255]));
+            imageops::overlay(
+                &mut frame,
+                code,
+                i64::from((width - code.width()) / 2),
+                i64::from((height - code.height()) / 2),
+            );
+            draw_indicator(&mut frame, height, index as u32, codes.len() as u32);
+            frame
+        })
+        .collect();
+
+    Ok(frames)
+}
+
+/// Draws the position strip for part `index` of `count` into the rows of
+/// `frame` from `top` on.
+fn draw_indicator(frame: &mut GrayImage, top: u32, index: u32, count: u32) {
+    let width = frame.width();
+    let span = |i: u32| (i * width / count, (i + 1) * width / count);
+    let mut fill = |x0: u32, x1: u32, shade: u8| {
+        for x in x0..x1.min(width) {
+            for y in top + 2..top + INDICATOR_HEIGHT - 2 {
+                frame.put_pixel(x, y, Luma([shade]));
+            }
+        }
+    };
+
+    if width / count.max(1) >= MIN_SEGMENT_WIDTH {
+        for i in 0..count {
+            let (x0, x1) = span(i);
+            fill(x0 + 1, x1 - 1, if i == index { 0 } else { 200 });
+        }
+    } else {
+        fill(0, width, 200);
+        let x0 = span(index).0.min(width.saturating_sub(MIN_SEGMENT_WIDTH));
+        fill(x0, x0 + MIN_SEGMENT_WIDTH, 0);
+    }
+}
+
+/// Encodes the chunks as a looping animated GIF showing `fps` codes per second.
+pub fn generate_qr_animation(
+    chunks: &[String],
+    fps: u8,
+    options: &RenderOptions,
+) -> Result<Vec<u8>, QrServiceError> {
+    encode_animation(&render_animation_frames(chunks, options)?, fps)
+}
+
+/// Encodes frames from [`render_animation_frames`] as a looping animated GIF
+/// showing `fps` of them per second.
+pub fn encode_animation(frames: &[GrayImage], fps: u8) -> Result<Vec<u8>, QrServiceError> {
+    let delay = Delay::from_numer_denom_ms(1000, u32::from(fps.max(1)));
+    let frames = frames.iter().map(|frame| {
+        let rgba = image::DynamicImage::ImageLuma8(frame.clone()).into_rgba8();
+        Frame::from_parts(rgba, 0, 0, delay)
+    });
+
+    let mut buffer = Vec::new();
+    {
+        let mut encoder = GifEncoder::new_with_speed(&mut buffer, 10);
+        encoder
+            .set_repeat(Repeat::Infinite)
+            .and_then(|_| encoder.encode_frames(frames))
+            .map_err(|e| QrServiceError::GenerationFailed(e.to_string()))?;
+    }
+
+    Ok(buffer)
+}
+
+/// Images whose shorter side is below this many pixels are upscaled before
+/// the preprocessed passes, so small codes get enough pixels per module.
+const MIN_DECODE_SIDE: u32 = 600;
+
+/// Larger photos are scaled down so that their longer side has this many
+/// pixels; the detector is slow on big images and gains nothing from them.
+const MAX_DECODE_SIDE: u32 = 1000;
+
+/// Adaptive threshold passes tried in turn: the neighbourhood radius as a
+/// divisor of the shorter image side, and how much darker than its
+/// neighbourhood a pixel has to be to count as black.
+const THRESHOLD_PASSES: [(u32, i32); 3] = [(16, 7), (8, 10), (32, 5)];
+
+/// Rotations tried when the upright image doesn't decode; rqrr copes with
+/// perspective but not always with a whole frame turned on its side.
+const ROTATIONS: [fn(&GrayImage) -> GrayImage; 3] =
+    [imageops::rotate90, imageops::rotate180, imageops::rotate270];
+
+/// Share of the darkest and brightest pixels ignored by contrast stretching.
+const STRETCH_CLIP: f32 = 0.01;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadOptions {
+    /// Retry with contrast stretching, rescaling, adaptive thresholding and
+    /// rotated copies when the plain grayscale image contains no readable
+    /// code, which helps with photos taken in uneven light or at an angle.
+    pub preprocess: bool,
+}
+
+impl Default for ReadOptions {
+    fn default() -> Self {
+        Self { preprocess: true }
+    }
+}
+
 pub fn read_qr_from_image(filepath: &str) -> Result<String, QrServiceError> {
+    read_qr_from_image_with(filepath, ReadOptions::default())
+}
+
+pub fn read_qr_from_image_with(filepath: &str, options: ReadOptions) -> Result<String, QrServiceError> {
+    into_text(read_qr_bytes_from_image_with(filepath, options)?)
+}
+
+/// Like [`read_qr_from_image`], but returns the content as stored in the
+/// code, without requiring it to be UTF-8.
+pub fn read_qr_bytes_from_image(filepath: &str) -> Result<Vec<u8>, QrServiceError> {
+    read_qr_bytes_from_image_with(filepath, ReadOptions::default())
+}
+
+#[tracing::instrument(level = "debug", skip(options), fields(preprocess = options.preprocess))]
+pub fn read_qr_bytes_from_image_with(
+    filepath: &str,
+    options: ReadOptions,
+) -> Result<Vec<u8>, QrServiceError> {
+    read_qr_decoded_from_image(filepath, options).map(|decoded| decoded.content)
+}
+
+/// Like [`read_qr_bytes_from_image_with`], also telling which decoder read
+/// the code.
+pub fn read_qr_decoded_from_image(
+    filepath: &str,
+    options: ReadOptions,
+) -> Result<Decoded, QrServiceError> {
     let img = image::open(filepath)
         .map_err(|e| QrServiceError::ImageReadError(e.to_string()))?;
 
-    let img = img.to_luma8();
+    decode_luma_detailed(img.to_luma8(), options)
+}
+
+/// Decodes the codes in two images and tells whether they carry the same
+/// payload, e.g. to check a reprint against the original. Nothing is
+/// decrypted, so no password is needed; scaling or cropping of the image
+/// doesn't matter as long as the code stays readable.
+pub fn decode_and_compare(path_a: &str, path_b: &str) -> Result<bool, QrServiceError> {
+    let decode = |path: &str| {
+        read_qr_bytes_from_image(path).map_err(|e| QrServiceError::CompareFailed {
+            path: path.to_string(),
+            source: Box::new(e),
+        })
+    };
+    let payload_a = decode(path_a)?;
+    let payload_b = decode(path_b)?;
+    tracing::debug!(len_a = payload_a.len(), len_b = payload_b.len(), "compared");
+    Ok(payload_a == payload_b)
+}
+
+/// Decodes a QR code from an encoded image in memory, such as the PNG of a
+/// [`QrImage`].
+pub fn read_qr_from_bytes(image: &[u8]) -> Result<String, QrServiceError> {
+    into_text(read_qr_bytes_from_memory(image)?)
+}
+
+/// Like [`read_qr_from_bytes`], returning the content byte for byte.
+pub fn read_qr_bytes_from_memory(image: &[u8]) -> Result<Vec<u8>, QrServiceError> {
+    let img = image::load_from_memory(image)
+        .map_err(|e| QrServiceError::ImageReadError(e.to_string()))?;
+
+    decode_luma_bytes(img.to_luma8(), ReadOptions::default())
+}
+
+/// Decodes a QR code from the image currently on the system clipboard, e.g. a
+/// screenshot.
+pub fn read_qr_from_clipboard(options: ReadOptions) -> Result<String, QrServiceError> {
+    into_text(read_qr_bytes_from_clipboard(options)?)
+}
+
+/// Like [`read_qr_from_clipboard`], without requiring the content to be UTF-8.
+pub fn read_qr_bytes_from_clipboard(options: ReadOptions) -> Result<Vec<u8>, QrServiceError> {
+    let mut clipboard =
+        arboard::Clipboard::new().map_err(|e| QrServiceError::ClipboardError(e.to_string()))?;
+    let data = clipboard.get_image().map_err(|e| match e {
+        arboard::Error::ContentNotAvailable => QrServiceError::NoClipboardImage,
+        e => QrServiceError::ClipboardError(e.to_string()),
+    })?;
+
+    let img = image::RgbaImage::from_raw(
+        data.width as u32,
+        data.height as u32,
+        data.bytes.into_owned(),
+    )
+    .ok_or_else(|| QrServiceError::ImageReadError("malformed clipboard image".to_string()))?;
+
+    decode_luma_bytes(image::DynamicImage::ImageRgba8(img).to_luma8(), options)
+}
+
+/// A display [`read_qr_bytes_from_screen`] can capture.
+#[cfg(feature = "screen")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScreenMonitor {
+    pub id: u32,
+    pub name: String,
+}
+
+#[cfg(feature = "screen")]
+pub fn list_monitors() -> Result<Vec<ScreenMonitor>, QrServiceError> {
+    let monitors =
+        xcap::Monitor::all().map_err(|e| QrServiceError::ScreenCaptureError(e.to_string()))?;
+
+    Ok(monitors
+        .iter()
+        .filter_map(|monitor| {
+            Some(ScreenMonitor {
+                id: monitor.id().ok()?,
+                name: monitor.name().unwrap_or_default(),
+            })
+        })
+        .collect())
+}
+
+/// Captures the display `monitor`, or every display in turn when it is
+/// `None`, and decodes the first QR code found. Captures stay in memory.
+/// Displays that can't be captured are skipped as long as another one can.
+#[cfg(feature = "screen")]
+#[tracing::instrument(level = "debug", skip(options))]
+pub fn read_qr_bytes_from_screen(
+    monitor: Option<u32>,
+    options: ReadOptions,
+) -> Result<Vec<u8>, QrServiceError> {
+    let monitors =
+        xcap::Monitor::all().map_err(|e| QrServiceError::ScreenCaptureError(e.to_string()))?;
+
+    let mut attempts = 0;
+    let mut captured = false;
+    let mut capture_error = None;
+    for screen in monitors {
+        if monitor.is_some() && screen.id().ok() != monitor {
+            continue;
+        }
+
+        let img = match screen.capture_image() {
+            Ok(img) => img,
+            Err(e) => {
+                tracing::warn!("Could not capture display {:?}: {}", screen.name().ok(), e);
+                capture_error = Some(QrServiceError::ScreenCaptureError(e.to_string()));
+                continue;
+            }
+        };
+        captured = true;
+
+        match decode_luma_bytes(image::DynamicImage::ImageRgba8(img).to_luma8(), options) {
+            Err(QrServiceError::QrCodeNotFound { attempts: tried }) => attempts += tried,
+            result => return result,
+        }
+    }
+
+    if captured {
+        Err(QrServiceError::QrCodeNotFound { attempts })
+    } else {
+        Err(capture_error.unwrap_or(QrServiceError::NoDisplay))
+    }
+}
+
+/// Content of a code as text; byte-mode codes may hold anything.
+fn into_text(content: Vec<u8>) -> Result<String, QrServiceError> {
+    String::from_utf8(content).map_err(|_| QrServiceError::NotText)
+}
+
+/// Like [`decode_luma_bytes`], for codes that hold UTF-8 text.
+pub fn decode_luma(img: GrayImage, options: ReadOptions) -> Result<String, QrServiceError> {
+    into_text(decode_luma_bytes(img, options)?)
+}
+
+/// Decoder that read a code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decoder {
+    Rqrr,
+    /// Only with the `rxing` feature, after rqrr found nothing.
+    Rxing,
+}
+
+/// Content of a decoded code and the decoder that read it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decoded {
+    pub content: Vec<u8>,
+    pub decoder: Decoder,
+}
+
+/// Tries every candidate of `img` in turn and returns the content of the
+/// first decoded code, byte for byte.
+pub fn decode_luma_bytes(img: GrayImage, options: ReadOptions) -> Result<Vec<u8>, QrServiceError> {
+    decode_luma_detailed(img, options).map(|decoded| decoded.content)
+}
+
+/// Like [`decode_luma_bytes`], also telling which decoder read the code.
+///
+/// With the `rxing` feature, rxing gets a last try on the original image when
+/// none of the candidates decodes with rqrr. It only reports text, so binary
+/// content may not survive that path.
+#[tracing::instrument(level = "debug", skip_all, fields(width = img.width(), height = img.height()))]
+pub fn decode_luma_detailed(img: GrayImage, options: ReadOptions) -> Result<Decoded, QrServiceError> {
+    #[cfg(feature = "rxing")]
+    let fallback_img = img.clone();
+
+    let mut attempts = 0;
+    for candidate in Candidates::new(img, options) {
+        attempts += 1;
+        if let Some(content) = decode_grids(candidate) {
+            tracing::debug!(decoder = "rqrr", attempts, "decoded QR code");
+            return Ok(Decoded {
+                content,
+                decoder: Decoder::Rqrr,
+            });
+        }
+    }
+
+    #[cfg(feature = "rxing")]
+    {
+        attempts += 1;
+        if let Some(content) = decode_rxing(fallback_img) {
+            tracing::debug!(decoder = "rxing", attempts, "decoded QR code");
+            return Ok(Decoded {
+                content,
+                decoder: Decoder::Rxing,
+            });
+        }
+    }
+
+    Err(QrServiceError::QrCodeNotFound { attempts })
+}
+
+#[cfg(feature = "rxing")]
+fn decode_rxing(img: GrayImage) -> Option<Vec<u8>> {
+    let (width, height) = img.dimensions();
+    rxing::helpers::detect_in_luma(
+        img.into_raw(),
+        width,
+        height,
+        Some(rxing::BarcodeFormat::QR_CODE),
+    )
+    .ok()
+    .map(|result| result.getText().as_bytes().to_vec())
+}
+
+/// The images a decode tries in turn, computed lazily: the plain grayscale
+/// image and, with preprocessing, a contrast stretched and rescaled copy,
+/// its adaptive threshold passes, and that copy and the first threshold pass
+/// rotated by 90°, 180° and 270°.
+pub struct Candidates {
+    original: GrayImage,
+    scaled: Option<GrayImage>,
+    preprocess: bool,
+    step: usize,
+}
+
+impl Candidates {
+    pub fn new(img: GrayImage, options: ReadOptions) -> Self {
+        Self {
+            original: img,
+            scaled: None,
+            preprocess: options.preprocess,
+            step: 0,
+        }
+    }
+
+    fn scaled(&mut self) -> &GrayImage {
+        let original = &self.original;
+        self.scaled
+            .get_or_insert_with(|| rescale(stretch_contrast(original)))
+    }
+
+    fn threshold(&mut self, pass: usize) -> GrayImage {
+        let (divisor, offset) = THRESHOLD_PASSES[pass];
+        let scaled = self.scaled();
+        let radius = (scaled.width().min(scaled.height()) / divisor).max(1);
+        adaptive_threshold(scaled, radius, offset)
+    }
+}
+
+impl Iterator for Candidates {
+    type Item = GrayImage;
+
+    fn next(&mut self) -> Option<GrayImage> {
+        let step = self.step;
+        self.step += 1;
 
-    let mut decoder = rqrr::PreparedImage::prepare(img);
-    let grids = decoder.detect_grids();
+        if step == 0 {
+            return Some(self.original.clone());
+        }
+        if !self.preprocess {
+            return None;
+        }
 
-    if grids.is_empty() {
-        return Err(QrServiceError::QrCodeNotFound);
+        let passes = THRESHOLD_PASSES.len();
+        let index = step - 1;
+        if index == 0 {
+            Some(self.scaled().clone())
+        } else if index <= passes {
+            Some(self.threshold(index - 1))
+        } else if index <= passes + ROTATIONS.len() {
+            Some(ROTATIONS[index - passes - 1](self.scaled()))
+        } else if index <= passes + 2 * ROTATIONS.len() {
+            Some(ROTATIONS[index - passes - ROTATIONS.len() - 1](&self.threshold(0)))
+        } else {
+            None
+        }
     }
+}
+
+/// Text of every code in `img`, e.g. a scanned page holding several. The
+/// variants are tried in the same order as for [`decode_luma_bytes`] and the
+/// first one in which any grid decodes is used; codes that aren't UTF-8 are
+/// left out.
+pub fn read_all_qr_from_image(img: GrayImage, options: ReadOptions) -> Result<Vec<String>, QrServiceError> {
+    let mut attempts = 0;
+    for candidate in Candidates::new(img, options) {
+        attempts += 1;
+        let mut prepared = rqrr::PreparedImage::prepare(candidate);
+        let codes: Vec<String> = prepared
+            .detect_grids()
+            .iter()
+            .filter_map(|grid| grid.decode().ok().map(|(_, content)| content))
+            .collect();
+        if !codes.is_empty() {
+            tracing::debug!(attempts, codes = codes.len(), "decoded QR codes");
+            return Ok(codes);
+        }
+    }
+
+    Err(QrServiceError::QrCodeNotFound { attempts })
+}
+
+/// Raw content of the first grid that decodes. `decode_to` writes the segment
+/// data as is; `decode` would fail on anything that isn't UTF-8.
+fn decode_grids(img: GrayImage) -> Option<Vec<u8>> {
+    let mut prepared = rqrr::PreparedImage::prepare(img);
+
+    prepared.detect_grids().iter().find_map(|grid| {
+        let mut content = Vec::new();
+        grid.decode_to(&mut content).ok().map(|_| content)
+    })
+}
+
+/// Scales large photos down to [`MAX_DECODE_SIDE`] and small images up
+/// towards [`MIN_DECODE_SIDE`], by a whole factor that keeps the longer side
+/// within [`MAX_DECODE_SIDE`], so a thin strip doesn't grow into a huge image.
+fn rescale(img: GrayImage) -> GrayImage {
+    let (width, height) = img.dimensions();
+    let longer = width.max(height);
+    let shorter = width.min(height);
+    if shorter == 0 {
+        return img;
+    }
+
+    if longer > MAX_DECODE_SIDE {
+        let scale = MAX_DECODE_SIDE as f32 / longer as f32;
+        return imageops::resize(
+            &img,
+            ((width as f32 * scale) as u32).max(1),
+            ((height as f32 * scale) as u32).max(1),
+            imageops::FilterType::Triangle,
+        );
+    }
+    let factor = MIN_DECODE_SIDE
+        .div_ceil(shorter)
+        .min(MAX_DECODE_SIDE / longer);
+    if factor > 1 {
+        return imageops::resize(
+            &img,
+            width * factor,
+            height * factor,
+            imageops::FilterType::Triangle,
+        );
+    }
+
+    img
+}
+
+/// Spreads the brightness range between the darkest and brightest percent of
+/// pixels over the full range, for washed out or dim photos.
+fn stretch_contrast(img: &GrayImage) -> GrayImage {
+    let mut histogram = [0usize; 256];
+    for pixel in img.pixels() {
+        histogram[usize::from(pixel[0])] += 1;
+    }
+
+    let clip = (img.pixels().len() as f32 * STRETCH_CLIP) as usize;
+    let mut seen = 0;
+    let low = histogram
+        .iter()
+        .position(|&n| {
+            seen += n;
+            seen > clip
+        })
+        .unwrap_or(0) as i32;
+    seen = 0;
+    let high = 255
+        - histogram
+            .iter()
+            .rev()
+            .position(|&n| {
+                seen += n;
+                seen > clip
+            })
+            .unwrap_or(0) as i32;
+
+    if high <= low {
+        return img.clone();
+    }
+
+    GrayImage::from_fn(img.width(), img.height(), |x, y| {
+        let value = i32::from(img.get_pixel(x, y)[0]);
+        Luma([((value - low) * 255 / (high - low)).clamp(0, 255) as u8])
+    })
+}
+
+/// Binarizes `img` against the mean brightness of each pixel's
+/// `(2 * radius + 1)²` neighbourhood, computed with an integral image.
+fn adaptive_threshold(img: &GrayImage, radius: u32, offset: i32) -> GrayImage {
+    let (width, height) = img.dimensions();
+    let stride = width as usize + 1;
+
+    let mut integral = vec![0u64; stride * (height as usize + 1)];
+    for y in 0..height as usize {
+        let mut row_sum = 0u64;
+        for x in 0..width as usize {
+            row_sum += u64::from(img.get_pixel(x as u32, y as u32)[0]);
+            integral[(y + 1) * stride + x + 1] = integral[y * stride + x + 1] + row_sum;
+        }
+    }
+
+    GrayImage::from_fn(width, height, |x, y| {
+        let x0 = x.saturating_sub(radius) as usize;
+        let y0 = y.saturating_sub(radius) as usize;
+        let x1 = (x + radius + 1).min(width) as usize;
+        let y1 = (y + radius + 1).min(height) as usize;
 
-    let (_, content) = grids[0]
-        .decode()
-        .map_err(|_| QrServiceError::QrCodeNotFound)?;
+        let sum = integral[y1 * stride + x1] + integral[y0 * stride + x0]
+            - integral[y0 * stride + x1]
+            - integral[y1 * stride + x0];
+        let mean = (sum / ((x1 - x0) * (y1 - y0)) as u64) as i32;
 
-    Ok(content)
+        if i32::from(img.get_pixel(x, y)[0]) < mean - offset {
+            Luma([0])
+        } else {
+            Luma([255])
+        }
+    })
 }
@@ -0,0 +1,396 @@
+// src/qr/stream.rs
+//! Streaming form of a password-protected payload, for files too large to
+//! hold in memory at once.
+//!
+//! The input is read in blocks and fed to a zstd encoder. The compressed bytes
+//! are cut into segments of [`SEGMENT_LEN`], each sealed with libsodium's
+//! `crypto_secretstream_xchacha20poly1305`, which also authenticates their
+//! order and marks the last one, so a truncated stream is detected.
+//!
+//! The text is `QRDXS<version>:` (see [`STREAM_SCHEME`]) followed by the
+//! standard base64 encoding of `salt (16) | flags (1) | kdf_ops (4) |
+//! kdf_mem_kib (4) | [expires_at (8)] | check (8) | header (24) | segments`,
+//! every segment being its length as a big-endian `u32` and the sealed bytes.
+//! Salt, flags, check and the Argon2 limits mean the same as in a regular
+//! payload; the expiry is there when its flag is set. Everything before the
+//! header is authenticated along with the first segment. Version 1 streams
+//! lack the limits and the expiry and are decoded with
+//! [`DeserializeOptions::kdf`]. Like any payload, the text can be split with
+//! [`chunk`](super::chunk).
+use super::processor::{
+    self, DeserializeOptions, QrProcessorError, SerializeOptions, TextEncoding, FLAG_EXPIRES,
+    FLAG_KEYFILE,
+};
+use crate::crypto::crypto_utils::{
+    self, memcmp, Cipher, CryptoError, KdfLimits, Salt, KEY_CHECK_LEN, SALT_LEN,
+};
+use base64::engine::general_purpose::STANDARD;
+use base64::read::DecoderReader;
+use base64::write::EncoderWriter;
+use sodiumoxide::crypto::secretstream::{self, Header, Push, Stream, Tag, ABYTES, HEADERBYTES};
+use std::io::{self, Read, Write};
+use thiserror::Error;
+use zeroize::Zeroizing;
+
+/// Scheme in front of a streamed payload, followed by the version and `:`.
+pub const STREAM_SCHEME: &str = "QRDXS";
+/// Version of the layout written by [`encrypt_stream`].
+pub const STREAM_VERSION: u32 = 2;
+/// Compressed bytes per segment. Together with the zstd window this bounds
+/// what encoding and decoding keep in memory, whatever the input size.
+pub const SEGMENT_LEN: usize = 64 * 1024;
+
+#[derive(Error, Debug)]
+pub enum StreamError {
+    #[error(transparent)]
+    Processor(#[from] QrProcessorError),
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+}
+
+impl From<CryptoError> for StreamError {
+    fn from(e: CryptoError) -> Self {
+        StreamError::Processor(e.into())
+    }
+}
+
+/// Encrypts everything `input` yields into payload text written to `output`
+/// and returns the number of input bytes. The text is always base64 and
+/// encrypted with XChaCha20-Poly1305, so other encodings and ciphers are
+/// refused, as are archives.
+pub fn encrypt_stream<R: Read, W: Write>(
+    mut input: R,
+    mut output: W,
+    password: &str,
+    options: &SerializeOptions,
+) -> Result<u64, StreamError> {
+    if options.encoding != TextEncoding::Base64 {
+        return Err(QrProcessorError::NotForStreams("other text encodings than base64").into());
+    }
+    if options.cipher != Cipher::default() {
+        return Err(QrProcessorError::NotForStreams("a choice of cipher").into());
+    }
+    if options.archive {
+        return Err(QrProcessorError::NotForStreams("several files").into());
+    }
+    crypto_utils::init()?;
+
+    let keyfile = options.keyfile.as_ref().map(|k| k.as_slice());
+    let salt = crypto_utils::generate_salt();
+    let key = crypto_utils::derive_key_with_limits(password, keyfile, &salt, options.kdf)?;
+
+    let mut flags = 0;
+    if keyfile.is_some() {
+        flags |= FLAG_KEYFILE;
+    }
+    if options.expires_at.is_some() {
+        flags |= FLAG_EXPIRES;
+    }
+    let mut prefix = Vec::new();
+    prefix.extend_from_slice(&salt.0);
+    prefix.push(flags);
+    prefix.extend_from_slice(&options.kdf.ops_limit.to_be_bytes());
+    prefix.extend_from_slice(&options.kdf.mem_limit_kib.to_be_bytes());
+    if let Some(expires_at) = options.expires_at {
+        prefix.extend_from_slice(&expires_at.to_be_bytes());
+    }
+    prefix.extend_from_slice(&crypto_utils::key_check(&key)?);
+
+    let (stream, header) = Stream::init_push(&secretstream::Key(key.0))
+        .map_err(|_| CryptoError::EncryptionFailed)?;
+
+    write!(output, "{}{}:", STREAM_SCHEME, STREAM_VERSION)?;
+    let mut encoded = EncoderWriter::new(output, &STANDARD);
+    encoded.write_all(&prefix)?;
+    encoded.write_all(&header.0)?;
+
+    let segments = SegmentWriter {
+        inner: encoded,
+        stream,
+        buffer: Zeroizing::new(Vec::with_capacity(SEGMENT_LEN)),
+        additional_data: Some(prefix),
+    };
+    let mut encoder = zstd::stream::write::Encoder::new(segments, options.compression_level)?;
+    encoder.include_checksum(true)?;
+    let input_len = io::copy(&mut input, &mut encoder)?;
+
+    let mut output = encoder.finish()?.finish()?.finish()?;
+    output.flush()?;
+    tracing::debug!(input_len, "encrypted stream");
+    Ok(input_len)
+}
+
+/// Decrypts payload text written by [`encrypt_stream`] from `input` into
+/// `output`. Segments are written as soon as they are authenticated, so after
+/// an error `output` may hold a prefix of the data. Fails with
+/// [`QrProcessorError::DecompressedTooLarge`] once more than
+/// [`DeserializeOptions::max_decompressed_len`] bytes would be written.
+pub fn decrypt_stream<R: Read, W: Write>(
+    input: R,
+    output: W,
+    password: &str,
+    options: &DeserializeOptions,
+) -> Result<(), StreamError> {
+    crypto_utils::init()?;
+
+    let mut input = SkipWhitespace(input);
+    let version = read_scheme(&mut input)?;
+    let mut decoded = DecoderReader::new(input, &STANDARD);
+
+    let mut salt = [0u8; SALT_LEN];
+    let mut flags = [0u8; 1];
+    read_field(&mut decoded, &mut salt)?;
+    read_field(&mut decoded, &mut flags)?;
+    let mut prefix = [salt.as_slice(), flags.as_slice()].concat();
+
+    let (kdf, mut expires_at) = if version >= 2 {
+        let mut limits = [0u8; 8];
+        read_field(&mut decoded, &mut limits)?;
+        prefix.extend_from_slice(&limits);
+        let (ops, mem) = limits.split_at(4);
+        let kdf = KdfLimits {
+            ops_limit: u32::from_be_bytes(ops.try_into().expect("split at 4")),
+            mem_limit_kib: u32::from_be_bytes(mem.try_into().expect("split at 4")),
+        };
+        if !kdf.is_supported() {
+            return Err(QrProcessorError::Malformed(format!(
+                "unsupported Argon2 limits ({} passes, {} KiB)",
+                kdf.ops_limit, kdf.mem_limit_kib
+            ))
+            .into());
+        }
+
+        let expires_at = if flags[0] & FLAG_EXPIRES != 0 {
+            let mut expiry = [0u8; 8];
+            read_field(&mut decoded, &mut expiry)?;
+            prefix.extend_from_slice(&expiry);
+            Some(i64::from_be_bytes(expiry))
+        } else {
+            None
+        };
+        (kdf, expires_at)
+    } else {
+        (options.kdf, None)
+    };
+
+    let mut check = [0u8; KEY_CHECK_LEN];
+    let mut header = [0u8; HEADERBYTES];
+    read_field(&mut decoded, &mut check)?;
+    read_field(&mut decoded, &mut header)?;
+    prefix.extend_from_slice(&check);
+
+    let keyfile = if flags[0] & FLAG_KEYFILE != 0 {
+        let keyfile = options.keyfile.as_ref().ok_or(QrProcessorError::KeyfileRequired)?;
+        Some(keyfile.as_slice())
+    } else {
+        None
+    };
+    let key = crypto_utils::derive_key_with_limits(password, keyfile, &Salt(salt), kdf)?;
+    if !memcmp(&check, &crypto_utils::key_check(&key)?) {
+        return Err(QrProcessorError::WrongPassword.into());
+    }
+
+    let mut stream = Stream::init_pull(&Header(header), &secretstream::Key(key.0))
+        .map_err(|_| QrProcessorError::Malformed("invalid stream header".to_string()))?;
+    let limited = LimitedWriter {
+        inner: output,
+        remaining: options.max_decompressed_len,
+        exceeded: false,
+    };
+    let mut decoder = zstd::stream::write::Decoder::new(limited)?;
+    let mut sealed = Vec::with_capacity(SEGMENT_LEN + ABYTES);
+    let mut additional_data = (version >= 2).then_some(prefix);
+
+    loop {
+        let mut len = [0u8; 4];
+        read_field(&mut decoded, &mut len)?;
+        let len = u32::from_be_bytes(len) as usize;
+        if len > SEGMENT_LEN + ABYTES {
+            return Err(QrProcessorError::Malformed(format!("segment of {} bytes", len)).into());
+        }
+
+        sealed.resize(len, 0);
+        read_field(&mut decoded, &mut sealed)?;
+        let (segment, tag) = stream
+            .pull(&sealed, additional_data.take().as_deref())
+            .map_err(|_| QrProcessorError::Corrupted)?;
+        // The expiry is only trusted once the first segment authenticated it.
+        if let Some(expires_at) = expires_at.take() {
+            if processor::unix_now() >= expires_at {
+                return Err(QrProcessorError::Expired { expires_at }.into());
+            }
+        }
+
+        if let Err(e) = decoder.write_all(&Zeroizing::new(segment)) {
+            return Err(limit_error(&decoder, options, e));
+        }
+        if tag == Tag::Final {
+            break;
+        }
+    }
+
+    if decoded.read(&mut [0u8; 1])? != 0 {
+        return Err(QrProcessorError::Malformed("data after the last segment".to_string()).into());
+    }
+
+    if let Err(e) = decoder.flush() {
+        return Err(limit_error(&decoder, options, e));
+    }
+    decoder.into_inner().inner.flush()?;
+    Ok(())
+}
+
+/// Tells a write refused by the [`LimitedWriter`] under `decoder` from other
+/// IO errors.
+fn limit_error<W: Write>(
+    decoder: &zstd::stream::write::Decoder<'_, LimitedWriter<W>>,
+    options: &DeserializeOptions,
+    e: io::Error,
+) -> StreamError {
+    if decoder.get_ref().exceeded {
+        QrProcessorError::DecompressedTooLarge {
+            limit: options.max_decompressed_len,
+        }
+        .into()
+    } else {
+        StreamError::Io(e)
+    }
+}
+
+/// Reads `QRDXS<version>:` and returns the version if it is supported.
+fn read_scheme<R: Read>(input: &mut R) -> Result<u32, StreamError> {
+    // Longest accepted prefix: the scheme, a ten-digit version and the colon.
+    let mut prefix = Vec::new();
+    let mut byte = [0u8; 1];
+    while prefix.len() <= STREAM_SCHEME.len() + 10 {
+        if input.read(&mut byte)? == 0 {
+            if prefix.is_empty() {
+                return Err(QrProcessorError::EmptyInput.into());
+            }
+            break;
+        }
+        if byte[0] == b':' {
+            break;
+        }
+        prefix.push(byte[0]);
+    }
+
+    let prefix = String::from_utf8_lossy(&prefix);
+    let version = prefix
+        .get(..STREAM_SCHEME.len())
+        .filter(|scheme| scheme.eq_ignore_ascii_case(STREAM_SCHEME))
+        .and_then(|_| prefix[STREAM_SCHEME.len()..].parse::<u32>().ok())
+        .filter(|_| byte[0] == b':')
+        .ok_or(QrProcessorError::NotAPayload)?;
+
+    if !(1..=STREAM_VERSION).contains(&version) {
+        return Err(QrProcessorError::UnsupportedVersion(version).into());
+    }
+    Ok(version)
+}
+
+/// `read_exact` that reports truncated and invalid base64 input as malformed.
+fn read_field<R: Read>(input: &mut R, buf: &mut [u8]) -> Result<(), StreamError> {
+    input.read_exact(buf).map_err(|e| match e.kind() {
+        io::ErrorKind::UnexpectedEof => {
+            QrProcessorError::Malformed("the stream ends early".to_string()).into()
+        }
+        io::ErrorKind::InvalidData => QrProcessorError::Malformed(e.to_string()).into(),
+        _ => StreamError::Io(e),
+    })
+}
+
+/// Collects compressed bytes and seals every full segment; [`finish`](Self::finish)
+/// seals the rest as the final one.
+struct SegmentWriter<W: Write> {
+    inner: W,
+    stream: Stream<Push>,
+    buffer: Zeroizing<Vec<u8>>,
+    /// The unencrypted fields, authenticated with the first segment.
+    additional_data: Option<Vec<u8>>,
+}
+
+impl<W: Write> SegmentWriter<W> {
+    fn seal(&mut self, tag: Tag) -> io::Result<()> {
+        let sealed = self
+            .stream
+            .push(&self.buffer, self.additional_data.take().as_deref(), tag)
+            .map_err(|_| io::Error::other("segment encryption failed"))?;
+        self.inner.write_all(&(sealed.len() as u32).to_be_bytes())?;
+        self.inner.write_all(&sealed)?;
+        self.buffer.clear();
+        Ok(())
+    }
+
+    fn finish(mut self) -> io::Result<W> {
+        self.seal(Tag::Final)?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for SegmentWriter<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let len = data.len().min(SEGMENT_LEN - self.buffer.len());
+        self.buffer.extend_from_slice(&data[..len]);
+        if self.buffer.len() == SEGMENT_LEN {
+            self.seal(Tag::Message)?;
+        }
+        Ok(len)
+    }
+
+    /// Only flushes what is already sealed; a partial segment waits for more
+    /// data or for [`finish`](Self::finish).
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Passes on at most `remaining` bytes and fails the write that would go
+/// beyond, setting `exceeded`.
+struct LimitedWriter<W> {
+    inner: W,
+    remaining: usize,
+    exceeded: bool,
+}
+
+impl<W: Write> Write for LimitedWriter<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        if data.len() > self.remaining {
+            self.exceeded = true;
+            return Err(io::Error::other("decompression limit exceeded"));
+        }
+        let len = self.inner.write(data)?;
+        self.remaining -= len;
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Drops ASCII whitespace, such as line breaks or a trailing newline added by
+/// an editor.
+struct SkipWhitespace<R>(R);
+
+impl<R: Read> Read for SkipWhitespace<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let len = self.0.read(buf)?;
+            if len == 0 {
+                return Ok(0);
+            }
+
+            let mut kept = 0;
+            for i in 0..len {
+                if !buf[i].is_ascii_whitespace() {
+                    buf[kept] = buf[i];
+                    kept += 1;
+                }
+            }
+            if kept > 0 {
+                return Ok(kept);
+            }
+        }
+    }
+}
@@ -0,0 +1,110 @@
+// src/scan_session.rs
+//! Storage of the [`ScanSession`] in progress. The session is written to the
+//! cache folder after every new part, so a crash halfway through a long
+//! sequence loses nothing, and deleted once the payload is whole. Parts are
+//! encrypted payload text, like the history entries.
+use crate::settings;
+use qr_data_exchange::qr::scan::ScanSession;
+use qr_data_exchange::services;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::Mutex;
+
+const SESSION_FILE: &str = "scan-session.json";
+
+/// Generation handed to the next [`save`] or [`remove`].
+static NEXT_GENERATION: AtomicU64 = AtomicU64::new(1);
+
+/// Generation of the last write that reached the disk. Holding the lock
+/// keeps writes apart, and a write older than the last one is dropped, so a
+/// save that runs late can't bring back a session removed after it.
+static LAST_WRITTEN: Mutex<u64> = Mutex::const_new(0);
+
+fn session_path() -> Option<PathBuf> {
+    settings::cache_dir().map(|dir| dir.join(SESSION_FILE))
+}
+
+/// Loads the stored session, if there is one with at least one part.
+pub fn load() -> Option<ScanSession> {
+    let path = session_path()?;
+
+    let session: ScanSession = match std::fs::read_to_string(&path) {
+        Ok(contents) => match serde_json::from_str(&contents) {
+            Ok(session) => session,
+            Err(e) => {
+                tracing::warn!("Ignoring invalid scan session {}: {}", path.display(), e);
+                return None;
+            }
+        },
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return None,
+        Err(e) => {
+            tracing::warn!("Could not read scan session {}: {}", path.display(), e);
+            return None;
+        }
+    };
+
+    if !session.is_valid() {
+        tracing::warn!("Ignoring inconsistent scan session {}", path.display());
+        return None;
+    }
+    (!session.parts.is_empty()).then_some(session)
+}
+
+/// Writes the session. Failures are only logged, as for the settings.
+///
+/// The write takes its place in line when this is called, not when the
+/// future first runs.
+pub fn save(session: ScanSession) -> impl Future<Output = ()> {
+    let generation = NEXT_GENERATION.fetch_add(1, Ordering::Relaxed);
+    write(generation, Some(session))
+}
+
+/// Deletes the stored session, after the payload was put together or the
+/// user discarded the scan. Ordered with [`save`] like the saves themselves.
+pub fn remove() -> impl Future<Output = ()> {
+    let generation = NEXT_GENERATION.fetch_add(1, Ordering::Relaxed);
+    write(generation, None)
+}
+
+/// Saves `session`, or removes the stored one for `None`, unless a later
+/// generation was written already.
+async fn write(generation: u64, session: Option<ScanSession>) {
+    let mut last_written = LAST_WRITTEN.lock().await;
+    if *last_written > generation {
+        tracing::debug!(generation, last = *last_written, "dropping stale scan session write");
+        return;
+    }
+    *last_written = generation;
+
+    let Some(path) = session_path() else {
+        return;
+    };
+
+    let Some(session) = session else {
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => tracing::warn!("Could not delete scan session {}: {}", path.display(), e),
+        }
+        return;
+    };
+
+    let contents = match serde_json::to_string(&session) {
+        Ok(contents) => contents,
+        Err(e) => {
+            tracing::warn!("Could not serialize the scan session: {}", e);
+            return;
+        }
+    };
+
+    if let Some(parent) = path.parent() {
+        if let Err(e) = tokio::fs::create_dir_all(parent).await {
+            tracing::warn!("Could not create {}: {}", parent.display(), e);
+            return;
+        }
+    }
+    if let Err(e) = services::save_bytes_atomic(&path, contents.as_bytes()).await {
+        tracing::warn!("Could not write scan session: {}", e);
+    }
+}
@@ -0,0 +1,121 @@
+// src/selftest.rs
+//! Round trip of a known plaintext through the whole pipeline, to check that
+//! the crypto and compression libraries work on this platform before real
+//! data is trusted to the build.
+use crate::error::Error;
+use crate::qr::processor::{DeserializeOptions, QrDataProcessor, SerializeOptions};
+use crate::qr::service::{self, RenderOptions};
+use std::fmt;
+use std::time::Duration;
+use thiserror::Error;
+use web_time::Instant;
+
+const PASSWORD: &str = "self-test password";
+
+/// Compressible text followed by every byte value, so that binary data and
+/// the compression path are both covered.
+fn sample() -> Vec<u8> {
+    let mut data = b"QR Data Exchange self-test. ".repeat(8);
+    data.extend(0..=u8::MAX);
+    data
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelfTestStep {
+    /// Serialize and deserialize in memory.
+    Payload,
+    /// Render the payload as a QR code and read it back from the PNG.
+    QrImage,
+}
+
+impl SelfTestStep {
+    pub const ALL: [SelfTestStep; 2] = [SelfTestStep::Payload, SelfTestStep::QrImage];
+}
+
+impl fmt::Display for SelfTestStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SelfTestStep::Payload => "encrypt and decrypt",
+            SelfTestStep::QrImage => "render and read QR code",
+        })
+    }
+}
+
+#[derive(Error, Debug, Clone)]
+pub enum SelfTestError {
+    #[error(transparent)]
+    Failed(#[from] Error),
+    #[error("the recovered data differs from the original")]
+    Mismatch,
+}
+
+#[derive(Debug, Clone)]
+pub struct StepResult {
+    pub step: SelfTestStep,
+    pub duration: Duration,
+    pub outcome: Result<(), SelfTestError>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SelfTestReport {
+    pub steps: Vec<StepResult>,
+}
+
+impl SelfTestReport {
+    pub fn passed(&self) -> bool {
+        self.steps.iter().all(|step| step.outcome.is_ok())
+    }
+}
+
+/// Runs every step; a failing step doesn't stop the ones after it. Takes a
+/// few seconds, as every key derivation does.
+pub fn run() -> SelfTestReport {
+    let steps = SelfTestStep::ALL
+        .into_iter()
+        .map(|step| {
+            let started = Instant::now();
+            let outcome = match step {
+                SelfTestStep::Payload => check_payload(),
+                SelfTestStep::QrImage => check_qr_image(),
+            };
+            StepResult {
+                step,
+                duration: started.elapsed(),
+                outcome,
+            }
+        })
+        .collect();
+
+    SelfTestReport { steps }
+}
+
+fn check_payload() -> Result<(), SelfTestError> {
+    let data = sample();
+    let payload = QrDataProcessor::serialize_with(&data, PASSWORD, &SerializeOptions::default())
+        .map_err(Error::from)?;
+    check_recovered(&payload, &data)
+}
+
+fn check_qr_image() -> Result<(), SelfTestError> {
+    let data = sample();
+    let payload = QrDataProcessor::serialize_with(&data, PASSWORD, &SerializeOptions::default())
+        .map_err(Error::from)?;
+
+    let qr = service::generate_qr_image(&payload, &RenderOptions::default()).map_err(Error::from)?;
+    let read = service::read_qr_from_bytes(&qr.png).map_err(Error::from)?;
+    if read != payload {
+        return Err(SelfTestError::Mismatch);
+    }
+
+    check_recovered(&read, &data)
+}
+
+fn check_recovered(payload: &str, data: &[u8]) -> Result<(), SelfTestError> {
+    let recovered =
+        QrDataProcessor::deserialize_with(payload, PASSWORD, &DeserializeOptions::default())
+            .map_err(Error::from)?;
+    if recovered != data {
+        return Err(SelfTestError::Mismatch);
+    }
+    Ok(())
+}
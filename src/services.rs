@@ -0,0 +1,906 @@
+// src/services.rs
+use crate::error::{Error, Result};
+use crate::keys;
+use crate::qr;
+use crate::qr::archive::{self, ArchiveEntry};
+use crate::qr::chunk::{self, Chunk, ChunkError};
+use crate::qr::fountain::{self, FountainDecoder, FountainEncoder};
+use crate::qr::pdf::PdfPage;
+use crate::qr::processor::{
+    self, DeserializeOptions, PayloadMetadata, PayloadMode, Protection, QrDataProcessor,
+    QrProcessorError, SerializeOptions, Stage, TextEncoding,
+};
+use crate::qr::service::{EcLevel, QrCapacity, QrDimensions, RenderOptions};
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio_util::sync::CancellationToken;
+use zeroize::Zeroizing;
+
+/// Number of bytes at the start of a file that a size estimate compresses.
+const ESTIMATE_SAMPLE_LEN: u64 = 256 * 1024;
+
+/// Default limit for a file encoded into a single QR code. Even very
+/// compressible files beyond this size would not fit.
+pub const MAX_INPUT_LEN: u64 = 4 * 1024 * 1024;
+/// Default limit for a file spread over an animated sequence.
+pub const MAX_ANIMATION_INPUT_LEN: u64 = 32 * 1024 * 1024;
+
+/// Reads a whole input file, refusing files larger than `limit` bytes before
+/// anything is loaded into memory.
+pub async fn read_input(filename: impl AsRef<Path>, limit: u64) -> Result<Vec<u8>> {
+    let filename = filename.as_ref();
+    let read_failed = |e| Error::read_failed(filename, e);
+    let file = tokio::fs::File::open(filename).await.map_err(read_failed)?;
+    let size = file.metadata().await.map_err(read_failed)?.len();
+    if size > limit {
+        return Err(Error::FileTooLarge { size, limit });
+    }
+
+    // The file may have grown since the check; never read past the limit.
+    let mut data = Vec::with_capacity(size as usize);
+    file.take(limit + 1).read_to_end(&mut data).await.map_err(read_failed)?;
+    if data.len() as u64 > limit {
+        return Err(Error::FileTooLarge {
+            size: data.len() as u64,
+            limit,
+        });
+    }
+
+    Ok(data)
+}
+
+/// Reads `paths` and packs them into one archive under their file names. The
+/// files together may not exceed `limit` bytes.
+pub async fn pack_files(paths: Vec<PathBuf>, limit: u64) -> Result<Vec<u8>> {
+    let mut entries = Vec::with_capacity(paths.len());
+    let mut remaining = limit;
+    for path in &paths {
+        let data = read_input(path, remaining).await.map_err(|e| match e {
+            Error::FileTooLarge { size, .. } => Error::FileTooLarge {
+                size: limit - remaining + size,
+                limit,
+            },
+            e => e,
+        })?;
+        remaining -= data.len() as u64;
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        entries.push(ArchiveEntry { name, data });
+    }
+    Ok(archive::pack(&entries)?)
+}
+
+/// Unpacks decrypted archive data into `dir`; see [`archive::extract`].
+pub async fn extract_archive(data: Zeroizing<Vec<u8>>, dir: PathBuf) -> Result<Vec<PathBuf>> {
+    tokio::task::spawn_blocking(move || {
+        let entries = archive::unpack(&data)?;
+        Ok(archive::extract(&entries, &dir)?)
+    })
+    .await
+    .map_err(std::io::Error::other)?
+}
+
+/// Largest payload text file [`load_payload_text`] accepts. Anything that
+/// fits into QR codes is far smaller.
+pub const MAX_PAYLOAD_TEXT_LEN: u64 = 64 * 1024;
+
+/// Reads payload text saved to a file. Whitespace is dropped, which also joins
+/// lines wrapped by mail clients.
+pub async fn load_payload_text(path: PathBuf) -> Result<String> {
+    let data = read_input(&path, MAX_PAYLOAD_TEXT_LEN).await?;
+    let text = String::from_utf8(data).map_err(|_| QrProcessorError::NotAPayload)?;
+    Ok(text.split_whitespace().collect())
+}
+
+/// Tells the UI which [`Stage`] a long encode or decode has reached, and
+/// stops it before the next stage once cancelled. A step that has started,
+/// e.g. key derivation, still runs to its end; long steps made of many parts
+/// also [`check`](Self::check) between them. The default reports nothing
+/// and is never cancelled.
+#[derive(Debug, Clone, Default)]
+pub struct Progress {
+    cancel: CancellationToken,
+    stages: Option<UnboundedSender<Stage>>,
+}
+
+impl Progress {
+    /// Progress stopped by `cancel`, and the stages as they are reached.
+    pub fn channel(cancel: CancellationToken) -> (Self, UnboundedReceiver<Stage>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let progress = Self {
+            cancel,
+            stages: Some(sender),
+        };
+        (progress, receiver)
+    }
+
+    /// Reports `stage`, or fails with [`QrProcessorError::Cancelled`].
+    pub fn enter(&self, stage: Stage) -> std::result::Result<(), QrProcessorError> {
+        if self.cancel.is_cancelled() {
+            return Err(QrProcessorError::Cancelled);
+        }
+        if let Some(stages) = &self.stages {
+            // The receiver is gone when nobody watches any more.
+            let _ = stages.send(stage);
+        }
+        Ok(())
+    }
+
+    /// Fails with [`QrProcessorError::Cancelled`] once cancelled. Blocking
+    /// work calls this between its parts, as aborting the async task that
+    /// waits for it doesn't stop it.
+    pub fn check(&self) -> std::result::Result<(), QrProcessorError> {
+        if self.cancel.is_cancelled() {
+            return Err(QrProcessorError::Cancelled);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct QrGenerationResult {
+    pub qr_text: String,
+    pub qr_image: Vec<u8>, // PNG bytes
+    pub capacity: QrCapacity,
+    pub dimensions: QrDimensions,
+}
+
+/// What to encode: a file on disk or bytes already in memory.
+#[derive(Debug, Clone)]
+pub enum EncodeInput {
+    File(PathBuf),
+    Bytes(Vec<u8>),
+}
+
+/// Encrypts the input into the payload text only, without rendering a QR
+/// code or checking that it fits into one. Files larger than `max_input_len`
+/// are refused.
+pub async fn encode_to_string(
+    input: EncodeInput,
+    protection: Protection,
+    options: SerializeOptions,
+    max_input_len: u64,
+) -> Result<String> {
+    let raw_data = match input {
+        EncodeInput::File(path) => read_input(&path, max_input_len).await?,
+        EncodeInput::Bytes(bytes) => bytes,
+    };
+
+    let qr_text = tokio::task::spawn_blocking(move || {
+        QrDataProcessor::serialize_protected(&raw_data, &protection, &options)
+    })
+    .await
+    .map_err(std::io::Error::other)??;
+
+    Ok(qr_text)
+}
+
+pub async fn generate_qr_async(
+    filename: String,
+    protection: Protection,
+    options: SerializeOptions,
+    render: RenderOptions,
+    max_input_len: u64,
+    progress: Progress,
+) -> Result<QrGenerationResult> {
+    progress.enter(Stage::Reading)?;
+    let raw_data = read_input(&filename, max_input_len).await?;
+
+    generate_qr_from_bytes(raw_data, protection, options, render, progress).await
+}
+
+pub async fn generate_qr_from_bytes(
+    raw_data: Vec<u8>,
+    protection: Protection,
+    options: SerializeOptions,
+    render: RenderOptions,
+    progress: Progress,
+) -> Result<QrGenerationResult> {
+    tokio::task::spawn_blocking(move || {
+        let qr_text = QrDataProcessor::serialize_protected_staged(
+            &raw_data,
+            &protection,
+            &options,
+            &mut |stage| progress.enter(stage),
+        )?;
+        progress.enter(Stage::Rendering)?;
+        render_qr_text(qr_text, &render)
+    })
+    .await
+    .map_err(std::io::Error::other)?
+}
+
+/// Encrypts `raw_data` and renders it as a QR code on the calling thread.
+///
+/// Key derivation and rendering take a noticeable amount of CPU time, so async
+/// callers should go through [`generate_qr_from_bytes`] instead.
+pub fn generate_qr(
+    raw_data: &[u8],
+    protection: &Protection,
+    options: &SerializeOptions,
+    render: &RenderOptions,
+) -> Result<QrGenerationResult> {
+    let qr_text = QrDataProcessor::serialize_protected(raw_data, protection, options)?;
+    render_qr_text(qr_text, render)
+}
+
+/// Renders payload text that was encrypted before, e.g. an entry of the
+/// history.
+pub async fn render_qr_text_async(
+    qr_text: String,
+    render: RenderOptions,
+) -> Result<QrGenerationResult> {
+    tokio::task::spawn_blocking(move || render_qr_text(qr_text, &render))
+        .await
+        .map_err(std::io::Error::other)?
+}
+
+/// Renders the text, or with [`RenderOptions::binary`] its packed bytes.
+/// `qr_text` stays the payload text either way, for copying and saving.
+fn render_qr_text(qr_text: String, render: &RenderOptions) -> Result<QrGenerationResult> {
+    let content: Cow<[u8]> = if render.binary {
+        Cow::Owned(QrDataProcessor::payload_to_bytes(&qr_text)?)
+    } else {
+        Cow::Borrowed(qr_text.as_bytes())
+    };
+    let ec_level = render.effective_ec_level();
+    let limit = if render.binary {
+        qr::service::max_qr_bytes(ec_level)
+    } else {
+        qr::service::max_payload_len(&qr_text, ec_level)
+    };
+    if content.len() >= limit {
+        return Err(Error::PayloadTooLarge {
+            size: content.len(),
+            limit,
+        });
+    }
+
+    let image = qr::service::generate_qr_image_bytes(&content, render)?;
+
+    Ok(QrGenerationResult {
+        qr_text,
+        qr_image: image.png,
+        capacity: image.capacity,
+        dimensions: image.dimensions,
+    })
+}
+
+/// Reads `png` back like a scanner would and checks that it yields
+/// `qr_text`, or its packed bytes for a binary code. `Ok(false)` means a code
+/// was found but held something else.
+pub async fn verify_qr_image(png: Vec<u8>, qr_text: String) -> Result<bool> {
+    let read = tokio::task::spawn_blocking(move || qr::service::read_qr_bytes_from_memory(&png))
+        .await
+        .map_err(std::io::Error::other)??;
+
+    match std::str::from_utf8(&read) {
+        Ok(text) => Ok(text == qr_text),
+        Err(_) => Ok(read == QrDataProcessor::payload_to_bytes(&qr_text)?),
+    }
+}
+
+/// A payload split over several QR codes that are shown one after another.
+#[derive(Debug, Clone)]
+pub struct QrAnimation {
+    pub qr_text: String,
+    pub chunks: Vec<String>, // text of each frame
+    pub frames: Vec<Vec<u8>>, // PNG bytes, one per chunk
+    pub gif: Vec<u8>,
+    pub fps: u8,
+}
+
+/// How a payload is spread over the frames of an animation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AnimationLayout {
+    /// Numbered chunks; every frame has to be scanned once.
+    #[default]
+    Sequence,
+    /// Fountain-coded frames; any slightly more than the number of blocks suffice.
+    Fountain,
+}
+
+/// Frames per second of generated animations; slow enough for phone scanners
+/// to lock onto every frame.
+pub const ANIMATION_FPS: u8 = 2;
+
+pub async fn generate_qr_animation_async(
+    filename: String,
+    protection: Protection,
+    options: SerializeOptions,
+    layout: AnimationLayout,
+    render: RenderOptions,
+    max_input_len: u64,
+    progress: Progress,
+) -> Result<QrAnimation> {
+    progress.enter(Stage::Reading)?;
+    let raw_data = read_input(&filename, max_input_len).await?;
+
+    generate_qr_animation_from_bytes(raw_data, protection, options, layout, render, progress).await
+}
+
+pub async fn generate_qr_animation_from_bytes(
+    raw_data: Vec<u8>,
+    protection: Protection,
+    options: SerializeOptions,
+    layout: AnimationLayout,
+    render: RenderOptions,
+    progress: Progress,
+) -> Result<QrAnimation> {
+    tokio::task::spawn_blocking(move || {
+        let qr_text = QrDataProcessor::serialize_protected_staged(
+            &raw_data,
+            &protection,
+            &options,
+            &mut |stage| progress.enter(stage),
+        )?;
+        progress.enter(Stage::Rendering)?;
+        let chunks = match layout {
+            AnimationLayout::Sequence => chunk::split(&qr_text, chunk::DEFAULT_CHUNK_LEN),
+            AnimationLayout::Fountain => {
+                let encoder = FountainEncoder::new(&qr_text, fountain::DEFAULT_BLOCK_SIZE);
+                encoder.frames(encoder.recommended_frame_count())
+            }
+        };
+
+        progress.check()?;
+        let images = qr::service::render_animation_frames(&chunks, &render)?;
+        let frames = images
+            .iter()
+            .map(|image| {
+                progress.check()?;
+                Ok(qr::service::encode_png(image)?)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        progress.check()?;
+        let gif = qr::service::encode_animation(&images, ANIMATION_FPS)?;
+
+        Ok(QrAnimation {
+            qr_text,
+            chunks,
+            frames,
+            gif,
+            fps: ANIMATION_FPS,
+        })
+    })
+    .await
+    .map_err(std::io::Error::other)?
+}
+
+/// Writes `data` to `path` through a temporary file in the same directory
+/// that replaces the target only once it is complete, so a failed write never
+/// leaves a truncated file behind. A replaced file keeps its permissions.
+/// Errors name the path.
+pub async fn save_bytes_atomic(path: impl AsRef<Path>, data: &[u8]) -> Result<()> {
+    let path = path.as_ref();
+    write_atomic(path, data, false).await.map_err(|e| Error::SaveFailed {
+        path: path.to_path_buf(),
+        source: Arc::new(e),
+    })
+}
+
+/// Like [`save_bytes_atomic`], for files only the user may read: on Unix the
+/// file is created with mode `0600`, and a replaced file gets that mode too.
+pub async fn save_private_bytes_atomic(path: impl AsRef<Path>, data: &[u8]) -> Result<()> {
+    let path = path.as_ref();
+    write_atomic(path, data, true).await.map_err(|e| Error::SaveFailed {
+        path: path.to_path_buf(),
+        source: Arc::new(e),
+    })
+}
+
+/// Where an automatically saved code goes: `<name>.qr.png` for a single
+/// image, `<name>.qr.<part>.png` with a 1-based part number for the frames
+/// of a split payload. `name` is the input's file name, extension included,
+/// so `a.txt` and `a.pdf` don't share images. Copies after the first get
+/// ` (<copy>)` after the name.
+pub fn auto_save_path(output_dir: &Path, name: &str, copy: usize, part: Option<usize>) -> PathBuf {
+    let name = match copy {
+        0 | 1 => name.to_string(),
+        copy => format!("{} ({})", name, copy),
+    };
+    match part {
+        Some(part) => output_dir.join(format!("{}.qr.{}.png", name, part)),
+        None => output_dir.join(format!("{}.qr.png", name)),
+    }
+}
+
+/// Saves the PNGs of one generated payload into `output_dir` under the first
+/// copy number none of whose paths are taken, so earlier output is never
+/// replaced. Returns the paths written. If a save fails, the images already
+/// saved stay and the paths reserved for the rest are freed again.
+pub async fn auto_save_pngs(
+    output_dir: PathBuf,
+    name: String,
+    pngs: Vec<Vec<u8>>,
+) -> Result<Vec<PathBuf>> {
+    let split = pngs.len() > 1;
+    let mut copy = 1;
+    let written = loop {
+        let paths: Vec<PathBuf> = (1..=pngs.len())
+            .map(|part| auto_save_path(&output_dir, &name, copy, split.then_some(part)))
+            .collect();
+        if reserve(&paths).await? {
+            break paths;
+        }
+        copy += 1;
+    };
+
+    for (saved, (path, png)) in written.iter().zip(&pngs).enumerate() {
+        if let Err(e) = save_bytes_atomic(path, png).await {
+            for reserved in &written[saved..] {
+                let _ = tokio::fs::remove_file(reserved).await;
+            }
+            return Err(e);
+        }
+    }
+    Ok(written)
+}
+
+/// Creates every one of `paths` as an empty file, so a concurrent save can't
+/// take them. Returns `false`, with nothing left behind, if one exists.
+async fn reserve(paths: &[PathBuf]) -> Result<bool> {
+    for (created, path) in paths.iter().enumerate() {
+        let result = tokio::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)
+            .await;
+        if let Err(e) = result {
+            for reserved in &paths[..created] {
+                let _ = tokio::fs::remove_file(reserved).await;
+            }
+            return match e.kind() {
+                std::io::ErrorKind::AlreadyExists => Ok(false),
+                _ => Err(Error::SaveFailed {
+                    path: path.clone(),
+                    source: Arc::new(e),
+                }),
+            };
+        }
+    }
+    Ok(true)
+}
+
+/// Hidden temporary file next to `path` that is renamed over it when done.
+/// The counter keeps concurrent writes to the same path apart.
+fn temp_path(path: &Path) -> std::io::Result<PathBuf> {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+
+    let name = path.file_name().ok_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, "not a file path")
+    })?;
+    Ok(path.with_file_name(format!(
+        ".{}.{}.{}.tmp",
+        name.to_string_lossy(),
+        std::process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    )))
+}
+
+async fn write_atomic(path: &Path, data: &[u8], private: bool) -> std::io::Result<()> {
+    let temp = temp_path(path)?;
+    let permissions = match tokio::fs::metadata(path).await {
+        Ok(_) if private => None,
+        Ok(metadata) => Some(metadata.permissions()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e),
+    };
+
+    let mut options = tokio::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    if private {
+        options.mode(0o600);
+    }
+
+    let result = async {
+        let mut file = options.open(&temp).await?;
+        // Before any data goes in, so a private file is never readable by
+        // others, not even as its temporary copy.
+        if let Some(permissions) = permissions {
+            file.set_permissions(permissions).await?;
+        }
+        file.write_all(data).await?;
+        file.sync_all().await?;
+        tokio::fs::rename(&temp, path).await
+    }
+    .await;
+
+    if result.is_err() {
+        let _ = tokio::fs::remove_file(&temp).await;
+    }
+    result
+}
+
+/// Encrypts the file at `input` into payload text at `output` without loading
+/// it into memory; see [`qr::stream`]. Returns the size of the input. Like
+/// [`save_bytes_atomic`], a failure leaves an existing `output` untouched.
+pub async fn encrypt_file_streaming(
+    input: PathBuf,
+    output: PathBuf,
+    password: Zeroizing<String>,
+    options: SerializeOptions,
+) -> Result<u64> {
+    tokio::task::spawn_blocking(move || {
+        let reader = BufReader::new(File::open(&input).map_err(|e| Error::read_failed(&input, e))?);
+        write_through_temp(&output, false, |writer| {
+            Ok(qr::stream::encrypt_stream(reader, writer, &password, &options)?)
+        })
+    })
+    .await
+    .map_err(std::io::Error::other)?
+}
+
+/// Decrypts payload text written by [`encrypt_file_streaming`] from `input`
+/// into `output`. The output is written to a temporary file only the user
+/// may read and only replaces `output` once every segment has been
+/// authenticated.
+pub async fn decrypt_file_streaming(
+    input: PathBuf,
+    output: PathBuf,
+    password: Zeroizing<String>,
+    options: DeserializeOptions,
+) -> Result<()> {
+    tokio::task::spawn_blocking(move || {
+        let reader = BufReader::new(File::open(&input).map_err(|e| Error::read_failed(&input, e))?);
+        write_through_temp(&output, true, |writer| {
+            Ok(qr::stream::decrypt_stream(reader, writer, &password, &options)?)
+        })
+    })
+    .await
+    .map_err(std::io::Error::other)?
+}
+
+/// Blocking counterpart of [`write_atomic`] for output produced as a stream:
+/// `write` fills a temporary file next to `path`, which replaces `path` only
+/// once `write` succeeded and the data is on disk. With `private`, the file
+/// is created with mode `0600` on Unix.
+fn write_through_temp<T>(
+    path: &Path,
+    private: bool,
+    write: impl FnOnce(&mut BufWriter<File>) -> Result<T>,
+) -> Result<T> {
+    let temp = temp_path(path)?;
+    let permissions = match std::fs::metadata(path) {
+        Ok(_) if private => None,
+        Ok(metadata) => Some(metadata.permissions()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    if private {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let file = options.open(&temp)?;
+
+    let result = (|| {
+        if let Some(permissions) = permissions {
+            file.set_permissions(permissions)?;
+        }
+        let mut writer = BufWriter::new(file);
+        let value = write(&mut writer)?;
+        writer.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        std::fs::rename(&temp, path)?;
+        Ok(value)
+    })();
+
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp);
+    }
+    result
+}
+
+/// Renders `pages` as a printable PDF and writes it to `path`.
+pub async fn export_qr_pdf(title: String, pages: Vec<PdfPage>, path: PathBuf) -> Result<()> {
+    let pdf = tokio::task::spawn_blocking(move || qr::pdf::generate_qr_pdf(&title, &pages))
+        .await
+        .map_err(std::io::Error::other)??;
+
+    tokio::fs::write(path, pdf).await?;
+    Ok(())
+}
+
+/// Expected payload length next to what fits into a single QR code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeEstimate {
+    pub payload_len: usize,
+    pub limit: usize,
+}
+
+impl SizeEstimate {
+    pub fn fits(&self) -> bool {
+        self.payload_len < self.limit
+    }
+}
+
+/// What decides the length of a payload and the room for it besides the
+/// data itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PayloadShape {
+    pub mode: PayloadMode,
+    /// Number of public keys in [`PayloadMode::Recipient`].
+    pub recipients: usize,
+    pub encoding: TextEncoding,
+    /// The size is that of the packed bytes rather than the text.
+    pub binary: bool,
+    /// The payload carries an expiry.
+    pub expires: bool,
+    /// The limit is that of a code at this level.
+    pub ec_level: EcLevel,
+}
+
+/// Estimates the payload size of a file without running the key derivation.
+pub async fn estimate_file_size(filename: String, shape: PayloadShape) -> Result<SizeEstimate> {
+    let read_failed = |e| Error::read_failed(&filename, e);
+    let file = tokio::fs::File::open(&filename).await.map_err(read_failed)?;
+    let total_len = file.metadata().await.map_err(read_failed)?.len();
+
+    let mut sample = Vec::new();
+    file.take(ESTIMATE_SAMPLE_LEN).read_to_end(&mut sample).await?;
+
+    tokio::task::spawn_blocking(move || estimate_size(&sample, total_len, shape))
+        .await
+        .map_err(std::io::Error::other)?
+}
+
+/// Estimates the payload size of `total_len` bytes of input starting with `sample`.
+pub fn estimate_size(sample: &[u8], total_len: u64, shape: PayloadShape) -> Result<SizeEstimate> {
+    let compressed_len = processor::estimate_compressed_len(sample, total_len)?;
+
+    Ok(SizeEstimate {
+        payload_len: single_code_len(compressed_len, &shape),
+        limit: single_code_limit(&shape),
+    })
+}
+
+/// What a single code holds for `compressed_len` bytes of compressed input:
+/// the payload text or, with `binary`, the packed bytes.
+fn single_code_len(compressed_len: usize, shape: &PayloadShape) -> usize {
+    if shape.binary {
+        processor::estimate_packed_len(compressed_len, shape.mode, shape.recipients, shape.expires)
+    } else {
+        text_payload_len(compressed_len, shape)
+    }
+}
+
+fn text_payload_len(compressed_len: usize, shape: &PayloadShape) -> usize {
+    processor::estimate_payload_len(
+        compressed_len,
+        shape.mode,
+        shape.recipients,
+        shape.encoding,
+        shape.expires,
+    )
+}
+
+/// Longest payload text, or packed payload, that fits into one code.
+fn single_code_limit(shape: &PayloadShape) -> usize {
+    match shape.encoding {
+        // Base45 payloads only use the QR alphanumeric set.
+        TextEncoding::Base45 if !shape.binary => {
+            qr::service::max_qr_alphanumeric_chars(shape.ec_level)
+        }
+        _ => qr::service::max_qr_bytes(shape.ec_level),
+    }
+}
+
+/// zstd levels compared by [`analyze_compression`].
+pub const ANALYZED_LEVELS: [i32; 3] = [1, 10, 19];
+
+/// How large the input gets at one compression level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LevelAnalysis {
+    pub level: i32,
+    pub compressed_len: usize,
+    pub payload_len: usize,
+    /// One if the payload fits into a single code, otherwise the number of
+    /// parts of an animated sequence.
+    pub codes: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompressionAnalysis {
+    pub input_len: usize,
+    pub levels: Vec<LevelAnalysis>,
+}
+
+/// Compresses the whole input at each of [`ANALYZED_LEVELS`] without
+/// encrypting it, so the user can see what a higher level buys before
+/// encoding. Incompressible input comes out about the same at every level.
+/// Split payloads are always text, so the number of parts is counted for
+/// the text even with `binary`.
+pub async fn analyze_compression(
+    input: EncodeInput,
+    shape: PayloadShape,
+    max_input_len: u64,
+) -> Result<CompressionAnalysis> {
+    let raw_data = match input {
+        EncodeInput::File(path) => read_input(&path, max_input_len).await?,
+        EncodeInput::Bytes(bytes) => bytes,
+    };
+
+    tokio::task::spawn_blocking(move || {
+        let limit = single_code_limit(&shape);
+        let levels = ANALYZED_LEVELS
+            .into_iter()
+            .map(|level| {
+                let compressed_len = processor::compressed_len(&raw_data, level)?;
+                let payload_len = single_code_len(compressed_len, &shape);
+                let codes = if payload_len < limit {
+                    1
+                } else {
+                    text_payload_len(compressed_len, &shape).div_ceil(chunk::DEFAULT_CHUNK_LEN)
+                };
+                Ok(LevelAnalysis {
+                    level,
+                    compressed_len,
+                    payload_len,
+                    codes,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(CompressionAnalysis {
+            input_len: raw_data.len(),
+            levels,
+        })
+    })
+    .await
+    .map_err(std::io::Error::other)?
+}
+
+/// Image types [`read_qr_from_image`] is offered in the file dialog and
+/// accepts by extension; PDFs go to [`read_qr_from_pdf`].
+pub const QR_IMAGE_EXTENSIONS: [&str; 6] = ["png", "jpg", "jpeg", "bmp", "webp", "gif"];
+
+/// Whether `path` is a PDF, judged by its extension.
+pub fn is_pdf(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("pdf"))
+}
+
+/// Whether `path` has an extension codes can be read from: one of
+/// [`QR_IMAGE_EXTENSIONS`] or a PDF.
+pub fn is_scannable(path: &Path) -> bool {
+    is_pdf(path)
+        || path.extension().is_some_and(|ext| {
+            QR_IMAGE_EXTENSIONS
+                .iter()
+                .any(|image| ext.eq_ignore_ascii_case(image))
+        })
+}
+
+/// Scans `filename` and returns the payload text; see
+/// [`QrDataProcessor::payload_from_bytes`] for codes that don't hold text.
+pub async fn read_qr_from_image(filename: String) -> Result<String> {
+    // The image decoder only reports IO errors as text.
+    tokio::fs::metadata(&filename)
+        .await
+        .map_err(|e| Error::read_failed(&filename, e))?;
+
+    // Decoding a large photo takes seconds; keep it off the UI runtime.
+    let content =
+        tokio::task::spawn_blocking(move || qr::service::read_qr_bytes_from_image(&filename))
+            .await
+            .map_err(std::io::Error::other)??;
+
+    Ok(QrDataProcessor::payload_from_bytes(&content)?)
+}
+
+/// Scans every page of the PDF `filename` and puts the payload back
+/// together from the codes found, see [`reassemble`].
+pub async fn read_qr_from_pdf(filename: String) -> Result<String> {
+    tokio::fs::metadata(&filename)
+        .await
+        .map_err(|e| Error::read_failed(&filename, e))?;
+
+    let codes = tokio::task::spawn_blocking(move || {
+        qr::pdf::read_qr_from_pdf(Path::new(&filename), qr::service::ReadOptions::default())
+    })
+    .await
+    .map_err(std::io::Error::other)??;
+
+    reassemble(&codes)
+}
+
+/// The payload from scanned codes: the parts of a split payload in any order,
+/// enough fountain frames, or a single code.
+pub fn reassemble<S: AsRef<str>>(codes: &[S]) -> Result<String> {
+    let is_part = |code: &S| {
+        let code = code.as_ref();
+        Chunk::parse(code).is_ok() || FountainDecoder::is_frame(code)
+    };
+
+    match codes {
+        [] => Err(ChunkError::Missing(1).into()),
+        [code] if !is_part(code) => Ok(code.as_ref().trim().to_string()),
+        _ if codes.iter().all(|code| FountainDecoder::is_frame(code.as_ref())) => {
+            let mut decoder = FountainDecoder::new();
+            for code in codes {
+                if let Some(payload) = decoder.add(code.as_ref())? {
+                    return Ok(payload);
+                }
+            }
+            let (recovered, total) = decoder.progress();
+            Err(Error::IncompleteFrames { recovered, total })
+        }
+        _ => Ok(chunk::join(codes)?),
+    }
+}
+
+pub async fn read_qr_from_clipboard() -> Result<String> {
+    let content = tokio::task::spawn_blocking(|| {
+        qr::service::read_qr_bytes_from_clipboard(qr::service::ReadOptions::default())
+    })
+    .await
+    .map_err(std::io::Error::other)??;
+
+    Ok(QrDataProcessor::payload_from_bytes(&content)?)
+}
+
+/// Scans the display `monitor`, or all of them; see
+/// [`qr::service::read_qr_bytes_from_screen`].
+#[cfg(feature = "screen")]
+pub async fn read_qr_from_screen(monitor: Option<u32>) -> Result<String> {
+    let content = tokio::task::spawn_blocking(move || {
+        qr::service::read_qr_bytes_from_screen(monitor, qr::service::ReadOptions::default())
+    })
+    .await
+    .map_err(std::io::Error::other)??;
+
+    Ok(QrDataProcessor::payload_from_bytes(&content)?)
+}
+
+#[cfg(feature = "screen")]
+pub async fn list_monitors() -> Result<Vec<qr::service::ScreenMonitor>> {
+    Ok(tokio::task::spawn_blocking(qr::service::list_monitors)
+        .await
+        .map_err(std::io::Error::other)??)
+}
+
+/// Decrypts `qr_text` with the password or, for payloads sealed to a public
+/// key, with the local key pair.
+pub async fn decrypt_qr_data(
+    qr_text: String,
+    password: Zeroizing<String>,
+    options: DeserializeOptions,
+    progress: Progress,
+) -> Result<(Zeroizing<Vec<u8>>, PayloadMetadata)> {
+    let keypair = match QrDataProcessor::payload_mode(&qr_text)? {
+        PayloadMode::Password => None,
+        PayloadMode::Recipient => Some(keys::load_keypair().await?),
+    };
+
+    // Key derivation takes seconds with the default limits.
+    let (data, metadata) = tokio::task::spawn_blocking(move || {
+        let on_stage = &mut |stage| progress.enter(stage);
+        match keypair {
+            None => QrDataProcessor::deserialize_with_metadata_staged(
+                &qr_text, &password, &options, on_stage,
+            ),
+            Some((public_key, secret_key)) => QrDataProcessor::deserialize_with_keypair_staged(
+                &qr_text,
+                &public_key,
+                &secret_key,
+                &options,
+                on_stage,
+            ),
+        }
+    })
+    .await
+    .map_err(std::io::Error::other)??;
+
+    Ok((Zeroizing::new(data), metadata))
+}
@@ -0,0 +1,125 @@
+// src/settings.rs
+use crate::i18n::Lang;
+use directories::ProjectDirs;
+use iced::Theme;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+const SETTINGS_FILE: &str = "settings.toml";
+
+/// Number of recently encoded files that are remembered.
+pub const MAX_RECENT_FILES: usize = 10;
+
+/// UI preferences kept across runs.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub language: Lang,
+    /// Name of one of iced's built-in themes.
+    pub theme: Option<String>,
+    /// Most recently chosen input files, newest first.
+    pub recent_files: Vec<PathBuf>,
+    /// Folder the input file dialog opened last.
+    pub input_dir: Option<PathBuf>,
+    /// Folder the QR image dialog opened last.
+    pub qr_image_dir: Option<PathBuf>,
+    /// Keep the payload history across runs; see [`crate::history`].
+    pub persist_history: bool,
+    /// Folder generated codes are saved to without asking.
+    pub output_dir: Option<PathBuf>,
+    /// Save the PNG of every generated code into [`Settings::output_dir`].
+    pub auto_save_png: bool,
+}
+
+impl Settings {
+    /// The stored theme, or iced's default one when none is set or the name is unknown.
+    pub fn theme(&self) -> Theme {
+        self.theme
+            .as_deref()
+            .and_then(|name| Theme::ALL.iter().find(|theme| theme.to_string() == name))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn set_theme(&mut self, theme: &Theme) {
+        self.theme = Some(theme.to_string());
+    }
+
+    /// Moves `path` to the front of the recent files.
+    pub fn remember_file(&mut self, path: &Path) {
+        self.recent_files.retain(|recent| recent != path);
+        self.recent_files.insert(0, path.to_path_buf());
+        self.recent_files.truncate(MAX_RECENT_FILES);
+    }
+}
+
+/// Directory of the settings and other state kept across runs.
+pub fn config_dir() -> Option<PathBuf> {
+    ProjectDirs::from("", "vaultecki", "qr-data-exchange")
+        .map(|dirs| dirs.config_dir().to_path_buf())
+}
+
+/// Directory of state that is only kept until a task is done.
+pub fn cache_dir() -> Option<PathBuf> {
+    ProjectDirs::from("", "vaultecki", "qr-data-exchange")
+        .map(|dirs| dirs.cache_dir().to_path_buf())
+}
+
+fn settings_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join(SETTINGS_FILE))
+}
+
+/// Loads the stored settings. A missing or unreadable file yields the
+/// defaults; recent files and folders that no longer exist are dropped.
+pub fn load() -> Settings {
+    let mut settings = load_file();
+    settings.recent_files.retain(|path| path.is_file());
+    settings.input_dir = settings.input_dir.filter(|dir| dir.is_dir());
+    settings.qr_image_dir = settings.qr_image_dir.filter(|dir| dir.is_dir());
+    settings.output_dir = settings.output_dir.filter(|dir| dir.is_dir());
+    settings
+}
+
+fn load_file() -> Settings {
+    let Some(path) = settings_path() else {
+        return Settings::default();
+    };
+
+    match std::fs::read_to_string(&path) {
+        Ok(contents) => toml::from_str(&contents).unwrap_or_else(|e| {
+            tracing::warn!("Ignoring invalid settings file {}: {}", path.display(), e);
+            Settings::default()
+        }),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Settings::default(),
+        Err(e) => {
+            tracing::warn!("Could not read settings file {}: {}", path.display(), e);
+            Settings::default()
+        }
+    }
+}
+
+/// Writes the settings. Failures are only logged; the app keeps working with
+/// the settings it has in memory.
+pub async fn save(settings: Settings) {
+    let Some(path) = settings_path() else {
+        return;
+    };
+
+    let contents = match toml::to_string(&settings) {
+        Ok(contents) => contents,
+        Err(e) => {
+            tracing::warn!("Could not serialize settings: {}", e);
+            return;
+        }
+    };
+
+    if let Some(parent) = path.parent() {
+        if let Err(e) = tokio::fs::create_dir_all(parent).await {
+            tracing::warn!("Could not create {}: {}", parent.display(), e);
+            return;
+        }
+    }
+    if let Err(e) = tokio::fs::write(&path, contents).await {
+        tracing::warn!("Could not write settings file {}: {}", path.display(), e);
+    }
+}
@@ -0,0 +1,82 @@
+// src/toast.rs
+//! Short-lived notifications shown in a corner of every window. Errors stay
+//! up longer than confirmations; a click dismisses either.
+use crate::i18n::{Lang, Notice, UiError};
+use std::time::{Duration, Instant};
+
+const INFO_DURATION: Duration = Duration::from_secs(4);
+const ERROR_DURATION: Duration = Duration::from_secs(10);
+/// Oldest toasts are dropped beyond this many.
+const MAX_TOASTS: usize = 5;
+
+#[derive(Debug, Clone)]
+pub enum ToastContent {
+    Info(Notice),
+    Error(UiError),
+}
+
+#[derive(Debug, Clone)]
+pub struct Toast {
+    pub id: u64,
+    pub content: ToastContent,
+    expires_at: Instant,
+}
+
+impl Toast {
+    pub fn is_error(&self) -> bool {
+        matches!(self.content, ToastContent::Error(_))
+    }
+
+    pub fn describe(&self, lang: Lang) -> String {
+        match self.content {
+            ToastContent::Info(notice) => notice.describe(lang).to_string(),
+            ToastContent::Error(ref error) => error.describe(lang),
+        }
+    }
+}
+
+/// Oldest first.
+#[derive(Debug, Default)]
+pub struct Toasts {
+    queue: Vec<Toast>,
+    next_id: u64,
+}
+
+impl Toasts {
+    pub fn info(&mut self, notice: Notice) {
+        self.push(ToastContent::Info(notice), INFO_DURATION);
+    }
+
+    pub fn error(&mut self, error: UiError) {
+        self.push(ToastContent::Error(error), ERROR_DURATION);
+    }
+
+    fn push(&mut self, content: ToastContent, duration: Duration) {
+        self.queue.push(Toast {
+            id: self.next_id,
+            content,
+            expires_at: Instant::now() + duration,
+        });
+        self.next_id += 1;
+
+        let excess = self.queue.len().saturating_sub(MAX_TOASTS);
+        self.queue.drain(..excess);
+    }
+
+    pub fn dismiss(&mut self, id: u64) {
+        self.queue.retain(|toast| toast.id != id);
+    }
+
+    /// Drops the toasts whose time is up at `now`.
+    pub fn expire(&mut self, now: Instant) {
+        self.queue.retain(|toast| toast.expires_at > now);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Toast> {
+        self.queue.iter()
+    }
+}
@@ -0,0 +1,61 @@
+// src/wasm.rs
+//! JavaScript bindings for the companion web decoder (`wasm-pack build --
+//! --no-default-features --features wasm`). Payloads are the same text the
+//! desktop app reads and writes.
+use crate::qr::processor::{DeserializeOptions, QrDataProcessor, SerializeOptions, TextEncoding};
+use wasm_bindgen::prelude::*;
+use zeroize::Zeroizing;
+
+/// Encrypts `data` with `password` (and `keyfile`, if given) into payload
+/// text. `encoding` is `"base64"`, `"base64url"` or `"base45"`, the more
+/// compact QR alphanumeric encoding.
+#[wasm_bindgen]
+pub fn encode(
+    data: &[u8],
+    password: &str,
+    keyfile: Option<Vec<u8>>,
+    encoding: &str,
+) -> Result<String, JsError> {
+    let encoding = match encoding {
+        "base64" => TextEncoding::Base64,
+        "base64url" => TextEncoding::Base64Url,
+        "base45" => TextEncoding::Base45,
+        other => return Err(JsError::new(&format!("Unknown text encoding {:?}", other))),
+    };
+    let options = SerializeOptions {
+        encoding,
+        keyfile: keyfile.map(Zeroizing::new),
+        ..SerializeOptions::default()
+    };
+
+    QrDataProcessor::serialize_with(data, password, &options).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Decrypts payload text in any of the encodings back into the original bytes.
+#[wasm_bindgen]
+pub fn decode(payload: &str, password: &str, keyfile: Option<Vec<u8>>) -> Result<Vec<u8>, JsError> {
+    let options = DeserializeOptions {
+        keyfile: keyfile.map(Zeroizing::new),
+        ..DeserializeOptions::default()
+    };
+
+    QrDataProcessor::deserialize_with(payload, password, &options).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// [`encode`] as the first web decoder called it; `base45` picks base45
+/// over base64.
+#[wasm_bindgen]
+pub fn serialize(
+    data: &[u8],
+    password: &str,
+    keyfile: Option<Vec<u8>>,
+    base45: bool,
+) -> Result<String, JsError> {
+    encode(data, password, keyfile, if base45 { "base45" } else { "base64" })
+}
+
+/// Same as [`decode`].
+#[wasm_bindgen]
+pub fn deserialize(payload: &str, password: &str, keyfile: Option<Vec<u8>>) -> Result<Vec<u8>, JsError> {
+    decode(payload, password, keyfile)
+}
@@ -0,0 +1,85 @@
+// tests/animation.rs
+//! Animation frames share one size and mark their position in a strip below
+//! the code, however many parts there are.
+use image::codecs::gif::GifDecoder;
+use image::{AnimationDecoder, GrayImage};
+use qr_data_exchange::qr::service::{self, RenderOptions};
+use std::io::Cursor;
+
+/// Height of the position strip below the code.
+const STRIP: u32 = 12;
+
+fn chunks(count: usize) -> Vec<String> {
+    (0..count).map(|i| format!("QRDX-part-{i:04}")).collect()
+}
+
+fn frames(count: usize) -> Vec<GrayImage> {
+    service::render_animation_frames(&chunks(count), &RenderOptions::default()).unwrap()
+}
+
+/// Columns of the strip's middle row that are black.
+fn marker(frame: &GrayImage) -> Vec<u32> {
+    let y = frame.height() - STRIP / 2;
+    (0..frame.width())
+        .filter(|&x| frame.get_pixel(x, y)[0] == 0)
+        .collect()
+}
+
+#[test]
+fn frames_share_one_size() {
+    let frames = frames(5);
+    assert_eq!(frames.len(), 5);
+    assert!(frames
+        .iter()
+        .all(|frame| frame.dimensions() == frames[0].dimensions()));
+}
+
+#[test]
+fn few_parts_get_a_segment_each() {
+    let frames = frames(4);
+    let width = frames[0].width();
+    for (index, frame) in frames.iter().enumerate() {
+        let marker = marker(frame);
+        assert!(marker.len() as u32 > width / 4 - 4, "part {index}");
+        let start = index as u32 * width / 4;
+        assert!(marker
+            .iter()
+            .all(|&x| (start..start + width / 4).contains(&x)));
+    }
+}
+
+#[test]
+fn many_parts_keep_a_visible_marker() {
+    let frames = frames(300);
+    let width = frames[0].width();
+    assert!(width / 300 < 3, "the test needs parts narrower than 3 px");
+
+    let mut previous = None;
+    for frame in [&frames[0], &frames[150], &frames[299]] {
+        let marker = marker(frame);
+        assert!(marker.len() >= 4, "marker is {} px wide", marker.len());
+        assert!(marker.iter().all(|&x| x < width));
+        assert!(previous < Some(marker[0]));
+        previous = Some(marker[0]);
+    }
+}
+
+#[test]
+fn the_gif_holds_the_rendered_frames() {
+    let frames = frames(3);
+    let gif = service::encode_animation(&frames, 2).unwrap();
+    assert_eq!(
+        gif,
+        service::generate_qr_animation(&chunks(3), 2, &RenderOptions::default()).unwrap()
+    );
+
+    let decoded = GifDecoder::new(Cursor::new(gif))
+        .unwrap()
+        .into_frames()
+        .collect_frames()
+        .unwrap();
+    assert_eq!(decoded.len(), frames.len());
+    for (decoded, frame) in decoded.iter().zip(&frames) {
+        assert_eq!(decoded.buffer().dimensions(), frame.dimensions());
+    }
+}
@@ -0,0 +1,169 @@
+// tests/archive.rs
+//! Several files survive the trip through one payload, and no entry name can
+//! make extraction write outside the chosen folder.
+use qr_data_exchange::qr::archive::{self, ArchiveEntry, ArchiveError};
+use qr_data_exchange::qr::processor::{DeserializeOptions, QrDataProcessor, SerializeOptions};
+use std::path::PathBuf;
+
+const PASSWORD: &str = "correct horse";
+
+fn entry(name: &str, data: &[u8]) -> ArchiveEntry {
+    ArchiveEntry {
+        name: name.to_string(),
+        data: data.to_vec(),
+    }
+}
+
+/// An empty folder of its own for each test.
+fn target_dir(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("qrdx-archive-{}-{}", test, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Encrypts packed data the way the app does and decrypts it again.
+fn round_trip(packed: &[u8]) -> Vec<ArchiveEntry> {
+    let options = SerializeOptions {
+        archive: true,
+        ..SerializeOptions::default()
+    };
+    let text = QrDataProcessor::serialize_with(packed, PASSWORD, &options).unwrap();
+    let (data, metadata) =
+        QrDataProcessor::deserialize_with_metadata(&text, PASSWORD, &DeserializeOptions::default())
+            .unwrap();
+    assert!(metadata.is_archive);
+    archive::unpack(&data).unwrap()
+}
+
+#[test]
+fn files_round_trip_and_extract() {
+    let entries = vec![
+        entry("notes.txt", b"first file"),
+        entry("keys/id.bin", &[0, 0xff, 0x80, 7]),
+        entry("empty", b""),
+    ];
+    let unpacked = round_trip(&archive::pack(&entries).unwrap());
+    assert_eq!(unpacked, entries);
+
+    let dir = target_dir("extract");
+    let written = archive::extract(&unpacked, &dir).unwrap();
+    assert_eq!(written.len(), 3);
+    assert_eq!(std::fs::read(dir.join("notes.txt")).unwrap(), b"first file");
+    assert_eq!(std::fs::read(dir.join("keys").join("id.bin")).unwrap(), [0, 0xff, 0x80, 7]);
+    assert!(std::fs::read(dir.join("empty")).unwrap().is_empty());
+
+    // Extracting again must not replace what is there.
+    assert!(matches!(
+        archive::extract(&unpacked, &dir),
+        Err(ArchiveError::Exists(_))
+    ));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn plain_payloads_are_not_archives() {
+    let text = QrDataProcessor::serialize_with(b"just data", PASSWORD, &SerializeOptions::default())
+        .unwrap();
+    let (_, metadata) =
+        QrDataProcessor::deserialize_with_metadata(&text, PASSWORD, &DeserializeOptions::default())
+            .unwrap();
+    assert!(!metadata.is_archive);
+}
+
+#[test]
+fn names_leaving_the_folder_are_refused() {
+    let dir = target_dir("traversal");
+    let evil = [
+        "../evil",
+        "a/../../evil",
+        "a/../evil",
+        "./evil",
+        "/etc/evil",
+        "..\\evil",
+        "C:evil",
+        "a//evil",
+        "evil/",
+        "",
+    ];
+
+    for name in evil {
+        assert!(
+            matches!(archive::pack(&[entry(name, b"x")]), Err(ArchiveError::UnsafeName(_))),
+            "{:?}",
+            name
+        );
+
+        // Another tool might not check names when packing, so build the
+        // archive by hand; unpacking and extracting refuse it all the same.
+        let packed = rmp_serde::to_vec(&[entry("fine.txt", b"ok"), entry(name, b"x")]).unwrap();
+        let text = QrDataProcessor::serialize_with(
+            &packed,
+            PASSWORD,
+            &SerializeOptions {
+                archive: true,
+                ..SerializeOptions::default()
+            },
+        )
+        .unwrap();
+        let (data, _) =
+            QrDataProcessor::deserialize_with_metadata(&text, PASSWORD, &DeserializeOptions::default())
+                .unwrap();
+        assert!(
+            matches!(archive::unpack(&data), Err(ArchiveError::UnsafeName(_))),
+            "{:?}",
+            name
+        );
+        assert!(
+            matches!(
+                archive::extract(&[entry("fine.txt", b"ok"), entry(name, b"x")], &dir),
+                Err(ArchiveError::UnsafeName(_))
+            ),
+            "{:?}",
+            name
+        );
+    }
+
+    // Names are checked before anything is written.
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+    assert!(!dir.parent().unwrap().join("evil").exists());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn duplicate_names_are_refused() {
+    let entries = [entry("a.txt", b"1"), entry("a.txt", b"2")];
+    assert!(matches!(
+        archive::pack(&entries),
+        Err(ArchiveError::DuplicateName(_))
+    ));
+}
+
+#[cfg(unix)]
+#[test]
+fn symlinked_folders_are_not_followed_out() {
+    let dir = target_dir("symlink");
+    let outside = target_dir("symlink-outside");
+    std::os::unix::fs::symlink(&outside, dir.join("link")).unwrap();
+
+    assert!(matches!(
+        archive::extract(&[entry("link/evil", b"x")], &dir),
+        Err(ArchiveError::UnsafeName(_))
+    ));
+    assert!(!outside.join("evil").exists());
+
+    // Deeper names are refused before any folder is made, even through the
+    // link, and so is everything else in the same archive.
+    let entries = [entry("fine/file", b"x"), entry("link/sub/deeper/evil", b"x")];
+    assert!(matches!(archive::extract(&entries, &dir), Err(ArchiveError::UnsafeName(_))));
+    assert!(!outside.join("sub").exists());
+    assert!(!dir.join("fine").exists());
+
+    // A link that stays inside the folder is followed.
+    std::fs::create_dir(dir.join("real")).unwrap();
+    std::os::unix::fs::symlink(dir.join("real"), dir.join("inside")).unwrap();
+    archive::extract(&[entry("inside/sub/file", b"x")], &dir).unwrap();
+    assert!(dir.join("real/sub/file").exists());
+    std::fs::remove_dir_all(&dir).unwrap();
+    std::fs::remove_dir_all(&outside).unwrap();
+}
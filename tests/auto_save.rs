@@ -0,0 +1,112 @@
+// tests/auto_save.rs
+//! Automatically saved codes are named after the whole input file name and
+//! never replace images already in the output folder.
+use qr_data_exchange::services;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap()
+        .block_on(future)
+}
+
+/// An empty directory of its own for each test.
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("qrdx-auto-save-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn save(dir: &Path, name: &str, pngs: &[&[u8]]) -> Vec<PathBuf> {
+    let pngs = pngs.iter().map(|png| png.to_vec()).collect();
+    block_on(services::auto_save_pngs(
+        dir.to_path_buf(),
+        name.to_string(),
+        pngs,
+    ))
+    .unwrap()
+}
+
+fn file_names(paths: &[PathBuf]) -> Vec<String> {
+    paths
+        .iter()
+        .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+        .collect()
+}
+
+#[test]
+fn inputs_differing_in_extension_get_their_own_images() {
+    let dir = scratch_dir("extension");
+    let txt = save(&dir, "a.txt", &[b"txt"]);
+    let pdf = save(&dir, "a.pdf", &[b"pdf"]);
+
+    assert_eq!(file_names(&txt), ["a.txt.qr.png"]);
+    assert_eq!(file_names(&pdf), ["a.pdf.qr.png"]);
+    assert_eq!(std::fs::read(&txt[0]).unwrap(), b"txt");
+    assert_eq!(std::fs::read(&pdf[0]).unwrap(), b"pdf");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn frames_are_numbered() {
+    let dir = scratch_dir("frames");
+    let paths = save(&dir, "big.bin", &[b"1", b"2", b"3"]);
+    assert_eq!(
+        file_names(&paths),
+        ["big.bin.qr.1.png", "big.bin.qr.2.png", "big.bin.qr.3.png"]
+    );
+    assert_eq!(std::fs::read(&paths[2]).unwrap(), b"3");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn taken_names_get_a_copy_number() {
+    let dir = scratch_dir("copies");
+    let first = save(&dir, "note", &[b"first"]);
+    let second = save(&dir, "note", &[b"second"]);
+    let third = save(&dir, "note", &[b"third"]);
+
+    assert_eq!(file_names(&second), ["note (2).qr.png"]);
+    assert_eq!(file_names(&third), ["note (3).qr.png"]);
+    assert_eq!(std::fs::read(&first[0]).unwrap(), b"first");
+    assert_eq!(std::fs::read(&second[0]).unwrap(), b"second");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn one_taken_frame_moves_the_whole_set() {
+    let dir = scratch_dir("partial");
+    std::fs::write(dir.join("x.txt.qr.2.png"), b"earlier").unwrap();
+    let paths = save(&dir, "x.txt", &[b"1", b"2"]);
+
+    assert_eq!(
+        file_names(&paths),
+        ["x.txt (2).qr.1.png", "x.txt (2).qr.2.png"]
+    );
+    assert!(!dir.join("x.txt.qr.1.png").exists());
+    assert_eq!(
+        std::fs::read(dir.join("x.txt.qr.2.png")).unwrap(),
+        b"earlier"
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn a_failed_save_frees_the_reserved_names() {
+    let dir = scratch_dir("failed");
+    // The image names fit, but the temporary files next to them are too
+    // long for the file system.
+    let name = "x".repeat(240);
+    let result = block_on(services::auto_save_pngs(
+        dir.clone(),
+        name,
+        vec![b"1".to_vec(), b"2".to_vec(), b"3".to_vec()],
+    ));
+
+    assert!(result.is_err());
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,90 @@
+// tests/cancel.rs
+//! A cancelled job stops at its next step, also inside blocking work.
+use qr_data_exchange::crypto::crypto_utils::KdfLimits;
+use qr_data_exchange::error::Error;
+use qr_data_exchange::qr::processor::{Protection, QrProcessorError, SerializeOptions, Stage};
+use qr_data_exchange::qr::service::RenderOptions;
+use qr_data_exchange::services::{self, AnimationLayout, Progress};
+use std::future::Future;
+use tokio_util::sync::CancellationToken;
+use zeroize::Zeroizing;
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap()
+        .block_on(future)
+}
+
+fn options() -> SerializeOptions {
+    SerializeOptions {
+        // Cheapest limits Argon2 accepts, to keep the test fast.
+        kdf: KdfLimits {
+            ops_limit: 3,
+            mem_limit_kib: 8,
+        },
+        ..SerializeOptions::default()
+    }
+}
+
+fn password() -> Protection {
+    Protection::Password(Zeroizing::new("correct horse".to_string()))
+}
+
+#[test]
+fn check_fails_once_cancelled() {
+    assert!(Progress::default().check().is_ok());
+
+    let cancel = CancellationToken::new();
+    let (progress, _stages) = Progress::channel(cancel.clone());
+    assert!(progress.check().is_ok());
+    cancel.cancel();
+    assert!(matches!(progress.check(), Err(QrProcessorError::Cancelled)));
+    assert!(matches!(
+        progress.enter(Stage::Rendering),
+        Err(QrProcessorError::Cancelled)
+    ));
+}
+
+#[test]
+fn cancelled_animations_stop_before_the_next_step() {
+    let cancel = CancellationToken::new();
+    let (progress, mut stages) = Progress::channel(cancel.clone());
+    cancel.cancel();
+
+    let result = block_on(services::generate_qr_animation_from_bytes(
+        vec![7; 4000],
+        password(),
+        options(),
+        AnimationLayout::Sequence,
+        RenderOptions::default(),
+        progress,
+    ));
+    assert!(matches!(
+        result,
+        Err(Error::Processor(QrProcessorError::Cancelled))
+    ));
+    assert!(stages.try_recv().is_err());
+}
+
+#[test]
+fn uncancelled_animations_report_every_stage() {
+    let (progress, mut stages) = Progress::channel(CancellationToken::new());
+    let animation = block_on(services::generate_qr_animation_from_bytes(
+        vec![7; 4000],
+        password(),
+        options(),
+        AnimationLayout::Sequence,
+        RenderOptions::default(),
+        progress,
+    ))
+    .unwrap();
+    assert_eq!(animation.frames.len(), animation.chunks.len());
+
+    let mut reported = Vec::new();
+    while let Ok(stage) = stages.try_recv() {
+        reported.push(stage);
+    }
+    // Reading belongs to the callers that load the input.
+    assert_eq!(reported, Stage::ENCODE[1..]);
+}
@@ -0,0 +1,78 @@
+// tests/capacity.rs
+//! What fits into a single code depends on the error correction level.
+use qr_data_exchange::error::Error;
+use qr_data_exchange::qr::processor::{PayloadMode, Protection, SerializeOptions, TextEncoding};
+use qr_data_exchange::qr::service::{self, EcLevel, RenderOptions};
+use qr_data_exchange::services::{self, PayloadShape};
+use zeroize::Zeroizing;
+
+/// Bytes zstd can't shrink, so the payload grows with them.
+fn noise(len: usize) -> Vec<u8> {
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        })
+        .collect()
+}
+
+fn render(ec_level: EcLevel) -> RenderOptions {
+    RenderOptions {
+        ec_level,
+        ..RenderOptions::default()
+    }
+}
+
+#[test]
+fn limits_shrink_with_the_error_correction_level() {
+    let levels = [EcLevel::L, EcLevel::M, EcLevel::Q, EcLevel::H];
+    for pair in levels.windows(2) {
+        assert!(service::max_qr_bytes(pair[0]) > service::max_qr_bytes(pair[1]));
+        assert!(
+            service::max_qr_alphanumeric_chars(pair[0])
+                > service::max_qr_alphanumeric_chars(pair[1])
+        );
+    }
+}
+
+#[test]
+fn estimate_uses_the_limit_of_the_level() {
+    let data = noise(1200);
+    let estimate = |ec_level| {
+        services::estimate_size(
+            &data,
+            data.len() as u64,
+            PayloadShape {
+                mode: PayloadMode::Password,
+                recipients: 0,
+                encoding: TextEncoding::Base64,
+                binary: false,
+                expires: false,
+                ec_level,
+            },
+        )
+        .unwrap()
+    };
+    assert!(estimate(EcLevel::L).fits());
+    assert!(!estimate(EcLevel::H).fits());
+    assert_eq!(
+        estimate(EcLevel::H).limit,
+        service::max_qr_bytes(EcLevel::H)
+    );
+}
+
+#[test]
+fn payload_too_large_for_level_h_is_refused() {
+    let data = noise(1200);
+    let protection = Protection::Password(Zeroizing::new("correct horse".to_string()));
+    let options = SerializeOptions::default();
+
+    assert!(services::generate_qr(&data, &protection, &options, &render(EcLevel::L)).is_ok());
+    assert!(matches!(
+        services::generate_qr(&data, &protection, &options, &render(EcLevel::H)),
+        Err(Error::PayloadTooLarge { limit, .. }) if limit == service::max_qr_bytes(EcLevel::H)
+    ));
+}
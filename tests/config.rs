@@ -0,0 +1,66 @@
+// tests/config.rs
+//! Each key of the config file is validated on its own, and the Argon2
+//! limits can only be raised.
+use qr_data_exchange::config::{self, AppConfig};
+use qr_data_exchange::crypto::crypto_utils::KdfLimits;
+use qr_data_exchange::qr::service::EcLevel;
+
+#[test]
+fn empty_file_gives_the_defaults() {
+    assert_eq!(config::parse("").unwrap(), AppConfig::default());
+}
+
+#[test]
+fn invalid_values_only_cost_their_key() {
+    let config = config::parse(
+        r#"
+        ec_level = "q"
+        compression_level = 40
+        image_size = "large"
+        min_password_length = 16
+        "#,
+    )
+    .unwrap();
+    let defaults = AppConfig::default();
+    assert_eq!(config.ec_level, EcLevel::Q);
+    assert_eq!(config.compression_level, defaults.compression_level);
+    assert_eq!(config.image_size, defaults.image_size);
+    assert_eq!(config.password_policy.min_length, 16);
+}
+
+#[test]
+fn unknown_keys_are_ignored() {
+    let config = config::parse("colour = \"blue\"\nimage_size = 1024").unwrap();
+    assert_eq!(config.image_size, 1024);
+}
+
+#[test]
+fn text_that_is_not_toml_is_refused() {
+    assert!(config::parse("ec_level = ").is_err());
+}
+
+#[test]
+fn argon2_limits_can_only_be_raised() {
+    let lowered = config::parse("kdf_ops_limit = 3\nkdf_mem_limit_kib = 8").unwrap();
+    assert_eq!(lowered.kdf, KdfLimits::MODERATE);
+
+    let raised = config::parse("kdf_ops_limit = 8\nkdf_mem_limit_kib = 262144").unwrap();
+    assert_eq!(
+        raised.kdf,
+        KdfLimits {
+            ops_limit: 8,
+            mem_limit_kib: 262144,
+        }
+    );
+
+    let beyond =
+        config::parse(&format!("kdf_ops_limit = {}", KdfLimits::MAX_OPS_LIMIT + 1)).unwrap();
+    assert_eq!(beyond.kdf, KdfLimits::MODERATE);
+}
+
+#[test]
+fn missing_file_gives_the_defaults() {
+    let path = std::env::temp_dir().join("qrdx-config-test-missing.toml");
+    assert_eq!(config::load(&path, false), AppConfig::default());
+    assert_eq!(config::load(&path, true), AppConfig::default());
+}
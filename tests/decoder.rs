@@ -0,0 +1,99 @@
+// tests/decoder.rs
+//! Decoding reports which decoder read a code. rqrr goes first; with the
+//! `rxing` feature, rxing reads what rqrr gives up on.
+use image::{GrayImage, Luma};
+use qr_data_exchange::qr::service::{self, Decoder, ReadOptions, RenderOptions};
+
+/// Printable text filling most of a version 40 code at level L.
+fn dense_text() -> String {
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    (0..2800)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            char::from(b'!' + (state % 94) as u8)
+        })
+        .collect()
+}
+
+fn render(text: &str, module_size: u32) -> GrayImage {
+    let options = RenderOptions {
+        size: 0,
+        min_module_size: module_size,
+        ..RenderOptions::default()
+    };
+    let png = service::generate_qr_image(text, &options).unwrap().png;
+    image::load_from_memory(&png).unwrap().to_luma8()
+}
+
+/// `img` turned by `degrees` around its center with bilinear sampling, on a
+/// white background, the way a slightly crooked scan comes out.
+fn rotate(img: &GrayImage, degrees: f32) -> GrayImage {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let (width, height) = img.dimensions();
+    let (cx, cy) = (width as f32 / 2.0, height as f32 / 2.0);
+    let sample = |x: f32, y: f32| -> f32 {
+        if x < 0.0 || y < 0.0 || x >= (width - 1) as f32 || y >= (height - 1) as f32 {
+            return 255.0;
+        }
+        let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+        let (fx, fy) = (x - x0 as f32, y - y0 as f32);
+        let at = |x, y| f32::from(img.get_pixel(x, y).0[0]);
+        let top = at(x0, y0) * (1.0 - fx) + at(x0 + 1, y0) * fx;
+        let bottom = at(x0, y0 + 1) * (1.0 - fx) + at(x0 + 1, y0 + 1) * fx;
+        top * (1.0 - fy) + bottom * fy
+    };
+    GrayImage::from_fn(width, height, |x, y| {
+        let (dx, dy) = (x as f32 - cx, y as f32 - cy);
+        let value = sample(cx + dx * cos + dy * sin, cy - dx * sin + dy * cos);
+        Luma([value.round() as u8])
+    })
+}
+
+/// A dense version 40 code at three pixels per module, turned a few degrees:
+/// the kind of scan rqrr's detector gives up on while zxing-style detectors
+/// still sample the grid.
+fn skewed_dense_code(text: &str) -> GrayImage {
+    rotate(&render(text, 3), 4.0)
+}
+
+#[test]
+fn plain_codes_are_read_by_rqrr() {
+    let decoded =
+        service::decode_luma_detailed(render("QRDX7:plain", 4), ReadOptions::default()).unwrap();
+    assert_eq!(decoded.content, b"QRDX7:plain");
+    assert_eq!(decoded.decoder, Decoder::Rqrr);
+}
+
+#[test]
+fn undecodable_images_report_the_attempts() {
+    let blank = GrayImage::from_pixel(64, 64, Luma([255]));
+    let err = service::decode_luma_detailed(blank, ReadOptions::default()).unwrap_err();
+    assert!(matches!(
+        err,
+        service::QrServiceError::QrCodeNotFound { attempts } if attempts > 1
+    ));
+}
+
+#[cfg(feature = "rxing")]
+#[test]
+fn rxing_reads_what_rqrr_gives_up_on() {
+    let text = dense_text();
+    let decoded =
+        service::decode_luma_detailed(skewed_dense_code(&text), ReadOptions::default()).unwrap();
+    assert_eq!(decoded.decoder, Decoder::Rxing);
+    assert_eq!(decoded.content, text.as_bytes());
+}
+
+#[cfg(not(feature = "rxing"))]
+#[test]
+fn rqrr_alone_gives_up_on_the_fallback_fixture() {
+    let err =
+        service::decode_luma_detailed(skewed_dense_code(&dense_text()), ReadOptions::default())
+            .unwrap_err();
+    assert!(matches!(
+        err,
+        service::QrServiceError::QrCodeNotFound { .. }
+    ));
+}
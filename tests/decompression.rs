@@ -0,0 +1,68 @@
+// tests/decompression.rs
+//! A payload may compress far better than any real data, so decoding stops
+//! with an error once the output grows beyond `max_decompressed_len`.
+use qr_data_exchange::crypto::crypto_utils::{self, KdfLimits};
+use qr_data_exchange::qr::processor::{
+    DeserializeOptions, QrDataProcessor, QrProcessorError, SerializeOptions,
+    DEFAULT_MAX_DECOMPRESSED_LEN,
+};
+
+const PASSWORD: &str = "correct horse";
+/// Cheapest limits Argon2 accepts, to keep the tests fast.
+const FAST: KdfLimits = KdfLimits {
+    ops_limit: 3,
+    mem_limit_kib: 8,
+};
+
+fn options() -> SerializeOptions {
+    SerializeOptions {
+        kdf: FAST,
+        ..SerializeOptions::default()
+    }
+}
+
+fn limited(max_decompressed_len: usize) -> DeserializeOptions {
+    DeserializeOptions {
+        max_decompressed_len,
+        ..DeserializeOptions::default()
+    }
+}
+
+#[test]
+fn a_bomb_beyond_the_default_limit_is_refused() {
+    let bomb = vec![0u8; DEFAULT_MAX_DECOMPRESSED_LEN + 1];
+    let payload = QrDataProcessor::serialize_with(&bomb, PASSWORD, &options()).unwrap();
+    assert!(payload.len() < 10_000, "payload is {} bytes", payload.len());
+
+    assert!(matches!(
+        QrDataProcessor::deserialize(&payload, PASSWORD),
+        Err(QrProcessorError::DecompressedTooLarge { limit }) if limit == DEFAULT_MAX_DECOMPRESSED_LEN
+    ));
+}
+
+#[test]
+fn data_up_to_the_limit_decodes() {
+    let data = vec![7u8; 64 * 1024];
+    let payload = QrDataProcessor::serialize_with(&data, PASSWORD, &options()).unwrap();
+
+    let decoded =
+        QrDataProcessor::deserialize_with(&payload, PASSWORD, &limited(data.len())).unwrap();
+    assert_eq!(decoded, data);
+    assert!(matches!(
+        QrDataProcessor::deserialize_with(&payload, PASSWORD, &limited(data.len() - 1)),
+        Err(QrProcessorError::DecompressedTooLarge { limit }) if limit == data.len() - 1
+    ));
+}
+
+#[test]
+fn recipient_payloads_are_limited_as_well() {
+    let (public, secret) = crypto_utils::generate_keypair();
+    let data = vec![0u8; 1024 * 1024];
+    let payload =
+        QrDataProcessor::serialize_for_recipient_with(&data, &public, &options()).unwrap();
+
+    assert!(matches!(
+        QrDataProcessor::deserialize_with_keypair(&payload, &public, &secret, &limited(4096)),
+        Err(QrProcessorError::DecompressedTooLarge { limit: 4096 })
+    ));
+}
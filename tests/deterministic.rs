@@ -0,0 +1,81 @@
+// tests/deterministic.rs
+//! With a fixed salt and nonce the same input always gives the same payload,
+//! so generated codes can be pinned as snapshots.
+use qr_data_exchange::crypto::crypto_utils::{Cipher, KdfLimits, NONCE_LEN, SALT_LEN};
+use qr_data_exchange::qr::processor::{QrDataProcessor, SerializeOptions};
+
+const PASSWORD: &str = "correct horse";
+const PLAINTEXT: &[u8] = b"pinned by a snapshot";
+const SALT: [u8; SALT_LEN] = [0x5a; SALT_LEN];
+
+/// Counts up from 1, so the leading bytes differ from the trailing ones.
+fn nonce() -> [u8; NONCE_LEN] {
+    std::array::from_fn(|i| i as u8 + 1)
+}
+
+fn options(cipher: Cipher) -> SerializeOptions {
+    SerializeOptions {
+        cipher,
+        // Cheapest limits Argon2 accepts, to keep the tests fast.
+        kdf: KdfLimits {
+            ops_limit: 3,
+            mem_limit_kib: 8,
+        },
+        ..SerializeOptions::default()
+    }
+}
+
+fn serialize(cipher: Cipher, nonce: [u8; NONCE_LEN]) -> String {
+    QrDataProcessor::serialize_deterministic(PLAINTEXT, PASSWORD, &options(cipher), SALT, nonce)
+        .unwrap()
+}
+
+/// `serialize(Cipher::XSalsa20Poly1305, nonce())`
+const XSALSA_SNAPSHOT: &str = "QRDX7:mdwAEFpaWlpaWlpaWlpaWlpaWlrcAGkBAgMEBQYHCAkKCwwNDg8QERITFBUWFxhTYMzVZAJUzLzMs8y9zPVOzJjM6EdFWyZWesyUzM7M+00AzNpuzPMxKgrMqEAVfAAYe1vMg8zPzJV4OwhfUBw8TXXM7FtPFcz9LMyvZMy2RszAeMzJzKAgLMzUzNQTzIzMz3zMoszASAxszMQcVFcIAACYQszWNUbMixMHzNWQAwg=";
+/// `serialize(Cipher::ChaCha20Poly1305, nonce())`
+const CHACHA_SNAPSHOT: &str = "QRDX7:mdwAEFpaWlpaWlpaWlpaWlpaWlrcAF0BAgMEBQYHCAkKCwzMu8zNag00UszPzNpZfMzqXsynzK3Mhm0VTAnMrAjM014YzPBKzNJIzKXMjsyBLEAqzLPMtMyYCgdBzIheWmY1zKVWzOXMwwLMkcyLzK9zDXw9GQjMr8zoHixnTcyEzPrMuszXzJnMpSXMhsznzPfM+cyXzIHMhsz0zOMIAAGYQszWNUbMixMHzNWQAwg=";
+
+#[test]
+fn same_salt_and_nonce_give_the_same_payload() {
+    for (cipher, snapshot) in [
+        (Cipher::XSalsa20Poly1305, XSALSA_SNAPSHOT),
+        (Cipher::ChaCha20Poly1305, CHACHA_SNAPSHOT),
+    ] {
+        let payload = serialize(cipher, nonce());
+        assert_eq!(payload, serialize(cipher, nonce()), "{cipher:?}");
+        assert_eq!(payload, snapshot, "{cipher:?}");
+    }
+}
+
+#[test]
+fn deterministic_payloads_decode() {
+    for cipher in Cipher::ALL {
+        assert_eq!(
+            QrDataProcessor::deserialize(&serialize(cipher, nonce()), PASSWORD).unwrap(),
+            PLAINTEXT,
+            "{cipher:?}"
+        );
+    }
+}
+
+#[test]
+fn chacha_uses_the_leading_nonce_bytes() {
+    let mut other_tail = nonce();
+    other_tail[12..].fill(0xff);
+    assert_eq!(
+        serialize(Cipher::ChaCha20Poly1305, other_tail),
+        serialize(Cipher::ChaCha20Poly1305, nonce())
+    );
+
+    let mut other_head = nonce();
+    other_head[0] ^= 1;
+    assert_ne!(
+        serialize(Cipher::ChaCha20Poly1305, other_head),
+        serialize(Cipher::ChaCha20Poly1305, nonce())
+    );
+    // XSalsa20 takes the whole nonce.
+    assert_ne!(
+        serialize(Cipher::XSalsa20Poly1305, other_tail),
+        serialize(Cipher::XSalsa20Poly1305, nonce())
+    );
+}
@@ -0,0 +1,167 @@
+// tests/estimate.rs
+//! The size estimate shown before generating stays close to the length of
+//! the payload that is actually produced.
+use qr_data_exchange::crypto::crypto_utils::{self, KdfLimits};
+use qr_data_exchange::qr::processor::{
+    self, PayloadMode, Protection, QrDataProcessor, SerializeOptions, TextEncoding,
+};
+use qr_data_exchange::qr::service::EcLevel;
+use qr_data_exchange::services::{self, PayloadShape};
+use zeroize::Zeroizing;
+
+/// Cheapest limits Argon2 accepts, to keep the tests fast.
+const FAST: KdfLimits = KdfLimits {
+    ops_limit: 3,
+    mem_limit_kib: 8,
+};
+
+/// Bytes zstd can't shrink, so the payload grows with them.
+fn noise(len: usize) -> Vec<u8> {
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        })
+        .collect()
+}
+
+/// Text that compresses to a small fraction of its length.
+fn prose(len: usize) -> Vec<u8> {
+    let words = [
+        "payload ", "salt ", "nonce ", "code ", "scanner ", "paper ", "key ",
+    ];
+    let mut text = Vec::with_capacity(len);
+    let mut state = 7usize;
+    while text.len() < len {
+        state = (state * 31 + 11) % 97;
+        text.extend_from_slice(words[state % words.len()].as_bytes());
+    }
+    text.truncate(len);
+    text
+}
+
+/// Estimated and actual payload length of `data` for `protection`.
+fn lengths(
+    data: &[u8],
+    protection: &Protection,
+    encoding: TextEncoding,
+    expires_at: Option<i64>,
+) -> (usize, usize) {
+    let (mode, recipients) = match protection {
+        Protection::Password(_) => (PayloadMode::Password, 0),
+        Protection::Recipients(keys) => (PayloadMode::Recipient, keys.len()),
+    };
+    let shape = PayloadShape {
+        mode,
+        recipients,
+        encoding,
+        binary: false,
+        expires: expires_at.is_some(),
+        ec_level: EcLevel::L,
+    };
+    let estimate = services::estimate_size(data, data.len() as u64, shape).unwrap();
+
+    let options = SerializeOptions {
+        encoding,
+        kdf: FAST,
+        expires_at,
+        ..SerializeOptions::default()
+    };
+    let payload = QrDataProcessor::serialize_protected(data, protection, &options).unwrap();
+    (estimate.payload_len, payload.len())
+}
+
+/// Salt, nonce and ciphertext are random, and MessagePack writes byte
+/// values above 127 in two bytes, so the real length scatters around the
+/// estimate by a few percent.
+const SCATTER_PERCENT: usize = 3;
+
+/// The estimate may exceed the real length by `over_percent` and fall short
+/// of it by [`SCATTER_PERCENT`], give or take a few bytes.
+fn assert_close(data: &[u8], protection: &Protection, encoding: TextEncoding, over_percent: usize) {
+    assert_close_expiring(data, protection, encoding, over_percent, None);
+}
+
+fn assert_close_expiring(
+    data: &[u8],
+    protection: &Protection,
+    encoding: TextEncoding,
+    over_percent: usize,
+    expires_at: Option<i64>,
+) {
+    let (estimate, actual) = lengths(data, protection, encoding, expires_at);
+    let margin = |percent: usize| actual * percent / 100 + 16;
+    assert!(
+        estimate + margin(SCATTER_PERCENT) >= actual && estimate <= actual + margin(over_percent),
+        "{} input bytes, {encoding:?}: estimated {estimate}, actual {actual}",
+        data.len()
+    );
+}
+
+fn password() -> Protection {
+    Protection::Password(Zeroizing::new("correct horse".to_string()))
+}
+
+#[test]
+fn incompressible_input_is_estimated_closely() {
+    // zstd stores the data as is at any level, so only the layout counts.
+    for len in [16, 300, 1500] {
+        for encoding in [TextEncoding::Base64, TextEncoding::Base45] {
+            assert_close(&noise(len), &password(), encoding, SCATTER_PERCENT);
+        }
+    }
+}
+
+#[test]
+fn compressible_input_is_estimated_closely() {
+    // The estimate compresses at a faster level than the payload, so it errs
+    // on the large side.
+    for len in [200, 5_000, 60_000] {
+        for encoding in [TextEncoding::Base64, TextEncoding::Base45] {
+            assert_close(&prose(len), &password(), encoding, 25);
+        }
+    }
+}
+
+#[test]
+fn recipient_payloads_are_estimated_closely() {
+    let one = Protection::Recipients(vec![crypto_utils::generate_keypair().0]);
+    let three =
+        Protection::Recipients((0..3).map(|_| crypto_utils::generate_keypair().0).collect());
+    for protection in [one, three] {
+        assert_close(
+            &noise(800),
+            &protection,
+            TextEncoding::Base64,
+            SCATTER_PERCENT,
+        );
+        assert_close(&prose(8_000), &protection, TextEncoding::Base64, 25);
+    }
+}
+
+#[test]
+fn an_expiry_is_counted() {
+    // The expiry is 8 more bytes of plaintext.
+    for mode in [PayloadMode::Password, PayloadMode::Recipient] {
+        assert_eq!(
+            processor::estimate_packed_len(500, mode, 1, true),
+            processor::estimate_packed_len(508, mode, 1, false)
+        );
+    }
+
+    // Far enough ahead that the payload never counts as expired.
+    let expires_at = Some(4_102_444_800);
+    for encoding in [TextEncoding::Base64, TextEncoding::Base45] {
+        assert_close_expiring(&noise(16), &password(), encoding, 0, expires_at);
+        assert_close_expiring(
+            &noise(1500),
+            &password(),
+            encoding,
+            SCATTER_PERCENT,
+            expires_at,
+        );
+    }
+}
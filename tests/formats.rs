@@ -0,0 +1,227 @@
+// tests/formats.rs
+//! Payloads written by earlier format versions keep decoding.
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use qr_data_exchange::crypto::crypto_utils::{self, KdfLimits};
+use qr_data_exchange::qr::processor::{
+    DeserializeOptions, QrDataProcessor, QrProcessorError, SerializeOptions, FORMAT_VERSION,
+};
+
+const PASSWORD: &str = "correct horse";
+
+/// `QrDataProcessor::serialize(b"written by format version <n>", PASSWORD)`
+/// as of the baseline commit, which wrote the bare version 1 layout.
+const V1: &str = "ktwAECTM2lDMm8yqX8yPMMyszNnMox3MvczTTwvcAEwLG8yLOcytzJB6OEcVzI3Mh0vM0MycdQcFfi5fzNnMsyFSzPLMvMyxAw5wzMrM1VNtzJEKYlXMqDEKES3MtsyGzPRpzL8EzLpNzOfM3S/Ml8y3zPVCzJwiFMyozKPMsMyOzIvMw25kzO80zJpazNtG";
+/// The same call as of the last commit writing each later version.
+const V2: &str = "QRDX2:lNwAEAUjzPQLNGEsXVPMwi8AzMPMxcypzMPcAExJDsyhzNjM7Mz6zI1UzJHM0MyfC8y/W3/MlgolzOLMqsyaDxgwzLzMiHd3RBTMmHPMwioqzM3MyBNozIo7d8zszMcAzPdkWkzMzCE6YMzDzMl4zN7Mycy3zLbMsmczAB3MywPMrMy8TnAwzL7MnMzEzOAAAA==";
+const V3: &str = "QRDX3:ldwAEEnMrXnMocyKzJVfzOcfcl/MpcySdAzMvdwATMylzN3MsTQ7zKDM98y3zPLMocz7zOQja8y1zLLMrhZ3Ni/MriMJzJIHzLlGe8zhzPXM6ADMvszkzJZVB3/MmC7MmEjM/1rMqMz2FxXMgltdM8yVzKrM08zUBBfMmsyRXTnMhRprzPXMiVMizL8wzKlXzLLMrgAAAA==";
+const V4: &str = "QRDX4:ltwAEMzfzNPMo8ymzKQ5zIdZzOPM1yp5b3/M6C7cAEzM38zizLh4zNDM6cyFFzbM6EPMgcyczLxxNXzM4MydzJDM78yILMzHTRvMzMzfzJlUBE7MlszlzOPM78ybzNJQzNpGzORaDhcuzODM28zIzOMlzNTMxMzBSMyKLTckzLdQVMyNLMy9zN1YzNdwJFFSzKwoOMy7AAAAmAvMjhPM6j3M6D94";
+const V5: &str = "QRDX5:l9wAEH/Mh2l2TWTM1cy4zOHM3MytbMybLszmzN3cAFDMvz7MgEnMqmXMx3oVzOLMhsyILczNzNPM7szZzMrMhhPM8wwiQ8zCEEDMtxwAzMbMqsyHzK3Mu8zgAlDM+8y6YszwGWzM33zM9cyTNMypGSxmKGPM/GzMygMjLVlOW28ZAsyNzL7M38yGOVVQP20JzNQOXgAAAJg+T8yZzOLM8iBmzNSQ";
+const V6: &str = "QRDX6:l9wAEDYeDMynDcz1FMyozKfMuDQ2zOTMssysC9wAcA1SRczxzO3MiMyIbszOcsz9YcygbczwzPV/BlV+zILM2wTM/yXM0FFWzPDMjSVcDg3M3sy8zJbMxczvzJzMlMyNzIJUdn18zOXMhirMhQAVG8zfKczre27M+yvM7VvM58yDzMLMhMzQOhEkAQbMr8yPzMvM/MzyzN57zOvMjBYqzPVpfFJ+zNkTeMy0zJDM/syeDszkzJHMgg8vZ8zDzNPM2czuzPwSX8ylzOsIAACYRMzszK09Zip0zKuQ";
+
+const FIXTURES: [(u32, &str); 6] = [(1, V1), (2, V2), (3, V3), (4, V4), (5, V5), (6, V6)];
+
+fn plaintext(version: u32) -> Vec<u8> {
+    format!("written by format version {}", version).into_bytes()
+}
+
+#[test]
+fn old_format_strings_still_decode() {
+    for (version, fixture) in FIXTURES {
+        assert_eq!(
+            QrDataProcessor::deserialize(fixture, PASSWORD).unwrap(),
+            plaintext(version),
+            "version {}",
+            version
+        );
+    }
+}
+
+/// The packed fields of a current payload, in wire order.
+type Fields = (
+    Vec<u8>,
+    Vec<u8>,
+    u8,
+    u8,
+    u8,
+    Vec<u8>,
+    Vec<Vec<u8>>,
+    u32,
+    u32,
+);
+
+/// Bit 3 of `flags`: a digest of the data precedes the compressed data.
+const FLAG_DIGEST: u8 = 0b0000_1000;
+
+/// Decodes a base64 payload, lets `edit` change its fields and encodes it
+/// again under the prefix of `version`.
+fn repack(payload: &str, version: u32, edit: impl FnOnce(&mut Fields)) -> String {
+    let (_, body) = payload.split_once(':').unwrap();
+    let mut fields: Fields = rmp_serde::from_slice(&STANDARD.decode(body).unwrap()).unwrap();
+    edit(&mut fields);
+    format!(
+        "QRDX{}:{}",
+        version,
+        STANDARD.encode(rmp_serde::to_vec(&fields).unwrap())
+    )
+}
+
+#[test]
+fn fields_from_later_versions_are_refused() {
+    let payload = QrDataProcessor::serialize(&plaintext(FORMAT_VERSION), PASSWORD).unwrap();
+    assert!(
+        QrDataProcessor::deserialize(&repack(&payload, FORMAT_VERSION, |_| ()), PASSWORD).is_ok()
+    );
+
+    // A current payload relabelled as an older version has fields that
+    // version didn't know.
+    for version in 2..FORMAT_VERSION {
+        let relabelled = repack(&payload, version, |_| ());
+        assert!(
+            matches!(
+                QrDataProcessor::deserialize(&relabelled, PASSWORD),
+                Err(QrProcessorError::Malformed(_))
+            ),
+            "version {}",
+            version
+        );
+    }
+}
+
+#[test]
+fn current_payloads_need_their_digest() {
+    let payload = QrDataProcessor::serialize(&plaintext(FORMAT_VERSION), PASSWORD).unwrap();
+    let stripped = repack(&payload, FORMAT_VERSION, |fields| fields.2 &= !FLAG_DIGEST);
+    assert!(matches!(
+        QrDataProcessor::deserialize(&stripped, PASSWORD),
+        Err(QrProcessorError::Malformed(_))
+    ));
+}
+
+#[test]
+fn only_current_payloads_have_a_byte_form() {
+    assert!(matches!(
+        QrDataProcessor::payload_to_bytes(V5),
+        Err(QrProcessorError::UnsupportedVersion(5))
+    ));
+    let payload = QrDataProcessor::serialize(&plaintext(FORMAT_VERSION), PASSWORD).unwrap();
+    let bytes = QrDataProcessor::payload_to_bytes(&payload).unwrap();
+    assert_eq!(
+        QrDataProcessor::payload_from_bytes(&bytes).unwrap(),
+        payload
+    );
+}
+
+#[test]
+fn payloads_record_their_argon2_limits() {
+    let options = SerializeOptions {
+        kdf: KdfLimits {
+            ops_limit: 3,
+            mem_limit_kib: 8,
+        },
+        ..SerializeOptions::default()
+    };
+    let payload =
+        QrDataProcessor::serialize_with(&plaintext(FORMAT_VERSION), PASSWORD, &options).unwrap();
+    // Decoding with the default limits still derives the same key.
+    assert_eq!(
+        QrDataProcessor::deserialize(&payload, PASSWORD).unwrap(),
+        plaintext(FORMAT_VERSION)
+    );
+
+    let greedy = repack(&payload, FORMAT_VERSION, |fields| {
+        fields.8 = KdfLimits::MAX_MEM_LIMIT_KIB + 1
+    });
+    assert!(matches!(
+        QrDataProcessor::deserialize(&greedy, PASSWORD),
+        Err(QrProcessorError::Malformed(_))
+    ));
+}
+
+/// Bytes serialized as MessagePack `bin`, as msgpack-python writes `bytes`.
+struct Bin(Vec<u8>);
+
+impl serde::Serialize for Bin {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(&self.0)
+    }
+}
+
+/// Packs the fields of `payload` again with every byte field as `bin`.
+fn as_bin(payload: &str) -> String {
+    let (_, body) = payload.split_once(':').unwrap();
+    let fields: Fields = rmp_serde::from_slice(&STANDARD.decode(body).unwrap()).unwrap();
+    let (salt, encrypted, flags, mode, cipher, check, keys, ops, mem) = fields;
+
+    let packed = rmp_serde::to_vec(&(
+        Bin(salt),
+        Bin(encrypted),
+        flags,
+        mode,
+        cipher,
+        Bin(check),
+        keys.into_iter().map(Bin).collect::<Vec<_>>(),
+        ops,
+        mem,
+    ))
+    .unwrap();
+    // 0xc4 starts a `bin 8`, here the salt or the empty salt of recipients.
+    assert_eq!(packed[1], 0xc4);
+    format!("QRDX{}:{}", FORMAT_VERSION, STANDARD.encode(packed))
+}
+
+#[test]
+fn byte_fields_may_be_msgpack_bin() {
+    let payload = QrDataProcessor::serialize(&plaintext(FORMAT_VERSION), PASSWORD).unwrap();
+    assert_eq!(
+        QrDataProcessor::deserialize(&as_bin(&payload), PASSWORD).unwrap(),
+        plaintext(FORMAT_VERSION)
+    );
+
+    let (public, secret) = crypto_utils::generate_keypair();
+    let (other, _) = crypto_utils::generate_keypair();
+    let payload = QrDataProcessor::serialize_for_recipients_with(
+        &plaintext(FORMAT_VERSION),
+        &[other, public],
+        &SerializeOptions::default(),
+    )
+    .unwrap();
+    assert_eq!(
+        QrDataProcessor::deserialize_with_keypair(
+            &as_bin(&payload),
+            &public,
+            &secret,
+            &DeserializeOptions::default()
+        )
+        .unwrap(),
+        plaintext(FORMAT_VERSION)
+    );
+}
+
+/// The smallest layout the decoder takes, as another tool could write it
+/// with PyNaCl and msgpack: no prefix, `[salt, nonce || ciphertext]` as
+/// `bin`, the key derived with the configured limits and a zstd frame inside.
+#[test]
+fn minimal_layout_decodes() {
+    crypto_utils::init().unwrap();
+    let limits = KdfLimits {
+        ops_limit: 3,
+        mem_limit_kib: 8,
+    };
+    let plaintext = b"written without this crate's packer";
+
+    let salt = crypto_utils::generate_salt();
+    let key = crypto_utils::derive_key_with_limits(PASSWORD, None, &salt, limits).unwrap();
+    let compressed = zstd::encode_all(&plaintext[..], 3).unwrap();
+    let encrypted = crypto_utils::encrypt(&compressed, &key).unwrap();
+    let packed = rmp_serde::to_vec(&(Bin(salt.0.to_vec()), Bin(encrypted))).unwrap();
+
+    let options = DeserializeOptions {
+        kdf: limits,
+        ..DeserializeOptions::default()
+    };
+    assert_eq!(
+        QrDataProcessor::deserialize_with(&STANDARD.encode(packed), PASSWORD, &options).unwrap(),
+        plaintext
+    );
+}
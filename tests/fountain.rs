@@ -0,0 +1,185 @@
+// tests/fountain.rs
+//! Fountain frames rebuild the payload from any large enough share of them,
+//! in whatever order they are scanned.
+use qr_data_exchange::qr::fountain::{
+    self, FountainDecoder, FountainEncoder, FountainError, FOUNTAIN_SCHEME,
+};
+
+const BLOCK_SIZE: usize = 64;
+
+/// Payload text over a good number of blocks, with a short last block.
+fn payload() -> String {
+    (0..200).map(|i| format!("{i:03}-")).collect::<String>() + "end"
+}
+
+/// Feeds `frames` to a new decoder and returns the payload and the number
+/// of frames it took.
+fn decode<'a>(frames: impl IntoIterator<Item = &'a String>) -> Option<(String, usize)> {
+    let mut decoder = FountainDecoder::new();
+    for (used, frame) in frames.into_iter().enumerate() {
+        if let Some(payload) = decoder.add(frame).unwrap() {
+            return Some((payload, used + 1));
+        }
+    }
+    None
+}
+
+/// A fixed shuffle, so a failure can be reproduced.
+fn shuffled(frames: &[String]) -> Vec<String> {
+    let mut frames = frames.to_vec();
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    for i in (1..frames.len()).rev() {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        frames.swap(i, (state % (i as u64 + 1)) as usize);
+    }
+    frames
+}
+
+#[test]
+fn frames_in_order_rebuild_the_payload() {
+    let payload = payload();
+    let encoder = FountainEncoder::new(&payload, BLOCK_SIZE);
+    assert_eq!(encoder.block_count(), payload.len().div_ceil(BLOCK_SIZE));
+
+    let frames = encoder.frames(encoder.recommended_frame_count());
+    let (decoded, used) = decode(&frames).unwrap();
+    assert_eq!(decoded, payload);
+    assert!(used >= encoder.block_count());
+}
+
+#[test]
+fn frames_out_of_order_rebuild_the_payload() {
+    let payload = payload();
+    let encoder = FountainEncoder::new(&payload, BLOCK_SIZE);
+    let frames = encoder.frames(encoder.recommended_frame_count());
+
+    assert_eq!(decode(frames.iter().rev()).unwrap().0, payload);
+    assert_eq!(decode(&shuffled(&frames)).unwrap().0, payload);
+}
+
+#[test]
+fn lost_frames_are_made_up_by_later_ones() {
+    let payload = payload();
+    let encoder = FountainEncoder::new(&payload, BLOCK_SIZE);
+    let k = encoder.block_count();
+    let frames = encoder.frames(4 * k);
+
+    // The first K frames never arrive.
+    assert_eq!(decode(&frames[k..]).unwrap().0, payload);
+    // Every third frame is missed, and the rest come in shuffled.
+    let kept: Vec<String> = frames
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| i % 3 != 0)
+        .map(|(_, frame)| frame.clone())
+        .collect();
+    assert_eq!(decode(&shuffled(&kept)).unwrap().0, payload);
+}
+
+#[test]
+fn too_few_frames_are_not_enough() {
+    let payload = payload();
+    let encoder = FountainEncoder::new(&payload, BLOCK_SIZE);
+    let k = encoder.block_count();
+
+    let mut decoder = FountainDecoder::new();
+    assert_eq!(decoder.progress(), (0, 0));
+    for frame in encoder.frames(k - 1) {
+        assert_eq!(decoder.add(&frame).unwrap(), None);
+    }
+    let (solved, total) = decoder.progress();
+    assert_eq!(total, k);
+    assert!(solved < k);
+}
+
+#[test]
+fn repeated_frames_add_nothing() {
+    let payload = payload();
+    let encoder = FountainEncoder::new(&payload, BLOCK_SIZE);
+    let frame = encoder.frame(7);
+
+    let mut decoder = FountainDecoder::new();
+    decoder.add(&frame).unwrap();
+    let progress = decoder.progress();
+    for _ in 0..encoder.recommended_frame_count() {
+        assert_eq!(decoder.add(&format!(" {frame}\n")).unwrap(), None);
+    }
+    assert_eq!(decoder.progress(), progress);
+}
+
+#[test]
+fn short_payloads_fit_one_block() {
+    for payload in [
+        "",
+        "x",
+        "exactly sixty-four bytes of payload text, filling one entire row",
+    ] {
+        let encoder = FountainEncoder::new(payload, BLOCK_SIZE);
+        assert_eq!(encoder.block_count(), 1);
+        assert_eq!(decode(&encoder.frames(1)).unwrap().0, payload);
+    }
+}
+
+#[test]
+fn frames_of_another_payload_are_refused() {
+    let encoder = FountainEncoder::new(&payload(), BLOCK_SIZE);
+    let other = FountainEncoder::new("another payload", BLOCK_SIZE);
+
+    let mut decoder = FountainDecoder::new();
+    decoder.add(&encoder.frame(0)).unwrap();
+    assert_eq!(
+        decoder.add(&other.frame(1)),
+        Err(FountainError::Inconsistent)
+    );
+}
+
+#[test]
+fn anything_but_a_frame_is_refused() {
+    let mut decoder = FountainDecoder::new();
+    assert_eq!(decoder.add("QRDX7:abc"), Err(FountainError::NotAFrame));
+    assert_eq!(
+        decoder.add(&format!("{FOUNTAIN_SCHEME}1:not base45")),
+        Err(FountainError::NotAFrame)
+    );
+    assert_eq!(
+        decoder.add(&format!("{FOUNTAIN_SCHEME}99:")),
+        Err(FountainError::UnsupportedVersion(99))
+    );
+    assert_eq!(decoder.progress(), (0, 0));
+
+    assert!(FountainDecoder::is_frame(" qrdxf1:x"));
+    assert!(!FountainDecoder::is_frame("QRDX7:abc"));
+}
+
+/// A frame with a hand-made header, as a hostile code could carry it.
+fn crafted_frame(total_len: u32, block_size: u16) -> String {
+    let mut packed = Vec::new();
+    packed.extend_from_slice(&total_len.to_be_bytes());
+    packed.extend_from_slice(&block_size.to_be_bytes());
+    packed.extend_from_slice(&0u32.to_be_bytes());
+    packed.resize(packed.len() + usize::from(block_size), 0);
+    format!("{FOUNTAIN_SCHEME}1:{}", base45::encode(&packed))
+}
+
+#[test]
+fn oversized_headers_are_refused() {
+    let mut decoder = FountainDecoder::new();
+    // 4 GiB in blocks of one byte
+    assert_eq!(
+        decoder.add(&crafted_frame(u32::MAX, 1)),
+        Err(FountainError::NotAFrame)
+    );
+    // A length within bounds, but far too many blocks
+    let blocks = fountain::MAX_BLOCKS as u32 + 1;
+    assert_eq!(
+        decoder.add(&crafted_frame(blocks, 1)),
+        Err(FountainError::NotAFrame)
+    );
+    assert_eq!(decoder.progress(), (0, 0));
+
+    let largest = decoder.add(&crafted_frame(fountain::MAX_PAYLOAD_LEN as u32, 512));
+    assert_eq!(largest, Ok(None));
+    assert_eq!(decoder.progress().1, fountain::MAX_BLOCKS);
+}
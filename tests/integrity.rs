@@ -0,0 +1,88 @@
+// tests/integrity.rs
+//! The digest a payload carries is checked against the decoded data, and
+//! payloads from before digests are marked as unverified.
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use qr_data_exchange::crypto::crypto_utils::{self, KdfLimits};
+use qr_data_exchange::qr::processor::{
+    DeserializeOptions, QrDataProcessor, QrProcessorError, FORMAT_VERSION,
+};
+
+const PASSWORD: &str = "correct horse";
+const DATA: &[u8] = b"checked on arrival";
+/// Bit 3 of `flags`: a digest of the data precedes the compressed data.
+const FLAG_DIGEST: u8 = 0b0000_1000;
+/// Cheapest limits Argon2 accepts, to keep the tests fast.
+const FAST: KdfLimits = KdfLimits {
+    ops_limit: 3,
+    mem_limit_kib: 8,
+};
+
+/// `QrDataProcessor::serialize(b"written by format version 5", PASSWORD)`
+/// as of the last commit writing version 5, the last without a digest.
+const V5: &str = "QRDX5:l9wAEH/Mh2l2TWTM1cy4zOHM3MytbMybLszmzN3cAFDMvz7MgEnMqmXMx3oVzOLMhsyILczNzNPM7szZzMrMhhPM8wwiQ8zCEEDMtxwAzMbMqsyHzK3Mu8zgAlDM+8y6YszwGWzM33zM9cyTNMypGSxmKGPM/GzMygMjLVlOW28ZAsyNzL7M38yGOVVQP20JzNQOXgAAAJg+T8yZzOLM8iBmzNSQ";
+
+/// A current payload for `DATA` whose plaintext starts with `digest`, built
+/// field by field so the digest can be wrong.
+fn payload_with_digest(digest: &[u8]) -> String {
+    let salt = crypto_utils::generate_salt();
+    let key = crypto_utils::derive_key_with_limits(PASSWORD, None, &salt, FAST).unwrap();
+
+    let mut plaintext = digest.to_vec();
+    plaintext.extend(zstd::encode_all(DATA, 3).unwrap());
+    let encrypted = crypto_utils::encrypt(&plaintext, &key).unwrap();
+    let check = crypto_utils::key_check(&key).unwrap();
+
+    let fields = (
+        salt.0.to_vec(),
+        encrypted,
+        FLAG_DIGEST,
+        0u8,
+        0u8,
+        check.to_vec(),
+        Vec::<Vec<u8>>::new(),
+        FAST.ops_limit,
+        FAST.mem_limit_kib,
+    );
+    let packed = rmp_serde::to_vec(&fields).unwrap();
+    format!("QRDX{}:{}", FORMAT_VERSION, STANDARD.encode(packed))
+}
+
+#[test]
+fn matching_digest_is_reported() {
+    crypto_utils::init().unwrap();
+    let digest = crypto_utils::digest(DATA).unwrap();
+    let (data, metadata) = QrDataProcessor::deserialize_with_metadata(
+        &payload_with_digest(&digest),
+        PASSWORD,
+        &DeserializeOptions::default(),
+    )
+    .unwrap();
+    assert_eq!(data, DATA);
+    assert_eq!(metadata.digest, Some(digest));
+}
+
+#[test]
+fn tampered_digest_fails_the_integrity_check() {
+    crypto_utils::init().unwrap();
+    let mut digest = crypto_utils::digest(DATA).unwrap();
+    digest[0] ^= 1;
+    assert!(matches!(
+        QrDataProcessor::deserialize_with_metadata(
+            &payload_with_digest(&digest),
+            PASSWORD,
+            &DeserializeOptions::default()
+        ),
+        Err(QrProcessorError::IntegrityCheckFailed)
+    ));
+}
+
+#[test]
+fn version_5_payloads_are_not_verified() {
+    let (data, metadata) =
+        QrDataProcessor::deserialize_with_metadata(V5, PASSWORD, &DeserializeOptions::default())
+            .unwrap();
+    assert_eq!(data, b"written by format version 5");
+    assert_eq!(metadata.digest, None);
+    assert_eq!(metadata.digest_hex(), None);
+}
@@ -0,0 +1,76 @@
+// tests/keyfile.rs
+//! A keyfile is needed in addition to the password exactly when the payload
+//! was made with one.
+use qr_data_exchange::crypto::crypto_utils::KdfLimits;
+use qr_data_exchange::qr::processor::{
+    DeserializeOptions, QrDataProcessor, QrProcessorError, SerializeOptions,
+};
+use zeroize::Zeroizing;
+
+const PASSWORD: &str = "correct horse";
+const DATA: &[u8] = b"for the holder of the keyfile";
+const KEYFILE: &[u8] = b"contents of a keyfile, e.g. a photo";
+/// Cheapest limits Argon2 accepts, to keep the tests fast.
+const FAST: KdfLimits = KdfLimits {
+    ops_limit: 3,
+    mem_limit_kib: 8,
+};
+
+fn payload(keyfile: Option<&[u8]>) -> String {
+    let options = SerializeOptions {
+        keyfile: keyfile.map(|keyfile| Zeroizing::new(keyfile.to_vec())),
+        kdf: FAST,
+        ..SerializeOptions::default()
+    };
+    QrDataProcessor::serialize_with(DATA, PASSWORD, &options).unwrap()
+}
+
+fn decode(
+    payload: &str,
+    password: &str,
+    keyfile: Option<&[u8]>,
+) -> Result<Vec<u8>, QrProcessorError> {
+    let options = DeserializeOptions {
+        keyfile: keyfile.map(|keyfile| Zeroizing::new(keyfile.to_vec())),
+        ..DeserializeOptions::default()
+    };
+    QrDataProcessor::deserialize_with(payload, password, &options)
+}
+
+#[test]
+fn password_only() {
+    let payload = payload(None);
+    assert_eq!(decode(&payload, PASSWORD, None).unwrap(), DATA);
+    // A keyfile selected anyway isn't used.
+    assert_eq!(decode(&payload, PASSWORD, Some(KEYFILE)).unwrap(), DATA);
+    assert!(matches!(
+        decode(&payload, "wrong horse", None),
+        Err(QrProcessorError::WrongPassword)
+    ));
+}
+
+#[test]
+fn password_and_keyfile() {
+    let payload = payload(Some(KEYFILE));
+    assert_eq!(decode(&payload, PASSWORD, Some(KEYFILE)).unwrap(), DATA);
+    assert!(matches!(
+        decode(&payload, "wrong horse", Some(KEYFILE)),
+        Err(QrProcessorError::WrongPassword)
+    ));
+}
+
+#[test]
+fn missing_keyfile_is_asked_for() {
+    assert!(matches!(
+        decode(&payload(Some(KEYFILE)), PASSWORD, None),
+        Err(QrProcessorError::KeyfileRequired)
+    ));
+}
+
+#[test]
+fn wrong_keyfile_is_a_wrong_password() {
+    assert!(matches!(
+        decode(&payload(Some(KEYFILE)), PASSWORD, Some(b"some other file")),
+        Err(QrProcessorError::WrongPassword)
+    ));
+}
@@ -0,0 +1,110 @@
+// tests/logo.rs
+//! A logo in the middle of a code leaves it readable, and colors that
+//! scanners can't tell apart are refused.
+use image::{Rgba, RgbaImage};
+use qr_data_exchange::qr::service::{
+    self, EcLevel, QrLogo, QrServiceError, QrStyle, RenderOptions, MAX_LOGO_AREA,
+    MIN_CONTRAST_RATIO,
+};
+use std::path::PathBuf;
+
+const PAYLOAD: &str = "QRDX7:bG9nbyBpbiB0aGUgbWlkZGxlLCBjb2RlIHN0aWxsIHJlYWRhYmxl";
+
+/// A busy logo with dark and light areas, saved as PNG.
+fn logo_file(name: &str) -> PathBuf {
+    let logo = RgbaImage::from_fn(64, 64, |x, y| {
+        if (x / 8 + y / 8) % 2 == 0 {
+            Rgba([20, 40, 160, 255])
+        } else {
+            Rgba([250, 200, 30, 255])
+        }
+    });
+    let path = std::env::temp_dir().join(format!("qrdx-logo-{}-{}.png", name, std::process::id()));
+    logo.save(&path).unwrap();
+    path
+}
+
+fn with_style(style: QrStyle) -> RenderOptions {
+    RenderOptions {
+        style,
+        ..RenderOptions::default()
+    }
+}
+
+#[test]
+fn a_code_with_the_largest_logo_still_decodes() {
+    let path = logo_file("largest");
+    let options = with_style(QrStyle {
+        logo: Some(QrLogo {
+            path: path.clone(),
+            relative_size: MAX_LOGO_AREA.sqrt(),
+        }),
+        ..QrStyle::default()
+    });
+    assert_eq!(options.effective_ec_level(), EcLevel::H);
+
+    let image = service::generate_qr_image(PAYLOAD, &options).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(service::read_qr_from_bytes(&image.png).unwrap(), PAYLOAD);
+}
+
+#[test]
+fn a_logo_beyond_the_limit_is_refused() {
+    let path = logo_file("too-large");
+    let options = with_style(QrStyle {
+        logo: Some(QrLogo {
+            path: path.clone(),
+            relative_size: MAX_LOGO_AREA.sqrt() + 0.05,
+        }),
+        ..QrStyle::default()
+    });
+    let result = service::generate_qr_image(PAYLOAD, &options);
+    std::fs::remove_file(&path).unwrap();
+    assert!(matches!(result, Err(QrServiceError::LogoTooLarge { .. })));
+}
+
+#[test]
+fn colors_with_enough_contrast_render() {
+    let style = QrStyle {
+        dark: [0, 40, 110],
+        light: [255, 250, 235],
+        ..QrStyle::default()
+    };
+    assert!(style.contrast_ratio() >= MIN_CONTRAST_RATIO);
+    let image = service::generate_qr_image(PAYLOAD, &with_style(style)).unwrap();
+    assert_eq!(service::read_qr_from_bytes(&image.png).unwrap(), PAYLOAD);
+}
+
+#[test]
+fn black_on_white_has_the_highest_contrast() {
+    let ratio = QrStyle::default().contrast_ratio();
+    assert!((ratio - 21.0).abs() < 0.01, "{ratio}");
+}
+
+#[test]
+fn colors_too_close_together_are_refused() {
+    let style = QrStyle {
+        dark: [120, 120, 120],
+        light: [170, 170, 170],
+        ..QrStyle::default()
+    };
+    assert!(matches!(
+        service::generate_qr_image(PAYLOAD, &with_style(style)),
+        Err(QrServiceError::LowContrast { ratio, min_ratio })
+            if ratio < min_ratio && min_ratio == MIN_CONTRAST_RATIO
+    ));
+}
+
+#[test]
+fn light_codes_on_dark_backgrounds_are_refused() {
+    let style = QrStyle {
+        dark: [255, 255, 255],
+        light: [0, 0, 0],
+        ..QrStyle::default()
+    };
+    assert!(style.contrast_ratio() < 1.0);
+    assert!(matches!(
+        service::generate_qr_image(PAYLOAD, &with_style(style)),
+        Err(QrServiceError::LowContrast { .. })
+    ));
+}
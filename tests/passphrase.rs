@@ -0,0 +1,64 @@
+// tests/passphrase.rs
+//! Generated passwords have the entropy their style promises and are accepted
+//! wherever a typed password is.
+use qr_data_exchange::crypto::passphrase::{self, PassphraseStyle, MIN_ENTROPY_BITS};
+use qr_data_exchange::crypto::password::{PasswordPolicy, MAX_PASSWORD_LENGTH};
+use std::collections::HashSet;
+
+/// Size of the BIP-39 word list.
+const WORD_LIST_LEN: f64 = 2048.0;
+
+#[test]
+fn entropy_is_count_times_choices() {
+    for count in 1..=5 {
+        let expected = count as f64 * WORD_LIST_LEN.log2();
+        assert_eq!(PassphraseStyle::Words(count).entropy_bits(), expected);
+    }
+    assert_eq!(PassphraseStyle::Words(5).entropy_bits(), 55.0);
+
+    for len in [12, 16, 20] {
+        let expected = len as f64 * 62f64.log2();
+        assert!((PassphraseStyle::Alphanumeric(len).entropy_bits() - expected).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn every_style_fits_and_passes_the_policy() {
+    let policy = PasswordPolicy::default();
+    for style in PassphraseStyle::ALL {
+        assert!(style.max_len() <= MAX_PASSWORD_LENGTH, "{:?}", style);
+        assert!(style.entropy_bits() >= MIN_ENTROPY_BITS, "{:?}", style);
+        for _ in 0..50 {
+            let password = passphrase::generate(style).unwrap();
+            assert!(password.len() <= style.max_len(), "{:?}: {}", style, *password);
+            assert!(password.chars().all(|c| c.is_ascii_alphanumeric()), "{}", *password);
+            assert!(policy.check(&password).is_ok(), "{:?}: {}", style, *password);
+        }
+    }
+}
+
+#[test]
+fn words_start_with_capitals() {
+    for _ in 0..50 {
+        let password = passphrase::generate(PassphraseStyle::Words(5)).unwrap();
+        let words = password.chars().filter(char::is_ascii_uppercase).count();
+        assert_eq!(words, 5, "{}", *password);
+        assert!(password.chars().filter(|c| !c.is_ascii_uppercase()).all(|c| c.is_ascii_lowercase()));
+    }
+}
+
+#[test]
+fn alphanumeric_has_the_requested_length() {
+    for len in [12, 16, 20] {
+        assert_eq!(passphrase::generate(PassphraseStyle::Alphanumeric(len)).unwrap().len(), len);
+    }
+}
+
+#[test]
+fn passwords_do_not_repeat() {
+    // 55 bits per password; a repeat among a few hundred means a broken source.
+    let passwords: HashSet<String> = (0..500)
+        .map(|_| passphrase::generate(PassphraseStyle::Words(5)).unwrap().to_string())
+        .collect();
+    assert_eq!(passwords.len(), 500);
+}
@@ -0,0 +1,86 @@
+// tests/password.rs
+//! The strength estimate ranks passwords the way the meter and the policy
+//! expect.
+use qr_data_exchange::crypto::password::{
+    estimate_entropy, PasswordPolicy, PolicyViolation, Strength,
+};
+
+#[test]
+fn empty_passwords_have_no_entropy() {
+    assert_eq!(estimate_entropy(""), 0.0);
+}
+
+#[test]
+fn entropy_is_length_times_pool_bits() {
+    // 26 letters, nothing repeats or runs: 8 * log2(26).
+    let expected = 8.0 * 26f64.log2();
+    assert!((estimate_entropy("qwmtzpfk") - expected).abs() < 1e-9);
+
+    // Upper case, digits and punctuation each widen the pool.
+    let pools = [
+        ("qwmtzpfk", 26.0),
+        ("qwMtzPfk", 52.0),
+        ("qw4tz8fk", 36.0),
+        ("qw!tz?fk", 59.0),
+    ];
+    for (password, pool) in pools {
+        let expected = 8.0 * f64::log2(pool);
+        assert!(
+            (estimate_entropy(password) - expected).abs() < 1e-9,
+            "{}",
+            password
+        );
+    }
+}
+
+#[test]
+fn repeats_and_runs_count_a_quarter() {
+    // The first character counts in full, the seven after it a quarter each.
+    let expected = (1.0 + 7.0 * 0.25) * 26f64.log2();
+    for password in ["aaaaaaaa", "abcdefgh", "hgfedcba"] {
+        assert!(
+            (estimate_entropy(password) - expected).abs() < 1e-9,
+            "{}",
+            password
+        );
+    }
+    assert!(estimate_entropy("12345678") < estimate_entropy("19283746"));
+}
+
+#[test]
+fn longer_passwords_are_stronger() {
+    assert!(estimate_entropy("Tiger7") < estimate_entropy("Tiger7Orbit"));
+    assert_eq!(
+        Strength::from_entropy(estimate_entropy("password")),
+        Strength::Weak
+    );
+    assert_eq!(
+        Strength::from_entropy(estimate_entropy("aaaaaaaa")),
+        Strength::VeryWeak
+    );
+    assert_eq!(
+        Strength::from_entropy(estimate_entropy("TigeOrbiFlasKitcRamp")),
+        Strength::Strong
+    );
+}
+
+#[test]
+fn policy_checks_length_before_entropy() {
+    let policy = PasswordPolicy::default();
+    assert_eq!(policy.check("Ab1!"), Err(PolicyViolation::TooShort(8)));
+    assert!(matches!(
+        policy.check("aaaaaaaaaa"),
+        Err(PolicyViolation::TooWeak { .. })
+    ));
+    assert_eq!(policy.check("TigeOrbiFlas"), Ok(()));
+
+    let strict = PasswordPolicy {
+        min_length: 12,
+        min_entropy_bits: 60.0,
+    };
+    assert_eq!(
+        strict.check("TigeOrbiFl"),
+        Err(PolicyViolation::TooShort(12))
+    );
+    assert!(strict.check("TigeOrbiFlasKitc").is_ok());
+}
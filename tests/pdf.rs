@@ -0,0 +1,99 @@
+// tests/pdf.rs
+//! Exported PDFs read back into the payload they were made from, and scanned
+//! parts are put back together in any order.
+use lopdf::{Document, Object};
+use qr_data_exchange::error::Error;
+use qr_data_exchange::qr::chunk::{self, ChunkError};
+use qr_data_exchange::qr::fountain::FountainEncoder;
+use qr_data_exchange::qr::pdf::{self, PdfPage, SAMPLES_PER_MODULE};
+use qr_data_exchange::qr::service::ReadOptions;
+use qr_data_exchange::services;
+use std::path::PathBuf;
+
+const PAYLOAD: &str = "QRDX7:bm90IGEgcmVhbCBwYXlsb2FkLCBidXQgdGhlIHBkZiBkb2Vzbid0IGNhcmU=";
+
+fn export(parts: &[String], name: &str) -> PathBuf {
+    let pages: Vec<PdfPage> = parts
+        .iter()
+        .map(|part| PdfPage {
+            payload: part.clone(),
+            caption: vec![pdf::fingerprint(part)],
+        })
+        .collect();
+    let path = std::env::temp_dir().join(format!("qrdx-{}-{}.pdf", name, std::process::id()));
+    std::fs::write(&path, pdf::generate_qr_pdf("test", &pages).unwrap()).unwrap();
+    path
+}
+
+#[test]
+fn every_page_embeds_its_code_as_an_image() {
+    let parts = chunk::split(PAYLOAD, 30);
+    let path = export(&parts, "images");
+    let document = Document::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let pages = document.get_pages();
+    assert_eq!(pages.len(), parts.len());
+    for page_id in pages.values() {
+        let (own, inherited) = document.get_page_resources(*page_id);
+        let resources = own.unwrap_or_else(|| document.get_dictionary(inherited[0]).unwrap());
+        let xobjects = resources.get(b"XObject").unwrap();
+        let (_, xobjects) = document.dereference(xobjects).unwrap();
+        let images: Vec<_> = xobjects
+            .as_dict()
+            .unwrap()
+            .iter()
+            .filter_map(|(_, xobject)| match document.dereference(xobject) {
+                Ok((_, Object::Stream(stream))) => Some(stream.dict.clone()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(images.len(), 1);
+
+        let width = images[0].get(b"Width").unwrap().as_i64().unwrap() as usize;
+        assert_eq!(width % SAMPLES_PER_MODULE, 0);
+        assert!(!images[0].get(b"Interpolate").unwrap().as_bool().unwrap());
+    }
+}
+
+#[test]
+fn exported_pdf_reads_back() {
+    let parts = chunk::split(PAYLOAD, 30);
+    let path = export(&parts, "round-trip");
+    let codes = pdf::read_qr_from_pdf(&path, ReadOptions::default());
+    std::fs::remove_file(&path).unwrap();
+
+    let codes = codes.unwrap();
+    assert_eq!(codes, parts);
+    assert_eq!(services::reassemble(&codes).unwrap(), PAYLOAD);
+}
+
+#[test]
+fn parts_are_reassembled_in_any_order() {
+    let mut parts = chunk::split(PAYLOAD, 20);
+    parts.reverse();
+    assert_eq!(services::reassemble(&parts).unwrap(), PAYLOAD);
+
+    parts.remove(1);
+    assert!(matches!(
+        services::reassemble(&parts),
+        Err(Error::Chunk(ChunkError::Missing(_)))
+    ));
+}
+
+#[test]
+fn single_codes_and_fountain_frames_are_reassembled() {
+    assert_eq!(
+        services::reassemble(&[format!("{}\n", PAYLOAD)]).unwrap(),
+        PAYLOAD
+    );
+    assert!(services::reassemble::<&str>(&[]).is_err());
+
+    let encoder = FountainEncoder::new(PAYLOAD, 16);
+    let frames = encoder.frames(encoder.recommended_frame_count());
+    assert_eq!(services::reassemble(&frames).unwrap(), PAYLOAD);
+    assert!(matches!(
+        services::reassemble(&frames[..1]),
+        Err(Error::IncompleteFrames { .. })
+    ));
+}
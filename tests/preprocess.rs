@@ -0,0 +1,182 @@
+// tests/preprocess.rs
+//! Photos of a screen or of paper decode through the preprocessing passes,
+//! which [`Candidates`] yields one after another. The photos are synthetic:
+//! a rendered code blurred, turned and lit unevenly.
+use image::{imageops, GrayImage, Luma};
+use qr_data_exchange::qr::service::{self, Candidates, QrServiceError, ReadOptions, RenderOptions};
+
+const PAYLOAD: &str = "QRDX7:cGhvdG9ncmFwaGVkIG9uIGEgcGhvbmUgYW5kIHN0aWxsIHJlYWRhYmxl";
+
+/// Plain grayscale, contrast stretched, three threshold passes and both of
+/// those turned three times.
+const PREPROCESSED_CANDIDATES: usize = 1 + 1 + 3 + 3 + 3;
+
+fn preprocess(preprocess: bool) -> ReadOptions {
+    ReadOptions { preprocess }
+}
+
+fn render(text: &str, module_size: u32) -> GrayImage {
+    let options = RenderOptions {
+        size: 0,
+        min_module_size: module_size,
+        ..RenderOptions::default()
+    };
+    let png = service::generate_qr_image(text, &options).unwrap().png;
+    image::load_from_memory(&png).unwrap().to_luma8()
+}
+
+/// `img` turned by `degrees` around its center with bilinear sampling, on a
+/// white background.
+fn rotate(img: &GrayImage, degrees: f32) -> GrayImage {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let (width, height) = img.dimensions();
+    let (cx, cy) = (width as f32 / 2.0, height as f32 / 2.0);
+    let sample = |x: f32, y: f32| -> f32 {
+        if x < 0.0 || y < 0.0 || x >= (width - 1) as f32 || y >= (height - 1) as f32 {
+            return 255.0;
+        }
+        let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+        let (fx, fy) = (x - x0 as f32, y - y0 as f32);
+        let at = |x, y| f32::from(img.get_pixel(x, y).0[0]);
+        let top = at(x0, y0) * (1.0 - fx) + at(x0 + 1, y0) * fx;
+        let bottom = at(x0, y0 + 1) * (1.0 - fx) + at(x0 + 1, y0 + 1) * fx;
+        top * (1.0 - fy) + bottom * fy
+    };
+    GrayImage::from_fn(width, height, |x, y| {
+        let (dx, dy) = (x as f32 - cx, y as f32 - cy);
+        let value = sample(cx + dx * cos + dy * sin, cy - dx * sin + dy * cos);
+        Luma([value.round() as u8])
+    })
+}
+
+/// Squeezes black and white into `dark..=light` and lets the light fall off
+/// from left to right by up to `falloff`, like a lamp beside the page.
+fn light(img: &GrayImage, dark: u8, light: u8, falloff: f32) -> GrayImage {
+    let width = img.width() as f32;
+    GrayImage::from_fn(img.width(), img.height(), |x, y| {
+        let value = f32::from(img.get_pixel(x, y)[0]) / 255.0;
+        let lit = f32::from(dark) + value * f32::from(light - dark);
+        Luma([(lit * (1.0 - falloff * x as f32 / width)).round() as u8])
+    })
+}
+
+/// `img` in the middle of a larger frame of `background`.
+fn frame(img: &GrayImage, width: u32, height: u32, background: u8) -> GrayImage {
+    let mut canvas = GrayImage::from_pixel(width, height, Luma([background]));
+    imageops::overlay(
+        &mut canvas,
+        img,
+        i64::from((width - img.width()) / 2),
+        i64::from((height - img.height()) / 2),
+    );
+    canvas
+}
+
+/// A phone held sideways in front of a screen: a large photo, the code small
+/// within it, turned on its side, slightly blurred and unevenly lit.
+fn screen_photo() -> GrayImage {
+    let code = imageops::blur(&light(&render(PAYLOAD, 8), 60, 190, 0.35), 1.5);
+    frame(&imageops::rotate90(&code), 3000, 2200, 70)
+}
+
+/// A small, dim shot of a printout taken at a slant and upside down.
+fn paper_photo() -> GrayImage {
+    let code = light(&rotate(&render(PAYLOAD, 3), 7.0), 90, 170, 0.2);
+    imageops::blur(&imageops::rotate180(&code), 0.8)
+}
+
+#[test]
+fn without_preprocessing_only_the_image_itself_is_tried() {
+    let img = render(PAYLOAD, 4);
+    let candidates: Vec<GrayImage> = Candidates::new(img.clone(), preprocess(false)).collect();
+    assert_eq!(candidates, vec![img]);
+}
+
+#[test]
+fn preprocessing_yields_every_pass() {
+    let img = GrayImage::from_fn(800, 700, |x, y| Luma([((x + y) % 256) as u8]));
+    let candidates: Vec<GrayImage> = Candidates::new(img.clone(), preprocess(true)).collect();
+    assert_eq!(candidates.len(), PREPROCESSED_CANDIDATES);
+    assert_eq!(candidates[0], img);
+
+    for threshold in &candidates[2..5] {
+        assert!(threshold.pixels().all(|p| p[0] == 0 || p[0] == 255));
+    }
+    let upright = candidates[1].dimensions();
+    for turned in [
+        &candidates[5],
+        &candidates[7],
+        &candidates[8],
+        &candidates[10],
+    ] {
+        assert_eq!(turned.dimensions(), (upright.1, upright.0));
+    }
+    for upside_down in [&candidates[6], &candidates[9]] {
+        assert_eq!(upside_down.dimensions(), upright);
+    }
+}
+
+#[test]
+fn large_photos_are_scaled_down_and_small_ones_up() {
+    let scaled = |width, height| {
+        let img = GrayImage::from_pixel(width, height, Luma([128]));
+        Candidates::new(img, preprocess(true))
+            .nth(1)
+            .unwrap()
+            .dimensions()
+    };
+    let (width, height) = scaled(3000, 2200);
+    assert!((999..=1000).contains(&width.max(height)));
+    let (width, height) = scaled(200, 150);
+    assert!(width.min(height) >= 600);
+    assert!(width.max(height) <= 1000);
+}
+
+#[test]
+fn thin_images_are_not_blown_up() {
+    for (width, height) in [(1, 999), (999, 1), (3, 400), (120, 900)] {
+        let img = GrayImage::from_pixel(width, height, Luma([128]));
+        for candidate in Candidates::new(img, preprocess(true)) {
+            let (w, h) = candidate.dimensions();
+            assert!(w.max(h) <= 1000, "{width}x{height} became {w}x{h}");
+        }
+    }
+}
+
+#[test]
+fn contrast_is_stretched_to_the_full_range() {
+    let dim = light(&render(PAYLOAD, 4), 90, 170, 0.0);
+    let stretched = Candidates::new(dim, preprocess(true)).nth(1).unwrap();
+    let darkest = stretched.pixels().map(|p| p[0]).min().unwrap();
+    let brightest = stretched.pixels().map(|p| p[0]).max().unwrap();
+    assert!(darkest < 10 && brightest > 245, "{darkest}..{brightest}");
+}
+
+#[test]
+fn the_error_counts_the_variants_tried() {
+    let blank = || GrayImage::from_pixel(64, 64, Luma([255]));
+    for (options, attempts) in [(false, 1), (true, PREPROCESSED_CANDIDATES)] {
+        // rxing gets one more try on the original image.
+        let expected = attempts + usize::from(cfg!(feature = "rxing"));
+        match service::decode_luma_bytes(blank(), preprocess(options)) {
+            Err(QrServiceError::QrCodeNotFound { attempts }) => assert_eq!(attempts, expected),
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+}
+
+#[test]
+fn photo_of_a_screen_decodes_after_preprocessing() {
+    // The passes are what makes it readable, not the photo itself.
+    assert!(service::decode_luma(screen_photo(), preprocess(false)).is_err());
+    let decoded = service::decode_luma(screen_photo(), preprocess(true)).unwrap();
+    assert_eq!(decoded, PAYLOAD);
+}
+
+#[test]
+fn photo_of_paper_decodes_after_preprocessing() {
+    // The passes are what makes it readable, not the photo itself.
+    assert!(service::decode_luma(paper_photo(), preprocess(false)).is_err());
+    let decoded = service::decode_luma(paper_photo(), preprocess(true)).unwrap();
+    assert_eq!(decoded, PAYLOAD);
+}
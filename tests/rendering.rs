@@ -0,0 +1,94 @@
+// tests/rendering.rs
+//! Dense codes keep modules large enough to print and scan; the image grows
+//! past the preferred size instead.
+use qr_data_exchange::qr::service::{
+    self, QrImage, QrStyle, RenderOptions, DEFAULT_MIN_MODULE_SIZE, MAX_QR_BYTES, PRINT_IMAGE_SIZE,
+};
+
+/// Modules per side of a version 40 code, quiet zone excluded.
+const VERSION_40_MODULES: u32 = 177;
+
+/// Bytes that fill a version 40 code at level L.
+fn version_40_data() -> Vec<u8> {
+    vec![0xa5; MAX_QR_BYTES]
+}
+
+fn render(options: &RenderOptions) -> QrImage {
+    service::generate_qr_image_bytes(&version_40_data(), options).unwrap()
+}
+
+fn png_side(image: &QrImage) -> (u32, u32) {
+    image::load_from_memory(&image.png)
+        .unwrap()
+        .to_luma8()
+        .dimensions()
+}
+
+#[test]
+fn version_40_gets_at_least_4_px_per_module_by_default() {
+    let options = RenderOptions::default();
+    let image = render(&options);
+
+    assert_eq!(image.capacity.version, 40);
+    assert_eq!(DEFAULT_MIN_MODULE_SIZE, 4);
+    assert!(image.dimensions.module_size >= 4);
+    assert!(image.dimensions.side > options.size);
+    assert_eq!(
+        image.dimensions.side,
+        image.dimensions.modules * image.dimensions.module_size
+    );
+    assert_eq!(
+        png_side(&image),
+        (image.dimensions.side, image.dimensions.side)
+    );
+}
+
+#[test]
+fn version_40_gets_at_least_4_px_per_module_at_any_size() {
+    for size in [128, 512, PRINT_IMAGE_SIZE, 2048] {
+        let options = RenderOptions {
+            size,
+            ..RenderOptions::default()
+        };
+        assert!(render(&options).dimensions.module_size >= 4, "size {size}");
+    }
+}
+
+#[test]
+fn the_configured_minimum_module_size_holds() {
+    let options = RenderOptions {
+        min_module_size: 6,
+        ..RenderOptions::default()
+    };
+    let image = render(&options);
+    assert_eq!(image.dimensions.module_size, 6);
+    assert_eq!(png_side(&image).0, image.dimensions.modules * 6);
+}
+
+#[test]
+fn print_resolution_raises_the_module_size() {
+    let options = RenderOptions {
+        dpi: Some(600),
+        ..RenderOptions::default()
+    };
+    // 0.5 mm at 600 dpi is 11.8 px.
+    assert_eq!(render(&options).dimensions.module_size, 12);
+}
+
+#[test]
+fn the_quiet_zone_is_configurable() {
+    for quiet_zone in [0, 4, 10] {
+        let options = RenderOptions {
+            style: QrStyle {
+                quiet_zone,
+                ..QrStyle::default()
+            },
+            ..RenderOptions::default()
+        };
+        let image = render(&options);
+        assert_eq!(
+            image.dimensions.modules,
+            VERSION_40_MODULES + 2 * quiet_zone
+        );
+    }
+}
@@ -0,0 +1,255 @@
+// tests/save.rs
+//! Files are replaced in one step through a temporary copy that keeps the
+//! permissions of the file it replaces, or is private to the user, and
+//! leaves nothing behind.
+use qr_data_exchange::crypto::crypto_utils::KdfLimits;
+use qr_data_exchange::error::Error;
+use qr_data_exchange::qr::processor::{DeserializeOptions, SerializeOptions, TextEncoding};
+use qr_data_exchange::services;
+use std::future::Future;
+use std::path::PathBuf;
+use zeroize::Zeroizing;
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap()
+        .block_on(future)
+}
+
+/// An empty directory of its own for each test.
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("qrdx-save-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn entries(dir: &PathBuf) -> Vec<String> {
+    let mut names: Vec<String> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn writes_a_new_file_without_leftovers() {
+    let dir = scratch_dir("new");
+    let path = dir.join("out.txt");
+    block_on(services::save_bytes_atomic(&path, b"first")).unwrap();
+
+    assert_eq!(std::fs::read(&path).unwrap(), b"first");
+    assert_eq!(entries(&dir), ["out.txt"]);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn replaces_an_existing_file() {
+    let dir = scratch_dir("replace");
+    let path = dir.join("out.txt");
+    std::fs::write(&path, b"a longer earlier version").unwrap();
+    block_on(services::save_bytes_atomic(&path, b"second")).unwrap();
+
+    assert_eq!(std::fs::read(&path).unwrap(), b"second");
+    assert_eq!(entries(&dir), ["out.txt"]);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn a_replaced_file_keeps_its_permissions() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = scratch_dir("permissions");
+    let path = dir.join("secret.txt");
+    std::fs::write(&path, b"old").unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)).unwrap();
+    block_on(services::save_bytes_atomic(&path, b"new")).unwrap();
+
+    let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn private_files_are_readable_by_the_owner_only() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = scratch_dir("private");
+    let mode = |path: &PathBuf| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+
+    let new = dir.join("new.toml");
+    block_on(services::save_private_bytes_atomic(&new, b"new")).unwrap();
+    assert_eq!(mode(&new), 0o600);
+
+    let replaced = dir.join("replaced.toml");
+    std::fs::write(&replaced, b"old").unwrap();
+    std::fs::set_permissions(&replaced, std::fs::Permissions::from_mode(0o644)).unwrap();
+    block_on(services::save_private_bytes_atomic(&replaced, b"new")).unwrap();
+    assert_eq!(mode(&replaced), 0o600);
+    assert_eq!(std::fs::read(&replaced).unwrap(), b"new");
+
+    assert_eq!(entries(&dir), ["new.toml", "replaced.toml"]);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn concurrent_writes_to_one_path_do_not_collide() {
+    let dir = scratch_dir("concurrent");
+    let path = dir.join("out.txt");
+    // The file operations run on blocking threads, so the writes overlap
+    // even on a single-threaded runtime.
+    let results = block_on(async {
+        let writes: Vec<_> = (0..16u8)
+            .map(|i| {
+                let path = path.clone();
+                tokio::spawn(async move { services::save_bytes_atomic(&path, &[i; 512]).await })
+            })
+            .collect();
+        let mut results = Vec::new();
+        for write in writes {
+            results.push(write.await.unwrap());
+        }
+        results
+    });
+
+    assert!(results.iter().all(Result::is_ok), "{results:?}");
+    let data = std::fs::read(&path).unwrap();
+    assert_eq!(data.len(), 512);
+    assert!(data.iter().all(|&b| b == data[0]));
+    assert_eq!(entries(&dir), ["out.txt"]);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn errors_name_the_path_and_leave_nothing_behind() {
+    let dir = scratch_dir("missing");
+    let path = dir.join("no such dir").join("out.txt");
+    match block_on(services::save_bytes_atomic(&path, b"data")) {
+        Err(Error::SaveFailed { path: failed, .. }) => assert_eq!(failed, path),
+        other => panic!("unexpected result: {other:?}"),
+    }
+    assert!(entries(&dir).is_empty());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+fn password() -> Zeroizing<String> {
+    Zeroizing::new("correct horse".to_string())
+}
+
+fn stream_options() -> SerializeOptions {
+    SerializeOptions {
+        // Cheapest limits Argon2 accepts, to keep the tests fast.
+        kdf: KdfLimits {
+            ops_limit: 3,
+            mem_limit_kib: 8,
+        },
+        ..SerializeOptions::default()
+    }
+}
+
+#[test]
+fn failed_streams_leave_the_output_untouched() {
+    let dir = scratch_dir("stream-failed");
+    let input = dir.join("input.bin");
+    let encrypted = dir.join("encrypted.txt");
+    let decrypted = dir.join("decrypted.bin");
+    std::fs::write(&input, b"streamed data").unwrap();
+    std::fs::write(&encrypted, b"earlier payload").unwrap();
+    std::fs::write(&decrypted, b"earlier data").unwrap();
+
+    // Streams are base64 only, so this fails once the output is open.
+    let refused = SerializeOptions {
+        encoding: TextEncoding::Base45,
+        ..stream_options()
+    };
+    let result = block_on(services::encrypt_file_streaming(
+        input.clone(),
+        encrypted.clone(),
+        password(),
+        refused,
+    ));
+    assert!(result.is_err());
+    assert_eq!(std::fs::read(&encrypted).unwrap(), b"earlier payload");
+
+    let result = block_on(services::decrypt_file_streaming(
+        encrypted.clone(),
+        decrypted.clone(),
+        password(),
+        DeserializeOptions::default(),
+    ));
+    assert!(result.is_err());
+    assert_eq!(std::fs::read(&decrypted).unwrap(), b"earlier data");
+
+    assert_eq!(
+        entries(&dir),
+        ["decrypted.bin", "encrypted.txt", "input.bin"]
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn streams_replace_the_output_without_leftovers() {
+    let dir = scratch_dir("stream");
+    let input = dir.join("input.bin");
+    let encrypted = dir.join("encrypted.txt");
+    let decrypted = dir.join("decrypted.bin");
+    std::fs::write(&input, b"streamed data").unwrap();
+    std::fs::write(&decrypted, b"a longer earlier version").unwrap();
+
+    let size = block_on(services::encrypt_file_streaming(
+        input.clone(),
+        encrypted.clone(),
+        password(),
+        stream_options(),
+    ))
+    .unwrap();
+    assert_eq!(size, 13);
+    block_on(services::decrypt_file_streaming(
+        encrypted.clone(),
+        decrypted.clone(),
+        password(),
+        DeserializeOptions::default(),
+    ))
+    .unwrap();
+
+    assert_eq!(std::fs::read(&decrypted).unwrap(), b"streamed data");
+    assert_eq!(
+        entries(&dir),
+        ["decrypted.bin", "encrypted.txt", "input.bin"]
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn decrypted_streams_are_readable_by_the_owner_only() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = scratch_dir("stream-private");
+    let input = dir.join("input.bin");
+    let encrypted = dir.join("encrypted.txt");
+    let decrypted = dir.join("decrypted.bin");
+    std::fs::write(&input, b"secret").unwrap();
+    block_on(services::encrypt_file_streaming(
+        input,
+        encrypted.clone(),
+        password(),
+        stream_options(),
+    ))
+    .unwrap();
+    block_on(services::decrypt_file_streaming(
+        encrypted,
+        decrypted.clone(),
+        password(),
+        DeserializeOptions::default(),
+    ))
+    .unwrap();
+
+    let mode = std::fs::metadata(&decrypted).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,153 @@
+// tests/scan.rs
+//! A scan session collects the parts of a split payload in any order and
+//! refuses parts that belong to another payload.
+use qr_data_exchange::qr::chunk::{self, ChunkError};
+use qr_data_exchange::qr::scan::ScanSession;
+
+const PAYLOAD: &str = "QRDX7:c3BsaXQgb3ZlciBzZXZlcmFsIGNvZGVzIGFuZCBzY2FubmVkIG9uZSBieSBvbmU=";
+
+fn parts() -> Vec<String> {
+    chunk::split(PAYLOAD, 16)
+}
+
+#[test]
+fn parts_in_any_order_make_the_payload() {
+    let parts = parts();
+    assert!(parts.len() > 3);
+    let mut session = ScanSession::default();
+    for part in parts.iter().rev() {
+        assert!(!session.is_complete());
+        assert!(session.add(part).unwrap());
+    }
+    assert!(session.is_complete());
+    assert!(session.missing().is_empty());
+    assert_eq!(session.join().unwrap(), PAYLOAD);
+}
+
+#[test]
+fn missing_lists_the_parts_still_to_scan() {
+    let parts = parts();
+    let mut session = ScanSession::default();
+    assert!(session.missing().is_empty());
+
+    session.add(&parts[1]).unwrap();
+    session.add(&parts[3]).unwrap();
+    let expected: Vec<usize> = (1..=parts.len()).filter(|&i| i != 2 && i != 4).collect();
+    assert_eq!(session.missing(), expected);
+    assert!(matches!(session.join(), Err(ChunkError::Missing(1))));
+}
+
+#[test]
+fn a_part_scanned_twice_is_not_new() {
+    let parts = parts();
+    let mut session = ScanSession::default();
+    assert!(session.add(&parts[0]).unwrap());
+    assert!(!session.add(&format!("  {}\n", parts[0])).unwrap());
+    assert_eq!(session.parts.len(), 1);
+}
+
+#[test]
+fn parts_of_another_payload_are_refused() {
+    let mut session = ScanSession::default();
+    session.add(&parts()[0]).unwrap();
+
+    let other_count = chunk::split(PAYLOAD, 8);
+    assert!(matches!(
+        session.add(&other_count[1]),
+        Err(ChunkError::Inconsistent)
+    ));
+
+    let count = parts().len();
+    let same_index = format!("{}1/{}:something else", chunk::CHUNK_SCHEME, count);
+    assert!(matches!(
+        session.add(&same_index),
+        Err(ChunkError::Inconsistent)
+    ));
+    assert_eq!(session.parts.len(), 1);
+}
+
+#[test]
+fn anything_but_a_part_is_refused() {
+    let mut session = ScanSession::default();
+    assert!(session.add(PAYLOAD).is_err());
+    assert_eq!(session, ScanSession::default());
+}
+
+#[test]
+fn stored_sessions_are_checked_for_consistency() {
+    let parts = parts();
+    let mut session = ScanSession::default();
+    session.add(&parts[0]).unwrap();
+    session.add(&parts[2]).unwrap();
+    assert!(session.is_valid());
+
+    let stored = serde_json::to_string(&session).unwrap();
+    let loaded: ScanSession = serde_json::from_str(&stored).unwrap();
+    assert_eq!(loaded, session);
+    assert!(loaded.is_valid());
+
+    let mut moved = session.clone();
+    let part = moved.parts.remove(&3).unwrap();
+    moved.parts.insert(2, part);
+    assert!(!moved.is_valid());
+
+    let mut recounted = session.clone();
+    recounted.count += 1;
+    assert!(!recounted.is_valid());
+
+    let mut garbled = session;
+    garbled.parts.insert(2, "not a part".to_string());
+    assert!(!garbled.is_valid());
+}
+
+#[test]
+fn counts_beyond_the_limit_are_refused() {
+    let mut session = ScanSession::default();
+    let huge = format!("{}1/99999999999:x", chunk::CHUNK_SCHEME);
+    assert!(matches!(
+        session.add(&huge),
+        Err(ChunkError::TooManyParts(99_999_999_999))
+    ));
+    assert_eq!(session, ScanSession::default());
+
+    let largest = format!("{}1/{}:x", chunk::CHUNK_SCHEME, chunk::MAX_PARTS);
+    assert!(session.add(&largest).unwrap());
+    assert_eq!(session.missing().len(), chunk::MAX_PARTS - 1);
+
+    // A session stored by an older version without the limit.
+    let stored = format!(r#"{{"count":99999999999,"parts":{{"1":"{huge}"}}}}"#);
+    let loaded: ScanSession = serde_json::from_str(&stored).unwrap();
+    assert!(!loaded.is_valid());
+}
+
+#[test]
+fn join_refuses_counts_beyond_the_limit() {
+    for count in ["99999999999", "18446744073709551615"] {
+        let part = format!("{}1/{count}:x", chunk::CHUNK_SCHEME);
+        assert!(matches!(
+            chunk::join(&[part]),
+            Err(ChunkError::TooManyParts(_))
+        ));
+    }
+    // Too large even for a usize
+    let part = format!("{}1/18446744073709551616:x", chunk::CHUNK_SCHEME);
+    assert!(matches!(chunk::join(&[part]), Err(ChunkError::NotAChunk)));
+}
+
+#[test]
+fn join_reports_the_first_missing_part() {
+    let parts = parts();
+    let count = parts.len();
+    assert!(matches!(
+        chunk::join(&parts[..count - 1]),
+        Err(ChunkError::Missing(missing)) if missing == count
+    ));
+    assert!(matches!(
+        chunk::join::<String>(&[]),
+        Err(ChunkError::Missing(1))
+    ));
+    assert!(matches!(
+        chunk::join(&[&parts[0], &parts[0]]),
+        Err(ChunkError::Missing(2))
+    ));
+}
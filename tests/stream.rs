@@ -0,0 +1,127 @@
+// tests/stream.rs
+//! Streamed payloads round-trip, record their Argon2 limits and expiry, and
+//! refuse to decompress beyond the limit.
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use qr_data_exchange::crypto::crypto_utils::{Cipher, KdfLimits};
+use qr_data_exchange::qr::processor::{
+    unix_now, DeserializeOptions, QrProcessorError, SerializeOptions, TextEncoding,
+};
+use qr_data_exchange::qr::stream::{self, StreamError, STREAM_SCHEME, STREAM_VERSION};
+
+const PASSWORD: &str = "correct horse";
+const FAST: KdfLimits = KdfLimits {
+    ops_limit: 3,
+    mem_limit_kib: 8,
+};
+
+fn fast() -> SerializeOptions {
+    SerializeOptions {
+        kdf: FAST,
+        ..SerializeOptions::default()
+    }
+}
+
+fn encrypt(data: &[u8], options: &SerializeOptions) -> Result<Vec<u8>, StreamError> {
+    let mut text = Vec::new();
+    stream::encrypt_stream(data, &mut text, PASSWORD, options)?;
+    Ok(text)
+}
+
+fn decrypt(text: &[u8], options: &DeserializeOptions) -> Result<Vec<u8>, StreamError> {
+    let mut data = Vec::new();
+    stream::decrypt_stream(text, &mut data, PASSWORD, options)?;
+    Ok(data)
+}
+
+#[test]
+fn round_trip_with_the_recorded_limits() {
+    let data = b"a file too large for memory, in spirit".repeat(5000);
+    let text = encrypt(&data, &fast()).unwrap();
+    assert!(text.starts_with(format!("{}{}:", STREAM_SCHEME, STREAM_VERSION).as_bytes()));
+    // Decoded with the default limits, so these come from the stream.
+    assert_eq!(
+        decrypt(&text, &DeserializeOptions::default()).unwrap(),
+        data
+    );
+}
+
+#[test]
+fn decompression_stops_at_the_limit() {
+    let data = vec![0u8; 4 * 1024 * 1024];
+    let text = encrypt(&data, &fast()).unwrap();
+    let options = DeserializeOptions {
+        max_decompressed_len: 1024 * 1024,
+        ..DeserializeOptions::default()
+    };
+    assert!(matches!(
+        decrypt(&text, &options),
+        Err(StreamError::Processor(QrProcessorError::DecompressedTooLarge { limit }))
+            if limit == 1024 * 1024
+    ));
+}
+
+#[test]
+fn expired_streams_are_refused() {
+    let expired = SerializeOptions {
+        expires_at: Some(unix_now() - 1),
+        ..fast()
+    };
+    let text = encrypt(b"too late", &expired).unwrap();
+    assert!(matches!(
+        decrypt(&text, &DeserializeOptions::default()),
+        Err(StreamError::Processor(QrProcessorError::Expired { .. }))
+    ));
+
+    let valid = SerializeOptions {
+        expires_at: Some(unix_now() + 3600),
+        ..fast()
+    };
+    let text = encrypt(b"in time", &valid).unwrap();
+    assert_eq!(
+        decrypt(&text, &DeserializeOptions::default()).unwrap(),
+        b"in time"
+    );
+}
+
+#[test]
+fn changed_header_fields_fail_authentication() {
+    let expired = SerializeOptions {
+        expires_at: Some(unix_now() - 1),
+        ..fast()
+    };
+    let text = encrypt(b"too late", &expired).unwrap();
+    let (scheme, body) = text.split_at(text.iter().position(|&b| b == b':').unwrap() + 1);
+    let mut bytes = STANDARD.decode(body).unwrap();
+    // The lowest byte of the expiry, after salt, flags and limits.
+    bytes[16 + 1 + 8 + 7] ^= 0xff;
+    let tampered = [scheme, STANDARD.encode(&bytes).as_bytes()].concat();
+    assert!(matches!(
+        decrypt(&tampered, &DeserializeOptions::default()),
+        Err(StreamError::Processor(QrProcessorError::Corrupted))
+    ));
+}
+
+#[test]
+fn options_streams_cannot_honour_are_refused() {
+    let refused = [
+        SerializeOptions {
+            encoding: TextEncoding::Base45,
+            ..fast()
+        },
+        SerializeOptions {
+            cipher: Cipher::ChaCha20Poly1305,
+            ..fast()
+        },
+        SerializeOptions {
+            archive: true,
+            ..fast()
+        },
+    ];
+    for options in refused {
+        assert!(matches!(
+            encrypt(b"data", &options),
+            Err(StreamError::Processor(QrProcessorError::NotForStreams(_)))
+        ));
+    }
+}
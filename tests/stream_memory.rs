@@ -0,0 +1,123 @@
+// tests/stream_memory.rs
+//! Streaming keeps memory use bounded whatever the size of the file. Kept in
+//! a file of its own so no other test allocates while it measures.
+use qr_data_exchange::crypto::crypto_utils::KdfLimits;
+use qr_data_exchange::qr::processor::{DeserializeOptions, SerializeOptions};
+use qr_data_exchange::qr::stream;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Counts what is allocated through Rust; libsodium and zstd allocate on
+/// their own, but they only hold one segment and the zstd window.
+struct Counting;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let current = CURRENT.fetch_add(layout.size(), Ordering::SeqCst) + layout.size();
+            PEAK.fetch_max(current, Ordering::SeqCst);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        CURRENT.fetch_sub(layout.size(), Ordering::SeqCst);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+const PASSWORD: &str = "correct horse";
+const INPUT_LEN: u64 = 16 * 1024 * 1024;
+/// Far below `INPUT_LEN`, far above a segment.
+const MAX_GROWTH: usize = 2 * 1024 * 1024;
+const FAST: KdfLimits = KdfLimits {
+    ops_limit: 3,
+    mem_limit_kib: 8,
+};
+
+/// Endless bytes zstd can't shrink, made up as they are read.
+struct Noise(u64);
+
+impl Read for Noise {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        for byte in buf.iter_mut() {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            *byte = self.0 as u8;
+        }
+        Ok(buf.len())
+    }
+}
+
+/// How far the allocated memory grows above its level before `f` runs.
+fn growth(f: impl FnOnce()) -> usize {
+    let before = CURRENT.load(Ordering::SeqCst);
+    PEAK.store(before, Ordering::SeqCst);
+    f();
+    PEAK.load(Ordering::SeqCst) - before
+}
+
+#[test]
+fn memory_use_does_not_grow_with_the_input() {
+    let path = std::env::temp_dir().join(format!("qrdx-stream-memory-{}", std::process::id()));
+    let options = SerializeOptions {
+        compression_level: 1,
+        kdf: FAST,
+        ..SerializeOptions::default()
+    };
+
+    let encrypting = growth(|| {
+        let output = BufWriter::new(File::create(&path).unwrap());
+        let input = Noise(0x2545_f491_4f6c_dd1d).take(INPUT_LEN);
+        let len = stream::encrypt_stream(input, output, PASSWORD, &options).unwrap();
+        assert_eq!(len, INPUT_LEN);
+    });
+
+    let mut written = 0u64;
+    let decrypting = growth(|| {
+        let input = BufReader::new(File::open(&path).unwrap());
+        let options = DeserializeOptions {
+            max_decompressed_len: INPUT_LEN as usize,
+            ..DeserializeOptions::default()
+        };
+        let mut output = CountingSink(&mut written);
+        stream::decrypt_stream(input, &mut output, PASSWORD, &options).unwrap();
+    });
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(written, INPUT_LEN);
+    assert!(
+        encrypting < MAX_GROWTH,
+        "encrypting took {} bytes",
+        encrypting
+    );
+    assert!(
+        decrypting < MAX_GROWTH,
+        "decrypting took {} bytes",
+        decrypting
+    );
+}
+
+/// Counts the decrypted bytes instead of keeping them.
+struct CountingSink<'a>(&'a mut u64);
+
+impl io::Write for CountingSink<'_> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        *self.0 += data.len() as u64;
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,113 @@
+// tests/text_encoding.rs
+//! Payload text in either base64 alphabet, with or without padding and with
+//! line breaks picked up on the way, decodes to the same data, and so does
+//! base45 text, which makes for smaller codes.
+use qr_data_exchange::crypto::crypto_utils::KdfLimits;
+use qr_data_exchange::qr::processor::{
+    QrDataProcessor, SerializeOptions, TextEncoding, BASE45_PREFIX,
+};
+use qr_data_exchange::qr::service;
+
+const PASSWORD: &str = "correct horse";
+
+fn payload(data: &[u8], encoding: TextEncoding) -> String {
+    // Payloads record their Argon2 limits, so the cheapest ones keep this fast.
+    let options = SerializeOptions {
+        encoding,
+        kdf: KdfLimits {
+            ops_limit: 3,
+            mem_limit_kib: 8,
+        },
+        ..SerializeOptions::default()
+    };
+    QrDataProcessor::serialize_with(data, PASSWORD, &options).unwrap()
+}
+
+#[test]
+fn url_safe_strings_decode() {
+    // One length per remainder mod 3, so every padding length comes up.
+    for len in 0..3 {
+        let data = vec![0xfb; 40 + len];
+        let text = payload(&data, TextEncoding::Base64Url);
+        assert!(!text.contains(['+', '/', '=']), "{}", text);
+        assert_eq!(QrDataProcessor::deserialize(&text, PASSWORD).unwrap(), data);
+
+        // Padding added by some other tool is accepted as well.
+        let (_, body) = text.split_once(':').unwrap();
+        let padded = format!("{}{}", text, "=".repeat((4 - body.len() % 4) % 4));
+        assert_eq!(
+            QrDataProcessor::deserialize(&padded, PASSWORD).unwrap(),
+            data
+        );
+    }
+}
+
+#[test]
+fn stray_newlines_are_ignored() {
+    let data = b"wrapped by a mail client".to_vec();
+
+    for encoding in TextEncoding::ALL {
+        let text = payload(&data, encoding);
+        let wrapped: String = text
+            .chars()
+            .enumerate()
+            .flat_map(|(i, c)| {
+                (i > 0 && i % 20 == 0)
+                    .then_some('\n')
+                    .into_iter()
+                    .chain([c])
+            })
+            .collect();
+        let wrapped = format!("\r\n{}\r\n", wrapped.replace('\n', "\r\n"));
+
+        assert_eq!(
+            QrDataProcessor::deserialize(&wrapped, PASSWORD).unwrap(),
+            data,
+            "{}",
+            encoding
+        );
+    }
+}
+
+/// The characters of QR alphanumeric mode.
+const ALPHANUMERIC: &str = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+/// Bytes zstd can't shrink, so the payload grows with them.
+fn noise(len: usize) -> Vec<u8> {
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        })
+        .collect()
+}
+
+#[test]
+fn base45_round_trip() {
+    for len in [0, 1, 2, 3, 100, 1000] {
+        let data = noise(len);
+        let text = payload(&data, TextEncoding::Base45);
+        let (_, body) = text.split_once(':').unwrap();
+        assert!(body.starts_with(BASE45_PREFIX), "{}", text);
+        assert!(text.chars().all(|c| ALPHANUMERIC.contains(c)), "{}", text);
+
+        assert_eq!(QrDataProcessor::deserialize(&text, PASSWORD).unwrap(), data);
+        assert!(QrDataProcessor::deserialize(&text, "wrong horse").is_err());
+    }
+}
+
+#[test]
+fn base45_needs_a_smaller_code_than_base64() {
+    let data = noise(600);
+    let base64 = service::qr_capacity(&payload(&data, TextEncoding::Base64)).unwrap();
+    let base45 = service::qr_capacity(&payload(&data, TextEncoding::Base45)).unwrap();
+    assert!(
+        base45.version < base64.version,
+        "base45 version {}, base64 version {}",
+        base45.version,
+        base64.version
+    );
+}
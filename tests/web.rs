@@ -0,0 +1,128 @@
+// tests/web.rs
+//! The browser build against the desktop one. The known answers below were
+//! made by the desktop build, and both `cargo test` and `wasm-pack test --node
+//! -- --no-default-features --features wasm` check them, so the RustCrypto
+//! primitives of the browser build have to produce the same bytes as
+//! libsodium. Only compression differs: ruzstd writes other, equally valid
+//! frames. The round trips through the JavaScript bindings only run in the
+//! browser build.
+#![cfg(any(not(target_arch = "wasm32"), feature = "wasm"))]
+
+use qr_data_exchange::crypto::crypto_utils::{self, Cipher, KdfLimits, Salt};
+use qr_data_exchange::qr::processor::QrDataProcessor;
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen_test::wasm_bindgen_test as test;
+
+const PASSWORD: &str = "correct horse";
+const SALT: Salt = Salt([7; crypto_utils::SALT_LEN]);
+const NONCE: [u8; crypto_utils::NONCE_LEN] = [9; crypto_utils::NONCE_LEN];
+const PLAINTEXT: &[u8] = b"same bytes everywhere";
+/// Cheapest limits Argon2 accepts, to keep the tests fast.
+const FAST: KdfLimits = KdfLimits {
+    ops_limit: 3,
+    mem_limit_kib: 8,
+};
+
+/// `QrDataProcessor::serialize_with(b"made on the desktop", PASSWORD, ..)`
+/// with `FAST` limits, on the desktop.
+const DESKTOP_PAYLOAD: &str = "QRDX7:mdwAEMytzPnMmQTMncysLSPMk8zUzM/MnyTM11fM3dwAaA8azIkCQMyMzJLM+ih/Icy1zM7MymJSzIjMzMzKzINSzMQ/zMvMmXRPzJ/M88zOzKpvzOxqV0bM0QR5zO8kSWU9zLHMl8yabcy+eUJYzJgJAsySzMFWM0kKZgPMi3QDDMzwAmESzM7MvlgEzOpCzPPMtMyLEsymzIHMomc7Sz7M4hHM82bMicydzLoCTcyxzLUUE07MmMy/CAAAmDvMogXM+2oazJjMkZADCA==";
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[test]
+fn key_derivation_matches_the_desktop_build() {
+    crypto_utils::init().unwrap();
+    let key = crypto_utils::derive_key_with_limits(PASSWORD, None, &SALT, FAST).unwrap();
+    assert_eq!(
+        hex(&crypto_utils::key_check(&key).unwrap()),
+        "aa2e3c365fe2512b"
+    );
+
+    let keyfile = b"keyfile contents".as_slice();
+    let key = crypto_utils::derive_key_with_limits(PASSWORD, Some(keyfile), &SALT, FAST).unwrap();
+    assert_eq!(
+        hex(&crypto_utils::key_check(&key).unwrap()),
+        "e30e0b8e11a34523"
+    );
+}
+
+#[test]
+fn ciphers_match_the_desktop_build() {
+    crypto_utils::init().unwrap();
+    let key = crypto_utils::derive_key_with_limits(PASSWORD, None, &SALT, FAST).unwrap();
+    let expected = [
+        (
+            Cipher::XSalsa20Poly1305,
+            "090909090909090909090909090909090909090909090909\
+             bc968a980792b0451b27b4c383cd58b4f65a33af22771885fcba73e7231f571e0376d0e9f5",
+        ),
+        (
+            Cipher::ChaCha20Poly1305,
+            "090909090909090909090909\
+             2769613713757ce8129f7c9ad232014ed4e23e476e8eb3762d1353d1f295be654e462a0105",
+        ),
+    ];
+    for (cipher, ciphertext) in expected {
+        let nonce = &NONCE[..cipher.nonce_len()];
+        let encrypted = cipher.encrypt_with_nonce(PLAINTEXT, &key, nonce).unwrap();
+        assert_eq!(hex(&encrypted), ciphertext, "{}", cipher);
+    }
+}
+
+#[test]
+fn digest_matches_the_desktop_build() {
+    assert_eq!(
+        hex(&crypto_utils::digest(PLAINTEXT).unwrap()),
+        "7a0bad113da5a6593046e7a2dcc4f014c2c07a2b0acb3a9246626da99d0e2f6a"
+    );
+}
+
+#[test]
+fn desktop_payloads_decode() {
+    assert_eq!(
+        QrDataProcessor::deserialize(DESKTOP_PAYLOAD, PASSWORD).unwrap(),
+        b"made on the desktop"
+    );
+}
+
+#[cfg(target_arch = "wasm32")]
+mod bindings {
+    use qr_data_exchange::wasm::{decode, deserialize, encode, serialize};
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test]
+    fn round_trip() {
+        let data = b"QR Data Exchange im Browser".repeat(8);
+
+        for base45 in [false, true] {
+            let payload = serialize(&data, "correct horse", None, base45).unwrap();
+            assert_eq!(deserialize(&payload, "correct horse", None).unwrap(), data);
+            assert!(deserialize(&payload, "wrong horse", None).is_err());
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn encode_decode_in_every_encoding() {
+        let data = b"QR Data Exchange im Browser".repeat(8);
+
+        for encoding in ["base64", "base64url", "base45"] {
+            let payload = encode(&data, "correct horse", None, encoding).unwrap();
+            assert_eq!(decode(&payload, "correct horse", None).unwrap(), data);
+        }
+        assert!(encode(&data, "correct horse", None, "hex").is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn round_trip_with_keyfile() {
+        let keyfile = b"keyfile contents".to_vec();
+        let payload = serialize(b"secret", "correct horse", Some(keyfile.clone()), false).unwrap();
+
+        assert_eq!(
+            deserialize(&payload, "correct horse", Some(keyfile)).unwrap(),
+            b"secret"
+        );
+        assert!(deserialize(&payload, "correct horse", None).is_err());
+    }
+}